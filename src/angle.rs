@@ -0,0 +1,135 @@
+use std::{
+    fmt,
+    ops::{Add, Div, Mul, Neg, Sub},
+};
+
+/// An angle in radians.
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd)]
+pub struct Rad(pub f64);
+
+/// An angle in degrees.
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd)]
+pub struct Deg(pub f64);
+
+impl From<Deg> for Rad {
+    fn from(deg: Deg) -> Rad {
+        Rad(deg.0.to_radians())
+    }
+}
+
+impl From<Rad> for Deg {
+    fn from(rad: Rad) -> Deg {
+        Deg(rad.0.to_degrees())
+    }
+}
+
+/// Bare angles are in radians throughout the crate.
+impl From<f64> for Rad {
+    fn from(rad: f64) -> Rad {
+        Rad(rad)
+    }
+}
+
+impl From<Rad> for f64 {
+    fn from(rad: Rad) -> f64 {
+        rad.0
+    }
+}
+
+/// Arithmetic between angles of the same unit, and scaling by numbers.
+macro_rules! impl_angle_ops {
+    ($angle:ident) => {
+        impl Add for $angle {
+            type Output = $angle;
+
+            fn add(self, that: $angle) -> $angle {
+                $angle(self.0 + that.0)
+            }
+        }
+
+        impl Sub for $angle {
+            type Output = $angle;
+
+            fn sub(self, that: $angle) -> $angle {
+                $angle(self.0 - that.0)
+            }
+        }
+
+        impl Neg for $angle {
+            type Output = $angle;
+
+            fn neg(self) -> $angle {
+                $angle(-self.0)
+            }
+        }
+
+        impl Mul<f64> for $angle {
+            type Output = $angle;
+
+            fn mul(self, factor: f64) -> $angle {
+                $angle(self.0 * factor)
+            }
+        }
+
+        impl Div<f64> for $angle {
+            type Output = $angle;
+
+            fn div(self, divisor: f64) -> $angle {
+                $angle(self.0 / divisor)
+            }
+        }
+    };
+}
+
+impl_angle_ops!(Rad);
+impl_angle_ops!(Deg);
+
+impl fmt::Display for Rad {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)?;
+        f.write_str(" rad")
+    }
+}
+
+impl fmt::Display for Deg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)?;
+        f.write_str("°")
+    }
+}
+
+#[cfg(test)]
+mod angle_tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+    use rand::Rng;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn conversions_are_reversible() {
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..500 {
+            let deg = Deg(rng.gen_range(-720.0..720.0));
+            assert_approx_eq!(deg.0, Deg::from(Rad::from(deg)).0, 1e-10);
+        }
+        assert_eq!(Rad(PI), Deg(180.).into());
+        assert_eq!(Rad(1.), 1.0.into());
+        assert_eq!(1., f64::from(Rad(1.)));
+    }
+
+    #[test]
+    fn arithmetic_keeps_units() {
+        assert_eq!(Deg(30.), Deg(10.) + Deg(20.));
+        assert_eq!(Deg(-10.), Deg(10.) - Deg(20.));
+        assert_eq!(Rad(-1.), -Rad(1.));
+        assert_eq!(Rad(3.), Rad(1.5) * 2.);
+        assert_eq!(Deg(45.), Deg(90.) / 2.);
+    }
+
+    #[test]
+    fn display_shows_units() {
+        assert_eq!("1.5 rad", Rad(1.5).to_string());
+        assert_eq!("132.50°", format!("{:.2}", Deg(132.5)));
+    }
+}
@@ -0,0 +1,161 @@
+use super::math::lerp;
+
+/// Contrast threshold of the human eye used to define meteorological
+/// visibility: an object is no longer visible below 2% of contrast.
+pub const CONTRAST_THRESHOLD: f64 = 0.02;
+
+/// Simple homogeneous atmosphere, attenuating light exponentially with
+/// distance (Beer-Lambert law).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Atmosphere {
+    extinction: f64,
+}
+
+impl Atmosphere {
+    /// Atmosphere with the given per-meter extinction coefficient.
+    pub fn new(extinction: f64) -> Atmosphere {
+        assert!(extinction >= 0., "extinction must be non-negative");
+        Atmosphere { extinction }
+    }
+
+    /// Atmosphere with the given meteorological visibility in kilometers,
+    /// using Koschmieder's relation.
+    pub fn from_visibility_km(visibility_km: f64) -> Atmosphere {
+        assert!(visibility_km > 0., "visibility must be positive");
+        Atmosphere::new(-CONTRAST_THRESHOLD.ln() / (visibility_km * 1000.))
+    }
+
+    /// Extinction coefficient, per meter.
+    pub fn extinction(self) -> f64 {
+        self.extinction
+    }
+
+    /// Meteorological visibility, in kilometers.
+    pub fn visibility_km(self) -> f64 {
+        -CONTRAST_THRESHOLD.ln() / self.extinction / 1000.
+    }
+
+    /// Fraction (in `[0, 1]`) of the light of an object at the given distance
+    /// which reaches the observer.
+    pub fn transmittance(self, dist_in_meters: f64) -> f64 {
+        (-self.extinction * dist_in_meters).exp()
+    }
+
+    /// Whether an object at the given distance is realistically visible.
+    pub fn is_visible(self, dist_in_meters: f64) -> bool {
+        self.transmittance(dist_in_meters) >= CONTRAST_THRESHOLD
+    }
+}
+
+/// Sky colors (as linear RGB in `[0, 1]`) at the horizon and at the zenith
+/// for some elevations of the sun, in degrees, sorted by elevation.
+const SKY_PALETTE: [(f64, [f64; 3], [f64; 3]); 5] = [
+    (-18., [0.02, 0.03, 0.06], [0.01, 0.01, 0.03]),
+    (-6., [0.25, 0.22, 0.35], [0.05, 0.07, 0.18]),
+    (0., [0.95, 0.55, 0.30], [0.20, 0.30, 0.55]),
+    (6., [0.95, 0.80, 0.60], [0.30, 0.50, 0.80]),
+    (20., [0.75, 0.85, 0.95], [0.25, 0.50, 0.90]),
+];
+
+/// Colors of the sky at the horizon and at the zenith (as linear RGB in
+/// `[0, 1]`) for the given elevation of the sun (in radians), from night
+/// through twilight and golden hour to full day.
+///
+/// The horizon color is also the color haze takes at that time of the day.
+pub fn sky_colors(sun_elevation: f64) -> ([f64; 3], [f64; 3]) {
+    let e = sun_elevation.to_degrees();
+    let first = SKY_PALETTE[0];
+    let last = SKY_PALETTE[SKY_PALETTE.len() - 1];
+    if e <= first.0 {
+        return (first.1, first.2);
+    }
+    if e >= last.0 {
+        return (last.1, last.2);
+    }
+
+    let i = SKY_PALETTE.iter().rposition(|p| p.0 <= e).unwrap();
+    let (e0, horizon0, zenith0) = SKY_PALETTE[i];
+    let (e1, horizon1, zenith1) = SKY_PALETTE[i + 1];
+    let v = (e - e0) / (e1 - e0);
+    let mix = |c0: [f64; 3], c1: [f64; 3]| [0, 1, 2].map(|j| lerp(v, c0[j]..=c1[j]));
+    (mix(horizon0, horizon1), mix(zenith0, zenith1))
+}
+
+#[cfg(test)]
+mod atmosphere_tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+    use rand::Rng;
+
+    #[test]
+    fn from_visibility_km_is_correct_for_known_values() {
+        let a = Atmosphere::from_visibility_km(100.);
+        assert_approx_eq!(3.912e-5, a.extinction(), 1e-8);
+        assert_approx_eq!(100., a.visibility_km(), 1e-10);
+    }
+
+    #[test]
+    fn transmittance_is_threshold_at_visibility() {
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..500 {
+            let v = rng.gen::<f64>() * 300. + 1.;
+            let a = Atmosphere::from_visibility_km(v);
+            assert_approx_eq!(1., a.transmittance(0.), 1e-10);
+            assert_approx_eq!(CONTRAST_THRESHOLD, a.transmittance(v * 1000.), 1e-10);
+        }
+    }
+
+    #[test]
+    fn transmittance_decreases_with_distance() {
+        let mut rng = rand::thread_rng();
+        let a = Atmosphere::from_visibility_km(50.);
+
+        for _ in 0..500 {
+            let d = rng.gen::<f64>() * 1e5;
+            let t = a.transmittance(d);
+            assert!((0.0..=1.0).contains(&t));
+            assert!(a.transmittance(d + 1.) < t);
+        }
+    }
+
+    #[test]
+    fn is_visible_up_to_visibility() {
+        let a = Atmosphere::from_visibility_km(80.);
+        assert!(a.is_visible(79_000.));
+        assert!(!a.is_visible(81_000.));
+        assert!(Atmosphere::new(0.).is_visible(1e9));
+    }
+
+    #[test]
+    fn sky_colors_match_palette_at_key_elevations() {
+        for (deg, horizon, zenith) in SKY_PALETTE {
+            let (h, z) = sky_colors(deg.to_radians());
+            for j in 0..3 {
+                assert_approx_eq!(horizon[j], h[j], 1e-10);
+                assert_approx_eq!(zenith[j], z[j], 1e-10);
+            }
+        }
+    }
+
+    #[test]
+    fn sky_colors_are_clamped_outside_palette() {
+        assert_eq!(sky_colors(-1.5), sky_colors((-18_f64).to_radians()));
+        assert_eq!(sky_colors(1.5), sky_colors(20_f64.to_radians()));
+    }
+
+    #[test]
+    fn sky_colors_are_in_range_and_brighter_by_day() {
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..500 {
+            let e = rng.gen_range(-1.5..1.5);
+            let (h, z) = sky_colors(e);
+            assert!(h.iter().chain(z.iter()).all(|c| (0.0..=1.0).contains(c)));
+        }
+
+        let (_, night) = sky_colors((-20_f64).to_radians());
+        let (_, day) = sky_colors(30_f64.to_radians());
+        assert!(night.iter().sum::<f64>() < day.iter().sum::<f64>());
+    }
+}
@@ -0,0 +1,876 @@
+use std::{
+    f64::consts::{FRAC_PI_4, TAU},
+    fmt,
+    ops::{Add, AddAssign, Sub, SubAssign},
+    str::FromStr,
+};
+
+use thiserror::Error;
+
+use crate::{
+    angle::{Deg, Rad},
+    math::angular_distance,
+};
+
+/// Errors on angles.
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum AngleError {
+    #[error("azimuth {0} is not canonical, i.e. in [0, 2π)")]
+    NotCanonical(f64),
+    #[error("invalid azimuth: {0:?}")]
+    Invalid(String),
+    #[error("azimuth {0} is not finite")]
+    NotFinite(f64),
+}
+
+pub fn is_canonical(azimuth: impl Into<Rad>) -> bool {
+    (0.0..TAU).contains(&azimuth.into().0)
+}
+
+pub fn canonicalize(azimuth: impl Into<Rad>) -> f64 {
+    let canonical = azimuth.into().0.rem_euclid(TAU);
+    // rem_euclid rounds tiny negative values up to exactly 2π
+    if canonical == TAU {
+        0.
+    } else {
+        canonical
+    }
+}
+
+/// Number of points of a compass rose.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CompassResolution {
+    /// N, NE, E, …
+    #[default]
+    Eight,
+    /// N, NNE, NE, ENE, E, …
+    Sixteen,
+    /// N, NbE, NNE, NEbN, NE, …
+    ThirtyTwo,
+}
+
+impl CompassResolution {
+    pub fn points(self) -> usize {
+        match self {
+            CompassResolution::Eight => 8,
+            CompassResolution::Sixteen => 16,
+            CompassResolution::ThirtyTwo => 32,
+        }
+    }
+}
+
+/// Signed angle to turn from `heading` to face `target`, in `[-π, π[`.
+///
+/// Positive values are to starboard (clockwise, to the right), negative ones
+/// to port (counterclockwise, to the left).
+pub fn relative_bearing(heading: impl Into<Rad>, target: impl Into<Rad>) -> f64 {
+    angular_distance(heading, target)
+}
+
+/// Direction in which to turn to face a target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Counterclockwise, toward port.
+    Left,
+    /// Clockwise, toward starboard.
+    Right,
+}
+
+/// Direction and (non-negative) angle of the shortest turn from the `current`
+/// azimuth to the `target` one.
+pub fn turn_to(current: impl Into<Rad>, target: impl Into<Rad>) -> (Direction, f64) {
+    let d = angular_distance(current, target);
+    if d < 0. {
+        (Direction::Left, -d)
+    } else {
+        (Direction::Right, d)
+    }
+}
+
+/// Check whether `azimuth` lies in the sector of angular `width` centered on
+/// `center`, correctly handling sectors which cross north.
+///
+/// A width of 2π or more covers every azimuth.
+pub fn in_sector(azimuth: impl Into<Rad>, center: impl Into<Rad>, width: impl Into<Rad>) -> bool {
+    let width = width.into().0;
+    width >= TAU || angular_distance(center, azimuth).abs() <= width / 2.
+}
+
+/// Angular units in which an azimuth can be formatted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AngleUnit {
+    /// Decimal degrees, e.g. `132.5°`.
+    Degrees,
+    /// Degrees, minutes and seconds, e.g. `132°30′00″`.
+    DegreesMinutesSeconds,
+    /// Gradians (400 per turn), e.g. `147.2 gon`.
+    Gradians,
+    /// NATO mils (6400 per turn), e.g. `2356 mil`.
+    Mils,
+}
+
+impl AngleUnit {
+    fn per_turn(self) -> f64 {
+        match self {
+            AngleUnit::Degrees | AngleUnit::DegreesMinutesSeconds => 360.,
+            AngleUnit::Gradians => 400.,
+            AngleUnit::Mils => 6400.,
+        }
+    }
+}
+
+/// Format an azimuth (in radians) in the given unit, rounded to `precision`
+/// decimals (of the seconds, for degrees-minutes-seconds).
+pub fn format(azimuth: impl Into<Rad>, unit: AngleUnit, precision: usize) -> String {
+    // rounding is done in the smallest displayed unit so that values close
+    // to a full turn wrap to 0 instead of being displayed as 360°
+    let smallest_per_turn = match unit {
+        AngleUnit::DegreesMinutesSeconds => 360. * 3600.,
+        _ => unit.per_turn(),
+    };
+    let scale = 10_f64.powi(precision as i32);
+    let mut v = (canonicalize(azimuth) / TAU * smallest_per_turn * scale).round() / scale;
+    if v >= smallest_per_turn {
+        v -= smallest_per_turn;
+    }
+
+    match unit {
+        AngleUnit::Degrees => format!("{:.*}°", precision, v),
+        AngleUnit::Gradians => format!("{:.*} gon", precision, v),
+        AngleUnit::Mils => format!("{:.*} mil", precision, v),
+        AngleUnit::DegreesMinutesSeconds => format_seconds_as_dms(v, precision),
+    }
+}
+
+/// Format a non-negative angle given in seconds of arc, already rounded to
+/// `precision` decimals, as degrees, minutes and seconds.
+pub(crate) fn format_seconds_as_dms(v: f64, precision: usize) -> String {
+    let d = (v / 3600.).floor();
+    let m = ((v - d * 3600.) / 60.).floor();
+    let s = v - d * 3600. - m * 60.;
+    let width = if precision > 0 { precision + 3 } else { 2 };
+    format!("{}°{:02}′{:0w$.p$}″", d, m, s, w = width, p = precision)
+}
+
+/// An azimuth in radians which is canonical (in `[0, 2π[`) by construction.
+///
+/// Every arithmetic operation re-canonicalizes its result, so code holding an
+/// `Azimuth` never has to check `is_canonical` again.
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "f64", into = "f64")
+)]
+pub struct Azimuth(f64);
+
+impl Azimuth {
+    pub const NORTH: Azimuth = Azimuth(0.);
+    pub const EAST: Azimuth = Azimuth(TAU / 4.);
+    pub const SOUTH: Azimuth = Azimuth(TAU / 2.);
+    pub const WEST: Azimuth = Azimuth(3. * TAU / 4.);
+
+    /// Create an azimuth from any finite angle, in radians unless given as
+    /// [`Deg`](crate::angle::Deg).
+    ///
+    /// # Panics
+    ///
+    /// If the angle is not finite, see [`try_new`](Azimuth::try_new) for
+    /// angles which are not known to be.
+    pub fn new(angle: impl Into<Rad>) -> Azimuth {
+        Azimuth::try_new(angle).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Create an azimuth from an angle like [`new`](Azimuth::new), failing
+    /// if it is not finite.
+    pub fn try_new(angle: impl Into<Rad>) -> Result<Azimuth, AngleError> {
+        let rad = angle.into().0;
+        if rad.is_finite() {
+            Ok(Azimuth(canonicalize(rad)))
+        } else {
+            Err(AngleError::NotFinite(rad))
+        }
+    }
+
+    /// Create an azimuth from any finite angle in degrees.
+    ///
+    /// # Panics
+    ///
+    /// If the angle is not finite.
+    pub fn from_degrees(deg: f64) -> Azimuth {
+        Azimuth::new(deg.to_radians())
+    }
+
+    /// Create an azimuth from a mathematical angle (counterclockwise from east).
+    pub fn from_math(rad: f64) -> Azimuth {
+        Azimuth::new(rad).to_math()
+    }
+
+    pub fn radians(self) -> f64 {
+        self.0
+    }
+
+    pub fn degrees(self) -> f64 {
+        self.0.to_degrees()
+    }
+
+    /// The equivalent mathematical angle, measured counterclockwise.
+    pub fn to_math(self) -> Azimuth {
+        Azimuth::new(TAU - self.0)
+    }
+
+    pub fn to_octant_str(self, n: &str, e: &str, s: &str, w: &str) -> String {
+        let inc = FRAC_PI_4;
+        let val = ((self.0 / inc) + 0.5).floor() as i32;
+        let arr: Vec<String> = vec![
+            n.to_string(),
+            format!("{}{}", n, e),
+            e.to_string(),
+            format!("{}{}", s, e),
+            s.to_string(),
+            format!("{}{}", s, w),
+            w.to_string(),
+            format!("{}{}", n, w),
+        ];
+
+        arr.get((val % 8) as usize).unwrap().to_string()
+    }
+
+    /// Name of the nearest point of the compass rose of the given
+    /// resolution, e.g. `"NNE"` for 25° on the 16-wind rose.
+    pub fn to_compass_str(self, resolution: CompassResolution) -> &'static str {
+        let points = resolution.points();
+        let i = ((self.0 / TAU * points as f64) + 0.5).floor() as usize % points;
+        COMPASS_POINTS[i * COMPASS_POINTS.len() / points]
+    }
+}
+
+impl From<Azimuth> for f64 {
+    fn from(azimuth: Azimuth) -> f64 {
+        azimuth.0
+    }
+}
+
+impl From<Azimuth> for Rad {
+    fn from(azimuth: Azimuth) -> Rad {
+        Rad(azimuth.0)
+    }
+}
+
+impl From<Azimuth> for Deg {
+    fn from(azimuth: Azimuth) -> Deg {
+        Deg(azimuth.degrees())
+    }
+}
+
+impl TryFrom<f64> for Azimuth {
+    type Error = AngleError;
+
+    /// Azimuth of the canonical angle `rad`, failing for any other angle.
+    fn try_from(rad: f64) -> Result<Azimuth, AngleError> {
+        if is_canonical(rad) {
+            Ok(Azimuth(rad))
+        } else {
+            Err(AngleError::NotCanonical(rad))
+        }
+    }
+}
+
+impl<A: Into<Rad>> Add<A> for Azimuth {
+    type Output = Azimuth;
+
+    fn add(self, angle: A) -> Azimuth {
+        Azimuth::new(self.0 + angle.into().0)
+    }
+}
+
+impl<A: Into<Rad>> Sub<A> for Azimuth {
+    type Output = Azimuth;
+
+    fn sub(self, angle: A) -> Azimuth {
+        Azimuth::new(self.0 - angle.into().0)
+    }
+}
+
+impl<A: Into<Rad>> AddAssign<A> for Azimuth {
+    fn add_assign(&mut self, angle: A) {
+        *self = *self + angle;
+    }
+}
+
+impl<A: Into<Rad>> SubAssign<A> for Azimuth {
+    fn sub_assign(&mut self, angle: A) {
+        *self = *self - angle;
+    }
+}
+
+impl FromStr for Azimuth {
+    type Err = AngleError;
+
+    fn from_str(s: &str) -> Result<Azimuth, AngleError> {
+        parse(s).map(Azimuth)
+    }
+}
+
+impl fmt::Display for Azimuth {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}°", self.degrees())
+    }
+}
+
+/// Parse a human-style bearing into a canonical azimuth (in radians).
+///
+/// Three notations are accepted, case-insensitively:
+/// - compass points of the 16-wind rose, e.g. `"N"`, `"NNE"` or `"SW"`,
+/// - quadrant bearings, e.g. `"N45°E"` or `"S30W"`, where the angle (between
+///   0° and 90°) is measured from north or south toward east or west,
+/// - decimal degrees with an optional degree sign, e.g. `"132.5°"`.
+pub fn parse(s: &str) -> Result<f64, AngleError> {
+    let invalid = || AngleError::Invalid(s.to_string());
+    let s = s.trim().to_uppercase();
+
+    if let Some(i) = COMPASS_POINTS.iter().step_by(2).position(|p| *p == s) {
+        return Ok(i as f64 * TAU / 16.);
+    }
+
+    if let Some(azimuth) = parse_quadrant(&s) {
+        return Ok(canonicalize(azimuth));
+    }
+
+    let deg = parse_degrees(&s).ok_or_else(invalid)?;
+    Ok(canonicalize(deg.to_radians()))
+}
+
+/// Points of the 32-wind rose, of which every second one is a point of the
+/// 16-wind rose and every fourth one a point of the 8-wind rose.
+const COMPASS_POINTS: [&str; 32] = [
+    "N", "NbE", "NNE", "NEbN", "NE", "NEbE", "ENE", "EbN", "E", "EbS", "ESE", "SEbE", "SE", "SEbS",
+    "SSE", "SbE", "S", "SbW", "SSW", "SWbS", "SW", "SWbW", "WSW", "WbS", "W", "WbN", "WNW", "NWbW",
+    "NW", "NWbN", "NNW", "NbW",
+];
+
+fn parse_quadrant(s: &str) -> Option<f64> {
+    let mut chars = s.chars();
+    let from = chars.next()?;
+    let toward = chars.next_back()?;
+    let deg = parse_degrees(chars.as_str())?;
+
+    if !(0.0..=90.0).contains(&deg) {
+        return None;
+    }
+
+    let azimuth_deg = match (from, toward) {
+        ('N', 'E') => deg,
+        ('S', 'E') => 180. - deg,
+        ('S', 'W') => 180. + deg,
+        ('N', 'W') => 360. - deg,
+        _ => return None,
+    };
+
+    Some(azimuth_deg.to_radians())
+}
+
+fn parse_degrees(s: &str) -> Option<f64> {
+    let deg: f64 = s.strip_suffix('°').unwrap_or(s).parse().ok()?;
+    if deg.is_finite() {
+        Some(deg)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod azimuth_tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+    use rand::Rng;
+    use std::f64::consts::{FRAC_PI_2, PI};
+
+    /// didn't want to use nightly so I just copied the rust code from
+    /// https://doc.rust-lang.org/src/core/num/f64.rs.html#769
+    fn next_down(v: f64) -> f64 {
+        // We must use strictly integer arithmetic to prevent denormals from
+        // flushing to zero after an arithmetic operation on some platforms.
+        const NEG_TINY_BITS: u64 = 0x8000_0000_0000_0001; // Smallest (in magnitude) negative f64.
+        const CLEAR_SIGN_MASK: u64 = 0x7fff_ffff_ffff_ffff;
+
+        let bits = v.to_bits();
+        if v.is_nan() || bits == f64::NEG_INFINITY.to_bits() {
+            return v;
+        }
+
+        let abs = bits & CLEAR_SIGN_MASK;
+        let next_bits = if abs == 0 {
+            NEG_TINY_BITS
+        } else if bits == abs {
+            bits - 1
+        } else {
+            bits + 1
+        };
+
+        f64::from_bits(next_bits)
+    }
+
+    #[test]
+    fn is_canonical_true_for_0() {
+        assert!(is_canonical(0.));
+    }
+
+    #[test]
+    fn is_canonical_false_for_0_pred() {
+        assert!(!(is_canonical(next_down(0.0))));
+    }
+
+    #[test]
+    fn is_canonical_true_for_2pi_pred() {
+        assert!(is_canonical(next_down(TAU)));
+    }
+
+    #[test]
+    fn is_canonical_false_for_2pi() {
+        assert!(!(is_canonical(TAU)));
+    }
+
+    #[test]
+    fn is_canonical_is_true_for_random_canonical_azimuths() {
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..500 {
+            assert!(is_canonical(rng.gen::<f64>() * TAU));
+        }
+    }
+
+    #[test]
+    fn canonicalize_works_on_rounded_random_angles() {
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..500 {
+            let a_deg = rng.gen_range(-5000..5000);
+            let a_rad = (a_deg as f64).to_radians();
+            let canonical_rad = canonicalize(a_rad);
+            assert!((0.0..TAU).contains(&canonical_rad));
+
+            let mut cannonical_a_deg = canonical_rad.to_degrees().round() as i32;
+            if cannonical_a_deg == 360 {
+                cannonical_a_deg = 0;
+            }
+            assert_eq!(cannonical_a_deg.rem_euclid(360), cannonical_a_deg);
+        }
+    }
+
+    #[test]
+    fn to_math_correctly_handles_0() {
+        assert_approx_eq!(0., Azimuth::NORTH.to_math().radians(), 1e-10);
+    }
+
+    #[test]
+    fn from_math_correctly_handles_0() {
+        assert_approx_eq!(0., Azimuth::from_math(0.).radians(), 1e-10);
+    }
+
+    #[test]
+    fn to_math_works_for_known_values() {
+        let vs: Vec<f64> = vec![0., 0., 90., 270., 180., 180., 270., 90.];
+        for i in (0..vs.len()).step_by(2) {
+            let a = Azimuth::from_degrees(vs[i]).to_math().radians();
+            assert_approx_eq!(vs[i + 1].to_radians(), a, 1e-10);
+        }
+    }
+
+    #[test]
+    fn from_math_works_for_known_values() {
+        let vs: Vec<f64> = vec![0., 0., 90., 270., 180., 180., 270., 90.];
+        for i in (0..vs.len()).step_by(2) {
+            let a = Azimuth::from_math(vs[i].to_radians()).radians();
+            assert_approx_eq!(vs[i + 1].to_radians(), a, 1e-10);
+        }
+    }
+
+    #[test]
+    fn azimuth_is_never_2pi() {
+        assert!(Azimuth::try_from(TAU).is_err());
+        assert!(Azimuth::try_from(-1.).is_err());
+        assert_eq!(Azimuth::NORTH, Azimuth::new(TAU));
+        assert_eq!(Azimuth::NORTH, Azimuth::from_math(TAU));
+        assert_eq!(Azimuth::NORTH, Azimuth::NORTH.to_math());
+    }
+
+    #[test]
+    fn to_octant_str_correctly_cycle_through_values() {
+        let n = "north";
+        let e = "east";
+        let s = "south";
+        let w = "west";
+        let expected: Vec<String> = vec![
+            n.to_string(),
+            format!("{}{}", n, e),
+            e.to_string(),
+            format!("{}{}", s, e),
+            s.to_string(),
+            format!("{}{}", s, w),
+            w.to_string(),
+            format!("{}{}", n, w),
+        ];
+
+        for deg in 0..360 {
+            let i = ((deg as f64 + 22.5) / 45.).floor() as usize % 8;
+            let actual = Azimuth::from_degrees(deg as f64).to_octant_str(n, e, s, w);
+            assert_eq!(expected[i], actual);
+        }
+    }
+
+    #[test]
+    fn to_compass_str_keeps_octant_behavior() {
+        for deg in 0..360 {
+            let a = Azimuth::from_degrees(deg as f64);
+            assert_eq!(
+                a.to_octant_str("N", "E", "S", "W"),
+                a.to_compass_str(CompassResolution::Eight)
+            );
+        }
+    }
+
+    #[test]
+    fn to_compass_str_is_inverse_of_parse() {
+        for p in COMPASS_POINTS.iter().step_by(2) {
+            let a = Azimuth(parse(p).unwrap());
+            assert_eq!(*p, a.to_compass_str(CompassResolution::Sixteen));
+        }
+    }
+
+    #[test]
+    fn to_compass_str_is_correct_on_32_wind_rose() {
+        let data = [
+            (0., "N"),
+            (5.6, "N"),
+            (5.7, "NbE"),
+            (11.25, "NbE"),
+            (22.5, "NNE"),
+            (101.25, "EbS"),
+            (348.75, "NbW"),
+            (354.3, "NbW"),
+            (354.4, "N"),
+        ];
+        for (deg, expected) in data {
+            let a = Azimuth::from_degrees(deg);
+            assert_eq!(expected, a.to_compass_str(CompassResolution::ThirtyTwo));
+        }
+        let a = Azimuth::from_degrees(25.);
+        assert_eq!("NNE", a.to_compass_str(CompassResolution::Sixteen));
+        assert_eq!("NE", a.to_compass_str(CompassResolution::default()));
+    }
+
+    #[test]
+    fn parse_works_on_compass_points() {
+        let points = [
+            "N", "NNE", "NE", "ENE", "E", "ESE", "SE", "SSE", "S", "SSW", "SW", "WSW", "W", "WNW",
+            "NW", "NNW",
+        ];
+        for (i, p) in points.iter().enumerate() {
+            assert_approx_eq!((i as f64 * 22.5).to_radians(), parse(p).unwrap(), 1e-10);
+        }
+        assert_approx_eq!(FRAC_PI_4, parse(" ne ").unwrap(), 1e-10);
+    }
+
+    #[test]
+    fn parse_works_on_quadrant_bearings() {
+        let data: Vec<(&str, f64)> = vec![
+            ("N45°E", 45.),
+            ("S30°E", 150.),
+            ("S30°W", 210.),
+            ("N10W", 350.),
+            ("N0°E", 0.),
+            ("s12.5°w", 192.5),
+        ];
+        for (s, expected) in data {
+            assert_approx_eq!(expected.to_radians(), parse(s).unwrap(), 1e-10);
+        }
+    }
+
+    #[test]
+    fn parse_works_on_degrees() {
+        assert_approx_eq!(132.5_f64.to_radians(), parse("132.5°").unwrap(), 1e-10);
+        assert_approx_eq!(90_f64.to_radians(), parse("90").unwrap(), 1e-10);
+        assert_approx_eq!(0., parse("360°").unwrap(), 1e-10);
+        assert_approx_eq!(270_f64.to_radians(), parse("-90°").unwrap(), 1e-10);
+    }
+
+    #[test]
+    fn parse_result_is_canonical() {
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..500 {
+            let deg = rng.gen_range(-5000.0..5000.0);
+            assert!(is_canonical(parse(&format!("{}°", deg)).unwrap()));
+        }
+    }
+
+    #[test]
+    fn parse_error_for_invalid_bearings() {
+        for s in [
+            "", "X", "NNNE", "N95°E", "E45°N", "N-10°E", "12..5°", "NaN", "inf°",
+        ] {
+            assert!(parse(s).is_err(), "{} should not parse", s);
+        }
+    }
+
+    #[test]
+    fn canonicalize_never_returns_2pi() {
+        assert!(is_canonical(canonicalize(-1e-20)));
+        assert!(is_canonical(canonicalize(next_down(0.))));
+    }
+
+    #[test]
+    fn azimuth_is_canonical_on_random_angles() {
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..500 {
+            let a = Azimuth::new(rng.gen_range(-100.0..100.0));
+            assert!(is_canonical(a.radians()));
+        }
+    }
+
+    #[test]
+    fn try_new_fails_on_angles_which_are_not_finite() {
+        assert_eq!(Ok(Azimuth::WEST), Azimuth::try_new(-FRAC_PI_2));
+        assert_eq!(Ok(Azimuth::EAST), Azimuth::try_new(Deg(450.)));
+        for angle in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert!(matches!(
+                Azimuth::try_new(angle),
+                Err(AngleError::NotFinite(_))
+            ));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "not finite")]
+    fn new_panics_on_nan_in_every_build() {
+        Azimuth::new(f64::NAN);
+    }
+
+    #[test]
+    fn azimuth_arithmetic_stays_canonical() {
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..500 {
+            let a = Azimuth::new(rng.gen::<f64>() * TAU);
+            let d = rng.gen_range(-100.0..100.0);
+            assert!(is_canonical((a + d).radians()));
+            assert!(is_canonical((a - d).radians()));
+
+            let mut b = a;
+            b += d;
+            b -= d;
+            assert_approx_eq!(0., angular_distance(a.radians(), b.radians()), 1e-10);
+        }
+    }
+
+    #[test]
+    fn azimuth_wraps_around_north() {
+        assert_approx_eq!(
+            350.,
+            (Azimuth::from_degrees(10.) - 20_f64.to_radians()).degrees(),
+            1e-10
+        );
+        assert_approx_eq!(
+            10.,
+            (Azimuth::from_degrees(350.) + 20_f64.to_radians()).degrees(),
+            1e-10
+        );
+        assert_eq!(Azimuth::NORTH, Azimuth::new(TAU));
+    }
+
+    #[test]
+    fn azimuth_to_math_and_from_math_are_reversible() {
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..500 {
+            let a = Azimuth::new(rng.gen::<f64>() * TAU);
+            assert_approx_eq!(
+                a.radians(),
+                Azimuth::from_math(a.to_math().radians()).radians(),
+                1e-10
+            );
+        }
+        assert_approx_eq!(
+            Azimuth::WEST.radians(),
+            Azimuth::EAST.to_math().radians(),
+            1e-10
+        );
+    }
+
+    #[test]
+    fn azimuth_from_str_uses_parse() {
+        assert_eq!(Ok(Azimuth::EAST), "E".parse::<Azimuth>());
+        assert_eq!(
+            Err(AngleError::Invalid("nowhere".to_string())),
+            "nowhere".parse::<Azimuth>()
+        );
+        assert_eq!(
+            "invalid azimuth: \"nowhere\"",
+            "nowhere".parse::<Azimuth>().unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn relative_bearing_is_correct_on_known_values() {
+        let data: Vec<f64> = vec![
+            0., 23., 23., 23., 0., -23., 350., 10., 20., 10., 350., -20., 90., 300., -150.,
+        ];
+
+        for i in (0..data.len()).step_by(3) {
+            let heading = data[i].to_radians();
+            let target = data[i + 1].to_radians();
+            let expected = data[i + 2].to_radians();
+            assert_approx_eq!(expected, relative_bearing(heading, target), 1e-10);
+        }
+    }
+
+    #[test]
+    fn relative_bearing_turns_heading_into_target() {
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..500 {
+            let heading = rng.gen::<f64>() * TAU;
+            let target = rng.gen::<f64>() * TAU;
+            let b = relative_bearing(heading, target);
+            assert!((-PI..PI).contains(&b));
+            assert_approx_eq!(0., angular_distance(target, heading + b), 1e-10);
+        }
+    }
+
+    #[test]
+    fn in_sector_handles_sectors_crossing_north() {
+        let center = 350_f64.to_radians();
+        let width = 60_f64.to_radians();
+        assert!(in_sector(10_f64.to_radians(), center, width));
+        assert!(in_sector(325_f64.to_radians(), center, width));
+        assert!(in_sector(0., center, width));
+        assert!(!in_sector(30_f64.to_radians(), center, width));
+        assert!(!in_sector(300_f64.to_radians(), center, width));
+    }
+
+    #[test]
+    fn angle_functions_accept_degrees() {
+        assert!(in_sector(Deg(10.), Deg(350.), Deg(60.)));
+        assert!(!in_sector(Deg(30.), Deg(350.), Deg(60.)));
+        assert_eq!(
+            "90°00′00″",
+            format(Deg(90.), AngleUnit::DegreesMinutesSeconds, 0)
+        );
+        let (dir, a) = turn_to(Deg(10.), Deg(347.));
+        assert_eq!(Direction::Left, dir);
+        assert_approx_eq!(23_f64.to_radians(), a, 1e-10);
+    }
+
+    #[test]
+    fn azimuth_accepts_typed_angles() {
+        assert_eq!(Azimuth::EAST, Azimuth::new(Deg(90.)));
+        assert_eq!(Azimuth::EAST, Azimuth::new(Rad(FRAC_PI_2)));
+        assert_approx_eq!(
+            20.,
+            (Azimuth::from_degrees(350.) + Deg(30.)).degrees(),
+            1e-10
+        );
+        let mut a = Azimuth::NORTH;
+        a -= Deg(90.);
+        assert_eq!(Azimuth::WEST, a);
+        assert_eq!(Deg(180.), Deg::from(Azimuth::SOUTH));
+        assert_eq!(Rad(PI), Rad::from(Azimuth::SOUTH));
+    }
+
+    #[test]
+    fn in_sector_is_true_for_full_circle() {
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..500 {
+            let a = rng.gen::<f64>() * TAU;
+            let c = rng.gen::<f64>() * TAU;
+            assert!(in_sector(a, c, TAU));
+        }
+    }
+
+    #[test]
+    fn in_sector_is_true_for_center_and_false_for_opposite() {
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..500 {
+            let c = rng.gen::<f64>() * TAU;
+            let w = rng.gen::<f64>() * PI;
+            assert!(in_sector(c, c, w));
+            assert!(!in_sector(canonicalize(c + PI), c, w));
+        }
+    }
+
+    #[test]
+    fn format_works_for_known_values() {
+        let a = 132.5_f64.to_radians();
+        assert_eq!("132.50°", format(a, AngleUnit::Degrees, 2));
+        assert_eq!("132°30′00″", format(a, AngleUnit::DegreesMinutesSeconds, 0));
+        assert_eq!("147.2 gon", format(a, AngleUnit::Gradians, 1));
+        assert_eq!("2356 mil", format(a, AngleUnit::Mils, 0));
+        assert_eq!(
+            "7°05′03.6″",
+            format(
+                (7. + 5. / 60. + 3.6_f64 / 3600.).to_radians(),
+                AngleUnit::DegreesMinutesSeconds,
+                1
+            )
+        );
+        assert_eq!("1600 mil", format(FRAC_PI_2, AngleUnit::Mils, 0));
+        assert_eq!("100.000 gon", format(FRAC_PI_2, AngleUnit::Gradians, 3));
+    }
+
+    #[test]
+    fn format_wraps_values_rounded_to_a_full_turn() {
+        let a = next_down(TAU);
+        assert_eq!("0°", format(a, AngleUnit::Degrees, 0));
+        assert_eq!(
+            "0°00′00.00″",
+            format(a, AngleUnit::DegreesMinutesSeconds, 2)
+        );
+        assert_eq!("0.0 mil", format(a, AngleUnit::Mils, 1));
+        assert_eq!(
+            "359°",
+            format(359.4_f64.to_radians(), AngleUnit::Degrees, 0)
+        );
+    }
+
+    #[test]
+    fn format_canonicalizes_azimuth() {
+        assert_eq!("270.0°", format(-FRAC_PI_2, AngleUnit::Degrees, 1));
+    }
+
+    #[test]
+    fn turn_to_is_correct_on_known_values() {
+        let (dir, a) = turn_to(10_f64.to_radians(), 347_f64.to_radians());
+        assert_eq!(Direction::Left, dir);
+        assert_approx_eq!(23_f64.to_radians(), a, 1e-10);
+
+        let (dir, a) = turn_to(350_f64.to_radians(), 20_f64.to_radians());
+        assert_eq!(Direction::Right, dir);
+        assert_approx_eq!(30_f64.to_radians(), a, 1e-10);
+
+        let (dir, a) = turn_to(1., 1.);
+        assert_eq!(Direction::Right, dir);
+        assert_approx_eq!(0., a, 1e-10);
+    }
+
+    #[test]
+    fn turn_to_takes_the_shortest_way() {
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..500 {
+            let current = rng.gen::<f64>() * TAU;
+            let target = rng.gen::<f64>() * TAU;
+            let (dir, a) = turn_to(current, target);
+            assert!((0.0..=PI).contains(&a));
+
+            let reached = match dir {
+                Direction::Left => current - a,
+                Direction::Right => current + a,
+            };
+            assert_approx_eq!(0., angular_distance(target, reached), 1e-10);
+        }
+    }
+}
@@ -0,0 +1,541 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use serde::{Deserialize, Deserializer};
+
+use crate::{
+    azimuth::Azimuth,
+    config::{self, DemConfig, ObserverConfig, RenderConfig},
+    dem::{CompositeDem, DiscreteElevationModel, TileCache},
+    error::AlpanoError,
+    interval::{Interval1D, Interval2D},
+    render,
+};
+
+/// Observer of a batch of renders, named to tell its outputs apart, and
+/// looking toward the azimuth of the camera of the batch unless given its
+/// own.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BatchObserver {
+    /// Name of the observer, by default its position in the list from 1.
+    #[serde(default)]
+    pub name: String,
+    /// Latitude in degrees.
+    pub latitude: f64,
+    /// Longitude in degrees.
+    pub longitude: f64,
+    /// Elevation in meters, by default that of the ground plus the eye
+    /// height.
+    #[serde(default)]
+    pub elevation: Option<f64>,
+    /// Height of the eyes above the ground, in meters, by default that of
+    /// the observer of the batch.
+    #[serde(default)]
+    pub eye_height: Option<f64>,
+    #[serde(default, deserialize_with = "deserialize_azimuth")]
+    pub azimuth: Option<Azimuth>,
+}
+
+impl BatchObserver {
+    /// Configuration of the render of this observer: that of `base`, with
+    /// this observer and its azimuth if any, and with the paths of the
+    /// outputs suffixed with its name, like `pano-niesen.png`.
+    pub fn config(&self, base: &RenderConfig) -> RenderConfig {
+        let mut config = base.clone();
+        config.observer = ObserverConfig {
+            elevation: self.elevation,
+            eye_height: self.eye_height.unwrap_or(base.observer.eye_height),
+            ..ObserverConfig::new(self.latitude, self.longitude)
+        };
+        config.camera.azimuth = self.azimuth.unwrap_or(base.camera.azimuth);
+
+        let output = &mut config.output;
+        output.path = suffixed(&output.path, &self.name);
+        for path in [
+            &mut output.tiles,
+            &mut output.depth,
+            &mut output.normals,
+            &mut output.kml,
+            &mut output.panorama,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            *path = suffixed(path, &self.name);
+        }
+        config
+    }
+}
+
+/// Observers of the JSON array `json` of objects with the fields of
+/// [`BatchObserver`], like
+/// `[{"name": "niesen", "latitude": 46.6456, "longitude": 7.6496}]`.
+pub fn parse_observers_json(json: &str) -> io::Result<Vec<BatchObserver>> {
+    let observers =
+        serde_json::from_str(json).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+    named(observers)
+}
+
+/// Observers of the CSV table `csv`, whose first line names its columns
+/// among the fields of [`BatchObserver`], like:
+///
+/// ```text
+/// name,latitude,longitude,elevation,azimuth
+/// niesen,46.6456,7.6496,2362,S
+/// moleson,46.5469,7.0178,,E
+/// ```
+///
+/// Only the latitude and longitude are required, empty values take their
+/// default and values are not quoted.
+pub fn parse_observers_csv(csv: &str) -> io::Result<Vec<BatchObserver>> {
+    let invalid = |message: String| io::Error::new(ErrorKind::InvalidData, message);
+    let mut lines = csv
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty());
+    let columns: Vec<&str> = match lines.next() {
+        Some((_, header)) => header.split(',').map(str::trim).collect(),
+        None => Vec::new(),
+    };
+    let column = |name: &str| columns.iter().position(|&c| c == name);
+    if let Some(unknown) = columns.iter().find(|c| {
+        !matches!(
+            **c,
+            "name" | "latitude" | "longitude" | "elevation" | "eye_height" | "azimuth"
+        )
+    }) {
+        return Err(invalid(format!("unknown column: {}", unknown)));
+    }
+    let (Some(latitude), Some(longitude)) = (column("latitude"), column("longitude")) else {
+        return Err(invalid("missing latitude or longitude column".to_string()));
+    };
+
+    let mut observers = Vec::new();
+    for (i, line) in lines {
+        let values: Vec<&str> = line.split(',').map(str::trim).collect();
+        if values.len() != columns.len() {
+            return Err(invalid(format!(
+                "line {}: expected {} values, found {}",
+                i + 1,
+                columns.len(),
+                values.len()
+            )));
+        }
+        let value = |column: Option<usize>| column.map(|c| values[c]).filter(|v| !v.is_empty());
+        let number = |column: Option<usize>| {
+            value(column)
+                .map(|v| {
+                    v.parse::<f64>()
+                        .map_err(|_| invalid(format!("line {}: invalid number: {}", i + 1, v)))
+                })
+                .transpose()
+        };
+        let required = |column: usize| {
+            number(Some(column))?
+                .ok_or_else(|| invalid(format!("line {}: missing {}", i + 1, columns[column])))
+        };
+        observers.push(BatchObserver {
+            name: value(column("name")).unwrap_or_default().to_string(),
+            latitude: required(latitude)?,
+            longitude: required(longitude)?,
+            elevation: number(column("elevation"))?,
+            eye_height: number(column("eye_height"))?,
+            azimuth: value(column("azimuth"))
+                .map(|v| {
+                    v.parse()
+                        .map_err(|e| invalid(format!("line {}: {}", i + 1, e)))
+                })
+                .transpose()?,
+        });
+    }
+    named(observers)
+}
+
+/// Observers of the file at the given path, a JSON array if its extension
+/// is `json` and a CSV table otherwise, see [`parse_observers_json`] and
+/// [`parse_observers_csv`].
+pub fn load_observers(path: impl AsRef<Path>) -> io::Result<Vec<BatchObserver>> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path)?;
+    if path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("json"))
+    {
+        parse_observers_json(&text)
+    } else {
+        parse_observers_csv(&text)
+    }
+}
+
+/// Tiles of a DEM directory loaded once for a batch of renders, from which
+/// the DEM of each of them is assembled.
+pub struct BatchTiles {
+    tiles: HashMap<(i32, i32), Arc<dyn DiscreteElevationModel>>,
+}
+
+impl BatchTiles {
+    /// Load the tiles of the directory of `dem` covering every point within
+    /// the maximum distance of the observers of `configs`, failing if any of
+    /// them is missing.
+    ///
+    /// With a memory budget, the tiles are only checked and then read in a
+    /// [`TileCache`] shared by all the renders, see [`render::tile_cache`].
+    pub fn load(dem: &DemConfig, configs: &[RenderConfig]) -> Result<BatchTiles, AlpanoError> {
+        let mut corners = Vec::new();
+        for config in configs {
+            let position = config.observer.position()?;
+            for tile in render::tiles_around(position, config.camera.max_distance.meters()) {
+                if !corners.contains(&tile) {
+                    corners.push(tile);
+                }
+            }
+        }
+
+        let mut tiles: HashMap<(i32, i32), Arc<dyn DiscreteElevationModel>> = HashMap::new();
+        match dem.memory_budget_bytes() {
+            Some(budget) if !corners.is_empty() => {
+                let cache = Arc::new(render::tile_cache(dem, &corners, budget)?);
+                for (south, west) in corners {
+                    let cache = Arc::clone(&cache);
+                    tiles.insert((south, west), Arc::new(CachedTile { cache, south, west }));
+                }
+            }
+            _ => {
+                for (south, west) in corners {
+                    tiles.insert(
+                        (south, west),
+                        Arc::from(render::load_tile(dem, south, west)?),
+                    );
+                }
+            }
+        }
+        Ok(BatchTiles { tiles })
+    }
+
+    pub fn tile_count(&self) -> usize {
+        self.tiles.len()
+    }
+
+    /// DEM of the tiles covering the panorama of `config`, which must be one
+    /// of those the tiles were loaded for.
+    pub fn dem(&self, config: &RenderConfig) -> Result<CompositeDem, AlpanoError> {
+        let position = config.observer.position()?;
+        let mut dems: Vec<Box<dyn DiscreteElevationModel>> = Vec::new();
+        for tile in render::tiles_around(position, config.camera.max_distance.meters()) {
+            let dem = self.tiles.get(&tile).ok_or_else(|| {
+                io::Error::new(
+                    ErrorKind::NotFound,
+                    format!(
+                        "tile {} not loaded",
+                        config.dem.format.file_name(tile.0, tile.1)
+                    ),
+                )
+            })?;
+            dems.push(Box::new(dem.clone()));
+        }
+        Ok(CompositeDem::new(dems)?)
+    }
+}
+
+/// Tile of a [`TileCache`] shared by the renders of a batch, whose
+/// south-west corner is at the given latitude and longitude, in degrees.
+struct CachedTile {
+    cache: Arc<TileCache>,
+    south: i32,
+    west: i32,
+}
+
+impl DiscreteElevationModel for CachedTile {
+    fn samples_per_degree(&self) -> u32 {
+        self.cache.samples_per_degree()
+    }
+
+    fn extent(&self) -> Interval2D {
+        let spd = self.cache.samples_per_degree() as i32;
+        Interval2D::new(
+            Interval1D::new(self.west * spd, (self.west + 1) * spd),
+            Interval1D::new(self.south * spd, (self.south + 1) * spd),
+        )
+    }
+
+    fn elevation_sample(&self, x: i32, y: i32) -> f64 {
+        // samples on the boundary of two tiles are read from this one, the
+        // other one may not exist
+        self.cache
+            .tile(self.south, self.west)
+            .elevation_sample(x, y)
+    }
+}
+
+/// `observers`, named after their position in the list from 1 when
+/// unnamed, failing if there are none or if two of them would write the same
+/// outputs, their names being the same once [`sanitized`].
+fn named(mut observers: Vec<BatchObserver>) -> io::Result<Vec<BatchObserver>> {
+    if observers.is_empty() {
+        return Err(io::Error::new(ErrorKind::InvalidData, "no observers"));
+    }
+    for (i, observer) in observers.iter_mut().enumerate() {
+        if observer.name.is_empty() {
+            observer.name = (i + 1).to_string();
+        }
+    }
+    let mut names = HashMap::new();
+    for observer in &observers {
+        if let Some(other) = names.insert(sanitized(&observer.name), &observer.name) {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("duplicate observer name: {} and {}", other, observer.name),
+            ));
+        }
+    }
+    Ok(observers)
+}
+
+/// `name` with its characters other than letters, digits, `-` and `_`
+/// replaced by `_`.
+fn sanitized(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            c if c.is_alphanumeric() || c == '-' || c == '_' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+/// `path` with the [`sanitized`] `name` appended to its file stem.
+fn suffixed(path: &Path, name: &str) -> PathBuf {
+    let name = sanitized(name);
+    let mut file_name = path.file_stem().unwrap_or_default().to_os_string();
+    file_name.push("-");
+    file_name.push(name);
+    if let Some(extension) = path.extension() {
+        file_name.push(".");
+        file_name.push(extension);
+    }
+    path.with_file_name(file_name)
+}
+
+fn deserialize_azimuth<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Azimuth>, D::Error> {
+    config::deserialize_azimuth(deserializer).map(Some)
+}
+
+#[cfg(test)]
+mod batch_tests {
+    use super::*;
+    use crate::{config::CameraConfig, dem::HgtDem};
+
+    fn base() -> RenderConfig {
+        let mut config = RenderConfig {
+            observer: ObserverConfig::new(0., 0.),
+            camera: CameraConfig::new(Azimuth::SOUTH),
+            dem: Default::default(),
+            output: Default::default(),
+        };
+        config.observer.eye_height = 1.5;
+        config.output.path = PathBuf::from("out/pano.png");
+        config.output.depth = Some(PathBuf::from("depth.pfm"));
+        config.output.tiles = Some(PathBuf::from("tiles"));
+        config
+    }
+
+    #[test]
+    fn parse_observers_csv_reads_named_columns() {
+        let observers = parse_observers_csv(
+            "latitude, longitude,elevation,azimuth,name\n\
+             46.6456,7.6496,2362,S,niesen\n\
+             \n\
+             46.5469,7.0178,,90,\n",
+        )
+        .unwrap();
+        assert_eq!(2, observers.len());
+        assert_eq!("niesen", observers[0].name);
+        assert_eq!(46.6456, observers[0].latitude);
+        assert_eq!(Some(2362.), observers[0].elevation);
+        assert_eq!(Some(Azimuth::SOUTH), observers[0].azimuth);
+        assert_eq!("2", observers[1].name);
+        assert_eq!(None, observers[1].elevation);
+        assert_eq!(None, observers[1].eye_height);
+        assert_eq!(Some(Azimuth::EAST), observers[1].azimuth);
+
+        let kind = |csv: &str| parse_observers_csv(csv).err().unwrap().kind();
+        assert_eq!(ErrorKind::InvalidData, kind(""));
+        assert_eq!(ErrorKind::InvalidData, kind("name,latitude\na,46"));
+        assert_eq!(ErrorKind::InvalidData, kind("latitude,longitude,x\n46,7,1"));
+        assert_eq!(ErrorKind::InvalidData, kind("latitude,longitude\n46"));
+        assert_eq!(ErrorKind::InvalidData, kind("latitude,longitude\n46,seven"));
+        assert_eq!(ErrorKind::InvalidData, kind("latitude,longitude\n46,"));
+    }
+
+    #[test]
+    fn parse_observers_json_reads_objects() {
+        let observers = parse_observers_json(
+            r#"[
+                {"name": "niesen", "latitude": 46.6456, "longitude": 7.6496,
+                 "elevation": 2362, "azimuth": "SW"},
+                {"latitude": 46.5469, "longitude": 7.0178, "eye_height": 10}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!("niesen", observers[0].name);
+        assert_eq!(Some(Azimuth::from_degrees(225.)), observers[0].azimuth);
+        assert_eq!("2", observers[1].name);
+        assert_eq!(Some(10.), observers[1].eye_height);
+        assert_eq!(None, observers[1].azimuth);
+
+        assert!(parse_observers_json("[]").is_err());
+        assert!(parse_observers_json(r#"[{"latitude": 46}]"#).is_err());
+        assert!(parse_observers_json(r#"[{"lat": 46, "lon": 7}]"#).is_err());
+    }
+
+    #[test]
+    fn observers_whose_outputs_would_collide_are_rejected() {
+        let kind = |csv: &str| parse_observers_csv(csv).err().unwrap().kind();
+        assert_eq!(
+            ErrorKind::InvalidData,
+            kind(
+                "name,latitude,longitude
+niesen,46,7
+niesen,47,8"
+            )
+        );
+        assert_eq!(
+            ErrorKind::InvalidData,
+            kind(
+                "name,latitude,longitude
+a b,46,7
+a_b,47,8"
+            )
+        );
+        assert_eq!(
+            ErrorKind::InvalidData,
+            kind(
+                "name,latitude,longitude
+2,46,7
+,47,8"
+            )
+        );
+        assert!(parse_observers_json(
+            r#"[{"name": "a/b", "latitude": 46, "longitude": 7},
+                {"name": "a.b", "latitude": 47, "longitude": 8}]"#
+        )
+        .is_err());
+        assert!(parse_observers_csv(
+            "name,latitude,longitude
+1,46,7
+,47,8"
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn config_replaces_the_observer_and_suffixes_outputs() {
+        let observer = BatchObserver {
+            name: "Dent Blanche".to_string(),
+            latitude: 46.03,
+            longitude: 7.61,
+            elevation: None,
+            eye_height: None,
+            azimuth: Some(Azimuth::NORTH),
+        };
+        let config = observer.config(&base());
+        assert_eq!(46.03, config.observer.latitude);
+        assert_eq!(None, config.observer.elevation);
+        assert_eq!(1.5, config.observer.eye_height);
+        assert_eq!(Azimuth::NORTH, config.camera.azimuth);
+        assert_eq!(
+            PathBuf::from("out/pano-Dent_Blanche.png"),
+            config.output.path
+        );
+        assert_eq!(
+            Some(PathBuf::from("depth-Dent_Blanche.pfm")),
+            config.output.depth
+        );
+        assert_eq!(
+            Some(PathBuf::from("tiles-Dent_Blanche")),
+            config.output.tiles
+        );
+        assert_eq!(None, config.output.kml);
+
+        let unnamed = BatchObserver {
+            azimuth: None,
+            ..observer
+        };
+        assert_eq!(Azimuth::SOUTH, unnamed.config(&base()).camera.azimuth);
+    }
+
+    #[test]
+    fn batch_tiles_are_loaded_once_and_shared() {
+        let dir = std::env::temp_dir().join("alpano_batch_tests");
+        fs::create_dir_all(&dir).unwrap();
+        for west in [7, 8] {
+            fs::write(
+                dir.join(HgtDem::file_name(46, west)),
+                vec![0; 1201 * 1201 * 2],
+            )
+            .unwrap();
+        }
+        let config = |longitude: f64| {
+            let mut config = base();
+            config.observer = ObserverConfig::new(46.5, longitude);
+            config.camera.max_distance = "10km".parse().unwrap();
+            config
+        };
+        let configs = [config(7.5), config(8.5), config(7.95)];
+
+        let dem = DemConfig {
+            dir,
+            ..DemConfig::default()
+        };
+        let tiles = BatchTiles::load(&dem, &configs).unwrap();
+        assert_eq!(2, tiles.tile_count());
+        assert_eq!(1201, tiles.dem(&configs[1]).unwrap().extent().x().size());
+        assert_eq!(2401, tiles.dem(&configs[2]).unwrap().extent().x().size());
+        assert!(tiles.dem(&config(9.5)).is_err());
+        assert!(BatchTiles::load(&dem, &[config(9.5)]).is_err());
+    }
+
+    #[test]
+    fn batch_tiles_with_a_memory_budget_are_read_in_a_shared_cache() {
+        let dir = std::env::temp_dir().join("alpano_batch_budget_tests");
+        fs::create_dir_all(&dir).unwrap();
+        for west in [7, 8] {
+            fs::write(
+                dir.join(HgtDem::file_name(46, west)),
+                (west as i16 * 100).to_be_bytes().repeat(1201 * 1201),
+            )
+            .unwrap();
+        }
+        let config = |longitude: f64| {
+            let mut config = base();
+            config.observer = ObserverConfig::new(46.5, longitude);
+            config.camera.max_distance = "10km".parse().unwrap();
+            config
+        };
+        let dem = DemConfig {
+            dir,
+            // less than a tile, which is still kept in memory
+            memory_budget: Some(0),
+            ..DemConfig::default()
+        };
+
+        let tiles = BatchTiles::load(&dem, &[config(7.5), config(7.95)]).unwrap();
+        assert_eq!(2, tiles.tile_count());
+        let west = tiles.dem(&config(7.5)).unwrap();
+        assert_eq!(1201, west.extent().x().size());
+        // the boundary with the tile of 8°E, not part of this DEM
+        assert_eq!(700., west.elevation_sample(8 * 1200, 46 * 1200 + 600));
+        let both = tiles.dem(&config(7.95)).unwrap();
+        assert_eq!(2401, both.extent().x().size());
+        assert_eq!(700., both.elevation_sample(7 * 1200 + 600, 46 * 1200 + 600));
+        assert_eq!(800., both.elevation_sample(8 * 1200 + 600, 46 * 1200 + 600));
+        assert!(BatchTiles::load(&dem, &[config(9.5)]).is_err());
+    }
+}
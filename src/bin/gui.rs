@@ -0,0 +1,384 @@
+//! Desktop viewer rendering panoramas interactively, like the JavaFX GUI of
+//! the original Alpano.
+
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, Receiver, TryRecvError},
+        Arc,
+    },
+    thread,
+};
+
+use alpano::{
+    azimuth::Azimuth,
+    config::{CameraConfig, DemConfig, ObserverConfig, OutputConfig, PainterKind, RenderConfig},
+    distance::Length,
+    image::Image,
+    panorama::{Panorama, ProjectionKind},
+};
+use eframe::egui;
+
+const PAINTERS: [(PainterKind, &str); 4] = [
+    (PainterKind::Grey, "grey"),
+    (PainterKind::Classic, "classic"),
+    (PainterKind::Hillshade, "hillshade"),
+    (PainterKind::Palette, "palette"),
+];
+
+const PROJECTIONS: [(ProjectionKind, &str); 6] = [
+    (ProjectionKind::Equirectangular, "equirectangular"),
+    (ProjectionKind::Cylindrical, "cylindrical"),
+    (ProjectionKind::Rectilinear, "rectilinear"),
+    (ProjectionKind::Mercator, "mercator"),
+    (ProjectionKind::Fisheye, "fisheye"),
+    (ProjectionKind::Stereographic, "stereographic"),
+];
+
+fn main() -> eframe::Result {
+    // the optional argument is a configuration file to start from
+    let config = match std::env::args().nth(1) {
+        Some(path) => match RenderConfig::load(&path) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("cannot read {}: {}", path, e);
+                std::process::exit(1);
+            }
+        },
+        None => niesen(),
+    };
+
+    eframe::run_native(
+        "Alpano",
+        eframe::NativeOptions::default(),
+        Box::new(|_| Ok(Box::new(Viewer::new(config)))),
+    )
+}
+
+/// View south from the Niesen, the example of the original Alpano.
+fn niesen() -> RenderConfig {
+    RenderConfig {
+        observer: ObserverConfig {
+            elevation: Some(2362.),
+            ..ObserverConfig::new(46.6456, 7.6496)
+        },
+        camera: CameraConfig::new(Azimuth::SOUTH),
+        dem: DemConfig::default(),
+        output: OutputConfig {
+            painter: PainterKind::Classic,
+            ..OutputConfig::default()
+        },
+    }
+}
+
+/// Render running in the background.
+struct Job {
+    done: Arc<AtomicUsize>,
+    total: usize,
+    result: Receiver<Result<(Panorama, Image), String>>,
+}
+
+struct Viewer {
+    config: RenderConfig,
+    job: Option<Job>,
+    image: Option<(Image, egui::TextureHandle)>,
+    /// Panorama of the image, telling what is seen under the pointer.
+    panorama: Option<Panorama>,
+    zoom: f32,
+    status: String,
+}
+
+impl Viewer {
+    fn new(config: RenderConfig) -> Viewer {
+        Viewer {
+            config,
+            job: None,
+            image: None,
+            panorama: None,
+            zoom: 1.,
+            status: String::new(),
+        }
+    }
+
+    /// Start rendering the panorama of the current configuration.
+    fn start_render(&mut self) {
+        let total = self.config.camera.width;
+        let done = Arc::new(AtomicUsize::new(0));
+        let (sender, result) = mpsc::channel();
+        let config = self.config.clone();
+        let counter = done.clone();
+        thread::spawn(move || {
+            let panorama = alpano::render::compute(&config, move |done, _| {
+                counter.fetch_max(done, Ordering::Relaxed);
+            });
+            let result = panorama.map(|panorama| {
+                let image = alpano::render::paint(&panorama, &config);
+                (panorama, image)
+            });
+            // the viewer may have been closed in the meantime
+            let _ = sender.send(result.map_err(|e| e.to_string()));
+        });
+
+        self.status = "rendering…".to_string();
+        self.job = Some(Job {
+            done,
+            total,
+            result,
+        });
+    }
+
+    /// Show the image of the finished job, if any.
+    fn poll_job(&mut self, ctx: &egui::Context) {
+        let Some(job) = &self.job else {
+            return;
+        };
+        let result = match job.result.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => Err("render failed".to_string()),
+        };
+        self.job = None;
+
+        match result {
+            Ok((panorama, image)) => self.show(ctx, panorama, image),
+            Err(e) => self.status = e,
+        }
+    }
+
+    /// Show `image`, the painted image of `panorama`.
+    fn show(&mut self, ctx: &egui::Context, panorama: Panorama, image: Image) {
+        let rgba: Vec<u8> = (0..image.height())
+            .flat_map(|y| (0..image.width()).map(move |x| (x, y)))
+            .flat_map(|(x, y)| image.get(x, y))
+            .collect();
+        let texture = ctx.load_texture(
+            "panorama",
+            egui::ColorImage::from_rgba_unmultiplied([image.width(), image.height()], &rgba),
+            egui::TextureOptions::LINEAR,
+        );
+        self.status = format!("{}×{} pixels", image.width(), image.height());
+        self.image = Some((image, texture));
+        self.panorama = Some(panorama);
+    }
+
+    /// Paint the panorama of the image shown again with the painter of the
+    /// current configuration, without computing it again.
+    fn repaint(&mut self, ctx: &egui::Context) {
+        if let Some(panorama) = self.panorama.take() {
+            let image = alpano::render::paint(&panorama, &self.config);
+            self.show(ctx, panorama, image);
+        }
+    }
+
+    fn save(&mut self) {
+        let Some((image, _)) = &self.image else {
+            return;
+        };
+        let output = &self.config.output;
+        self.status = match image.save(&output.path, output.format(), output.quality) {
+            Ok(()) => format!("panorama written to {}", output.path.display()),
+            Err(e) => format!("cannot write {}: {}", output.path.display(), e),
+        };
+    }
+
+    fn controls(&mut self, ui: &mut egui::Ui) {
+        let observer = &mut self.config.observer;
+        let camera = &mut self.config.camera;
+        let mut repaint = false;
+
+        egui::Grid::new("parameters").num_columns(2).show(ui, |ui| {
+            ui.label("Latitude");
+            ui.add(
+                egui::DragValue::new(&mut observer.latitude)
+                    .range(-90. ..=90.)
+                    .speed(0.001)
+                    .suffix("°"),
+            );
+            ui.end_row();
+            ui.label("Longitude");
+            ui.add(
+                egui::DragValue::new(&mut observer.longitude)
+                    .range(-180. ..=180.)
+                    .speed(0.001)
+                    .suffix("°"),
+            );
+            ui.end_row();
+            ui.label("Elevation");
+            ui.horizontal(|ui| {
+                let mut on_ground = observer.elevation.is_none();
+                ui.checkbox(&mut on_ground, "on the ground");
+                match (on_ground, &mut observer.elevation) {
+                    (true, elevation) => {
+                        *elevation = None;
+                        ui.add(egui::DragValue::new(&mut observer.eye_height).suffix(" m"))
+                            .on_hover_text("height of the eyes above the ground");
+                    }
+                    (false, elevation) => {
+                        let elevation = elevation.get_or_insert(2000.);
+                        ui.add(egui::DragValue::new(elevation).suffix(" m"));
+                    }
+                }
+            });
+            ui.end_row();
+
+            ui.label("Azimuth");
+            let mut azimuth = camera.azimuth.degrees();
+            if ui
+                .add(
+                    egui::DragValue::new(&mut azimuth)
+                        .range(0. ..=360.)
+                        .suffix("°"),
+                )
+                .changed()
+            {
+                camera.azimuth = Azimuth::from_degrees(azimuth);
+            }
+            ui.end_row();
+            ui.label("Field of view");
+            ui.add(
+                egui::DragValue::new(&mut camera.fov)
+                    .range(1. ..=360.)
+                    .suffix("°"),
+            );
+            ui.end_row();
+            ui.label("Max distance");
+            let mut km = camera.max_distance.meters() / 1000.;
+            if ui
+                .add(
+                    egui::DragValue::new(&mut km)
+                        .range(1. ..=500.)
+                        .suffix(" km"),
+                )
+                .changed()
+            {
+                camera.max_distance = Length::from_meters(km * 1000.);
+            }
+            ui.end_row();
+            ui.label("Width");
+            ui.add(egui::DragValue::new(&mut camera.width).range(2..=20_000));
+            ui.end_row();
+            ui.label("Height");
+            ui.add(egui::DragValue::new(&mut camera.height).range(2..=10_000));
+            ui.end_row();
+            ui.label("Projection");
+            combo(ui, "projection", &mut camera.projection, &PROJECTIONS);
+            ui.end_row();
+
+            ui.label("Painter");
+            repaint = combo(ui, "painter", &mut self.config.output.painter, &PAINTERS);
+            ui.end_row();
+            ui.label("DEM directory");
+            let mut dir = self.config.dem.dir.display().to_string();
+            if ui.text_edit_singleline(&mut dir).changed() {
+                self.config.dem.dir = PathBuf::from(dir);
+            }
+            ui.end_row();
+            ui.label("Output");
+            let mut path = self.config.output.path.display().to_string();
+            if ui.text_edit_singleline(&mut path).changed() {
+                self.config.output.path = PathBuf::from(path);
+            }
+            ui.end_row();
+        });
+
+        // changing the painter only paints the computed panorama again
+        if repaint && self.job.is_none() {
+            self.repaint(ui.ctx());
+        }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(self.job.is_none(), egui::Button::new("Render"))
+                .clicked()
+            {
+                self.start_render();
+            }
+            if ui
+                .add_enabled(self.image.is_some(), egui::Button::new("Save"))
+                .clicked()
+            {
+                self.save();
+            }
+        });
+        if let Some(job) = &self.job {
+            let done = job.done.load(Ordering::Relaxed);
+            ui.add(egui::ProgressBar::new(done as f32 / job.total as f32).show_percentage());
+        }
+        ui.add(egui::Slider::new(&mut self.zoom, 0.1..=4.).text("zoom"));
+        ui.label(&self.status);
+    }
+}
+
+/// Combo box choosing `value` among the labelled `choices`, telling whether
+/// another choice was made.
+fn combo<T: Copy + PartialEq>(
+    ui: &mut egui::Ui,
+    id: &str,
+    value: &mut T,
+    choices: &[(T, &str)],
+) -> bool {
+    let selected = choices
+        .iter()
+        .find(|(choice, _)| choice == value)
+        .map_or("", |(_, label)| label);
+    let mut changed = false;
+    egui::ComboBox::from_id_salt(id)
+        .selected_text(selected)
+        .show_ui(ui, |ui| {
+            for (choice, label) in choices {
+                changed |= ui.selectable_value(value, *choice, *label).changed();
+            }
+        });
+    changed
+}
+
+/// Description of the terrain seen at the pixel `(x, y)` of `panorama`, if
+/// any.
+fn terrain_at(panorama: &Panorama, x: f32, y: f32) -> Option<String> {
+    let parameters = panorama.parameters();
+    if x < 0. || y < 0. {
+        return None;
+    }
+    let (x, y) = (x as usize, y as usize);
+    if x >= parameters.width() || y >= parameters.height() {
+        return None;
+    }
+    let point = panorama.geopoint_at(x, y)?;
+    Some(format!(
+        "{}\n{:.0} m, {:.1} km away",
+        point.to_dms_string(0),
+        panorama.elevation_at(x, y),
+        parameters.observer_position().distance_to(&point) / 1000.
+    ))
+}
+
+impl eframe::App for Viewer {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.poll_job(ctx);
+        if self.job.is_some() {
+            ctx.request_repaint_after(std::time::Duration::from_millis(100));
+        }
+
+        egui::SidePanel::left("controls").show(ctx, |ui| self.controls(ui));
+        egui::CentralPanel::default().show(ctx, |ui| {
+            if let Some((_, texture)) = &self.image {
+                // dragging the image pans it
+                egui::ScrollArea::both().show(ui, |ui| {
+                    let response = ui.add(egui::Image::new(egui::load::SizedTexture::new(
+                        texture.id(),
+                        texture.size_vec2() * self.zoom,
+                    )));
+                    if let (Some(panorama), Some(pointer)) = (&self.panorama, response.hover_pos())
+                    {
+                        let pixel = (pointer - response.rect.min) / self.zoom;
+                        if let Some(text) = terrain_at(panorama, pixel.x, pixel.y) {
+                            response.on_hover_text_at_pointer(text);
+                        }
+                    }
+                });
+            }
+        });
+    }
+}
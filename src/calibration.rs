@@ -0,0 +1,247 @@
+use thiserror::Error;
+
+use super::{azimuth::Azimuth, math::angular_distance};
+
+/// Errors of calibration.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum CalibrationError {
+    #[error("at least 2 correspondences are needed, got {0}")]
+    NotEnoughPoints(usize),
+    #[error("image of {0}×{1} pixels is too small")]
+    ImageTooSmall(u32, u32),
+    #[error("correspondences do not determine the scale of the image")]
+    Degenerate,
+    #[error("correspondences imply a mirrored image")]
+    Mirrored,
+}
+
+/// A point of a photograph (typically a summit clicked by the user) whose
+/// direction from the camera is known.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Correspondence {
+    /// Pixel coordinates in the photograph, y growing downward.
+    pub x: f64,
+    pub y: f64,
+    /// Direction of the point from the camera position, in radians.
+    pub azimuth: f64,
+    pub altitude: f64,
+}
+
+/// Framing of a photograph, in the angle-per-pixel (cylindrical) model used
+/// by rendered panoramas.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Calibration {
+    /// Azimuth of the center of the photograph.
+    pub heading: Azimuth,
+    /// Horizontal field of view, in radians.
+    pub fov: f64,
+    /// Altitude of the center of the photograph, in radians.
+    pub tilt: f64,
+}
+
+impl Calibration {
+    /// Direction (azimuth and altitude) seen at the pixel `(x, y)` of the
+    /// `width`×`height` photograph.
+    pub fn direction_at(&self, x: f64, y: f64, width: u32, height: u32) -> (Azimuth, f64) {
+        let d = self.angle_per_pixel(width);
+        let (cx, cy) = center(width, height);
+        (self.heading + (x - cx) * d, self.tilt - (y - cy) * d)
+    }
+
+    /// Pixel of the `width`×`height` photograph at which the direction of
+    /// the given azimuth and altitude is seen, possibly outside of it.
+    pub fn pixel_for(
+        &self,
+        azimuth: Azimuth,
+        altitude: f64,
+        width: u32,
+        height: u32,
+    ) -> (f64, f64) {
+        let d = self.angle_per_pixel(width);
+        let (cx, cy) = center(width, height);
+        (
+            cx + angular_distance(self.heading.radians(), azimuth.radians()) / d,
+            cy + (self.tilt - altitude) / d,
+        )
+    }
+
+    fn angle_per_pixel(&self, width: u32) -> f64 {
+        self.fov / (width.max(2) - 1) as f64
+    }
+}
+
+/// Pixel at the center of a `width`×`height` photograph.
+fn center(width: u32, height: u32) -> (f64, f64) {
+    (
+        (width.max(1) - 1) as f64 / 2.,
+        (height.max(1) - 1) as f64 / 2.,
+    )
+}
+
+/// Estimate the heading, field of view and tilt of a `width`×`height`
+/// photograph from at least two correspondences, by least squares.
+///
+/// Fails if the correspondences do not determine the scale of the
+/// photograph (e.g. if they are all at the same pixel), or if they imply a
+/// mirrored image.
+pub fn calibrate(
+    points: &[Correspondence],
+    width: u32,
+    height: u32,
+) -> Result<Calibration, CalibrationError> {
+    if points.len() < 2 {
+        return Err(CalibrationError::NotEnoughPoints(points.len()));
+    }
+    if width < 2 {
+        return Err(CalibrationError::ImageTooSmall(width, height));
+    }
+
+    let (cx, cy) = center(width, height);
+    let n = points.len() as f64;
+
+    // unwrap azimuths around the first one so sectors crossing north work
+    let a0 = points[0].azimuth;
+    let (mut su, mut sv, mut suu, mut svv) = (0., 0., 0., 0.);
+    let (mut sa, mut sb, mut sua, mut svb) = (0., 0., 0., 0.);
+    for p in points {
+        let (u, v) = (p.x - cx, p.y - cy);
+        let a = a0 + angular_distance(a0, p.azimuth);
+        let b = p.altitude;
+        su += u;
+        sv += v;
+        suu += u * u;
+        svv += v * v;
+        sa += a;
+        sb += b;
+        sua += u * a;
+        svb += v * b;
+    }
+
+    // minimize Σ(heading + u·d - a)² + Σ(tilt - v·d - b)² where d is the
+    // angle covered by a pixel
+    let denominator = suu + svv - su * su / n - sv * sv / n;
+    if denominator <= 1e-9 {
+        return Err(CalibrationError::Degenerate);
+    }
+    let d = (sua - svb - sa * su / n + sb * sv / n) / denominator;
+    if d <= 0. {
+        return Err(CalibrationError::Mirrored);
+    }
+
+    Ok(Calibration {
+        heading: Azimuth::new((sa - d * su) / n),
+        fov: d * (width - 1) as f64,
+        tilt: (sb + d * sv) / n,
+    })
+}
+
+#[cfg(test)]
+mod calibration_tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+    use rand::Rng;
+
+    fn project(c: &Calibration, width: u32, height: u32, x: f64, y: f64) -> Correspondence {
+        let d = c.fov / (width - 1) as f64;
+        Correspondence {
+            x,
+            y,
+            azimuth: (c.heading + (x - (width - 1) as f64 / 2.) * d).radians(),
+            altitude: c.tilt - (y - (height - 1) as f64 / 2.) * d,
+        }
+    }
+
+    #[test]
+    fn calibrate_recovers_exact_framing() {
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..100 {
+            let expected = Calibration {
+                heading: Azimuth::new(rng.gen::<f64>() * std::f64::consts::TAU),
+                fov: rng.gen_range(0.1..2.),
+                tilt: rng.gen_range(-0.2..0.2),
+            };
+            let points: Vec<Correspondence> = (0..4)
+                .map(|_| {
+                    let x = rng.gen_range(0.0..4000.);
+                    let y = rng.gen_range(0.0..3000.);
+                    project(&expected, 4000, 3000, x, y)
+                })
+                .collect();
+
+            let actual = calibrate(&points, 4000, 3000).unwrap();
+            assert_approx_eq!(
+                0.,
+                angular_distance(expected.heading.radians(), actual.heading.radians()),
+                1e-9
+            );
+            assert_approx_eq!(expected.fov, actual.fov, 1e-9);
+            assert_approx_eq!(expected.tilt, actual.tilt, 1e-9);
+        }
+    }
+
+    #[test]
+    fn pixel_for_is_the_inverse_of_direction_at() {
+        let calibration = Calibration {
+            heading: Azimuth::new(0.02),
+            fov: 1.,
+            tilt: 0.1,
+        };
+        for (x, y) in [(0., 0.), (10., 300.), (499.5, 200.), (990., 12.)] {
+            let (azimuth, altitude) = calibration.direction_at(x, y, 1000, 500);
+            let c = project(&calibration, 1000, 500, x, y);
+            assert_approx_eq!(0., angular_distance(c.azimuth, azimuth.radians()), 1e-9);
+            assert_approx_eq!(c.altitude, altitude, 1e-9);
+
+            let (px, py) = calibration.pixel_for(azimuth, altitude, 1000, 500);
+            assert_approx_eq!(x, px, 1e-9);
+            assert_approx_eq!(y, py, 1e-9);
+        }
+    }
+
+    #[test]
+    fn calibrate_handles_photos_across_north() {
+        let expected = Calibration {
+            heading: Azimuth::new(0.01),
+            fov: 1.,
+            tilt: 0.05,
+        };
+        let points = [
+            project(&expected, 1000, 500, 10., 300.),
+            project(&expected, 1000, 500, 990., 200.),
+        ];
+        assert!(points[0].azimuth > 5.);
+
+        let actual = calibrate(&points, 1000, 500).unwrap();
+        assert_approx_eq!(0.01, actual.heading.radians(), 1e-9);
+        assert_approx_eq!(1., actual.fov, 1e-9);
+    }
+
+    #[test]
+    fn calibrate_fails_on_degenerate_input() {
+        let p = Correspondence {
+            x: 10.,
+            y: 10.,
+            azimuth: 1.,
+            altitude: 0.,
+        };
+        assert_eq!(
+            Err(CalibrationError::NotEnoughPoints(1)),
+            calibrate(&[p], 100, 100)
+        );
+        assert_eq!(
+            Err(CalibrationError::Degenerate),
+            calibrate(&[p, p], 100, 100)
+        );
+
+        let mirrored = Correspondence {
+            x: 90.,
+            azimuth: 0.5,
+            ..p
+        };
+        assert_eq!(
+            Err(CalibrationError::Mirrored),
+            calibrate(&[p, mirrored], 100, 100)
+        );
+    }
+}
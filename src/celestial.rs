@@ -0,0 +1,99 @@
+use chrono::{DateTime, Utc};
+
+use super::{azimuth::Azimuth, sun::julian_day};
+
+/// Julian day number of the J2000.0 epoch.
+pub const J2000_JD: f64 = 2451545.0;
+
+/// Julian centuries elapsed since J2000.0 at the given instant.
+pub fn julian_centuries(date_time: DateTime<Utc>) -> f64 {
+    (julian_day(date_time) - J2000_JD) / 36525.
+}
+
+/// Greenwich mean sidereal time at the given instant, in radians.
+pub fn sidereal_time(date_time: DateTime<Utc>) -> f64 {
+    let d = julian_day(date_time) - J2000_JD;
+    let t = d / 36525.;
+    let deg = 280.46061837 + 360.98564736629 * d + t * t * (0.000387933 - t / 38710000.);
+    deg.rem_euclid(360.).to_radians()
+}
+
+/// Mean obliquity of the ecliptic at the given instant, in radians.
+pub fn obliquity(date_time: DateTime<Utc>) -> f64 {
+    (23.439291 - 0.0130042 * julian_centuries(date_time)).to_radians()
+}
+
+/// Equatorial coordinates (right ascension, declination) of a body of the
+/// given ecliptic longitude and latitude, all in radians.
+pub fn ecliptic_to_equatorial(longitude: f64, latitude: f64, obliquity: f64) -> (f64, f64) {
+    let (sin_e, cos_e) = obliquity.sin_cos();
+    let (sin_l, cos_l) = longitude.sin_cos();
+    let (sin_b, cos_b) = latitude.sin_cos();
+
+    let ra = (sin_l * cos_e - latitude.tan() * sin_e).atan2(cos_l);
+    let dec = (sin_b * cos_e + cos_b * sin_e * sin_l).asin();
+    (ra, dec)
+}
+
+/// Horizontal coordinates (azimuth, elevation in radians) of a body of the
+/// given right ascension and declination (in radians), seen at the given
+/// instant from the given latitude and longitude (in radians).
+pub fn equatorial_to_horizontal(
+    ra: f64,
+    dec: f64,
+    date_time: DateTime<Utc>,
+    lat: f64,
+    lon: f64,
+) -> (Azimuth, f64) {
+    let hour_angle = sidereal_time(date_time) + lon - ra;
+
+    let (sin_lat, cos_lat) = lat.sin_cos();
+    let (sin_dec, cos_dec) = dec.sin_cos();
+    let (sin_ha, cos_ha) = hour_angle.sin_cos();
+
+    let elevation = (sin_lat * sin_dec + cos_lat * cos_dec * cos_ha).asin();
+    let azimuth = (-sin_ha * cos_dec).atan2(cos_lat * sin_dec - sin_lat * cos_dec * cos_ha);
+    (Azimuth::new(azimuth), elevation)
+}
+
+#[cfg(test)]
+mod celestial_tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+    use chrono::TimeZone;
+
+    #[test]
+    fn sidereal_time_is_correct_for_known_values() {
+        let t = Utc.with_ymd_and_hms(2000, 1, 1, 12, 0, 0).unwrap();
+        assert_approx_eq!(280.46061837, sidereal_time(t).to_degrees(), 1e-6);
+
+        // Meeus, example 12.a: 1987-04-10 0h UT
+        let t = Utc.with_ymd_and_hms(1987, 4, 10, 0, 0, 0).unwrap();
+        assert_approx_eq!(197.693195, sidereal_time(t).to_degrees(), 1e-4);
+    }
+
+    #[test]
+    fn ecliptic_to_equatorial_is_identity_without_obliquity() {
+        let (ra, dec) = ecliptic_to_equatorial(1., 0.3, 0.);
+        assert_approx_eq!(1., ra, 1e-10);
+        assert_approx_eq!(0.3, dec, 1e-10);
+    }
+
+    #[test]
+    fn ecliptic_to_equatorial_is_correct_at_solstice_point() {
+        let e = 23.44_f64.to_radians();
+        let (ra, dec) = ecliptic_to_equatorial(90_f64.to_radians(), 0., e);
+        assert_approx_eq!(90., ra.to_degrees(), 1e-10);
+        assert_approx_eq!(23.44, dec.to_degrees(), 1e-10);
+    }
+
+    #[test]
+    fn celestial_pole_is_at_latitude_elevation_due_north() {
+        let t = Utc.with_ymd_and_hms(2024, 5, 1, 21, 0, 0).unwrap();
+        let lat = 46.5_f64.to_radians();
+        let (azimuth, elevation) =
+            equatorial_to_horizontal(0.3, 90_f64.to_radians() - 1e-9, t, lat, 0.1);
+        assert_approx_eq!(46.5, elevation.to_degrees(), 1e-6);
+        assert!(azimuth.degrees() < 1e-3 || azimuth.degrees() > 360. - 1e-3);
+    }
+}
@@ -0,0 +1,314 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use crate::{
+    dem::{DiscreteElevationModel, ElevationProfile},
+    image::{Image, ImageFormat},
+    overlay::{self, TEXT_SIZE},
+};
+
+/// Number of points at which the terrain of charts is sampled.
+const SAMPLES: usize = 500;
+/// Color of the background of charts.
+const BACKGROUND: [u8; 4] = [255, 255, 255, 255];
+/// Color of the terrain of charts.
+const TERRAIN_COLOR: [u8; 4] = [140, 125, 100, 255];
+/// Color of the sea level, curving down with the distance.
+const SEA_LEVEL_COLOR: [u8; 4] = [40, 110, 200, 255];
+/// Color of the horizontal of the observer.
+const HORIZONTAL_COLOR: [u8; 4] = [150, 150, 150, 255];
+/// Color of the sight line.
+const SIGHT_LINE_COLOR: [u8; 4] = [220, 40, 30, 255];
+/// Color of the caption.
+const CAPTION_COLOR: [u8; 4] = [0, 0, 0, 255];
+/// Margin around the plot, as a fraction of the elevations it spans.
+const MARGIN: f64 = 0.05;
+
+/// Chart of the terrain along an elevation profile as seen from an observer
+/// at its origin: the elevations are lowered by the curvature drop, the
+/// depth below the horizontal of the observer to which the sea level falls
+/// over the distance, so that rays of light are straight lines. The sight
+/// line leaves the eye of the observer grazing the terrain horizon, and
+/// separates the terrain seen below it from that hidden beyond.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfileChart {
+    length: f64,
+    observer_elevation: f64,
+    effective_radius: f64,
+    /// Distances and elevations lowered by the curvature drop of the
+    /// samples of the terrain.
+    terrain: Vec<(f64, f64)>,
+    sight_slope: f64,
+}
+
+impl ProfileChart {
+    /// Chart of `profile` seen from an observer at `observer_elevation`, up
+    /// to `length` meters, over an earth of the given effective radius, see
+    /// [`effective_radius`](crate::distance::effective_radius).
+    pub fn new<D: DiscreteElevationModel>(
+        profile: &ElevationProfile<D>,
+        observer_elevation: f64,
+        length: f64,
+        effective_radius: f64,
+    ) -> ProfileChart {
+        let terrain: Vec<(f64, f64)> = (0..=SAMPLES)
+            .map(|i| {
+                let d = length * i as f64 / SAMPLES as f64;
+                (d, profile.elevation_at(d) - d * d / (2. * effective_radius))
+            })
+            .collect();
+        let sight_slope = terrain[1..]
+            .iter()
+            .map(|&(d, e)| (e - observer_elevation) / d)
+            .fold(f64::NEG_INFINITY, f64::max);
+        ProfileChart {
+            length,
+            observer_elevation,
+            effective_radius,
+            terrain,
+            sight_slope,
+        }
+    }
+
+    /// Slope of the sight line, the tangent of its altitude.
+    pub fn sight_slope(&self) -> f64 {
+        self.sight_slope
+    }
+
+    /// Curvature drop at the given distance, in meters.
+    pub fn drop_at(&self, distance: f64) -> f64 {
+        distance * distance / (2. * self.effective_radius)
+    }
+
+    /// Sea level lowered by the curvature drop, every sample.
+    fn sea_level(&self) -> impl Iterator<Item = (f64, f64)> + '_ {
+        self.terrain.iter().map(|&(d, _)| (d, -self.drop_at(d)))
+    }
+
+    /// Elevation of the sight line at the end of the chart.
+    fn sight_end(&self) -> f64 {
+        self.observer_elevation + self.sight_slope * self.length
+    }
+
+    fn caption(&self) -> String {
+        format!(
+            "{:.1} km, curvature drop {:.0} m",
+            self.length / 1000.,
+            self.drop_at(self.length)
+        )
+    }
+
+    /// Pixel of a `width`×`height` chart at the given distance and elevation.
+    fn frame(&self, width: usize, height: usize) -> impl Fn((f64, f64)) -> (f64, f64) {
+        let elevations = self.terrain.iter().map(|&(_, e)| e).chain([
+            self.observer_elevation,
+            self.sight_end(),
+            -self.drop_at(self.length),
+        ]);
+        let (low, high) = elevations.fold((f64::INFINITY, f64::NEG_INFINITY), |(l, h), e| {
+            (l.min(e), h.max(e))
+        });
+        let margin = (high - low).max(1.) * MARGIN;
+        let (low, high) = (low - margin, high + margin);
+        let (w, h, length) = ((width - 1) as f64, (height - 1) as f64, self.length);
+        move |(d, e)| (d / length * w, (high - e) / (high - low) * h)
+    }
+
+    /// Draw the chart on a `width`×`height` image.
+    pub fn image(&self, width: usize, height: usize) -> Image {
+        let mut image = Image::new(width, height);
+        let frame = self.frame(width, height);
+        overlay::rectangle(
+            &mut image,
+            (0, 0),
+            (width as isize, height as isize),
+            BACKGROUND,
+        );
+
+        // the terrain, filled down to the bottom column by column
+        for x in 0..width {
+            let i = (x * SAMPLES + (width - 1) / 2) / (width - 1).max(1);
+            let (_, top) = frame(self.terrain[i]);
+            overlay::rectangle(
+                &mut image,
+                (x as isize, top.round() as isize),
+                (x as isize + 1, height as isize),
+                TERRAIN_COLOR,
+            );
+        }
+        let sea_level: Vec<(f64, f64)> = self.sea_level().map(&frame).collect();
+        for segment in sea_level.windows(2) {
+            overlay::line(&mut image, segment[0], segment[1], 1.5, SEA_LEVEL_COLOR);
+        }
+        let (eye, end) = (
+            frame((0., self.observer_elevation)),
+            frame((self.length, self.observer_elevation)),
+        );
+        overlay::line(&mut image, eye, end, 1., HORIZONTAL_COLOR);
+        let sight_end = frame((self.length, self.sight_end()));
+        overlay::line(&mut image, eye, sight_end, 1.5, SIGHT_LINE_COLOR);
+        overlay::text(
+            &mut image,
+            &self.caption(),
+            (4., TEXT_SIZE + 2.),
+            TEXT_SIZE,
+            0.,
+            CAPTION_COLOR,
+        );
+        image
+    }
+
+    /// Write the chart to `writer` as a `width`×`height` SVG image.
+    pub fn write_svg(&self, mut writer: impl Write, width: usize, height: usize) -> io::Result<()> {
+        let frame = self.frame(width, height);
+        let points = |points: &mut dyn Iterator<Item = (f64, f64)>| {
+            points
+                .map(|p| {
+                    let (x, y) = frame(p);
+                    format!("{:.2},{:.2}", x, y)
+                })
+                .collect::<Vec<String>>()
+                .join(" ")
+        };
+        let color = |c: [u8; 4]| format!("#{:02x}{:02x}{:02x}", c[0], c[1], c[2]);
+        let bottom = (height - 1) as f64;
+
+        writeln!(
+            writer,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{1}" viewBox="0 0 {0} {1}">"#,
+            width, height
+        )?;
+        writeln!(
+            writer,
+            r#"<rect width="100%" height="100%" fill="{}"/>"#,
+            color(BACKGROUND)
+        )?;
+        writeln!(
+            writer,
+            r#"<polygon points="0,{1:.2} {0} {2:.2},{1:.2}" fill="{3}"/>"#,
+            points(&mut self.terrain.iter().copied()),
+            bottom,
+            (width - 1) as f64,
+            color(TERRAIN_COLOR)
+        )?;
+        writeln!(
+            writer,
+            r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="1.5"/>"#,
+            points(&mut self.sea_level()),
+            color(SEA_LEVEL_COLOR)
+        )?;
+        for (end, stroke, stroke_width) in [
+            (self.observer_elevation, HORIZONTAL_COLOR, 1.),
+            (self.sight_end(), SIGHT_LINE_COLOR, 1.5),
+        ] {
+            writeln!(
+                writer,
+                r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="{}"/>"#,
+                points(&mut [(0., self.observer_elevation), (self.length, end)].into_iter()),
+                color(stroke),
+                stroke_width
+            )?;
+        }
+        writeln!(
+            writer,
+            r#"<text x="4" y="{}" font-family="sans-serif" font-size="{}" fill="{}">{}</text>"#,
+            TEXT_SIZE + 2.,
+            TEXT_SIZE,
+            color(CAPTION_COLOR),
+            self.caption()
+        )?;
+        writeln!(writer, "</svg>")
+    }
+
+    /// Write the `width`×`height` chart to the file at the given path, as an
+    /// SVG image if its extension is `svg`, and as an image otherwise.
+    pub fn save(&self, path: impl AsRef<Path>, width: usize, height: usize) -> io::Result<()> {
+        let path = path.as_ref();
+        if path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("svg"))
+        {
+            let mut writer = BufWriter::new(File::create(path)?);
+            self.write_svg(&mut writer, width, height)?;
+            writer.flush()
+        } else {
+            self.image(width, height).save(
+                path,
+                ImageFormat::from_path(path).unwrap_or_default(),
+                90,
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod chart_tests {
+    use super::*;
+    use crate::{
+        azimuth::Azimuth,
+        dem::ContinuousElevationModel,
+        distance::{effective_radius, REFRACTION_COEFFICIENT},
+        fixtures::{flat, wall},
+        geo::GeoPoint,
+    };
+    use assert_approx_eq::assert_approx_eq;
+
+    fn origin() -> GeoPoint {
+        GeoPoint::from_degrees(7., 46.5).unwrap()
+    }
+
+    #[test]
+    fn sight_line_grazes_the_highest_terrain() {
+        let cem = ContinuousElevationModel::new(wall());
+        let profile = ElevationProfile::new(&cem, origin(), Azimuth::EAST, 100_000.);
+        let radius = effective_radius(REFRACTION_COEFFICIENT);
+        let chart = ProfileChart::new(&profile, 1100., 100_000., radius);
+
+        let wall = origin().distance_to(&GeoPoint::from_degrees(8., 46.5).unwrap());
+        let expected = (3000. - chart.drop_at(wall) - 1100.) / wall;
+        // the samples of the terrain miss the very top of the wall
+        assert_approx_eq!(expected, chart.sight_slope(), 1e-3);
+        assert_approx_eq!(100_000_f64.powi(2) / (2. * radius), chart.drop_at(100_000.));
+    }
+
+    #[test]
+    fn image_draws_the_terrain_below_the_sight_line() {
+        let cem = ContinuousElevationModel::new(flat(1000.));
+        let profile = ElevationProfile::new(&cem, origin(), Azimuth::EAST, 50_000.);
+        let chart = ProfileChart::new(&profile, 1100., 50_000., effective_radius(0.));
+        // the sea level drops 196 m, and the terrain with it
+        assert_approx_eq!(196., chart.drop_at(50_000.), 0.5);
+
+        let image = chart.image(400, 200);
+        assert_eq!(BACKGROUND, image.get(200, 2));
+        assert_eq!(TERRAIN_COLOR, image.get(5, 199));
+        assert_eq!(TERRAIN_COLOR, image.get(395, 199));
+        // the terrain is lower at the end, below the sight line
+        let top = |x| {
+            (0..200)
+                .find(|&y| image.get(x, y) == TERRAIN_COLOR)
+                .unwrap()
+        };
+        assert!(top(395) > top(100) + 20);
+        assert!((0..200).any(|y| image.get(395, y) == SIGHT_LINE_COLOR));
+    }
+
+    #[test]
+    fn write_svg_writes_the_terrain_and_lines() {
+        let cem = ContinuousElevationModel::new(wall());
+        let profile = ElevationProfile::new(&cem, origin(), Azimuth::EAST, 100_000.);
+        let chart = ProfileChart::new(&profile, 1100., 100_000., effective_radius(0.13));
+        let mut svg = Vec::new();
+        chart.write_svg(&mut svg, 600, 300).unwrap();
+        let svg = String::from_utf8(svg).unwrap();
+
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        assert_eq!(1, svg.matches("<polygon").count());
+        assert_eq!(3, svg.matches("<polyline").count());
+        assert!(svg.contains(">100.0 km, curvature drop 683 m</text>"));
+    }
+}
@@ -0,0 +1,1307 @@
+use std::{
+    f64::consts::FRAC_PI_2,
+    fs,
+    io::{self, ErrorKind},
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer};
+use thiserror::Error;
+
+use crate::{
+    angle::Deg,
+    atmosphere::{self, Atmosphere},
+    azimuth::Azimuth,
+    calibration::Correspondence,
+    dem::{ContinuousElevationModel, DiscreteElevationModel, HgtDem, Interpolation},
+    distance::Length,
+    error::AlpanoError,
+    geo::{GeoError, GeoPoint},
+    image::ImageFormat,
+    painter::Palette,
+    panorama::{PanoramaParameters, ProjectionKind, RenderError},
+    sun::{self, SolarPosition},
+};
+
+/// Contrast ratio below which the nearest and farthest colors of a palette
+/// are hard to tell apart, that required of large text by the Web Content
+/// Accessibility Guidelines.
+const MIN_PALETTE_CONTRAST: f64 = 3.;
+
+/// Render job, as declared in a TOML file like:
+///
+/// ```toml
+/// [observer]
+/// latitude = 46.6456
+/// longitude = 7.6496
+/// elevation = 2362
+///
+/// [camera]
+/// azimuth = "S"
+/// fov = 60
+/// max_distance = "100km"
+///
+/// [dem]
+/// dir = "srtm"
+/// interpolation = "bicubic"
+///
+/// [output]
+/// path = "niesen.png"
+/// painter = "classic"
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RenderConfig {
+    pub observer: ObserverConfig,
+    pub camera: CameraConfig,
+    #[serde(default)]
+    pub dem: DemConfig,
+    #[serde(default)]
+    pub output: OutputConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ObserverConfig {
+    /// Latitude in degrees.
+    pub latitude: f64,
+    /// Longitude in degrees.
+    pub longitude: f64,
+    /// Elevation in meters, by default that of the ground below the observer
+    /// plus `eye_height`.
+    #[serde(default)]
+    pub elevation: Option<f64>,
+    /// Height of the eyes of the observer above the ground, in meters, when
+    /// its elevation is not given.
+    #[serde(default = "ObserverConfig::default_eye_height")]
+    pub eye_height: f64,
+}
+
+impl ObserverConfig {
+    /// Observer at the given latitude and longitude (in degrees), standing on
+    /// the ground.
+    pub fn new(latitude: f64, longitude: f64) -> ObserverConfig {
+        ObserverConfig {
+            latitude,
+            longitude,
+            elevation: None,
+            eye_height: ObserverConfig::default_eye_height(),
+        }
+    }
+
+    fn default_eye_height() -> f64 {
+        2.
+    }
+
+    /// Position of the observer, failing if its coordinates are invalid.
+    pub fn position(&self) -> Result<GeoPoint, GeoError> {
+        GeoPoint::from_degrees(self.longitude, self.latitude)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CameraConfig {
+    /// Azimuth of the center of the image, in degrees or as parsed by
+    /// [`azimuth::parse`](crate::azimuth::parse).
+    #[serde(deserialize_with = "deserialize_azimuth")]
+    pub azimuth: Azimuth,
+    /// Horizontal field of view, in degrees.
+    #[serde(default = "CameraConfig::default_fov")]
+    pub fov: f64,
+    /// Maximum distance, in meters or with a unit like `"100km"`.
+    #[serde(
+        default = "CameraConfig::default_max_distance",
+        deserialize_with = "deserialize_length"
+    )]
+    pub max_distance: Length,
+    #[serde(default = "CameraConfig::default_width")]
+    pub width: usize,
+    #[serde(default = "CameraConfig::default_height")]
+    pub height: usize,
+    /// Projection of the image, `"equirectangular"`, `"cylindrical"`,
+    /// `"rectilinear"`, `"mercator"`, `"fisheye"` or `"stereographic"`.
+    #[serde(default)]
+    pub projection: ProjectionKind,
+    /// Number of columns computed by each task of parallel renders, by
+    /// default chosen by rayon, see
+    /// [`PanoramaComputer::with_chunk_size`](crate::panorama::PanoramaComputer::with_chunk_size).
+    #[serde(default)]
+    pub chunk_size: Option<NonZeroUsize>,
+    /// Memory the render may take, in MiB, failing before computing the
+    /// panorama if the DEM tiles and panoramas in memory, the planes of the
+    /// panorama and the pixels of its image would take more, see
+    /// [`stats`](crate::stats::stats).
+    #[serde(default)]
+    pub max_memory: Option<usize>,
+}
+
+impl CameraConfig {
+    /// Camera looking at `azimuth`, with default settings otherwise.
+    pub fn new(azimuth: Azimuth) -> CameraConfig {
+        CameraConfig {
+            azimuth,
+            fov: CameraConfig::default_fov(),
+            max_distance: CameraConfig::default_max_distance(),
+            width: CameraConfig::default_width(),
+            height: CameraConfig::default_height(),
+            projection: ProjectionKind::default(),
+            chunk_size: None,
+            max_memory: None,
+        }
+    }
+
+    /// Memory the render may take, in bytes.
+    pub fn max_memory_bytes(&self) -> Option<usize> {
+        self.max_memory.map(|mib| mib.saturating_mul(1 << 20))
+    }
+
+    fn default_fov() -> f64 {
+        60.
+    }
+
+    fn default_max_distance() -> Length {
+        Length::from_meters(100_000.)
+    }
+
+    fn default_width() -> usize {
+        2500
+    }
+
+    fn default_height() -> usize {
+        800
+    }
+}
+
+/// Source of the elevation data.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DemConfig {
+    /// Directory containing the tiles, named like `N46E007.hgt` or
+    /// `N46E007.tif` according to their format.
+    pub dir: PathBuf,
+    /// Format of the tiles, `"hgt"` or `"geotiff"`.
+    #[serde(default)]
+    pub format: DemFormat,
+    /// Interpolation between the samples of the tiles, `"bilinear"` or
+    /// `"bicubic"`.
+    #[serde(default, deserialize_with = "deserialize_interpolation")]
+    pub interpolation: Interpolation,
+    /// Memory taken by the tiles read in memory, in MiB, keeping only the
+    /// most recently used ones and reading the others again when needed,
+    /// see [`TileCache`](crate::dem::TileCache). Without it, all the tiles
+    /// are mapped in memory.
+    #[serde(default)]
+    pub memory_budget: Option<usize>,
+}
+
+impl DemConfig {
+    /// Memory budget of the tiles, in bytes.
+    pub fn memory_budget_bytes(&self) -> Option<usize> {
+        self.memory_budget.map(|mib| mib.saturating_mul(1 << 20))
+    }
+}
+
+impl Default for DemConfig {
+    fn default() -> DemConfig {
+        DemConfig {
+            dir: PathBuf::from("."),
+            format: DemFormat::default(),
+            interpolation: Interpolation::default(),
+            memory_budget: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OutputConfig {
+    /// Path of the image file to write.
+    #[serde(default = "OutputConfig::default_path")]
+    pub path: PathBuf,
+    /// Format of the image file, by default given by the extension of its
+    /// path, or PNG.
+    #[serde(default)]
+    pub format: Option<ImageFormat>,
+    /// Quality of JPEG images, in `[1, 100]`.
+    #[serde(default = "OutputConfig::default_quality")]
+    pub quality: u8,
+    #[serde(default)]
+    pub painter: PainterKind,
+    /// Colors of the `palette` painter.
+    #[serde(default)]
+    pub palette: PaletteConfig,
+    /// Instant of the render, in the RFC 3339 format like
+    /// `2024-06-21T19:30:00+02:00`. If given, the sun lighting the
+    /// `hillshade` painter is at its actual position at that instant, and the
+    /// colors of the sky and haze follow its elevation.
+    #[serde(default, deserialize_with = "deserialize_time")]
+    pub time: Option<DateTime<Utc>>,
+    /// Azimuth of the sun lighting the `hillshade` painter without `time`,
+    /// like the azimuth of the camera.
+    #[serde(
+        default = "OutputConfig::default_sun_azimuth",
+        deserialize_with = "deserialize_azimuth"
+    )]
+    pub sun_azimuth: Azimuth,
+    /// Altitude of the sun lighting the `hillshade` painter without `time`,
+    /// in degrees.
+    #[serde(default = "OutputConfig::default_sun_altitude")]
+    pub sun_altitude: f64,
+    /// Sky painted behind the terrain of the `classic` and `hillshade`
+    /// painters, transparent if absent.
+    #[serde(default)]
+    pub sky: Option<SkyConfig>,
+    /// Haze veiling the distant terrain of the `classic` and `hillshade`
+    /// painters, none if absent.
+    #[serde(default)]
+    pub haze: Option<HazeConfig>,
+    /// Shadows cast by the terrain on the `classic` and `hillshade`
+    /// painters, none if absent.
+    #[serde(default)]
+    pub shadows: Option<ShadowConfig>,
+    /// GPX file whose tracks and routes are drawn over the image where they
+    /// are seen, see [`Track::draw`](crate::gpx::Track::draw).
+    #[serde(default)]
+    pub gpx: Option<PathBuf>,
+    /// Directory to which the image is also written as a pyramid of web map
+    /// tiles, see [`tiles::export_tiles`](crate::tiles::export_tiles).
+    #[serde(default)]
+    pub tiles: Option<PathBuf>,
+    /// File to which the depth map of the panorama is also written, see
+    /// [`maps::save_depth`](crate::maps::save_depth).
+    #[serde(default)]
+    pub depth: Option<PathBuf>,
+    /// Image file to which the normal map of the panorama is also written,
+    /// see [`maps::normal_map`](crate::maps::normal_map).
+    #[serde(default)]
+    pub normals: Option<PathBuf>,
+    /// File of the summits to label, an Overpass API response to
+    /// [`overpass::query`](crate::overpass::query). Without it, builds with
+    /// the `overpass` feature fetch them from the Overpass API.
+    #[serde(default)]
+    pub summits: Option<PathBuf>,
+    /// File to which the summits seen in the panorama are also written, see
+    /// [`kml::save`](crate::kml::save).
+    #[serde(default)]
+    pub kml: Option<PathBuf>,
+    /// File to which the computed panorama is also written, to paint it
+    /// again without computing it, see
+    /// [`Panorama::save`](crate::panorama::Panorama::save).
+    #[serde(default)]
+    pub panorama: Option<PathBuf>,
+    /// Directory of the computed panoramas reused when rendering the same
+    /// panorama over the same tiles again, see
+    /// [`PanoramaCache`](crate::panorama::PanoramaCache).
+    #[serde(default)]
+    pub cache: Option<PathBuf>,
+    /// Whether renders of the same configuration over the same tiles must
+    /// give bit-identical outputs. Panoramas are computed column by column,
+    /// the same way whatever the number of threads, but the panoramas of
+    /// the cache are then kept at full precision, apart from the others,
+    /// and the summits are only read from the summits file, as those of the
+    /// Overpass API change over time.
+    #[serde(default)]
+    pub deterministic: bool,
+    /// Photograph taken from the observer onto which the skyline and the
+    /// labels of the panorama are also drawn, none if absent.
+    #[serde(default)]
+    pub photo: Option<PhotoConfig>,
+    /// Marks drawn over the image, none by default.
+    #[serde(default)]
+    pub overlays: OverlayConfig,
+}
+
+impl OutputConfig {
+    fn default_path() -> PathBuf {
+        PathBuf::from("pano.png")
+    }
+
+    fn default_quality() -> u8 {
+        90
+    }
+
+    /// Format of the image file to write.
+    pub fn format(&self) -> ImageFormat {
+        self.format
+            .or_else(|| ImageFormat::from_path(&self.path))
+            .unwrap_or_default()
+    }
+
+    /// North-west, the usual lighting of shaded relief maps.
+    fn default_sun_azimuth() -> Azimuth {
+        Azimuth::from_degrees(315.)
+    }
+
+    fn default_sun_altitude() -> f64 {
+        45.
+    }
+}
+
+impl Default for OutputConfig {
+    fn default() -> OutputConfig {
+        OutputConfig {
+            path: OutputConfig::default_path(),
+            format: None,
+            quality: OutputConfig::default_quality(),
+            painter: PainterKind::default(),
+            palette: PaletteConfig::default(),
+            time: None,
+            sun_azimuth: OutputConfig::default_sun_azimuth(),
+            sun_altitude: OutputConfig::default_sun_altitude(),
+            sky: None,
+            haze: None,
+            shadows: None,
+            gpx: None,
+            tiles: None,
+            depth: None,
+            normals: None,
+            summits: None,
+            kml: None,
+            panorama: None,
+            cache: None,
+            deterministic: false,
+            photo: None,
+            overlays: OverlayConfig::default(),
+        }
+    }
+}
+
+/// Gradient of the sky, see [`painter::sky`](crate::painter::sky).
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SkyConfig {
+    /// Color at the horizon, as RGB in `[0, 1]`.
+    #[serde(default = "SkyConfig::default_horizon")]
+    pub horizon: [f64; 3],
+    /// Color at the zenith, as RGB in `[0, 1]`.
+    #[serde(default = "SkyConfig::default_zenith")]
+    pub zenith: [f64; 3],
+}
+
+impl SkyConfig {
+    /// Horizon color of the sky by day.
+    fn default_horizon() -> [f64; 3] {
+        atmosphere::sky_colors(FRAC_PI_2).0
+    }
+
+    /// Zenith color of the sky by day.
+    fn default_zenith() -> [f64; 3] {
+        atmosphere::sky_colors(FRAC_PI_2).1
+    }
+}
+
+/// Colors of the `palette` painter: those of a preset, unless given.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PaletteConfig {
+    #[serde(default)]
+    pub preset: PalettePreset,
+    /// Colors from the nearest to the farthest terrain, as RGB in `[0, 1]`,
+    /// instead of those of the preset.
+    #[serde(default)]
+    pub colors: Option<Vec<[f64; 3]>>,
+}
+
+impl PaletteConfig {
+    /// Palette of the given colors, or else of the preset.
+    pub fn palette(&self) -> Palette {
+        match &self.colors {
+            Some(colors) => Palette::new(colors.clone()),
+            None => match self.preset {
+                PalettePreset::Colorblind => Palette::colorblind(),
+                PalettePreset::Contrast => Palette::high_contrast(),
+            },
+        }
+    }
+}
+
+/// Palettes of the `palette` painter.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PalettePreset {
+    /// See [`Palette::colorblind`].
+    #[default]
+    Colorblind,
+    /// See [`Palette::high_contrast`].
+    Contrast,
+}
+
+/// Atmospheric haze, see [`painter::haze`](crate::painter::haze).
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HazeConfig {
+    /// Meteorological visibility, in meters or with a unit like `"80km"`.
+    #[serde(
+        default = "HazeConfig::default_visibility",
+        deserialize_with = "deserialize_length"
+    )]
+    pub visibility: Length,
+    /// Color of the haze, as RGB in `[0, 1]`.
+    #[serde(default = "SkyConfig::default_horizon")]
+    pub color: [f64; 3],
+}
+
+impl HazeConfig {
+    /// Haze with the given visibility, of the color of the horizon by day.
+    pub fn new(visibility: Length) -> HazeConfig {
+        HazeConfig {
+            visibility,
+            color: SkyConfig::default_horizon(),
+        }
+    }
+
+    fn default_visibility() -> Length {
+        Length::from_meters(100_000.)
+    }
+
+    /// Atmosphere of the visibility of the haze.
+    pub fn atmosphere(&self) -> Atmosphere {
+        Atmosphere::from_visibility_km(self.visibility.meters() / 1000.)
+    }
+}
+
+impl Default for HazeConfig {
+    fn default() -> HazeConfig {
+        HazeConfig::new(HazeConfig::default_visibility())
+    }
+}
+
+/// Shadows cast by the terrain lit by the sun of the output, see
+/// [`PanoramaComputer::with_shadows`](crate::panorama::PanoramaComputer::with_shadows).
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ShadowConfig {
+    /// Darkening of the terrain in shadow, in `[0, 1]`.
+    #[serde(default = "ShadowConfig::default_darkness")]
+    pub darkness: f64,
+}
+
+impl ShadowConfig {
+    fn default_darkness() -> f64 {
+        0.6
+    }
+}
+
+impl Default for ShadowConfig {
+    fn default() -> ShadowConfig {
+        ShadowConfig {
+            darkness: ShadowConfig::default_darkness(),
+        }
+    }
+}
+
+impl Default for SkyConfig {
+    fn default() -> SkyConfig {
+        SkyConfig {
+            horizon: SkyConfig::default_horizon(),
+            zenith: SkyConfig::default_zenith(),
+        }
+    }
+}
+
+/// Photograph onto which the skyline and the labels of a panorama are drawn,
+/// see [`overlay::composite`](crate::overlay::composite).
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PhotoConfig {
+    /// Image file of the photograph.
+    pub path: PathBuf,
+    /// Image file to which the photograph is written with the skyline and
+    /// the labels.
+    pub out: PathBuf,
+    /// Points of the photograph whose directions are known, at least two,
+    /// from which its framing is calibrated, see
+    /// [`calibration::calibrate`](crate::calibration::calibrate).
+    pub points: Vec<PhotoPoint>,
+    /// Opacity of the skyline and the labels, in `[0, 1]`.
+    #[serde(default = "PhotoConfig::default_opacity")]
+    pub opacity: f64,
+}
+
+impl PhotoConfig {
+    fn default_opacity() -> f64 {
+        0.8
+    }
+}
+
+/// Point of a photograph, typically a summit, seen in a known direction.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PhotoPoint {
+    /// Pixel coordinates in the photograph, y growing downward.
+    pub x: f64,
+    pub y: f64,
+    /// Azimuth of the point, like the azimuth of the camera.
+    #[serde(deserialize_with = "deserialize_azimuth")]
+    pub azimuth: Azimuth,
+    /// Altitude of the point above the horizontal, in degrees.
+    pub altitude: f64,
+}
+
+impl PhotoPoint {
+    pub fn correspondence(&self) -> Correspondence {
+        Correspondence {
+            x: self.x,
+            y: self.y,
+            azimuth: self.azimuth.radians(),
+            altitude: self.altitude.to_radians(),
+        }
+    }
+}
+
+/// Marks drawn over the images of panoramas, see
+/// [`overlay`](crate::overlay).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OverlayConfig {
+    /// Compass ribbon along the bottom of the image, see
+    /// [`overlay::compass`](crate::overlay::compass).
+    #[serde(default)]
+    pub compass: bool,
+    /// Sunrise and sunset over the terrain horizon on the day of the render,
+    /// marked on the horizon and on the compass ribbon with their time.
+    /// Requires the time of the render.
+    #[serde(default)]
+    pub sunrise: bool,
+    /// Daily path of the sun on the day of the render, see
+    /// [`overlay::sun_path`](crate::overlay::sun_path). Requires the time of
+    /// the render.
+    #[serde(default)]
+    pub sun_path: bool,
+    /// Moon in the sky at the time of the render, see
+    /// [`overlay::moon`](crate::overlay::moon). Requires the time of the
+    /// render.
+    #[serde(default)]
+    pub moon: bool,
+    /// Bright stars and planets in the night sky at the time of the render,
+    /// see [`overlay::stars`](crate::overlay::stars). Requires the time of
+    /// the render.
+    #[serde(default)]
+    pub stars: bool,
+    /// Names and elevations of the summits seen in the panorama, see
+    /// [`overlay::labels`](crate::overlay::labels), among the summits of the
+    /// `summits` file of the output or else fetched from the Overpass API.
+    #[serde(default)]
+    pub labels: bool,
+}
+
+/// Error of a configuration whose settings do not fit together.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ConfigError {
+    #[error("the {0} overlay requires the time of the render")]
+    OverlayWithoutTime(&'static str),
+    #[error("the visibility of the haze must be positive")]
+    NonPositiveVisibility,
+    #[error("the opacity of the marks drawn over the photograph must be in [0, 1]")]
+    InvalidPhotoOpacity,
+    #[error("the colors of the palette must be RGB in [0, 1], and at least one")]
+    InvalidPalette,
+}
+
+/// Format of the 1°×1° tiles of a DEM directory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[serde(rename_all = "lowercase")]
+pub enum DemFormat {
+    /// SRTM tiles, see [`HgtDem`].
+    #[default]
+    Hgt,
+    /// Single band GeoTIFF tiles, see
+    /// [`GeoTiffDem`](crate::dem::GeoTiffDem).
+    GeoTiff,
+}
+
+impl DemFormat {
+    /// Name of the file of the tile whose south-west corner is at the given
+    /// latitude and longitude (in degrees), e.g. `N46E007.hgt` or
+    /// `N46E007.tif`.
+    pub fn file_name(self, south: i32, west: i32) -> String {
+        let name = HgtDem::file_name(south, west);
+        match self {
+            DemFormat::Hgt => name,
+            DemFormat::GeoTiff => name.replace(".hgt", ".tif"),
+        }
+    }
+}
+
+/// Painting of the terrain of rendered panoramas.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[serde(rename_all = "lowercase")]
+pub enum PainterKind {
+    /// Shades of grey by distance, over a blue sky, see
+    /// [`Image::from_panorama`](crate::image::Image::from_panorama).
+    #[default]
+    Grey,
+    /// Hue by distance and brightness by slope, see
+    /// [`painter::classic`](crate::painter::classic).
+    Classic,
+    /// Shades of grey lit by the sun, see
+    /// [`painter::shaded_relief`](crate::painter::shaded_relief).
+    Hillshade,
+    /// Colors of the palette of the output by distance, see
+    /// [`painter::palette`](crate::painter::palette).
+    Palette,
+}
+
+impl RenderConfig {
+    pub fn from_toml(s: &str) -> Result<RenderConfig, toml::de::Error> {
+        toml::from_str(s)
+    }
+
+    /// Read the configuration from the TOML file at the given path, failing
+    /// if it is not [valid](RenderConfig::validate).
+    pub fn load(path: impl AsRef<Path>) -> io::Result<RenderConfig> {
+        let config = RenderConfig::from_toml(&fs::read_to_string(path)?)
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+        config
+            .validate()
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+        Ok(config)
+    }
+
+    /// Check that the settings fit together, e.g. that the time of the render
+    /// is given for the overlays depending on it, and that the visibility of
+    /// the haze is positive.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let overlays = &self.output.overlays;
+        for (enabled, name) in [
+            (overlays.sunrise, "sunrise"),
+            (overlays.sun_path, "sun_path"),
+            (overlays.moon, "moon"),
+            (overlays.stars, "stars"),
+        ] {
+            if enabled && self.output.time.is_none() {
+                return Err(ConfigError::OverlayWithoutTime(name));
+            }
+        }
+        if let Some(haze) = &self.output.haze {
+            let visibility = haze.visibility.meters();
+            if visibility <= 0. || visibility.is_nan() {
+                return Err(ConfigError::NonPositiveVisibility);
+            }
+        }
+        if let Some(photo) = &self.output.photo {
+            if !(0. ..=1.).contains(&photo.opacity) {
+                return Err(ConfigError::InvalidPhotoOpacity);
+            }
+        }
+        if let Some(colors) = &self.output.palette.colors {
+            if colors.is_empty()
+                || colors
+                    .as_flattened()
+                    .iter()
+                    .any(|c| !(0. ..=1.).contains(c))
+            {
+                return Err(ConfigError::InvalidPalette);
+            }
+        }
+        Ok(())
+    }
+
+    /// Settings which are valid but likely unwanted, like a palette whose
+    /// nearest and farthest colors are hard to tell apart, to warn about.
+    /// The configuration must be [valid](RenderConfig::validate).
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if self.output.painter == PainterKind::Palette {
+            let contrast = self.output.palette.palette().contrast_ratio();
+            if contrast < MIN_PALETTE_CONTRAST {
+                warnings.push(format!(
+                    "the nearest and farthest colors of the palette have a contrast ratio \
+                     of {:.1}, below {}, which makes distances hard to tell apart",
+                    contrast, MIN_PALETTE_CONTRAST
+                ));
+            }
+        }
+        warnings
+    }
+
+    /// Parameters of the panorama to render, failing if they are invalid or
+    /// if the elevation of the observer is not given, see
+    /// [`parameters_over`](RenderConfig::parameters_over).
+    pub fn parameters(&self) -> Result<PanoramaParameters, AlpanoError> {
+        let elevation = self
+            .observer
+            .elevation
+            .ok_or(RenderError::UnknownObserverElevation)?;
+        self.parameters_at(elevation)
+    }
+
+    /// Parameters of the panorama to render over `cem`, where the observer
+    /// stands `eye_height` above the ground unless its elevation is given,
+    /// failing if they are invalid.
+    pub fn parameters_over<D: DiscreteElevationModel>(
+        &self,
+        cem: &ContinuousElevationModel<D>,
+    ) -> Result<PanoramaParameters, AlpanoError> {
+        let elevation = match self.observer.elevation {
+            Some(elevation) => elevation,
+            None => cem.elevation_at(&self.observer.position()?) + self.observer.eye_height,
+        };
+        self.parameters_at(elevation)
+    }
+
+    /// Parameters of the panorama to render with the observer at `elevation`.
+    fn parameters_at(&self, elevation: f64) -> Result<PanoramaParameters, AlpanoError> {
+        let parameters = PanoramaParameters::new_with_projection(
+            self.observer.position()?,
+            elevation,
+            self.camera.azimuth,
+            self.camera.fov.to_radians(),
+            self.camera.max_distance.meters(),
+            self.camera.width,
+            self.camera.height,
+            self.camera.projection,
+        )?;
+        Ok(parameters)
+    }
+
+    /// Position of the sun seen by the observer at the instant of the
+    /// render, if any.
+    pub fn sun_position(&self) -> Option<SolarPosition> {
+        self.output.time.map(|time| {
+            sun::position(
+                time,
+                self.observer.latitude.to_radians(),
+                self.observer.longitude.to_radians(),
+            )
+        })
+    }
+
+    /// Azimuth and altitude (in radians) of the sun lighting the terrain:
+    /// its position at the instant of the render if any, and otherwise the
+    /// configured one.
+    pub fn sun_direction(&self) -> (Azimuth, f64) {
+        match self.sun_position() {
+            Some(sun) => (sun.azimuth, sun.elevation),
+            None => (
+                self.output.sun_azimuth,
+                self.output.sun_altitude.to_radians(),
+            ),
+        }
+    }
+
+    /// Configuration of the panorama of `parameters`, with default settings
+    /// of the DEM and output.
+    pub fn from_parameters(parameters: &PanoramaParameters) -> RenderConfig {
+        let observer = parameters.observer_position();
+        RenderConfig {
+            observer: ObserverConfig {
+                elevation: Some(parameters.observer_elevation()),
+                ..ObserverConfig::new(
+                    observer.latitude().to_degrees(),
+                    observer.longitude().to_degrees(),
+                )
+            },
+            camera: CameraConfig {
+                azimuth: parameters.center_azimuth(),
+                fov: parameters.horizontal_field_of_view().to_degrees(),
+                max_distance: Length::from_meters(parameters.max_distance()),
+                width: parameters.width(),
+                height: parameters.height(),
+                projection: parameters.projection(),
+                chunk_size: None,
+                max_memory: None,
+            },
+            dem: DemConfig::default(),
+            output: OutputConfig::default(),
+        }
+    }
+}
+
+/// Number or string value of a configuration entry.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum NumberOrString {
+    Number(f64),
+    String(String),
+}
+
+pub(crate) fn deserialize_azimuth<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Azimuth, D::Error> {
+    match NumberOrString::deserialize(deserializer)? {
+        NumberOrString::Number(degrees) => {
+            Azimuth::try_new(Deg(degrees)).map_err(serde::de::Error::custom)
+        }
+        NumberOrString::String(s) => s.parse().map_err(serde::de::Error::custom),
+    }
+}
+
+fn deserialize_length<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Length, D::Error> {
+    match NumberOrString::deserialize(deserializer)? {
+        NumberOrString::Number(meters) => Ok(Length::from_meters(meters)),
+        NumberOrString::String(s) => s.parse().map_err(serde::de::Error::custom),
+    }
+}
+
+/// Instant given as a TOML date-time or an RFC 3339 string.
+fn deserialize_time<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<DateTime<Utc>>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum DatetimeOrString {
+        Datetime(toml::value::Datetime),
+        String(String),
+    }
+
+    let s = match DatetimeOrString::deserialize(deserializer)? {
+        DatetimeOrString::Datetime(datetime) => datetime.to_string(),
+        DatetimeOrString::String(s) => s,
+    };
+    DateTime::parse_from_rfc3339(&s)
+        .map(|time| Some(time.with_timezone(&Utc)))
+        .map_err(|e| serde::de::Error::custom(format!("invalid time: {}, {}", s, e)))
+}
+
+fn deserialize_interpolation<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Interpolation, D::Error> {
+    match String::deserialize(deserializer)?.as_str() {
+        "bilinear" => Ok(Interpolation::Bilinear),
+        "bicubic" => Ok(Interpolation::Bicubic),
+        s => Err(serde::de::Error::custom(format!(
+            "invalid interpolation: {}, expected bilinear or bicubic",
+            s
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod config_tests {
+    use super::*;
+    use crate::{
+        dem::MemoryDem,
+        distance::LengthUnit,
+        interval::{Interval1D, Interval2D},
+    };
+    use assert_approx_eq::assert_approx_eq;
+
+    const NIESEN: &str = r#"
+        [observer]
+        latitude = 46.6456
+        longitude = 7.6496
+        elevation = 2362
+
+        [camera]
+        azimuth = "S"
+        fov = 45
+        max_distance = "120km"
+        width = 1000
+
+        [dem]
+        dir = "srtm"
+        interpolation = "bicubic"
+
+        [output]
+        path = "niesen.png"
+        painter = "classic"
+    "#;
+
+    #[test]
+    fn from_toml_reads_every_section() {
+        let config = RenderConfig::from_toml(NIESEN).unwrap();
+        assert_eq!(Some(2362.), config.observer.elevation);
+        assert_eq!(Azimuth::SOUTH, config.camera.azimuth);
+        assert_eq!(45., config.camera.fov);
+        assert_eq!(
+            Length::new(120., LengthUnit::Kilometers),
+            config.camera.max_distance
+        );
+        assert_eq!(1000, config.camera.width);
+        assert_eq!(800, config.camera.height);
+        assert_eq!(PathBuf::from("srtm"), config.dem.dir);
+        assert_eq!(Interpolation::Bicubic, config.dem.interpolation);
+        assert_eq!(PathBuf::from("niesen.png"), config.output.path);
+        assert_eq!(PainterKind::Classic, config.output.painter);
+        assert_eq!(ProjectionKind::Equirectangular, config.camera.projection);
+    }
+
+    #[test]
+    fn from_toml_reads_the_format_and_memory_budget_of_tiles() {
+        assert_eq!(
+            DemFormat::Hgt,
+            RenderConfig::from_toml(NIESEN).unwrap().dem.format
+        );
+        let config = RenderConfig::from_toml(&NIESEN.replace(
+            r#"dir = "srtm""#,
+            "dir = \"glo30\"\nformat = \"geotiff\"\nmemory_budget = 512",
+        ))
+        .unwrap();
+        assert_eq!(DemFormat::GeoTiff, config.dem.format);
+        assert_eq!("N46E007.tif", config.dem.format.file_name(46, 7));
+        assert_eq!("S01W072.hgt", DemFormat::Hgt.file_name(-1, -72));
+        assert_eq!(Some(512 << 20), config.dem.memory_budget_bytes());
+    }
+
+    #[test]
+    fn parameters_over_put_observers_without_elevation_on_the_ground() {
+        let config =
+            RenderConfig::from_toml(&NIESEN.replace("elevation = 2362", "eye_height = 10"))
+                .unwrap();
+        assert_eq!(None, config.observer.elevation);
+        assert!(matches!(
+            config.parameters(),
+            Err(AlpanoError::Render(RenderError::UnknownObserverElevation))
+        ));
+
+        let extent = Interval2D::new(Interval1D::new(760, 770), Interval1D::new(4660, 4670));
+        let cem = ContinuousElevationModel::new(MemoryDem::from_fn(100, extent, |_, _| 2300.));
+        let parameters = config.parameters_over(&cem).unwrap();
+        assert_eq!(2310., parameters.observer_elevation());
+        let given = RenderConfig::from_toml(NIESEN).unwrap();
+        assert_eq!(
+            given.parameters().unwrap(),
+            given.parameters_over(&cem).unwrap()
+        );
+        let standing = RenderConfig::from_toml(&NIESEN.replace("elevation = 2362", "")).unwrap();
+        assert_eq!(2., standing.observer.eye_height);
+    }
+
+    #[test]
+    fn parameters_use_projection_of_camera() {
+        let config = RenderConfig::from_toml(
+            &NIESEN.replace("width = 1000", "width = 1000\nprojection = \"rectilinear\""),
+        )
+        .unwrap();
+        assert_eq!(ProjectionKind::Rectilinear, config.camera.projection);
+        assert_eq!(
+            ProjectionKind::Rectilinear,
+            config.parameters().unwrap().projection()
+        );
+
+        let config = RenderConfig::from_toml(
+            &NIESEN.replace("fov = 45", "fov = 200\nprojection = \"rectilinear\""),
+        )
+        .unwrap();
+        assert!(matches!(config.parameters(), Err(AlpanoError::Render(_))));
+
+        // too tall for the default projection, but not for a little planet
+        let config = RenderConfig::from_toml(&NIESEN.replace(
+            "fov = 45",
+            "fov = 270\nheight = 1000\nprojection = \"stereographic\"",
+        ))
+        .unwrap();
+        assert!(config.parameters().is_ok());
+    }
+
+    #[test]
+    fn from_toml_reads_sun_of_hillshade() {
+        let config = RenderConfig::from_toml(&NIESEN.replace(
+            "painter = \"classic\"",
+            "painter = \"hillshade\"\nsun_azimuth = \"W\"\nsun_altitude = 20",
+        ))
+        .unwrap();
+        assert_eq!(PainterKind::Hillshade, config.output.painter);
+        assert_eq!(Azimuth::WEST, config.output.sun_azimuth);
+        assert_eq!(20., config.output.sun_altitude);
+        let config = RenderConfig::from_toml(NIESEN).unwrap();
+        assert_approx_eq!(315., config.output.sun_azimuth.degrees(), 1e-9);
+        assert_eq!(45., config.output.sun_altitude);
+    }
+
+    #[test]
+    fn format_defaults_to_extension_then_png() {
+        let mut output = OutputConfig::default();
+        assert_eq!(ImageFormat::Png, output.format());
+        output.path = PathBuf::from("pano.webp");
+        assert_eq!(ImageFormat::WebP, output.format());
+        output.path = PathBuf::from("pano.raw");
+        assert_eq!(ImageFormat::Png, output.format());
+
+        let config = RenderConfig::from_toml(&NIESEN.replace(
+            "painter = \"classic\"",
+            "painter = \"classic\"\nformat = \"jpeg\"\nquality = 75",
+        ))
+        .unwrap();
+        assert_eq!(ImageFormat::Jpeg, config.output.format());
+        assert_eq!(75, config.output.quality);
+    }
+
+    #[test]
+    fn from_toml_reads_tiles() {
+        assert_eq!(None, RenderConfig::from_toml(NIESEN).unwrap().output.tiles);
+        let config = RenderConfig::from_toml(&NIESEN.replace(
+            "painter = \"classic\"",
+            "painter = \"classic\"\ntiles = \"niesen-tiles\"",
+        ))
+        .unwrap();
+        assert_eq!(Some(PathBuf::from("niesen-tiles")), config.output.tiles);
+    }
+
+    #[test]
+    fn from_toml_reads_sky() {
+        let config = RenderConfig::from_toml(NIESEN).unwrap();
+        assert_eq!(None, config.output.sky);
+        let config =
+            RenderConfig::from_toml(&format!("{}\n[output.sky]\nzenith = [0, 0, 0.5]", NIESEN))
+                .unwrap();
+        let sky = config.output.sky.unwrap();
+        assert_eq!(SkyConfig::default().horizon, sky.horizon);
+        assert_eq!([0., 0., 0.5], sky.zenith);
+    }
+
+    #[test]
+    fn from_toml_reads_haze() {
+        let config = RenderConfig::from_toml(NIESEN).unwrap();
+        assert_eq!(None, config.output.haze);
+        let config =
+            RenderConfig::from_toml(&format!("{}\n[output.haze]\nvisibility = \"40km\"", NIESEN))
+                .unwrap();
+        let haze = config.output.haze.unwrap();
+        assert_eq!(40_000., haze.visibility.meters());
+        assert_eq!(SkyConfig::default().horizon, haze.color);
+        assert_approx_eq!(40., haze.atmosphere().visibility_km(), 1e-9);
+        assert_eq!(Ok(()), config.validate());
+    }
+
+    #[test]
+    fn from_toml_reads_palettes() {
+        let config = RenderConfig::from_toml(&NIESEN.replace("classic", "palette")).unwrap();
+        assert_eq!(PainterKind::Palette, config.output.painter);
+        assert_eq!(Palette::colorblind(), config.output.palette.palette());
+        assert!(config.warnings().is_empty());
+
+        let config = RenderConfig::from_toml(&format!(
+            "{}\n[output.palette]\npreset = \"contrast\"",
+            NIESEN
+        ))
+        .unwrap();
+        assert_eq!(Palette::high_contrast(), config.output.palette.palette());
+
+        let config = RenderConfig::from_toml(&format!(
+            "{}\n[output.palette]\ncolors = [[0.2, 0.4, 0.2], [0.3, 0.4, 0.3]]",
+            NIESEN.replace("classic", "palette")
+        ))
+        .unwrap();
+        assert_eq!(Ok(()), config.validate());
+        assert_eq!(
+            &[[0.2, 0.4, 0.2], [0.3, 0.4, 0.3]],
+            config.output.palette.palette().colors()
+        );
+        // too close to tell the near terrain from the far one
+        let warnings = config.warnings();
+        assert_eq!(1, warnings.len());
+        assert!(warnings[0].contains("contrast ratio of 1.1"));
+    }
+
+    #[test]
+    fn validate_rejects_invalid_palettes() {
+        for colors in ["[]", "[[0, 0, 0], [1, 1.5, 1]]", "[[0, -0.1, 0]]"] {
+            let config = RenderConfig::from_toml(&format!(
+                "{}\n[output.palette]\ncolors = {}",
+                NIESEN, colors
+            ))
+            .unwrap();
+            assert_eq!(Err(ConfigError::InvalidPalette), config.validate());
+        }
+    }
+
+    #[test]
+    fn validate_rejects_haze_without_positive_visibility() {
+        for visibility in ["0", "-5000", "\"-80km\"", "nan"] {
+            let config = RenderConfig::from_toml(&format!(
+                "{}\n[output.haze]\nvisibility = {}",
+                NIESEN, visibility
+            ))
+            .unwrap();
+            assert_eq!(Err(ConfigError::NonPositiveVisibility), config.validate());
+        }
+    }
+
+    #[test]
+    fn from_toml_reads_photos() {
+        let config = RenderConfig::from_toml(&format!(
+            "{}\n[output.photo]\npath = \"photo.jpg\"\nout = \"composite.png\"\n\
+             points = [{{ x = 120, y = 40, azimuth = 180.5, altitude = 2 }}, \
+                       {{ x = 900, y = 60.5, azimuth = \"S10°W\", altitude = 1.5 }}]",
+            NIESEN
+        ))
+        .unwrap();
+        let photo = config.output.photo.as_ref().unwrap();
+        assert_eq!(PathBuf::from("composite.png"), photo.out);
+        assert_eq!(0.8, photo.opacity);
+        let points: Vec<Correspondence> = photo.points.iter().map(|p| p.correspondence()).collect();
+        assert_eq!((900., 60.5), (points[1].x, points[1].y));
+        assert_approx_eq!(190_f64.to_radians(), points[1].azimuth, 1e-12);
+        assert_approx_eq!(2_f64.to_radians(), points[0].altitude, 1e-12);
+        assert_eq!(Ok(()), config.validate());
+
+        for opacity in ["-0.1", "1.5", "nan"] {
+            let config = RenderConfig::from_toml(&format!(
+                "{}\n[output.photo]\npath = \"photo.jpg\"\nout = \"composite.png\"\n\
+                 points = []\nopacity = {}",
+                NIESEN, opacity
+            ))
+            .unwrap();
+            assert_eq!(Err(ConfigError::InvalidPhotoOpacity), config.validate());
+        }
+    }
+
+    #[test]
+    fn from_toml_applies_defaults() {
+        let config = RenderConfig::from_toml(
+            "observer = { latitude = 46.5, longitude = 7, elevation = 500 }\n\
+             camera = { azimuth = 90 }",
+        )
+        .unwrap();
+        assert_eq!(Azimuth::EAST, config.camera.azimuth);
+        assert_eq!(60., config.camera.fov);
+        assert_eq!(100_000., config.camera.max_distance.meters());
+        assert_eq!((2500, 800), (config.camera.width, config.camera.height));
+        assert_eq!(DemConfig::default(), config.dem);
+        assert_eq!(OutputConfig::default(), config.output);
+    }
+
+    #[test]
+    fn from_toml_fails_on_invalid_input() {
+        assert!(RenderConfig::from_toml("").is_err());
+        assert!(RenderConfig::from_toml(&NIESEN.replace("\"S\"", "\"X\"")).is_err());
+        assert!(RenderConfig::from_toml(&NIESEN.replace("\"S\"", "nan")).is_err());
+        assert!(RenderConfig::from_toml(&NIESEN.replace("120km", "120 parsecs")).is_err());
+        assert!(RenderConfig::from_toml(&NIESEN.replace("classic", "neon")).is_err());
+        assert!(RenderConfig::from_toml(&NIESEN.replace("bicubic", "nearest")).is_err());
+        assert!(RenderConfig::from_toml(&NIESEN.replace("fov", "zoom")).is_err());
+    }
+
+    #[test]
+    fn parameters_are_in_radians_and_meters() {
+        let parameters = RenderConfig::from_toml(NIESEN)
+            .unwrap()
+            .parameters()
+            .unwrap();
+        assert_approx_eq!(
+            7.6496_f64.to_radians(),
+            parameters.observer_position().longitude(),
+            1e-12
+        );
+        assert_approx_eq!(
+            45_f64.to_radians(),
+            parameters.horizontal_field_of_view(),
+            1e-12
+        );
+        assert_eq!(120_000., parameters.max_distance());
+
+        let config = RenderConfig::from_toml(&NIESEN.replace("46.6456", "91")).unwrap();
+        assert!(matches!(config.parameters(), Err(AlpanoError::Geo(_))));
+        let config = RenderConfig::from_toml(&NIESEN.replace("fov = 45", "fov = 0")).unwrap();
+        assert!(matches!(config.parameters(), Err(AlpanoError::Render(_))));
+    }
+
+    #[test]
+    fn from_parameters_describes_the_same_panorama() {
+        let mut config = RenderConfig::from_toml(NIESEN).unwrap();
+        config.camera.projection = ProjectionKind::Rectilinear;
+        let parameters = config.parameters().unwrap();
+        let from_parameters = RenderConfig::from_parameters(&parameters);
+
+        assert_eq!(config.camera.width, from_parameters.camera.width);
+        assert_eq!(
+            ProjectionKind::Rectilinear,
+            from_parameters.camera.projection
+        );
+        assert_approx_eq!(46.6456, from_parameters.observer.latitude, 1e-12);
+        assert_approx_eq!(45., from_parameters.camera.fov, 1e-12);
+        assert_eq!(
+            parameters.max_distance(),
+            from_parameters.parameters().unwrap().max_distance()
+        );
+    }
+
+    #[test]
+    fn from_toml_reads_time() {
+        let config = RenderConfig::from_toml(NIESEN).unwrap();
+        assert_eq!(None, config.output.time);
+        assert_eq!(None, config.sun_position());
+        assert_eq!(
+            (Azimuth::from_degrees(315.), 45_f64.to_radians()),
+            config.sun_direction()
+        );
+
+        let summer_evening = DateTime::parse_from_rfc3339("2024-06-21T17:30:00Z").unwrap();
+        for time in [
+            "2024-06-21T19:30:00+02:00",
+            "\"2024-06-21T19:30:00+02:00\"",
+            "\"2024-06-21T17:30:00Z\"",
+        ] {
+            let config = RenderConfig::from_toml(&NIESEN.replace(
+                "painter = \"classic\"",
+                &format!("painter = \"classic\"\ntime = {}", time),
+            ))
+            .unwrap();
+            assert_eq!(Some(summer_evening.with_timezone(&Utc)), config.output.time);
+            let (azimuth, altitude) = config.sun_direction();
+            // low in the west north-west
+            assert!((280. ..300.).contains(&azimuth.degrees()));
+            assert!((10. ..20.).contains(&altitude.to_degrees()));
+        }
+
+        for time in ["2024-06-21", "\"yesterday\""] {
+            assert!(RenderConfig::from_toml(
+                &NIESEN.replace("painter = \"classic\"", &format!("time = {}", time))
+            )
+            .is_err());
+        }
+    }
+
+    #[test]
+    fn from_toml_reads_overlays() {
+        let config = RenderConfig::from_toml(NIESEN).unwrap();
+        assert_eq!(OverlayConfig::default(), config.output.overlays);
+        assert_eq!(Ok(()), config.validate());
+
+        let overlays = format!(
+            "{}\n[output.overlays]\ncompass = true\nsunrise = true\nlabels = true",
+            NIESEN
+        );
+        let config = RenderConfig::from_toml(&overlays).unwrap();
+        assert!(config.output.overlays.compass && config.output.overlays.sunrise);
+        assert!(config.output.overlays.labels);
+        assert_eq!(
+            Err(ConfigError::OverlayWithoutTime("sunrise")),
+            config.validate()
+        );
+        let config = RenderConfig::from_toml(&overlays.replace(
+            "painter = \"classic\"",
+            "painter = \"classic\"\ntime = 2024-06-21T19:30:00+02:00",
+        ))
+        .unwrap();
+        assert_eq!(Ok(()), config.validate());
+        let sun_path =
+            RenderConfig::from_toml(&format!("{}\n[output.overlays]\nsun_path = true", NIESEN))
+                .unwrap();
+        assert_eq!(
+            Err(ConfigError::OverlayWithoutTime("sun_path")),
+            sun_path.validate()
+        );
+        let moon = RenderConfig::from_toml(&format!("{}\n[output.overlays]\nmoon = true", NIESEN))
+            .unwrap();
+        assert_eq!(
+            Err(ConfigError::OverlayWithoutTime("moon")),
+            moon.validate()
+        );
+        assert!(
+            RenderConfig::from_toml(&format!("{}\n[output.overlays]\nrose = true", NIESEN))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn from_toml_reads_shadows() {
+        assert_eq!(
+            None,
+            RenderConfig::from_toml(NIESEN).unwrap().output.shadows
+        );
+        let config = RenderConfig::from_toml(&format!("{}\n[output.shadows]\n", NIESEN)).unwrap();
+        assert_eq!(Some(ShadowConfig::default()), config.output.shadows);
+        let config =
+            RenderConfig::from_toml(&format!("{}\n[output.shadows]\ndarkness = 0.3\n", NIESEN))
+                .unwrap();
+        assert_eq!(Some(0.3), config.output.shadows.map(|s| s.darkness));
+    }
+}
@@ -0,0 +1,255 @@
+use std::{
+    fs,
+    io::{Error, ErrorKind, Result},
+    ops::RangeInclusive,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use super::{DiscreteElevationModel, HgtDem};
+use crate::{
+    interval::{Interval1D, Interval2D},
+    stats::Cached,
+};
+
+/// Function loading the 1°×1° tile whose south-west corner is at the given
+/// latitude and longitude, in degrees.
+type TileLoader = Box<dyn Fn(i32, i32) -> Result<Box<dyn DiscreteElevationModel>> + Send + Sync>;
+
+/// Tile in memory, with the latitude and longitude of its south-west corner,
+/// counted in the caches of [`stats`](crate::stats::stats) until evicted.
+type ResidentTile = (i32, i32, Cached, Arc<dyn DiscreteElevationModel>);
+
+/// Discrete elevation model over a rectangle of 1°×1° tiles, such as the SRTM
+/// tiles of a directory, keeping only the most recently used tiles in memory
+/// and reloading the others when needed.
+pub struct TileCache {
+    south: RangeInclusive<i32>,
+    west: RangeInclusive<i32>,
+    samples_per_degree: u32,
+    capacity: usize,
+    load: TileLoader,
+    /// Resident tiles, from the most to the least recently used.
+    tiles: Mutex<Vec<ResidentTile>>,
+}
+
+impl TileCache {
+    /// Create a cache over the tiles of `dir` whose south-west corners are
+    /// in the given ranges of latitudes and longitudes (in degrees), keeping
+    /// at most `memory_budget` bytes of tiles in memory (but at least one
+    /// tile).
+    ///
+    /// Fails unless all the tiles exist and have the same resolution.
+    pub fn new(
+        dir: impl Into<PathBuf>,
+        south: RangeInclusive<i32>,
+        west: RangeInclusive<i32>,
+        memory_budget: usize,
+    ) -> Result<TileCache> {
+        let dir = dir.into();
+        let mut sizes = Vec::new();
+        for s in south.clone() {
+            for w in west.clone() {
+                sizes.push(fs::metadata(dir.join(HgtDem::file_name(s, w)))?.len());
+            }
+        }
+
+        let side = match sizes.first() {
+            Some(&n) if n == 3601 * 3601 * 2 => 3601,
+            Some(&n) if n == 1201 * 1201 * 2 => 1201,
+            Some(&n) => return Err(invalid_input(format!("invalid HGT file size: {}", n))),
+            None => return Err(invalid_input("empty range of tiles".to_string())),
+        };
+        if sizes.iter().any(|&n| n != sizes[0]) {
+            return Err(invalid_input(
+                "tiles have different resolutions".to_string(),
+            ));
+        }
+
+        Ok(TileCache::with_loader(
+            south,
+            west,
+            side as u32 - 1,
+            (memory_budget / (side * side * 2)).max(1),
+            move |s, w| Ok(Box::new(HgtDem::open(dir.join(HgtDem::file_name(s, w)))?)),
+        ))
+    }
+
+    /// Create a cache over the tiles whose south-west corners are in the given
+    /// ranges of latitudes and longitudes (in degrees), loaded by `load` from
+    /// any source, keeping at most `capacity` tiles (but at least one) in
+    /// memory.
+    ///
+    /// The tiles must all have the given resolution, and a tile which cannot
+    /// be loaded when needed makes the cache panic.
+    pub fn with_loader<D: DiscreteElevationModel + 'static>(
+        south: RangeInclusive<i32>,
+        west: RangeInclusive<i32>,
+        samples_per_degree: u32,
+        capacity: usize,
+        load: impl Fn(i32, i32) -> Result<D> + Send + Sync + 'static,
+    ) -> TileCache {
+        TileCache {
+            south,
+            west,
+            samples_per_degree,
+            capacity: capacity.max(1),
+            load: Box::new(move |s, w| Ok(Box::new(load(s, w)?))),
+            tiles: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Maximum number of tiles kept in memory.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Number of tiles currently in memory.
+    pub fn resident(&self) -> usize {
+        self.tiles.lock().unwrap().len()
+    }
+
+    /// Tile whose south-west corner is at the given latitude and longitude,
+    /// loading it and evicting the least recently used tile if needed.
+    pub(crate) fn tile(&self, south: i32, west: i32) -> Arc<dyn DiscreteElevationModel> {
+        let mut tiles = self.tiles.lock().unwrap();
+        if let Some(i) = tiles.iter().position(|&(s, w, ..)| (s, w) == (south, west)) {
+            let entry = tiles.remove(i);
+            let tile = Arc::clone(&entry.3);
+            tiles.insert(0, entry);
+            return tile;
+        }
+
+        let tile: Arc<dyn DiscreteElevationModel> = Arc::from(
+            (self.load)(south, west)
+                .unwrap_or_else(|e| panic!("cannot reload tile {} {}: {}", south, west, e)),
+        );
+        assert_eq!(
+            self.samples_per_degree,
+            tile.samples_per_degree(),
+            "tile {} {} has a different resolution",
+            south,
+            west
+        );
+        tiles.truncate(self.capacity - 1);
+        let cached = Cached::new(tile.memory_size());
+        tiles.insert(0, (south, west, cached, Arc::clone(&tile)));
+        tile
+    }
+}
+
+impl DiscreteElevationModel for TileCache {
+    fn samples_per_degree(&self) -> u32 {
+        self.samples_per_degree
+    }
+
+    fn extent(&self) -> Interval2D {
+        let spd = self.samples_per_degree as i32;
+        Interval2D::new(
+            Interval1D::new(self.west.start() * spd, (self.west.end() + 1) * spd),
+            Interval1D::new(self.south.start() * spd, (self.south.end() + 1) * spd),
+        )
+    }
+
+    fn elevation_sample(&self, x: i32, y: i32) -> f64 {
+        assert!(
+            self.extent().contains(x, y),
+            "sample ({}, {}) outside of tile cache",
+            x,
+            y
+        );
+        // samples on the boundary of two tiles are in both
+        let spd = self.samples_per_degree as i32;
+        let south = y.div_euclid(spd).min(*self.south.end());
+        let west = x.div_euclid(spd).min(*self.west.end());
+        self.tile(south, west).elevation_sample(x, y)
+    }
+}
+
+fn invalid_input(message: String) -> Error {
+    Error::new(ErrorKind::InvalidInput, message)
+}
+
+#[cfg(test)]
+mod cache_tests {
+    use super::*;
+
+    /// Directory containing the 3" tiles of the given corners, each at a
+    /// constant elevation computed from its corner.
+    fn tiles_dir(name: &str, corners: &[(i32, i32)]) -> PathBuf {
+        let dir = std::env::temp_dir().join("alpano_cache_tests").join(name);
+        fs::create_dir_all(&dir).unwrap();
+        for &(south, west) in corners {
+            let elevation = (south * 100 + west) as i16;
+            let bytes = elevation.to_be_bytes().repeat(1201 * 1201);
+            fs::write(dir.join(HgtDem::file_name(south, west)), bytes).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn elevation_sample_reloads_evicted_tiles() {
+        let dir = tiles_dir("square", &[(46, 7), (46, 8), (47, 7), (47, 8)]);
+        let cache = TileCache::new(dir, 46..=47, 7..=8, 2 * 1201 * 1201 * 2).unwrap();
+        assert_eq!(2, cache.capacity());
+        assert_eq!(
+            Interval2D::new(
+                Interval1D::new(7 * 1200, 9 * 1200),
+                Interval1D::new(46 * 1200, 48 * 1200)
+            ),
+            cache.extent()
+        );
+
+        for _ in 0..2 {
+            for (south, west) in [(46, 7), (46, 8), (47, 7), (47, 8)] {
+                let (x, y) = (west * 1200 + 600, south * 1200 + 600);
+                assert_eq!((south * 100 + west) as f64, cache.elevation_sample(x, y));
+                assert!(cache.resident() <= 2);
+            }
+        }
+        // north-east corner of the cache
+        assert_eq!(4708., cache.elevation_sample(9 * 1200, 48 * 1200));
+    }
+
+    #[test]
+    fn new_keeps_at_least_one_tile() {
+        let dir = tiles_dir("single", &[(46, 7)]);
+        let cache = TileCache::new(dir, 46..=46, 7..=7, 0).unwrap();
+        assert_eq!(1, cache.capacity());
+        assert_eq!(4607., cache.elevation_sample(7 * 1200, 46 * 1200));
+        assert_eq!(1, cache.resident());
+    }
+
+    #[test]
+    fn new_fails_on_missing_or_invalid_tiles() {
+        let dir = tiles_dir("missing", &[(46, 7)]);
+        assert!(TileCache::new(&dir, 46..=46, 7..=8, usize::MAX).is_err());
+        fs::write(dir.join("N46E009.hgt"), [0; 10]).unwrap();
+        assert!(TileCache::new(&dir, 46..=46, 9..=9, usize::MAX).is_err());
+    }
+
+    #[test]
+    fn with_loader_works_with_any_source() {
+        use crate::dem::MemoryDem;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let loads = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&loads);
+        let cache = TileCache::with_loader(0..=0, 0..=2, 10, 1, move |south, west| {
+            counter.fetch_add(1, Ordering::Relaxed);
+            let extent = Interval2D::new(
+                Interval1D::new(west * 10, west * 10 + 10),
+                Interval1D::new(south * 10, south * 10 + 10),
+            );
+            Ok(MemoryDem::from_fn(10, extent, |x, _| x as f64))
+        });
+
+        assert_eq!(25., cache.elevation_sample(25, 5));
+        assert_eq!(25., cache.elevation_sample(25, 6));
+        assert_eq!(1, loads.load(Ordering::Relaxed));
+        assert_eq!(5., cache.elevation_sample(5, 5));
+        assert_eq!(25., cache.elevation_sample(25, 5));
+        assert_eq!(3, loads.load(Ordering::Relaxed));
+        assert_eq!(1, cache.resident());
+    }
+}
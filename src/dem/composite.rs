@@ -0,0 +1,147 @@
+use super::{DemError, DiscreteElevationModel};
+use crate::interval::Interval2D;
+
+/// Discrete elevation model made of several models of the same resolution
+/// which together cover a rectangle, like adjacent SRTM tiles.
+///
+/// Models may share their boundary samples, as adjacent HGT tiles do, but
+/// must not overlap otherwise.
+pub struct CompositeDem {
+    dems: Vec<Box<dyn DiscreteElevationModel>>,
+    extent: Interval2D,
+}
+
+impl CompositeDem {
+    pub fn new(dems: Vec<Box<dyn DiscreteElevationModel>>) -> Result<CompositeDem, DemError> {
+        let first = dems.first().ok_or(DemError::Empty)?;
+        let spd = first.samples_per_degree();
+        if dems.iter().any(|d| d.samples_per_degree() != spd) {
+            return Err(DemError::ResolutionMismatch);
+        }
+
+        let extents: Vec<Interval2D> = dems.iter().map(|d| d.extent()).collect();
+        for (i, e1) in extents.iter().enumerate() {
+            if extents[i + 1..].iter().any(|e2| overlap_area(e1, e2) > 0) {
+                return Err(DemError::Overlapping);
+            }
+        }
+
+        let extent = extents[1..]
+            .iter()
+            .fold(extents[0], |u, e| u.bounding_union(e));
+        // without overlaps, the models cover their bounding rectangle iff
+        // their areas add up to its area
+        if extents.iter().map(area).sum::<i64>() != area(&extent) {
+            return Err(DemError::NotContiguous);
+        }
+
+        Ok(CompositeDem { dems, extent })
+    }
+}
+
+impl DiscreteElevationModel for CompositeDem {
+    fn samples_per_degree(&self) -> u32 {
+        self.dems[0].samples_per_degree()
+    }
+
+    fn extent(&self) -> Interval2D {
+        self.extent
+    }
+
+    fn elevation_sample(&self, x: i32, y: i32) -> f64 {
+        self.dems
+            .iter()
+            .find(|d| d.extent().contains(x, y))
+            .unwrap_or_else(|| panic!("sample ({}, {}) outside of composite DEM", x, y))
+            .elevation_sample(x, y)
+    }
+
+    fn memory_size(&self) -> usize {
+        self.dems.iter().map(|d| d.memory_size()).sum()
+    }
+}
+
+/// Area of the extent, in cells between samples.
+fn area(e: &Interval2D) -> i64 {
+    (e.x().size() - 1) as i64 * (e.y().size() - 1) as i64
+}
+
+/// Area of the intersection of two extents, in cells between samples.
+fn overlap_area(e1: &Interval2D, e2: &Interval2D) -> i64 {
+    e1.intersection(e2).map_or(0, |i| area(&i))
+}
+
+#[cfg(test)]
+mod composite_tests {
+    use super::*;
+    use crate::{dem::HgtDem, interval::Interval1D};
+
+    /// 3" tile whose elevation is constant.
+    fn tile(south: i32, west: i32, elevation: i16) -> Box<dyn DiscreteElevationModel> {
+        let bytes = elevation.to_be_bytes().repeat(1201 * 1201);
+        Box::new(HgtDem::from_bytes(south, west, bytes).unwrap())
+    }
+
+    #[test]
+    fn new_accepts_square_of_adjacent_tiles() {
+        let dem = CompositeDem::new(vec![
+            tile(46, 7, 1),
+            tile(46, 8, 2),
+            tile(47, 7, 3),
+            tile(47, 8, 4),
+        ])
+        .unwrap();
+
+        assert_eq!(1200, dem.samples_per_degree());
+        assert_eq!(
+            Interval2D::new(
+                Interval1D::new(7 * 1200, 9 * 1200),
+                Interval1D::new(46 * 1200, 48 * 1200)
+            ),
+            dem.extent()
+        );
+        assert_eq!(1., dem.elevation_sample(7 * 1200 + 10, 46 * 1200 + 10));
+        assert_eq!(2., dem.elevation_sample(8 * 1200 + 10, 46 * 1200 + 10));
+        assert_eq!(3., dem.elevation_sample(7 * 1200 + 10, 47 * 1200 + 10));
+        assert_eq!(4., dem.elevation_sample(9 * 1200, 48 * 1200));
+    }
+
+    #[test]
+    fn composites_can_be_nested() {
+        let row = CompositeDem::new(vec![tile(46, 7, 1), tile(46, 8, 2)]).unwrap();
+        let dem = CompositeDem::new(vec![Box::new(row), tile(46, 9, 3)]).unwrap();
+        assert_eq!(3., dem.elevation_sample(9 * 1200 + 1, 46 * 1200));
+    }
+
+    #[test]
+    fn new_fails_on_invalid_unions() {
+        assert_eq!(Some(DemError::Empty), CompositeDem::new(vec![]).err());
+        assert_eq!(
+            Some(DemError::NotContiguous),
+            CompositeDem::new(vec![tile(46, 7, 0), tile(46, 9, 0)]).err()
+        );
+        assert_eq!(
+            Some(DemError::NotContiguous),
+            CompositeDem::new(vec![tile(46, 7, 0), tile(46, 8, 0), tile(47, 7, 0)]).err()
+        );
+        assert_eq!(
+            Some(DemError::Overlapping),
+            CompositeDem::new(vec![tile(46, 7, 0), tile(46, 7, 0)]).err()
+        );
+
+        let fine: Box<dyn DiscreteElevationModel> =
+            Box::new(HgtDem::from_bytes(46, 8, vec![0; 3601 * 3601 * 2]).unwrap());
+        assert_eq!(
+            Some(DemError::ResolutionMismatch),
+            CompositeDem::new(vec![tile(46, 7, 0), fine]).err()
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn elevation_sample_panics_outside_of_extent() {
+        CompositeDem::new(vec![tile(46, 7, 0)])
+            .unwrap()
+            .elevation_sample(0, 0);
+    }
+}
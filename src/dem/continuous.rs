@@ -0,0 +1,352 @@
+use std::array;
+
+use super::DiscreteElevationModel;
+use crate::interval::Interval2D;
+use crate::{
+    azimuth::Azimuth,
+    distance,
+    geo::GeoPoint,
+    math::{bicubic, bilerp},
+};
+
+/// Interpolation between the samples of a discrete elevation model.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Interpolation {
+    /// Between the 4 samples around a point, see [`bilerp`].
+    #[default]
+    Bilinear,
+    /// Between the 16 samples around a point, see [`bicubic`]. Slower, but
+    /// without the faceting of bilinear interpolation on close terrain.
+    Bicubic,
+}
+
+/// Elevation model defined everywhere, interpolating between the samples of
+/// a discrete elevation model.
+pub struct ContinuousElevationModel<D: DiscreteElevationModel> {
+    dem: D,
+    fill_value: f64,
+    interpolation: Interpolation,
+}
+
+impl<D: DiscreteElevationModel> ContinuousElevationModel<D> {
+    /// Create a model over `dem` interpolating bilinearly, whose elevation is
+    /// 0 outside of its extent.
+    pub fn new(dem: D) -> ContinuousElevationModel<D> {
+        ContinuousElevationModel {
+            dem,
+            fill_value: 0.,
+            interpolation: Interpolation::default(),
+        }
+    }
+
+    /// Interpolate between samples with `interpolation` instead.
+    pub fn with_interpolation(self, interpolation: Interpolation) -> ContinuousElevationModel<D> {
+        ContinuousElevationModel {
+            interpolation,
+            ..self
+        }
+    }
+
+    /// Use `fill_value` as the elevation outside of the extent of the model.
+    pub fn with_fill_value(self, fill_value: f64) -> ContinuousElevationModel<D> {
+        ContinuousElevationModel { fill_value, ..self }
+    }
+
+    pub fn dem(&self) -> &D {
+        &self.dem
+    }
+
+    pub fn fill_value(&self) -> f64 {
+        self.fill_value
+    }
+
+    pub fn interpolation(&self) -> Interpolation {
+        self.interpolation
+    }
+
+    /// Elevation in meters at `point`.
+    pub fn elevation_at(&self, point: &GeoPoint) -> f64 {
+        self.interpolate(point, |x, y| self.dem.elevation_sample(x, y))
+            .unwrap_or(self.fill_value)
+    }
+
+    /// Slope of the terrain at `point`, in radians from the horizontal. The
+    /// terrain is flat outside of the extent of the model.
+    pub fn slope_at(&self, point: &GeoPoint) -> f64 {
+        self.interpolate(point, |x, y| {
+            let (east, north) = self.sample_gradient(point, x, y);
+            east.hypot(north).atan()
+        })
+        .unwrap_or(0.)
+    }
+
+    /// Aspect of the terrain at `point`, i.e. the azimuth of its steepest
+    /// descent. Flat terrain, like the terrain outside of the extent of the
+    /// model, faces north.
+    pub fn aspect_at(&self, point: &GeoPoint) -> Azimuth {
+        let east = self.interpolate(point, |x, y| self.sample_gradient(point, x, y).0);
+        let north = self.interpolate(point, |x, y| self.sample_gradient(point, x, y).1);
+        match (east, north) {
+            (Some(east), Some(north)) if east != 0. || north != 0. => {
+                Azimuth::new(f64::atan2(-east, -north))
+            }
+            _ => Azimuth::NORTH,
+        }
+    }
+
+    /// Rise of the terrain at the sample `(x, y)` per meter eastward and per
+    /// meter northward, the samples being spaced as around `point`.
+    fn sample_gradient(&self, point: &GeoPoint, x: i32, y: i32) -> (f64, f64) {
+        // meters between adjacent samples along a meridian and along the
+        // parallel of the point
+        let dy = distance::to_meter((1. / self.dem.samples_per_degree() as f64).to_radians());
+        let dx = dy * point.latitude().cos();
+
+        // on the east and north edges, use the differences with the previous
+        // samples
+        let extent = self.dem.extent();
+        let x = x.min(extent.x().included_to() - 1);
+        let y = y.min(extent.y().included_to() - 1);
+        let z = self.dem.elevation_sample(x, y);
+        let dzx = self.dem.elevation_sample(x + 1, y) - z;
+        let dzy = self.dem.elevation_sample(x, y + 1) - z;
+        (dzx / dx, dzy / dy)
+    }
+
+    /// Interpolation at `point` of the value `f(x, y)` of the samples around
+    /// it, failing outside of the extent of the model.
+    fn interpolate(&self, point: &GeoPoint, f: impl Fn(i32, i32) -> f64) -> Option<f64> {
+        let spd = self.dem.samples_per_degree() as f64;
+        let x = point.longitude().to_degrees() * spd;
+        let y = point.latitude().to_degrees() * spd;
+
+        let extent = self.dem.extent();
+        if x < extent.x().included_from() as f64
+            || x > extent.x().included_to() as f64
+            || y < extent.y().included_from() as f64
+            || y > extent.y().included_to() as f64
+        {
+            return None;
+        }
+
+        // on the east and north edges, interpolate in the last cell
+        let x0 = (x.floor() as i32).min(extent.x().included_to() - 1);
+        let y0 = (y.floor() as i32).min(extent.y().included_to() - 1);
+        let (x, y) = (x - x0 as f64, y - y0 as f64);
+        Some(match self.interpolation {
+            Interpolation::Bilinear => bilerp(
+                f(x0, y0),
+                f(x0 + 1, y0),
+                f(x0, y0 + 1),
+                f(x0 + 1, y0 + 1),
+                x,
+                y,
+            ),
+            Interpolation::Bicubic => {
+                let z = array::from_fn(|j| {
+                    array::from_fn(|i| {
+                        extrapolated(&f, &extent, x0 + i as i32 - 1, y0 + j as i32 - 1)
+                    })
+                });
+                bicubic(z, x, y)
+            }
+        })
+    }
+}
+
+/// Value `f(x, y)` of the sample `(x, y)`, extrapolated linearly from the
+/// nearest samples when it is just outside of `extent`.
+fn extrapolated(f: &impl Fn(i32, i32) -> f64, extent: &Interval2D, x: i32, y: i32) -> f64 {
+    if x < extent.x().included_from() {
+        2. * extrapolated(f, extent, x + 1, y) - extrapolated(f, extent, x + 2, y)
+    } else if x > extent.x().included_to() {
+        2. * extrapolated(f, extent, x - 1, y) - extrapolated(f, extent, x - 2, y)
+    } else if y < extent.y().included_from() {
+        2. * extrapolated(f, extent, x, y + 1) - extrapolated(f, extent, x, y + 2)
+    } else if y > extent.y().included_to() {
+        2. * extrapolated(f, extent, x, y - 1) - extrapolated(f, extent, x, y - 2)
+    } else {
+        f(x, y)
+    }
+}
+
+#[cfg(test)]
+mod continuous_tests {
+    use super::*;
+    use crate::interval::Interval1D;
+    use assert_approx_eq::assert_approx_eq;
+    use rand::Rng;
+
+    /// Plane `z = 2x + 3y` over 10 samples per degree, from 6° to 8° of
+    /// longitude and 46° to 47° of latitude.
+    struct Plane;
+
+    impl DiscreteElevationModel for Plane {
+        fn samples_per_degree(&self) -> u32 {
+            10
+        }
+
+        fn extent(&self) -> Interval2D {
+            Interval2D::new(Interval1D::new(60, 80), Interval1D::new(460, 470))
+        }
+
+        fn elevation_sample(&self, x: i32, y: i32) -> f64 {
+            assert!(self.extent().contains(x, y));
+            (2 * x + 3 * y) as f64
+        }
+    }
+
+    /// Parabolic valley `z = (x - 70)²` over the extent of [`Plane`].
+    struct Valley;
+
+    impl DiscreteElevationModel for Valley {
+        fn samples_per_degree(&self) -> u32 {
+            Plane.samples_per_degree()
+        }
+
+        fn extent(&self) -> Interval2D {
+            Plane.extent()
+        }
+
+        fn elevation_sample(&self, x: i32, y: i32) -> f64 {
+            assert!(self.extent().contains(x, y));
+            ((x - 70) * (x - 70)) as f64
+        }
+    }
+
+    #[test]
+    fn elevation_at_matches_samples() {
+        let cem = ContinuousElevationModel::new(Plane);
+        let p = GeoPoint::from_degrees(7.3, 46.4).unwrap();
+        assert_approx_eq!((2 * 73 + 3 * 464) as f64, cem.elevation_at(&p), 1e-9);
+    }
+
+    #[test]
+    fn elevation_at_interpolates_plane_exactly() {
+        let cem = ContinuousElevationModel::new(Plane);
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..500 {
+            let lon: f64 = rng.gen_range(6.0..=8.0);
+            let lat: f64 = rng.gen_range(46.0..=47.0);
+            let p = GeoPoint::from_degrees(lon, lat).unwrap();
+            assert_approx_eq!(20. * lon + 30. * lat, cem.elevation_at(&p), 1e-6);
+        }
+    }
+
+    #[test]
+    fn elevation_at_works_on_north_east_corner() {
+        let cem = ContinuousElevationModel::new(Plane);
+        let p = GeoPoint::from_degrees(8., 47.).unwrap();
+        assert_approx_eq!((2 * 80 + 3 * 470) as f64, cem.elevation_at(&p), 1e-6);
+    }
+
+    #[test]
+    fn elevation_at_returns_fill_value_outside_of_extent() {
+        let outside = GeoPoint::from_degrees(8.01, 46.5).unwrap();
+        assert_eq!(
+            0.,
+            ContinuousElevationModel::new(Plane).elevation_at(&outside)
+        );
+
+        let cem = ContinuousElevationModel::new(Plane).with_fill_value(-1.);
+        assert_eq!(-1., cem.fill_value());
+        assert_eq!(-1., cem.elevation_at(&outside));
+        let below = GeoPoint::from_degrees(7., 45.99).unwrap();
+        assert_eq!(-1., cem.elevation_at(&below));
+    }
+
+    #[test]
+    fn bicubic_elevation_at_interpolates_plane_exactly_up_to_edges() {
+        let cem = ContinuousElevationModel::new(Plane).with_interpolation(Interpolation::Bicubic);
+        assert_eq!(Interpolation::Bicubic, cem.interpolation());
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..500 {
+            let lon: f64 = rng.gen_range(6.0..=8.0);
+            let lat: f64 = rng.gen_range(46.0..=47.0);
+            let p = GeoPoint::from_degrees(lon, lat).unwrap();
+            assert_approx_eq!(20. * lon + 30. * lat, cem.elevation_at(&p), 1e-6);
+        }
+    }
+
+    #[test]
+    fn bicubic_elevation_at_is_smoother_than_bilinear() {
+        let bilinear = ContinuousElevationModel::new(Valley);
+        assert_eq!(Interpolation::Bilinear, bilinear.interpolation());
+        let bicubic =
+            ContinuousElevationModel::new(Valley).with_interpolation(Interpolation::Bicubic);
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..500 {
+            // away from the edges, where the valley is not extrapolated
+            let lon: f64 = rng.gen_range(6.1..=7.9);
+            let p = GeoPoint::from_degrees(lon, 46.5).unwrap();
+            let expected = (lon * 10. - 70.).powi(2);
+            assert_approx_eq!(expected, bicubic.elevation_at(&p), 1e-6);
+            assert!(bilinear.elevation_at(&p) >= expected - 1e-6);
+        }
+        let between = GeoPoint::from_degrees(7.05, 46.5).unwrap();
+        assert_approx_eq!(0.5, bilinear.elevation_at(&between), 1e-6);
+        assert_approx_eq!(0.25, bicubic.elevation_at(&between), 1e-6);
+    }
+
+    #[test]
+    fn slope_at_is_correct_on_plane() {
+        let cem = ContinuousElevationModel::new(Plane);
+        let dy = distance::to_meter(0.1_f64.to_radians());
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..500 {
+            let lon: f64 = rng.gen_range(6.0..=8.0);
+            let lat: f64 = rng.gen_range(46.0..=47.0);
+            let p = GeoPoint::from_degrees(lon, lat).unwrap();
+            let dx = dy * lat.to_radians().cos();
+            let expected = ((2. / dx).powi(2) + (3. / dy).powi(2)).sqrt().atan();
+            assert_approx_eq!(expected, cem.slope_at(&p), 1e-6);
+        }
+    }
+
+    #[test]
+    fn slope_at_is_zero_outside_of_extent() {
+        let cem = ContinuousElevationModel::new(Plane).with_fill_value(100.);
+        assert_eq!(0., cem.slope_at(&GeoPoint::from_degrees(9., 46.5).unwrap()));
+    }
+
+    #[test]
+    fn aspect_at_is_correct_on_plane() {
+        let cem = ContinuousElevationModel::new(Plane);
+        let dy = distance::to_meter(0.1_f64.to_radians());
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..500 {
+            let lon: f64 = rng.gen_range(6.0..=8.0);
+            let lat: f64 = rng.gen_range(46.0..=47.0);
+            let p = GeoPoint::from_degrees(lon, lat).unwrap();
+            let dx = dy * lat.to_radians().cos();
+            // the plane rises to the north-east, so it faces south-west
+            let expected = Azimuth::new(f64::atan2(-2. / dx, -3. / dy));
+            assert!(expected.degrees() > 180. && expected.degrees() < 270.);
+            assert_approx_eq!(expected.radians(), cem.aspect_at(&p).radians(), 1e-9);
+        }
+    }
+
+    #[test]
+    fn aspect_at_faces_down_the_valley_sides() {
+        let cem = ContinuousElevationModel::new(Valley);
+        let west_side = GeoPoint::from_degrees(6.5, 46.5).unwrap();
+        let east_side = GeoPoint::from_degrees(7.5, 46.5).unwrap();
+        assert_approx_eq!(
+            Azimuth::EAST.radians(),
+            cem.aspect_at(&west_side).radians(),
+            1e-9
+        );
+        assert_approx_eq!(
+            Azimuth::WEST.radians(),
+            cem.aspect_at(&east_side).radians(),
+            1e-9
+        );
+        let outside = GeoPoint::from_degrees(9., 46.5).unwrap();
+        assert_eq!(Azimuth::NORTH, cem.aspect_at(&outside));
+    }
+}
@@ -0,0 +1,253 @@
+use std::{
+    fs::File,
+    io::{BufReader, Error, ErrorKind, Read, Result, Seek},
+    path::Path,
+};
+
+use tiff::{
+    decoder::{Decoder, DecodingResult},
+    tags::Tag,
+};
+
+use super::DiscreteElevationModel;
+use crate::{
+    interval::{Interval1D, Interval2D},
+    stats::Held,
+};
+
+/// Key of the GeoTIFF raster type, telling whether pixels are areas or
+/// points.
+const RASTER_TYPE_GEO_KEY: u16 = 1025;
+/// Raster type of pixels whose tie point is their center.
+const RASTER_PIXEL_IS_POINT: u16 = 2;
+
+/// Discrete elevation model backed by a single band GeoTIFF in geographic
+/// coordinates, such as Copernicus GLO-30 or ASTER GDEM tiles.
+///
+/// The centers of the pixels must lie on a regular grid of an integral number
+/// of samples per degree. Samples with the GDAL `nodata` value, or which are
+/// not numbers, are reported at sea level.
+pub struct GeoTiffDem {
+    samples_per_degree: u32,
+    extent: Interval2D,
+    width: usize,
+    samples: Vec<f32>,
+    held: Held,
+}
+
+impl GeoTiffDem {
+    /// Open the GeoTIFF file at the given path.
+    pub fn open(path: impl AsRef<Path>) -> Result<GeoTiffDem> {
+        GeoTiffDem::read(BufReader::new(File::open(path)?))
+    }
+
+    /// Read a GeoTIFF from `reader`.
+    pub fn read(reader: impl Read + Seek) -> Result<GeoTiffDem> {
+        let mut decoder = Decoder::new(reader).map_err(invalid_data)?;
+        let (width, height) = decoder.dimensions().map_err(invalid_data)?;
+        let (width, height) = (width as usize, height as usize);
+
+        let scale = decoder
+            .get_tag_f64_vec(Tag::ModelPixelScaleTag)
+            .map_err(invalid_data)?;
+        let tie_point = decoder
+            .get_tag_f64_vec(Tag::ModelTiepointTag)
+            .map_err(invalid_data)?;
+        if scale.len() < 2 || tie_point.len() < 6 || tie_point[0] != 0. || tie_point[1] != 0. {
+            return Err(unsupported("unsupported georeferencing"));
+        }
+
+        let spd = (1. / scale[0]).round();
+        if spd < 1. || (1. / scale[0] - spd).abs() > 1e-6 || (scale[0] - scale[1]).abs() > 1e-12 {
+            return Err(unsupported(
+                "pixels are not square with an integral number per degree",
+            ));
+        }
+
+        // the tie point is the top left corner of the first pixel, unless
+        // pixels are points
+        let pixel_is_point = decoder
+            .find_tag_unsigned_vec::<u16>(Tag::GeoKeyDirectoryTag)
+            .map_err(invalid_data)?
+            .map(|keys| raster_type(&keys) == Some(RASTER_PIXEL_IS_POINT))
+            .unwrap_or(false);
+        let offset = if pixel_is_point { 0. } else { 0.5 };
+        let x_min = tie_point[3] * spd + offset;
+        let y_max = tie_point[4] * spd - offset;
+        if (x_min - x_min.round()).abs() > 1e-6 || (y_max - y_max.round()).abs() > 1e-6 {
+            return Err(unsupported(
+                "pixels are not aligned on whole fractions of degrees",
+            ));
+        }
+        let (x_min, y_max) = (x_min.round() as i32, y_max.round() as i32);
+
+        let nodata = match decoder.find_tag(Tag::GdalNodata).map_err(invalid_data)? {
+            Some(value) => value
+                .into_string()
+                .map_err(invalid_data)?
+                .trim_end_matches('\0')
+                .trim()
+                .parse::<f32>()
+                .ok(),
+            None => None,
+        };
+
+        let samples: Vec<f32> = match decoder.read_image().map_err(invalid_data)? {
+            DecodingResult::I16(v) => v.into_iter().map(f32::from).collect(),
+            DecodingResult::U16(v) => v.into_iter().map(f32::from).collect(),
+            DecodingResult::I32(v) => v.into_iter().map(|e| e as f32).collect(),
+            DecodingResult::F32(v) => v,
+            DecodingResult::F64(v) => v.into_iter().map(|e| e as f32).collect(),
+            _ => return Err(unsupported("unsupported sample format")),
+        };
+        if samples.len() != width * height {
+            return Err(unsupported("not a single band image"));
+        }
+        let samples: Vec<f32> = samples
+            .into_iter()
+            .map(|e| {
+                if e.is_nan() || Some(e) == nodata {
+                    0.
+                } else {
+                    e
+                }
+            })
+            .collect();
+
+        Ok(GeoTiffDem {
+            samples_per_degree: spd as u32,
+            extent: Interval2D::new(
+                Interval1D::new(x_min, x_min + width as i32 - 1),
+                Interval1D::new(y_max - height as i32 + 1, y_max),
+            ),
+            width,
+            held: Held::dem_tile(samples.len() * size_of::<f32>()),
+            samples,
+        })
+    }
+}
+
+impl DiscreteElevationModel for GeoTiffDem {
+    fn samples_per_degree(&self) -> u32 {
+        self.samples_per_degree
+    }
+
+    fn extent(&self) -> Interval2D {
+        self.extent
+    }
+
+    fn elevation_sample(&self, x: i32, y: i32) -> f64 {
+        assert!(
+            self.extent.contains(x, y),
+            "sample ({}, {}) outside of GeoTIFF",
+            x,
+            y
+        );
+        let row = (self.extent.y().included_to() - y) as usize;
+        let col = (x - self.extent.x().included_from()) as usize;
+        self.samples[row * self.width + col] as f64
+    }
+
+    fn memory_size(&self) -> usize {
+        self.held.bytes()
+    }
+}
+
+/// Value of the raster type key of a GeoTIFF key directory.
+fn raster_type(keys: &[u16]) -> Option<u16> {
+    let count = *keys.get(3)? as usize;
+    keys[4..]
+        .chunks_exact(4)
+        .take(count)
+        .find(|entry| entry[0] == RASTER_TYPE_GEO_KEY && entry[1] == 0)
+        .map(|entry| entry[3])
+}
+
+fn unsupported(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+fn invalid_data(e: impl std::error::Error + Send + Sync + 'static) -> Error {
+    Error::new(ErrorKind::InvalidData, e)
+}
+
+#[cfg(test)]
+mod geotiff_tests {
+    use super::*;
+    use std::io::Cursor;
+    use tiff::encoder::{colortype, TiffEncoder};
+
+    /// GeoTIFF of 11×6 float samples at 10 samples per degree, whose top left
+    /// pixel is centered on 7°E 46.5°N, with -9999 as nodata value.
+    fn geotiff(pixel_is_point: bool) -> Vec<u8> {
+        let samples: Vec<f32> = (0..66)
+            .map(|i| if i == 5 { -9999. } else { i as f32 })
+            .collect();
+        let offset = if pixel_is_point { 0. } else { 0.05 };
+        let raster_type = if pixel_is_point { 2 } else { 1 };
+
+        let mut bytes = Cursor::new(Vec::new());
+        let mut encoder = TiffEncoder::new(&mut bytes).unwrap();
+        let mut image = encoder.new_image::<colortype::Gray32Float>(11, 6).unwrap();
+        let dir = image.encoder();
+        dir.write_tag(Tag::ModelPixelScaleTag, &[0.1, 0.1, 0.][..])
+            .unwrap();
+        dir.write_tag(
+            Tag::ModelTiepointTag,
+            &[0., 0., 0., 7. - offset, 46.5 + offset, 0.][..],
+        )
+        .unwrap();
+        dir.write_tag(
+            Tag::GeoKeyDirectoryTag,
+            &[1u16, 1, 0, 1, 1025, 0, 1, raster_type][..],
+        )
+        .unwrap();
+        dir.write_tag(Tag::GdalNodata, "-9999").unwrap();
+        image.write_data(&samples).unwrap();
+        bytes.into_inner()
+    }
+
+    #[test]
+    fn read_georeferences_pixel_is_area_images() {
+        let dem = GeoTiffDem::read(Cursor::new(geotiff(false))).unwrap();
+        assert_eq!(10, dem.samples_per_degree());
+        assert_eq!(
+            Interval2D::new(Interval1D::new(70, 80), Interval1D::new(460, 465)),
+            dem.extent()
+        );
+        assert_eq!(0., dem.elevation_sample(70, 465));
+        assert_eq!(12., dem.elevation_sample(71, 464));
+        assert_eq!(65., dem.elevation_sample(80, 460));
+    }
+
+    #[test]
+    fn read_georeferences_pixel_is_point_images() {
+        let dem = GeoTiffDem::read(Cursor::new(geotiff(true))).unwrap();
+        assert_eq!(
+            dem.extent(),
+            GeoTiffDem::read(Cursor::new(geotiff(false)))
+                .unwrap()
+                .extent()
+        );
+        assert_eq!(12., dem.elevation_sample(71, 464));
+    }
+
+    #[test]
+    fn read_reports_nodata_at_sea_level() {
+        let dem = GeoTiffDem::read(Cursor::new(geotiff(false))).unwrap();
+        assert_eq!(0., dem.elevation_sample(75, 465));
+        assert_eq!(4., dem.elevation_sample(74, 465));
+    }
+
+    #[test]
+    fn read_fails_on_invalid_input() {
+        assert!(GeoTiffDem::read(Cursor::new(vec![0; 100])).is_err());
+
+        let mut bytes = Cursor::new(Vec::new());
+        TiffEncoder::new(&mut bytes)
+            .unwrap()
+            .write_image::<colortype::Gray32Float>(2, 2, &[0.; 4])
+            .unwrap();
+        assert!(GeoTiffDem::read(Cursor::new(bytes.into_inner())).is_err());
+    }
+}
@@ -0,0 +1,377 @@
+use std::{
+    fs,
+    io::{Error, ErrorKind, Result},
+    ops::Deref,
+    path::Path,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use memmap2::Mmap;
+
+use super::DiscreteElevationModel;
+use crate::geo::GeoPoint;
+use crate::interval::{Interval1D, Interval2D};
+use crate::stats::Held;
+
+/// Value of the samples of HGT files for which no elevation is known.
+const VOID: i16 = -32768;
+
+/// Discrete elevation model backed by a 1°×1° SRTM `.hgt` tile.
+///
+/// Tiles contain a square grid of big-endian 16-bit elevations in meters,
+/// row by row from north to south, with one sample every arc second (3601
+/// samples per side) or every 3 arc seconds (1201 samples per side).
+/// Adjacent tiles share their boundary rows and columns.
+pub struct HgtDem {
+    south: i32,
+    west: i32,
+    side: usize,
+    samples: Samples,
+    held: Held,
+}
+
+/// Content of an HGT file, read into memory or mapped from disk.
+enum Samples {
+    Owned(Vec<u8>),
+    #[cfg(not(target_arch = "wasm32"))]
+    Mapped(Mmap),
+}
+
+impl Deref for Samples {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Samples::Owned(bytes) => bytes,
+            #[cfg(not(target_arch = "wasm32"))]
+            Samples::Mapped(map) => map,
+        }
+    }
+}
+
+impl HgtDem {
+    /// Open the tile at the given path, whose south-west corner is given by
+    /// its name, e.g. `N46E007.hgt`.
+    pub fn open(path: impl AsRef<Path>) -> Result<HgtDem> {
+        let path = path.as_ref();
+        let (south, west) = corner_of(path)?;
+        HgtDem::from_bytes(south, west, fs::read(path)?)
+    }
+
+    /// Like [`open`](HgtDem::open), but mapping the file in memory instead of
+    /// reading it, so that its samples are only loaded when accessed, and
+    /// shared by all the processes mapping it.
+    ///
+    /// The file must not be modified while the tile is in use.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn map(path: impl AsRef<Path>) -> Result<HgtDem> {
+        let path = path.as_ref();
+        let (south, west) = corner_of(path)?;
+        // SAFETY: HGT files are read-only data which, as documented, must
+        // not be modified while mapped
+        let map = unsafe { Mmap::map(&fs::File::open(path)?)? };
+        HgtDem::from_samples(south, west, Samples::Mapped(map))
+    }
+
+    /// Tile whose south-west corner is at the given latitude and longitude
+    /// (in degrees), from the content of its HGT file.
+    pub fn from_bytes(south: i32, west: i32, samples: Vec<u8>) -> Result<HgtDem> {
+        HgtDem::from_samples(south, west, Samples::Owned(samples))
+    }
+
+    fn from_samples(south: i32, west: i32, samples: Samples) -> Result<HgtDem> {
+        if !(-90..90).contains(&south) || !(-180..180).contains(&west) {
+            return Err(invalid_data(format!(
+                "invalid HGT tile corner: {}°, {}°",
+                south, west
+            )));
+        }
+
+        let side = match samples.len() {
+            n if n == 3601 * 3601 * 2 => 3601,
+            n if n == 1201 * 1201 * 2 => 1201,
+            n => return Err(invalid_data(format!("invalid HGT file size: {}", n))),
+        };
+
+        let held = Held::dem_tile(match samples {
+            Samples::Owned(ref bytes) => bytes.len(),
+            #[cfg(not(target_arch = "wasm32"))]
+            Samples::Mapped(_) => 0,
+        });
+        Ok(HgtDem {
+            south,
+            west,
+            side,
+            samples,
+            held,
+        })
+    }
+
+    /// Name of the file of the tile whose south-west corner is at the given
+    /// latitude and longitude (in degrees), e.g. `N46E007.hgt`.
+    pub fn file_name(south: i32, west: i32) -> String {
+        format!(
+            "{}{:02}{}{:03}.hgt",
+            if south < 0 { 'S' } else { 'N' },
+            south.abs(),
+            if west < 0 { 'W' } else { 'E' },
+            west.abs()
+        )
+    }
+
+    /// Resolution of the tile, in arc seconds (1 or 3).
+    pub fn resolution(&self) -> u32 {
+        3600 / (self.side as u32 - 1)
+    }
+
+    /// Number of samples in each row and column of the tile.
+    pub fn side(&self) -> usize {
+        self.side
+    }
+
+    /// Elevation in meters of the sample at the given row (from north) and
+    /// column (from west). Voids in the data are reported at sea level.
+    pub fn elevation_at(&self, row: usize, col: usize) -> f64 {
+        assert!(
+            row < self.side && col < self.side,
+            "sample ({}, {}) outside of tile",
+            row,
+            col
+        );
+
+        let i = 2 * (row * self.side + col);
+        match i16::from_be_bytes([self.samples[i], self.samples[i + 1]]) {
+            VOID => 0.,
+            e => e as f64,
+        }
+    }
+
+    /// South-west corner of the tile.
+    pub fn south_west(&self) -> GeoPoint {
+        GeoPoint::from_degrees(self.west as f64, self.south as f64).unwrap()
+    }
+
+    /// North-east corner of the tile.
+    pub fn north_east(&self) -> GeoPoint {
+        GeoPoint::from_degrees(self.west as f64 + 1., self.south as f64 + 1.).unwrap()
+    }
+
+    /// Whether the given point is covered by the tile.
+    pub fn contains(&self, p: &GeoPoint) -> bool {
+        let (sw, ne) = (self.south_west(), self.north_east());
+        (sw.longitude()..=ne.longitude()).contains(&p.longitude())
+            && (sw.latitude()..=ne.latitude()).contains(&p.latitude())
+    }
+}
+
+impl DiscreteElevationModel for HgtDem {
+    fn samples_per_degree(&self) -> u32 {
+        self.side as u32 - 1
+    }
+
+    fn extent(&self) -> Interval2D {
+        let spd = self.samples_per_degree() as i32;
+        Interval2D::new(
+            Interval1D::new(self.west * spd, (self.west + 1) * spd),
+            Interval1D::new(self.south * spd, (self.south + 1) * spd),
+        )
+    }
+
+    fn elevation_sample(&self, x: i32, y: i32) -> f64 {
+        let extent = self.extent();
+        self.elevation_at(
+            (extent.y().included_to() - y) as usize,
+            (x - extent.x().included_from()) as usize,
+        )
+    }
+
+    fn memory_size(&self) -> usize {
+        self.held.bytes()
+    }
+}
+
+/// Latitude and longitude of the south-west corner of a tile, in degrees,
+/// from its name, e.g. `N46E007` or `S12W077`.
+fn parse_tile_name(name: &str) -> Option<(i32, i32)> {
+    let name = name.to_uppercase();
+    if name.len() != 7 || !name.is_ascii() {
+        return None;
+    }
+
+    let lat: i32 = name[1..3].parse().ok()?;
+    let lon: i32 = name[4..7].parse().ok()?;
+    let lat = match &name[0..1] {
+        "N" => lat,
+        "S" => -lat,
+        _ => return None,
+    };
+    let lon = match &name[3..4] {
+        "E" => lon,
+        "W" => -lon,
+        _ => return None,
+    };
+
+    Some((lat, lon))
+}
+
+/// South-west corner of the tile of the file at `path`, given by its name.
+fn corner_of(path: &Path) -> Result<(i32, i32)> {
+    path.file_stem()
+        .and_then(|s| s.to_str())
+        .and_then(parse_tile_name)
+        .ok_or_else(|| invalid_data(format!("invalid HGT file name: {}", path.display())))
+}
+
+fn invalid_data(message: String) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod hgt_tests {
+    use super::*;
+
+    /// Content of a 3" tile whose elevation is `row + col`.
+    fn ramp_bytes() -> Vec<u8> {
+        (0..1201 * 1201)
+            .flat_map(|i| (((i / 1201) + (i % 1201)) as i16).to_be_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn parse_tile_name_works_on_all_hemispheres() {
+        assert_eq!(Some((46, 7)), parse_tile_name("N46E007"));
+        assert_eq!(Some((-12, -77)), parse_tile_name("S12W077"));
+        assert_eq!(Some((0, 0)), parse_tile_name("n00e000"));
+        assert_eq!(None, parse_tile_name("N46E07"));
+        assert_eq!(None, parse_tile_name("X46E007"));
+        assert_eq!(None, parse_tile_name("N46Y007"));
+        assert_eq!(None, parse_tile_name("N4aE007"));
+    }
+
+    #[test]
+    fn file_name_is_inverse_of_parse_tile_name() {
+        assert_eq!("N46E007.hgt", HgtDem::file_name(46, 7));
+        assert_eq!("S12W077.hgt", HgtDem::file_name(-12, -77));
+        for (south, west) in [(0, 0), (-1, -1), (89, 179), (-90, -180)] {
+            let name = HgtDem::file_name(south, west);
+            assert_eq!(Some((south, west)), parse_tile_name(&name[..7]));
+        }
+    }
+
+    #[test]
+    fn from_bytes_infers_resolution_from_size() {
+        let dem = HgtDem::from_bytes(46, 7, ramp_bytes()).unwrap();
+        assert_eq!(3, dem.resolution());
+        assert_eq!(1201, dem.side());
+
+        let dem = HgtDem::from_bytes(46, 7, vec![0; 3601 * 3601 * 2]).unwrap();
+        assert_eq!(1, dem.resolution());
+        assert_eq!(3601, dem.side());
+    }
+
+    #[test]
+    fn from_bytes_fails_on_invalid_input() {
+        assert!(HgtDem::from_bytes(46, 7, vec![0; 1000]).is_err());
+        assert!(HgtDem::from_bytes(90, 7, ramp_bytes()).is_err());
+        assert!(HgtDem::from_bytes(46, 180, ramp_bytes()).is_err());
+    }
+
+    #[test]
+    fn elevation_at_reads_big_endian_samples() {
+        let dem = HgtDem::from_bytes(46, 7, ramp_bytes()).unwrap();
+        assert_eq!(0., dem.elevation_at(0, 0));
+        assert_eq!(1200., dem.elevation_at(1200, 0));
+        assert_eq!(1500., dem.elevation_at(700, 800));
+        assert_eq!(2400., dem.elevation_at(1200, 1200));
+    }
+
+    #[test]
+    fn elevation_at_reports_voids_at_sea_level() {
+        let mut bytes = ramp_bytes();
+        let i = 2 * (10 * 1201 + 20);
+        bytes[i..i + 2].copy_from_slice(&VOID.to_be_bytes());
+        let dem = HgtDem::from_bytes(46, 7, bytes).unwrap();
+        assert_eq!(0., dem.elevation_at(10, 20));
+    }
+
+    #[test]
+    #[should_panic]
+    fn elevation_at_panics_outside_of_tile() {
+        HgtDem::from_bytes(46, 7, ramp_bytes())
+            .unwrap()
+            .elevation_at(1201, 0);
+    }
+
+    #[test]
+    fn extent_is_given_by_south_west_corner() {
+        let dem = HgtDem::from_bytes(-12, -77, ramp_bytes()).unwrap();
+        assert_eq!(
+            GeoPoint::from_degrees(-77., -12.).unwrap(),
+            dem.south_west()
+        );
+        assert_eq!(
+            GeoPoint::from_degrees(-76., -11.).unwrap(),
+            dem.north_east()
+        );
+        assert!(dem.contains(&GeoPoint::from_degrees(-76.5, -11.5).unwrap()));
+        assert!(!dem.contains(&GeoPoint::from_degrees(-75.5, -11.5).unwrap()));
+    }
+
+    #[test]
+    fn open_reads_tile_from_disk() {
+        let path = std::env::temp_dir()
+            .join("alpano_hgt_tests")
+            .join("N46E007.hgt");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, ramp_bytes()).unwrap();
+
+        let dem = HgtDem::open(&path).unwrap();
+        assert_eq!(GeoPoint::from_degrees(7., 46.).unwrap(), dem.south_west());
+        assert_eq!(1500., dem.elevation_at(700, 800));
+
+        let bad = path.with_file_name("tile.hgt");
+        fs::write(&bad, ramp_bytes()).unwrap();
+        assert!(HgtDem::open(&bad).is_err());
+        assert!(HgtDem::open(path.with_file_name("N00E000.hgt")).is_err());
+    }
+
+    #[test]
+    fn map_reads_same_samples_as_open() {
+        let path = std::env::temp_dir()
+            .join("alpano_hgt_tests")
+            .join("N47E008.hgt");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, ramp_bytes()).unwrap();
+
+        let mapped = HgtDem::map(&path).unwrap();
+        let read = HgtDem::open(&path).unwrap();
+        assert_eq!(read.south_west(), mapped.south_west());
+        assert_eq!(read.side(), mapped.side());
+        for (row, col) in [(0, 0), (700, 800), (1200, 1200), (3, 1100)] {
+            assert_eq!(read.elevation_at(row, col), mapped.elevation_at(row, col));
+        }
+
+        assert!(HgtDem::map(path.with_file_name("N01E001.hgt")).is_err());
+        assert!(HgtDem::map(path.with_file_name("tile.hgt")).is_err());
+    }
+
+    #[test]
+    fn elevation_sample_uses_global_coordinates() {
+        let dem = HgtDem::from_bytes(46, 7, ramp_bytes()).unwrap();
+        assert_eq!(1200, dem.samples_per_degree());
+        assert_eq!(
+            Interval2D::new(Interval1D::new(8400, 9600), Interval1D::new(55200, 56400)),
+            dem.extent()
+        );
+        // north-west corner is the first sample of the file
+        assert_eq!(0., dem.elevation_sample(8400, 56400));
+        assert_eq!(
+            dem.elevation_at(1200, 1200),
+            dem.elevation_sample(9600, 55200)
+        );
+        assert_eq!(
+            dem.elevation_at(700, 800),
+            dem.elevation_sample(9200, 55700)
+        );
+    }
+}
@@ -0,0 +1,145 @@
+use super::{DemError, DiscreteElevationModel};
+use crate::{interval::Interval2D, stats::Held};
+
+/// Discrete elevation model whose samples are held in memory, e.g. computed
+/// or decoded from a format which is not supported directly.
+pub struct MemoryDem {
+    samples_per_degree: u32,
+    extent: Interval2D,
+    samples: Vec<f32>,
+    held: Held,
+}
+
+impl MemoryDem {
+    /// Create a model over `extent` from its samples, row by row from south
+    /// to north, each row from west to east.
+    ///
+    /// Fails unless there is exactly one sample per point of the extent.
+    pub fn new(
+        samples_per_degree: u32,
+        extent: Interval2D,
+        samples: Vec<f32>,
+    ) -> Result<MemoryDem, DemError> {
+        if samples.len() != extent.size() {
+            return Err(DemError::SampleCountMismatch(samples.len(), extent.size()));
+        }
+        Ok(MemoryDem {
+            samples_per_degree,
+            extent,
+            held: Held::dem_tile(samples.len() * size_of::<f32>()),
+            samples,
+        })
+    }
+
+    /// Create a model over `extent` whose sample `(x, y)` is `f(x, y)`.
+    pub fn from_fn(
+        samples_per_degree: u32,
+        extent: Interval2D,
+        f: impl Fn(i32, i32) -> f64,
+    ) -> MemoryDem {
+        let (xs, ys) = (extent.x(), extent.y());
+        let samples: Vec<f32> = (ys.included_from()..=ys.included_to())
+            .flat_map(|y| (xs.included_from()..=xs.included_to()).map(move |x| (x, y)))
+            .map(|(x, y)| f(x, y) as f32)
+            .collect();
+        MemoryDem {
+            samples_per_degree,
+            extent,
+            held: Held::dem_tile(samples.len() * size_of::<f32>()),
+            samples,
+        }
+    }
+
+    /// Copy of the samples of `dem` over `extent`, which must be in the
+    /// extent of `dem`.
+    pub fn copy_of(dem: &impl DiscreteElevationModel, extent: Interval2D) -> MemoryDem {
+        assert_eq!(
+            Some(extent),
+            dem.extent().intersection(&extent),
+            "extent {} not covered by the model",
+            extent
+        );
+        MemoryDem::from_fn(dem.samples_per_degree(), extent, |x, y| {
+            dem.elevation_sample(x, y)
+        })
+    }
+}
+
+impl DiscreteElevationModel for MemoryDem {
+    fn samples_per_degree(&self) -> u32 {
+        self.samples_per_degree
+    }
+
+    fn extent(&self) -> Interval2D {
+        self.extent
+    }
+
+    fn elevation_sample(&self, x: i32, y: i32) -> f64 {
+        assert!(
+            self.extent.contains(x, y),
+            "sample ({}, {}) outside of in-memory model",
+            x,
+            y
+        );
+        let row = (y - self.extent.y().included_from()) as usize;
+        let col = (x - self.extent.x().included_from()) as usize;
+        self.samples[row * self.extent.x().size() + col] as f64
+    }
+
+    fn memory_size(&self) -> usize {
+        self.held.bytes()
+    }
+}
+
+#[cfg(test)]
+mod memory_tests {
+    use super::*;
+    use crate::{dem::CompositeDem, interval::Interval1D};
+
+    fn extent(x_from: i32, x_to: i32, y_from: i32, y_to: i32) -> Interval2D {
+        Interval2D::new(Interval1D::new(x_from, x_to), Interval1D::new(y_from, y_to))
+    }
+
+    #[test]
+    fn new_stores_samples_row_by_row_from_south() {
+        let dem = MemoryDem::new(10, extent(5, 7, 20, 21), vec![1., 2., 3., 4., 5., 6.]).unwrap();
+        assert_eq!(1., dem.elevation_sample(5, 20));
+        assert_eq!(3., dem.elevation_sample(7, 20));
+        assert_eq!(4., dem.elevation_sample(5, 21));
+        assert_eq!(6., dem.elevation_sample(7, 21));
+    }
+
+    #[test]
+    fn new_fails_on_wrong_number_of_samples() {
+        assert_eq!(
+            Some(DemError::SampleCountMismatch(5, 6)),
+            MemoryDem::new(10, extent(5, 7, 20, 21), vec![0.; 5]).err()
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn elevation_sample_panics_outside_of_extent() {
+        MemoryDem::from_fn(10, extent(0, 3, 0, 3), |_, _| 0.).elevation_sample(4, 0);
+    }
+
+    #[test]
+    fn copy_of_matches_source() {
+        let source = MemoryDem::from_fn(10, extent(0, 20, 0, 10), |x, y| (x * 100 + y) as f64);
+        let copy = MemoryDem::copy_of(&source, extent(5, 15, 2, 8));
+        for y in 2..=8 {
+            for x in 5..=15 {
+                assert_eq!(source.elevation_sample(x, y), copy.elevation_sample(x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn in_memory_models_can_be_combined() {
+        let west = MemoryDem::from_fn(10, extent(0, 10, 0, 10), |x, _| x as f64);
+        let east = MemoryDem::from_fn(10, extent(10, 20, 0, 10), |x, _| x as f64);
+        let dem = CompositeDem::new(vec![Box::new(west), Box::new(east)]).unwrap();
+        assert_eq!(extent(0, 20, 0, 10), dem.extent());
+        assert_eq!(15., dem.elevation_sample(15, 5));
+    }
+}
@@ -0,0 +1,95 @@
+use std::sync::Arc;
+
+use thiserror::Error;
+
+use crate::interval::Interval2D;
+
+mod cache;
+mod composite;
+mod continuous;
+mod geotiff;
+mod hgt;
+mod memory;
+mod profile;
+
+pub use cache::TileCache;
+pub use composite::CompositeDem;
+pub use continuous::{ContinuousElevationModel, Interpolation};
+pub use geotiff::GeoTiffDem;
+pub use hgt::HgtDem;
+pub use memory::MemoryDem;
+pub use profile::ElevationProfile;
+
+/// Source of elevation samples regularly spaced in longitude and latitude.
+///
+/// Sources are interchangeable: continuous models, profiles, composite
+/// models and caches work with any of them, be it a file format, samples in
+/// memory or a function.
+pub trait DiscreteElevationModel: Send + Sync {
+    /// Number of samples per degree of longitude and latitude.
+    fn samples_per_degree(&self) -> u32;
+
+    /// Samples covered by the model.
+    fn extent(&self) -> Interval2D;
+
+    /// Elevation in meters of the sample `(x, y)`, which must be in the
+    /// extent of the model.
+    fn elevation_sample(&self, x: i32, y: i32) -> f64;
+
+    /// Bytes of memory held by the samples of the model, counted in
+    /// [`stats`](crate::stats::stats), by default none.
+    fn memory_size(&self) -> usize {
+        0
+    }
+}
+
+impl<D: DiscreteElevationModel + ?Sized> DiscreteElevationModel for Box<D> {
+    fn samples_per_degree(&self) -> u32 {
+        (**self).samples_per_degree()
+    }
+
+    fn extent(&self) -> Interval2D {
+        (**self).extent()
+    }
+
+    fn elevation_sample(&self, x: i32, y: i32) -> f64 {
+        (**self).elevation_sample(x, y)
+    }
+
+    fn memory_size(&self) -> usize {
+        (**self).memory_size()
+    }
+}
+
+impl<D: DiscreteElevationModel + ?Sized> DiscreteElevationModel for Arc<D> {
+    fn samples_per_degree(&self) -> u32 {
+        (**self).samples_per_degree()
+    }
+
+    fn extent(&self) -> Interval2D {
+        (**self).extent()
+    }
+
+    fn elevation_sample(&self, x: i32, y: i32) -> f64 {
+        (**self).elevation_sample(x, y)
+    }
+
+    fn memory_size(&self) -> usize {
+        (**self).memory_size()
+    }
+}
+
+/// Errors raised when building or combining elevation models.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum DemError {
+    #[error("no elevation model to combine")]
+    Empty,
+    #[error("elevation models have different resolutions")]
+    ResolutionMismatch,
+    #[error("elevation models overlap")]
+    Overlapping,
+    #[error("elevation models do not form a rectangle")]
+    NotContiguous,
+    #[error("{0} samples given for an extent of {1} samples")]
+    SampleCountMismatch(usize, usize),
+}
@@ -0,0 +1,115 @@
+use super::{ContinuousElevationModel, DiscreteElevationModel};
+use crate::{azimuth::Azimuth, geo::GeoPoint, math::angular_distance, math::lerp};
+
+/// Distance between the points of the profile which are computed exactly,
+/// in meters. Points in between are interpolated linearly.
+const SPACING: f64 = 4096.;
+
+/// Elevation of the terrain along a great circle leaving a point at a given
+/// azimuth, as a function of the distance to that point.
+pub struct ElevationProfile<'a, D: DiscreteElevationModel> {
+    cem: &'a ContinuousElevationModel<D>,
+    positions: Vec<GeoPoint>,
+}
+
+impl<'a, D: DiscreteElevationModel> ElevationProfile<'a, D> {
+    /// Create the profile of `length` meters leaving `origin` at `azimuth`.
+    pub fn new(
+        cem: &'a ContinuousElevationModel<D>,
+        origin: GeoPoint,
+        azimuth: Azimuth,
+        length: f64,
+    ) -> ElevationProfile<'a, D> {
+        assert!(length > 0., "profile length must be positive");
+        let count = (length / SPACING).ceil() as usize + 1;
+        ElevationProfile {
+            cem,
+            positions: (0..count)
+                .map(|i| origin.destination(azimuth, i as f64 * SPACING))
+                .collect(),
+        }
+    }
+
+    /// Position of the point at distance `x` from the origin. Beyond the
+    /// length of the profile, positions are extrapolated.
+    pub fn position_at(&self, x: f64) -> GeoPoint {
+        let t = x / SPACING;
+        let i = (t.floor().max(0.) as usize).min(self.positions.len() - 2);
+        let (p0, p1) = (self.positions[i], self.positions[i + 1]);
+        let t = t - i as f64;
+
+        let longitude = p0.longitude() + t * angular_distance(p0.longitude(), p1.longitude());
+        let latitude = lerp(t, p0.latitude()..=p1.latitude());
+        GeoPoint::new(angular_distance(0., longitude), latitude)
+            .expect("interpolated position is on the earth")
+    }
+
+    /// Elevation of the terrain at distance `x` from the origin, in meters.
+    pub fn elevation_at(&self, x: f64) -> f64 {
+        self.cem.elevation_at(&self.position_at(x))
+    }
+
+    /// Slope of the terrain at distance `x` from the origin.
+    pub fn slope_at(&self, x: f64) -> f64 {
+        self.cem.slope_at(&self.position_at(x))
+    }
+
+    /// Aspect of the terrain at distance `x` from the origin.
+    pub fn aspect_at(&self, x: f64) -> Azimuth {
+        self.cem.aspect_at(&self.position_at(x))
+    }
+}
+
+#[cfg(test)]
+mod profile_tests {
+    use super::*;
+    use crate::interval::{Interval1D, Interval2D};
+    use assert_approx_eq::assert_approx_eq;
+    use rand::Rng;
+
+    /// Plane `z = x` over 100 samples per degree, from 6° to 9° of longitude
+    /// and 45° to 48° of latitude.
+    struct EastwardSlope;
+
+    impl DiscreteElevationModel for EastwardSlope {
+        fn samples_per_degree(&self) -> u32 {
+            100
+        }
+
+        fn extent(&self) -> Interval2D {
+            Interval2D::new(Interval1D::new(600, 900), Interval1D::new(4500, 4800))
+        }
+
+        fn elevation_sample(&self, x: i32, _y: i32) -> f64 {
+            x as f64
+        }
+    }
+
+    #[test]
+    fn position_at_follows_great_circle() {
+        let cem = ContinuousElevationModel::new(EastwardSlope);
+        let origin = GeoPoint::from_degrees(7.5, 46.5).unwrap();
+        let azimuth = Azimuth::from_degrees(37.);
+        let profile = ElevationProfile::new(&cem, origin, azimuth, 100_000.);
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..500 {
+            let x = rng.gen_range(0. ..100_000.);
+            let expected = origin.destination(azimuth, x);
+            assert!(profile.position_at(x).distance_to(&expected) < 1.);
+        }
+    }
+
+    #[test]
+    fn elevation_at_is_correct_along_parallel() {
+        let cem = ContinuousElevationModel::new(EastwardSlope);
+        let origin = GeoPoint::from_degrees(7., 0.).unwrap();
+        let profile = ElevationProfile::new(&cem, origin, Azimuth::EAST, 10_000.);
+        assert_eq!(0., profile.elevation_at(5_000.));
+
+        let origin = GeoPoint::from_degrees(7., 46.).unwrap();
+        let profile = ElevationProfile::new(&cem, origin, Azimuth::NORTH, 10_000.);
+        assert_approx_eq!(700., profile.elevation_at(0.), 1e-9);
+        assert_approx_eq!(700., profile.elevation_at(7_777.), 1e-6);
+    }
+}
@@ -0,0 +1,610 @@
+use std::{cmp::Ordering, fmt, str::FromStr};
+
+use thiserror::Error;
+
+pub const EARTH_RADIUS: f64 = 6371000.0;
+
+/// Convert a distance given in meters on the surface of the earth (arc's length)
+/// to it's radians representation.
+pub fn to_rad(dist_in_meters: f64) -> f64 {
+    dist_in_meters / EARTH_RADIUS
+}
+
+/// Convert a radians to the distance equivalent on the earth surface (arc's length)
+pub fn to_meter(rad: f64) -> f64 {
+    EARTH_RADIUS * rad
+}
+
+/// Semi-major axis of the WGS84 ellipsoid, in meters.
+pub const WGS84_A: f64 = 6378137.0;
+
+/// Flattening of the WGS84 ellipsoid.
+pub const WGS84_F: f64 = 1. / 298.257223563;
+
+/// Square of the first eccentricity of the WGS84 ellipsoid.
+const WGS84_E2: f64 = WGS84_F * (2. - WGS84_F);
+
+/// Radius of curvature of the WGS84 ellipsoid along the meridian, at the given
+/// latitude (in radians).
+pub fn meridional_radius(lat: f64) -> f64 {
+    WGS84_A * (1. - WGS84_E2) / (1. - WGS84_E2 * lat.sin().powi(2)).powf(1.5)
+}
+
+/// Radius of curvature of the WGS84 ellipsoid along the prime vertical
+/// (perpendicular to the meridian), at the given latitude (in radians).
+pub fn prime_vertical_radius(lat: f64) -> f64 {
+    WGS84_A / (1. - WGS84_E2 * lat.sin().powi(2)).sqrt()
+}
+
+/// Radius of curvature of the WGS84 ellipsoid at the given latitude in the
+/// direction of the given azimuth (both in radians), by Euler's theorem.
+pub fn ellipsoid_radius(lat: f64, azimuth: f64) -> f64 {
+    let (sin_a, cos_a) = azimuth.sin_cos();
+    1. / (cos_a.powi(2) / meridional_radius(lat) + sin_a.powi(2) / prime_vertical_radius(lat))
+}
+
+/// Like [`to_rad`], but on the WGS84 ellipsoid for a short arc starting at the
+/// given latitude in the direction of the given azimuth.
+pub fn to_rad_on_ellipsoid(dist_in_meters: f64, lat: f64, azimuth: f64) -> f64 {
+    Wgs84.to_rad(dist_in_meters, lat, azimuth)
+}
+
+/// Like [`to_meter`], but on the WGS84 ellipsoid for a short arc starting at
+/// the given latitude in the direction of the given azimuth.
+pub fn to_meter_on_ellipsoid(rad: f64, lat: f64, azimuth: f64) -> f64 {
+    Wgs84.to_meter(rad, lat, azimuth)
+}
+
+/// Shape of the earth, given by the radius of curvature of its surface.
+pub trait EarthModel: Send + Sync {
+    /// Radius of curvature, in meters, at the given latitude in the direction
+    /// of the given azimuth (both in radians).
+    fn radius(&self, lat: f64, azimuth: f64) -> f64;
+
+    /// Like [`to_rad`], for a short arc starting at the given latitude in the
+    /// direction of the given azimuth.
+    fn to_rad(&self, dist_in_meters: f64, lat: f64, azimuth: f64) -> f64 {
+        dist_in_meters / self.radius(lat, azimuth)
+    }
+
+    /// Like [`to_meter`], for a short arc starting at the given latitude in
+    /// the direction of the given azimuth.
+    fn to_meter(&self, rad: f64, lat: f64, azimuth: f64) -> f64 {
+        self.radius(lat, azimuth) * rad
+    }
+
+    /// Like [`effective_radius`], at the given latitude in the direction of
+    /// the given azimuth.
+    fn effective_radius(&self, lat: f64, azimuth: f64, k: f64) -> f64 {
+        self.radius(lat, azimuth) / (1. - k)
+    }
+}
+
+/// Spherical earth of the given radius, in meters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sphere {
+    pub radius: f64,
+}
+
+impl Sphere {
+    /// Sphere of radius [`EARTH_RADIUS`], used by the free functions of this
+    /// module.
+    pub const EARTH: Sphere = Sphere {
+        radius: EARTH_RADIUS,
+    };
+}
+
+impl Default for Sphere {
+    fn default() -> Sphere {
+        Sphere::EARTH
+    }
+}
+
+impl EarthModel for Sphere {
+    fn radius(&self, _lat: f64, _azimuth: f64) -> f64 {
+        self.radius
+    }
+}
+
+/// The WGS84 ellipsoid, see [`ellipsoid_radius`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Wgs84;
+
+impl EarthModel for Wgs84 {
+    fn radius(&self, lat: f64, azimuth: f64) -> f64 {
+        ellipsoid_radius(lat, azimuth)
+    }
+}
+
+/// Standard atmospheric refraction coefficient.
+pub const REFRACTION_COEFFICIENT: f64 = 0.13;
+
+/// Radius of the earth as seen by a light ray bent by atmospheric refraction
+/// with coefficient `k`: the ray can then be considered straight.
+pub fn effective_radius(k: f64) -> f64 {
+    EARTH_RADIUS / (1. - k)
+}
+
+/// Like [`to_rad`], but on the effective earth for refraction coefficient `k`.
+pub fn to_rad_refracted(dist_in_meters: f64, k: f64) -> f64 {
+    dist_in_meters / effective_radius(k)
+}
+
+/// Like [`to_meter`], but on the effective earth for refraction coefficient `k`.
+pub fn to_meter_refracted(rad: f64, k: f64) -> f64 {
+    effective_radius(k) * rad
+}
+
+/// Distance in meters, along the surface, from an observer at the given
+/// height above sea level to its sea-level horizon, under refraction
+/// coefficient `k`.
+pub fn horizon_distance(observer_height: f64, k: f64) -> f64 {
+    let r = effective_radius(k);
+    to_meter_refracted(
+        ((observer_height * (2. * r + observer_height)).sqrt() / r).atan(),
+        k,
+    )
+}
+
+/// Height above sea level an observer must be at for its sea-level horizon to
+/// be at the given distance, under refraction coefficient `k`. This is the
+/// inverse of [`horizon_distance`].
+pub fn horizon_observer_height(dist_in_meters: f64, k: f64) -> f64 {
+    let r = effective_radius(k);
+    r / to_rad_refracted(dist_in_meters, k).cos() - r
+}
+
+/// Angle (in radians, positive downward) between the horizontal and the
+/// sea-level horizon seen by an observer at the given height, under refraction
+/// coefficient `k`.
+pub fn horizon_dip(observer_height: f64, k: f64) -> f64 {
+    // the line of sight is tangent to the effective earth, so the dip is the
+    // angle at the center of the earth between observer and horizon
+    to_rad_refracted(horizon_distance(observer_height, k), k)
+}
+
+/// Length in meters of one degree of latitude.
+pub fn meters_per_deg_lat() -> f64 {
+    to_meter(1_f64.to_radians())
+}
+
+/// Length in meters of one degree of longitude along the parallel at the
+/// given latitude (in radians).
+pub fn meters_per_deg_lon(lat: f64) -> f64 {
+    meters_per_deg_lat() * lat.cos()
+}
+
+/// Walking distance, in meters, along an elevation profile whose samples
+/// (in meters) are `spacing` meters apart along the surface of the earth.
+///
+/// This is always at least the arc distance `spacing * (elevations.len() - 1)`
+/// covered by the profile, the difference being due to its slopes.
+pub fn surface_length(elevations: &[f64], spacing: f64) -> f64 {
+    elevations
+        .windows(2)
+        .map(|w| spacing.hypot(w[1] - w[0]))
+        .sum()
+}
+
+/// Units in which a [`Length`] can be expressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LengthUnit {
+    Meters,
+    Kilometers,
+    Feet,
+    Miles,
+    NauticalMiles,
+}
+
+impl LengthUnit {
+    const ALL: [LengthUnit; 5] = [
+        LengthUnit::Meters,
+        LengthUnit::Kilometers,
+        LengthUnit::Feet,
+        LengthUnit::Miles,
+        LengthUnit::NauticalMiles,
+    ];
+
+    /// Length of one unit, in meters.
+    pub fn meters(self) -> f64 {
+        match self {
+            LengthUnit::Meters => 1.,
+            LengthUnit::Kilometers => 1000.,
+            LengthUnit::Feet => 0.3048,
+            LengthUnit::Miles => 1609.344,
+            LengthUnit::NauticalMiles => 1852.,
+        }
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            LengthUnit::Meters => "m",
+            LengthUnit::Kilometers => "km",
+            LengthUnit::Feet => "ft",
+            LengthUnit::Miles => "mi",
+            LengthUnit::NauticalMiles => "nmi",
+        }
+    }
+}
+
+/// Errors on lengths.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum LengthError {
+    #[error("invalid length: {0:?}")]
+    Invalid(String),
+    #[error("unknown length unit: {0:?}")]
+    UnknownUnit(String),
+}
+
+impl FromStr for LengthUnit {
+    type Err = LengthError;
+
+    fn from_str(s: &str) -> Result<LengthUnit, LengthError> {
+        LengthUnit::ALL
+            .iter()
+            .find(|u| u.symbol().eq_ignore_ascii_case(s))
+            .copied()
+            .ok_or_else(|| LengthError::UnknownUnit(s.to_string()))
+    }
+}
+
+/// A length together with the unit it is expressed in.
+///
+/// Lengths compare by their value in meters, whatever their unit.
+#[derive(Debug, Clone, Copy)]
+pub struct Length {
+    value: f64,
+    unit: LengthUnit,
+}
+
+impl Length {
+    pub fn new(value: f64, unit: LengthUnit) -> Length {
+        Length { value, unit }
+    }
+
+    pub fn from_meters(meters: f64) -> Length {
+        Length::new(meters, LengthUnit::Meters)
+    }
+
+    pub fn value(self) -> f64 {
+        self.value
+    }
+
+    pub fn unit(self) -> LengthUnit {
+        self.unit
+    }
+
+    pub fn meters(self) -> f64 {
+        self.value * self.unit.meters()
+    }
+
+    /// The same length, expressed in another unit.
+    pub fn to(self, unit: LengthUnit) -> Length {
+        Length::new(self.meters() / unit.meters(), unit)
+    }
+}
+
+impl PartialEq for Length {
+    fn eq(&self, other: &Length) -> bool {
+        self.meters() == other.meters()
+    }
+}
+
+impl PartialOrd for Length {
+    fn partial_cmp(&self, other: &Length) -> Option<Ordering> {
+        self.meters().partial_cmp(&other.meters())
+    }
+}
+
+impl fmt::Display for Length {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match f.precision() {
+            Some(p) => write!(f, "{:.*} {}", p, self.value, self.unit.symbol()),
+            None => write!(f, "{} {}", self.value, self.unit.symbol()),
+        }
+    }
+}
+
+/// Parse lengths such as `"120km"`, `"3.5 mi"` or `"8000 ft"`. A value
+/// without unit is in meters.
+impl FromStr for Length {
+    type Err = LengthError;
+
+    fn from_str(s: &str) -> Result<Length, LengthError> {
+        let invalid = || LengthError::Invalid(s.to_string());
+        let s = s.trim();
+        let split = s.trim_end_matches(|c: char| c.is_ascii_alphabetic()).len();
+        let (value, unit) = s.split_at(split);
+        let value: f64 = value.trim().parse().map_err(|_| invalid())?;
+        if !value.is_finite() {
+            return Err(invalid());
+        }
+
+        let unit = match unit {
+            "" => LengthUnit::Meters,
+            u => u.parse()?,
+        };
+
+        Ok(Length::new(value, unit))
+    }
+}
+
+#[cfg(test)]
+mod distance_tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+    use rand::Rng;
+
+    const EARTH_CIRCUMFERENCE: f64 = 40_030_174.0;
+
+    #[test]
+    fn to_rad_and_to_meter_are_reversible() {
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..500 {
+            let rad = std::f64::consts::TAU * rng.gen::<f64>();
+            let rad2 = to_rad(to_meter(rad));
+            assert_approx_eq!(rad, rad2, 1e-10);
+        }
+    }
+
+    #[test]
+    fn to_meter_is_correct_for_known_values() {
+        assert_approx_eq!(0., to_rad(0.));
+        assert_approx_eq!(EARTH_CIRCUMFERENCE, to_meter(std::f64::consts::TAU), 0.5);
+    }
+
+    #[test]
+    fn to_rad_is_correct_for_known_values() {
+        assert_approx_eq!(0., to_meter(0.));
+        assert_approx_eq!(std::f64::consts::TAU, to_rad(EARTH_CIRCUMFERENCE), 0.5);
+    }
+
+    #[test]
+    fn length_conversions_are_correct_for_known_values() {
+        let l = Length::new(1., LengthUnit::Miles);
+        assert_approx_eq!(1609.344, l.meters(), 1e-10);
+        assert_approx_eq!(1.609344, l.to(LengthUnit::Kilometers).value(), 1e-10);
+        assert_approx_eq!(5280., l.to(LengthUnit::Feet).value(), 1e-9);
+        assert_approx_eq!(
+            1.,
+            Length::new(1852., LengthUnit::Meters)
+                .to(LengthUnit::NauticalMiles)
+                .value(),
+            1e-10
+        );
+    }
+
+    #[test]
+    fn length_conversions_are_reversible() {
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..500 {
+            let v = rng.gen::<f64>() * 1e6;
+            for u in LengthUnit::ALL {
+                let l = Length::from_meters(v).to(u).to(LengthUnit::Meters);
+                assert_approx_eq!(v, l.value(), 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn length_compares_across_units() {
+        assert_eq!(
+            Length::new(1., LengthUnit::Kilometers),
+            Length::new(1000., LengthUnit::Meters)
+        );
+        assert!(Length::new(1., LengthUnit::Miles) > Length::new(1., LengthUnit::Kilometers));
+    }
+
+    #[test]
+    fn length_parses_values_with_units() {
+        let l: Length = "120km".parse().unwrap();
+        assert_eq!(LengthUnit::Kilometers, l.unit());
+        assert_approx_eq!(120_000., l.meters(), 1e-10);
+
+        let l: Length = " 3.5 MI ".parse().unwrap();
+        assert_eq!(LengthUnit::Miles, l.unit());
+        assert_approx_eq!(3.5, l.value(), 1e-10);
+
+        let l: Length = "1.5e3ft".parse().unwrap();
+        assert_approx_eq!(1500., l.value(), 1e-10);
+
+        let l: Length = "3200".parse().unwrap();
+        assert_eq!(LengthUnit::Meters, l.unit());
+
+        for s in ["", "km", "12 parsecs", "1e400 m", "twelve km"] {
+            assert!(s.parse::<Length>().is_err(), "{} should not parse", s);
+        }
+    }
+
+    #[test]
+    fn length_displays_with_unit_symbol() {
+        assert_eq!(
+            "120 km",
+            Length::new(120., LengthUnit::Kilometers).to_string()
+        );
+        assert_eq!(
+            "10498.7 ft",
+            format!(
+                "{:.1}",
+                Length::new(3200., LengthUnit::Meters).to(LengthUnit::Feet)
+            )
+        );
+    }
+
+    #[test]
+    fn meters_per_deg_are_correct_for_known_values() {
+        assert_approx_eq!(EARTH_CIRCUMFERENCE / 360., meters_per_deg_lat(), 1.);
+        assert_approx_eq!(meters_per_deg_lat(), meters_per_deg_lon(0.), 1e-10);
+        assert_approx_eq!(0., meters_per_deg_lon(std::f64::consts::FRAC_PI_2), 1e-8);
+        assert_approx_eq!(
+            meters_per_deg_lat() / 2.,
+            meters_per_deg_lon(60_f64.to_radians()),
+            1e-8
+        );
+    }
+
+    #[test]
+    fn effective_radius_is_correct_for_known_values() {
+        assert_approx_eq!(EARTH_RADIUS, effective_radius(0.), 1e-10);
+        assert_approx_eq!(
+            EARTH_RADIUS / 0.87,
+            effective_radius(REFRACTION_COEFFICIENT),
+            1e-6
+        );
+    }
+
+    #[test]
+    fn refracted_conversions_without_refraction_are_plain_ones() {
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..500 {
+            let d = rng.gen::<f64>() * 1e6;
+            assert_approx_eq!(to_rad(d), to_rad_refracted(d, 0.), 1e-15);
+            let rad = to_rad(d);
+            assert_approx_eq!(to_meter(rad), to_meter_refracted(rad, 0.), 1e-6);
+        }
+    }
+
+    #[test]
+    fn refracted_conversions_are_reversible() {
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..500 {
+            let rad = std::f64::consts::TAU * rng.gen::<f64>();
+            let k = rng.gen::<f64>() * 0.5;
+            assert_approx_eq!(rad, to_rad_refracted(to_meter_refracted(rad, k), k), 1e-10);
+        }
+    }
+
+    #[test]
+    fn horizon_distance_is_correct_for_known_values() {
+        assert_approx_eq!(0., horizon_distance(0., 0.), 1e-10);
+        // geometric horizon of a 1.7 m tall observer is about 4.65 km away
+        assert_approx_eq!(4654., horizon_distance(1.7, 0.), 1.);
+        assert_approx_eq!(
+            (2. * effective_radius(REFRACTION_COEFFICIENT) * 3200.).sqrt(),
+            horizon_distance(3200., REFRACTION_COEFFICIENT),
+            50.
+        );
+        assert!(horizon_distance(3200., REFRACTION_COEFFICIENT) > horizon_distance(3200., 0.));
+    }
+
+    #[test]
+    fn horizon_distance_and_observer_height_are_reversible() {
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..500 {
+            let h = rng.gen::<f64>() * 9000.;
+            let k = rng.gen::<f64>() * 0.3;
+            let h2 = horizon_observer_height(horizon_distance(h, k), k);
+            assert_approx_eq!(h, h2, 1e-4);
+        }
+    }
+
+    #[test]
+    fn horizon_dip_is_correct_for_known_values() {
+        assert_approx_eq!(0., horizon_dip(0., REFRACTION_COEFFICIENT), 1e-10);
+        // about 1.93 arc minutes per square root meter without refraction
+        assert_approx_eq!((1.93 * 10.) / 60., horizon_dip(100., 0.).to_degrees(), 0.01);
+        assert!(horizon_dip(3200., REFRACTION_COEFFICIENT) < horizon_dip(3200., 0.));
+    }
+
+    #[test]
+    fn horizon_dip_grows_with_height() {
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..500 {
+            let h = rng.gen::<f64>() * 9000.;
+            let dh = rng.gen::<f64>() * 100. + 1.;
+            assert!(
+                horizon_dip(h, REFRACTION_COEFFICIENT)
+                    < horizon_dip(h + dh, REFRACTION_COEFFICIENT)
+            );
+        }
+    }
+
+    #[test]
+    fn ellipsoid_radii_are_correct_for_known_values() {
+        use std::f64::consts::FRAC_PI_2;
+
+        assert_approx_eq!(6335439., meridional_radius(0.), 1.);
+        assert_approx_eq!(WGS84_A, prime_vertical_radius(0.), 1e-6);
+        assert_approx_eq!(6399594., meridional_radius(FRAC_PI_2), 1.);
+        assert_approx_eq!(6399594., prime_vertical_radius(FRAC_PI_2), 1.);
+
+        let lat = 46_f64.to_radians();
+        assert_approx_eq!(meridional_radius(lat), ellipsoid_radius(lat, 0.), 1e-6);
+        assert_approx_eq!(
+            prime_vertical_radius(lat),
+            ellipsoid_radius(lat, FRAC_PI_2),
+            1e-6
+        );
+    }
+
+    #[test]
+    fn degree_of_latitude_on_ellipsoid_is_correct_at_45_degrees() {
+        let d = to_meter_on_ellipsoid(1_f64.to_radians(), 45_f64.to_radians(), 0.);
+        assert_approx_eq!(111_132., d, 1.);
+    }
+
+    #[test]
+    fn ellipsoid_conversions_are_reversible() {
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..500 {
+            let rad = rng.gen::<f64>() * 0.1;
+            let lat = rng.gen_range(-1.5..1.5);
+            let azimuth = rng.gen::<f64>() * std::f64::consts::TAU;
+            let rad2 = to_rad_on_ellipsoid(to_meter_on_ellipsoid(rad, lat, azimuth), lat, azimuth);
+            assert_approx_eq!(rad, rad2, 1e-12);
+        }
+    }
+
+    #[test]
+    fn sphere_earth_model_matches_free_functions() {
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..500 {
+            let dist = rng.gen::<f64>() * 100_000.;
+            let lat = rng.gen_range(-1.5..1.5);
+            let azimuth = rng.gen::<f64>() * std::f64::consts::TAU;
+            assert_eq!(to_rad(dist), Sphere::EARTH.to_rad(dist, lat, azimuth));
+            assert_eq!(
+                effective_radius(REFRACTION_COEFFICIENT),
+                Sphere::default().effective_radius(lat, azimuth, REFRACTION_COEFFICIENT)
+            );
+        }
+        assert_eq!(1., Sphere { radius: 1000. }.to_rad(1000., 0., 0.));
+    }
+
+    #[test]
+    fn wgs84_earth_model_uses_ellipsoid_radius() {
+        let lat = 46.5_f64.to_radians();
+        assert_eq!(meridional_radius(lat), Wgs84.radius(lat, 0.));
+        assert_eq!(
+            to_meter_on_ellipsoid(0.01, lat, 1.),
+            Wgs84.to_meter(0.01, lat, 1.)
+        );
+    }
+
+    #[test]
+    fn surface_length_is_correct_for_known_profiles() {
+        assert_approx_eq!(0., surface_length(&[], 10.), 1e-10);
+        assert_approx_eq!(0., surface_length(&[1000.], 10.), 1e-10);
+        assert_approx_eq!(30., surface_length(&[500., 500., 500., 500.], 10.), 1e-10);
+        assert_approx_eq!(10., surface_length(&[0., 3., 0.], 4.), 1e-10);
+    }
+
+    #[test]
+    fn surface_length_is_at_least_arc_distance() {
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..100 {
+            let spacing = rng.gen::<f64>() * 100.;
+            let profile: Vec<f64> = (0..50).map(|_| rng.gen::<f64>() * 4000.).collect();
+            assert!(surface_length(&profile, spacing) >= spacing * 49.);
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use std::io;
+
+use thiserror::Error;
+
+use crate::{
+    azimuth::AngleError, calibration::CalibrationError, dem::DemError, distance::LengthError,
+    geo::GeoError, math::RootError, panorama::RenderError,
+};
+
+/// Any error of the crate, wrapping the errors of its modules.
+#[derive(Debug, Error)]
+pub enum AlpanoError {
+    #[error(transparent)]
+    Angle(#[from] AngleError),
+    #[error(transparent)]
+    Calibration(#[from] CalibrationError),
+    #[cfg(feature = "serde")]
+    #[error(transparent)]
+    Config(#[from] crate::config::ConfigError),
+    #[error(transparent)]
+    Dem(#[from] DemError),
+    #[error(transparent)]
+    Geo(#[from] GeoError),
+    #[error(transparent)]
+    Length(#[from] LengthError),
+    #[error(transparent)]
+    Render(#[from] RenderError),
+    #[error(transparent)]
+    Root(#[from] RootError),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
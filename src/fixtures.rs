@@ -0,0 +1,120 @@
+use crate::{
+    azimuth::Azimuth,
+    dem::{ContinuousElevationModel, DiscreteElevationModel, MemoryDem},
+    geo::GeoPoint,
+    interval::{Interval1D, Interval2D},
+    panorama::{Panorama, PanoramaComputer, PanoramaParameters},
+    viewshed::Viewshed,
+};
+
+/// Terrain over 10 samples per degree, from 6° to 9° of longitude and 45° to
+/// 48° of latitude, whose samples have the given elevation.
+pub(crate) fn terrain(elevation: impl Fn(i32, i32) -> f64) -> MemoryDem {
+    let extent = Interval2D::new(Interval1D::new(60, 90), Interval1D::new(450, 480));
+    MemoryDem::from_fn(10, extent, elevation)
+}
+
+/// Flat [`terrain`] at the given elevation.
+pub(crate) fn flat(elevation: f64) -> MemoryDem {
+    terrain(move |_, _| elevation)
+}
+
+/// Flat [`terrain`] at 1000 m, with a 3000 m high wall along the 8° meridian.
+pub(crate) fn wall() -> MemoryDem {
+    terrain(|x, _| if x == 80 { 3000. } else { 1000. })
+}
+
+/// Panorama of `parameters` over `dem`.
+pub(crate) fn compute(
+    dem: impl DiscreteElevationModel,
+    parameters: &PanoramaParameters,
+) -> Panorama {
+    PanoramaComputer::new(ContinuousElevationModel::new(dem)).compute(parameters)
+}
+
+/// Parameters of a panorama of 401×401 pixels over 40° toward the east,
+/// seen from 7°E 46.5°N at 1100 m, up to 150 km, which sees the [`wall`].
+pub(crate) fn wall_parameters() -> PanoramaParameters {
+    PanoramaParameters::new(
+        GeoPoint::from_degrees(7., 46.5).unwrap(),
+        1100.,
+        Azimuth::EAST,
+        40_f64.to_radians(),
+        150_000.,
+        401,
+        401,
+    )
+    .unwrap()
+}
+
+/// Panorama of the [`wall`] with the [`wall_parameters`].
+pub(crate) fn panorama() -> Panorama {
+    compute(wall(), &wall_parameters())
+}
+
+/// Panorama of 11×11 pixels over 10° toward the north, seen from 7.5°E
+/// 46.5°N at 500 m, up to 50 km, of [`flat`] terrain at sea level.
+pub(crate) fn flat_panorama() -> Panorama {
+    compute(flat(0.), &flat_parameters())
+}
+
+/// Parameters of the [`flat_panorama`].
+pub(crate) fn flat_parameters() -> PanoramaParameters {
+    PanoramaParameters::new(
+        GeoPoint::from_degrees(7.5, 46.5).unwrap(),
+        500.,
+        Azimuth::NORTH,
+        10_f64.to_radians(),
+        50_000.,
+        11,
+        11,
+    )
+    .unwrap()
+}
+
+/// Terrain over 100 samples per degree, from 7° to 8° of longitude and 46° to
+/// 47° of latitude, whose samples have the given elevation.
+pub(crate) fn fine_terrain(elevation: impl Fn(i32, i32) -> f64) -> MemoryDem {
+    let extent = Interval2D::new(Interval1D::new(700, 800), Interval1D::new(4600, 4700));
+    MemoryDem::from_fn(100, extent, elevation)
+}
+
+/// Flat [`fine_terrain`] at 1000 m, with a 2000 m high wall along the 7.55°
+/// meridian.
+pub(crate) fn meridian_wall() -> MemoryDem {
+    fine_terrain(|x, _| if x == 755 { 2000. } else { 1000. })
+}
+
+/// Viewshed of targets `target_height` above the [`meridian_wall`], seen
+/// from 7.5°E 46.5°N at 1010 m, up to 10 km.
+pub(crate) fn viewshed(target_height: f64) -> Viewshed {
+    Viewshed::compute(
+        &ContinuousElevationModel::new(meridian_wall()),
+        GeoPoint::from_degrees(7.5, 46.5).unwrap(),
+        1010.,
+        target_height,
+        10_000.,
+    )
+}
+
+/// Flat [`fine_terrain`] at 1000 m, with a 1500 m high wall along the 46.65°
+/// parallel.
+pub(crate) fn parallel_wall() -> MemoryDem {
+    fine_terrain(|_, y| if y == 4665 { 1500. } else { 1000. })
+}
+
+/// Panorama of 201×101 pixels over 20° toward the north, seen from 7.5°E
+/// 46.5°N at 1100 m, up to 30 km, of the [`parallel_wall`].
+pub(crate) fn parallel_wall_panorama() -> Panorama {
+    let parameters = PanoramaParameters::new(
+        GeoPoint::from_degrees(7.5, 46.5).unwrap(),
+        1100.,
+        Azimuth::NORTH,
+        20_f64.to_radians(),
+        30_000.,
+        201,
+        101,
+    )
+    .unwrap();
+    compute(parallel_wall(), &parameters)
+}
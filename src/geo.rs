@@ -0,0 +1,432 @@
+use std::{
+    f64::consts::{FRAC_PI_2, PI, TAU},
+    fmt,
+};
+
+use thiserror::Error;
+
+use crate::{
+    azimuth::{format_seconds_as_dms, Azimuth},
+    distance,
+    math::haversin,
+};
+
+/// Errors on points of the earth.
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum GeoError {
+    #[error("coordinates out of range: longitude {longitude}, latitude {latitude} (radians)")]
+    OutOfRange { longitude: f64, latitude: f64 },
+}
+
+/// A point on the surface of the earth, given by its longitude and latitude
+/// in radians.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "Coordinates")
+)]
+pub struct GeoPoint {
+    longitude: f64,
+    latitude: f64,
+}
+
+/// Unchecked coordinates of a point, validated when deserialized into a
+/// [`GeoPoint`].
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct Coordinates {
+    longitude: f64,
+    latitude: f64,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<Coordinates> for GeoPoint {
+    type Error = GeoError;
+
+    fn try_from(c: Coordinates) -> Result<GeoPoint, GeoError> {
+        GeoPoint::new(c.longitude, c.latitude)
+    }
+}
+
+impl GeoPoint {
+    /// Create a point, failing unless the longitude is in `[-π, π]` and the
+    /// latitude in `[-π/2, π/2]`.
+    pub fn new(longitude: f64, latitude: f64) -> Result<GeoPoint, GeoError> {
+        if !(-PI..=PI).contains(&longitude) || !(-FRAC_PI_2..=FRAC_PI_2).contains(&latitude) {
+            return Err(GeoError::OutOfRange {
+                longitude,
+                latitude,
+            });
+        }
+
+        Ok(GeoPoint {
+            longitude,
+            latitude,
+        })
+    }
+
+    /// Create a point from a longitude and latitude in degrees.
+    pub fn from_degrees(longitude: f64, latitude: f64) -> Result<GeoPoint, GeoError> {
+        GeoPoint::new(longitude.to_radians(), latitude.to_radians())
+    }
+
+    pub fn longitude(&self) -> f64 {
+        self.longitude
+    }
+
+    pub fn latitude(&self) -> f64 {
+        self.latitude
+    }
+
+    /// Great-circle distance to `that` point, in meters, by the haversine
+    /// formula.
+    pub fn distance_to(&self, that: &GeoPoint) -> f64 {
+        let a = haversin(self.latitude - that.latitude)
+            + self.latitude.cos() * that.latitude.cos() * haversin(self.longitude - that.longitude);
+        distance::to_meter(2. * a.sqrt().asin())
+    }
+
+    /// Initial azimuth of the great circle going to `that` point.
+    pub fn azimuth_to(&self, that: &GeoPoint) -> Azimuth {
+        let d_lon = self.longitude - that.longitude;
+        let math_angle = (d_lon.sin() * that.latitude.cos()).atan2(
+            self.latitude.cos() * that.latitude.sin()
+                - self.latitude.sin() * that.latitude.cos() * d_lon.cos(),
+        );
+        Azimuth::from_math(math_angle)
+    }
+
+    /// Point reached by following the great circle leaving this point at
+    /// `azimuth` over `distance` meters.
+    pub fn destination(&self, azimuth: Azimuth, distance: f64) -> GeoPoint {
+        let d = distance::to_rad(distance);
+        let a = azimuth.radians();
+        let latitude = (self.latitude.sin() * d.cos() + self.latitude.cos() * d.sin() * a.cos())
+            .clamp(-1., 1.)
+            .asin();
+        let longitude = self.longitude
+            + (a.sin() * d.sin() * self.latitude.cos())
+                .atan2(d.cos() - self.latitude.sin() * latitude.sin());
+
+        GeoPoint {
+            longitude: (longitude + PI).rem_euclid(TAU) - PI,
+            latitude,
+        }
+    }
+
+    /// Latitude and longitude of the point in degrees, minutes and seconds,
+    /// e.g. `46°31′18″ N, 6°34′44″ E`, with seconds rounded to `precision`
+    /// decimals.
+    pub fn to_dms_string(&self, precision: usize) -> String {
+        format!(
+            "{}, {}",
+            format_latitude(self.latitude, precision),
+            format_longitude(self.longitude, precision)
+        )
+    }
+
+    /// Length of the geodesic to `that` point on the WGS84 ellipsoid, in
+    /// meters, by Vincenty's inverse formula.
+    ///
+    /// Falls back to [`distance_to`](GeoPoint::distance_to) for nearly
+    /// antipodal points, on which the formula does not converge.
+    pub fn geodesic_distance_to(&self, that: &GeoPoint) -> f64 {
+        self.vincenty_inverse(that)
+            .map_or_else(|| self.distance_to(that), |(distance, _)| distance)
+    }
+
+    /// Initial azimuth of the geodesic going to `that` point on the WGS84
+    /// ellipsoid, by Vincenty's inverse formula.
+    ///
+    /// Falls back to [`azimuth_to`](GeoPoint::azimuth_to) for nearly
+    /// antipodal points, on which the formula does not converge.
+    pub fn geodesic_azimuth_to(&self, that: &GeoPoint) -> Azimuth {
+        self.vincenty_inverse(that)
+            .map_or_else(|| self.azimuth_to(that), |(_, azimuth)| azimuth)
+    }
+
+    /// Length and initial azimuth of the geodesic to `that` point, or `None`
+    /// if the iteration on the longitude difference does not converge.
+    fn vincenty_inverse(&self, that: &GeoPoint) -> Option<(f64, Azimuth)> {
+        const MAX_ITERATIONS: usize = 200;
+        const PRECISION: f64 = 1e-12;
+
+        let (a, f) = (distance::WGS84_A, distance::WGS84_F);
+        let b = a * (1. - f);
+
+        // reduced latitudes
+        let (sin_u1, cos_u1) = ((1. - f) * self.latitude.tan()).atan().sin_cos();
+        let (sin_u2, cos_u2) = ((1. - f) * that.latitude.tan()).atan().sin_cos();
+
+        let l = that.longitude - self.longitude;
+        let mut lambda = l;
+        for _ in 0..MAX_ITERATIONS {
+            let (sin_lambda, cos_lambda) = lambda.sin_cos();
+            let sin_sigma = ((cos_u2 * sin_lambda).powi(2)
+                + (cos_u1 * sin_u2 - sin_u1 * cos_u2 * cos_lambda).powi(2))
+            .sqrt();
+            if sin_sigma == 0. {
+                // coincident points
+                return Some((0., Azimuth::NORTH));
+            }
+            let cos_sigma = sin_u1 * sin_u2 + cos_u1 * cos_u2 * cos_lambda;
+            let sigma = sin_sigma.atan2(cos_sigma);
+            let sin_alpha = cos_u1 * cos_u2 * sin_lambda / sin_sigma;
+            let cos2_alpha = 1. - sin_alpha.powi(2);
+            // on the equator, cos²α is 0 and so is the term it multiplies
+            let cos_2sigma_m = if cos2_alpha == 0. {
+                0.
+            } else {
+                cos_sigma - 2. * sin_u1 * sin_u2 / cos2_alpha
+            };
+            let c = f / 16. * cos2_alpha * (4. + f * (4. - 3. * cos2_alpha));
+            let previous = lambda;
+            lambda = l
+                + (1. - c)
+                    * f
+                    * sin_alpha
+                    * (sigma
+                        + c * sin_sigma
+                            * (cos_2sigma_m + c * cos_sigma * (2. * cos_2sigma_m.powi(2) - 1.)));
+
+            if (lambda - previous).abs() < PRECISION {
+                let u2 = cos2_alpha * (a * a - b * b) / (b * b);
+                let big_a = 1. + u2 / 16384. * (4096. + u2 * (-768. + u2 * (320. - 175. * u2)));
+                let big_b = u2 / 1024. * (256. + u2 * (-128. + u2 * (74. - 47. * u2)));
+                let delta_sigma = big_b
+                    * sin_sigma
+                    * (cos_2sigma_m
+                        + big_b / 4.
+                            * (cos_sigma * (2. * cos_2sigma_m.powi(2) - 1.)
+                                - big_b / 6.
+                                    * cos_2sigma_m
+                                    * (4. * sin_sigma.powi(2) - 3.)
+                                    * (4. * cos_2sigma_m.powi(2) - 3.)));
+                let distance = b * big_a * (sigma - delta_sigma);
+
+                let (sin_lambda, cos_lambda) = lambda.sin_cos();
+                let azimuth =
+                    (cos_u2 * sin_lambda).atan2(cos_u1 * sin_u2 - sin_u1 * cos_u2 * cos_lambda);
+                return Some((distance, Azimuth::new(azimuth)));
+            }
+        }
+
+        None
+    }
+}
+
+/// Format a latitude (in radians) in degrees, minutes and seconds followed by
+/// its hemisphere, e.g. `46°31′07″ N`, with seconds rounded to `precision`
+/// decimals.
+pub fn format_latitude(latitude: f64, precision: usize) -> String {
+    format_with_hemisphere(latitude, precision, 'N', 'S')
+}
+
+/// Format a longitude (in radians) like [`format_latitude`], e.g.
+/// `6°34′44″ E`.
+pub fn format_longitude(longitude: f64, precision: usize) -> String {
+    format_with_hemisphere(longitude, precision, 'E', 'W')
+}
+
+fn format_with_hemisphere(angle: f64, precision: usize, positive: char, negative: char) -> String {
+    let scale = 10_f64.powi(precision as i32);
+    let seconds = (angle.abs().to_degrees() * 3600. * scale).round() / scale;
+    // angles rounded to 0 have no hemisphere to be in
+    let hemisphere = if seconds == 0. || angle > 0. {
+        positive
+    } else {
+        negative
+    };
+    format!(
+        "{} {}",
+        format_seconds_as_dms(seconds, precision),
+        hemisphere
+    )
+}
+
+impl fmt::Display for GeoPoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "({:.4},{:.4})",
+            self.longitude.to_degrees(),
+            self.latitude.to_degrees()
+        )
+    }
+}
+
+#[cfg(test)]
+mod geo_tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+    use rand::Rng;
+
+    fn epfl() -> GeoPoint {
+        GeoPoint::from_degrees(6.5790, 46.5218).unwrap()
+    }
+
+    fn moscow() -> GeoPoint {
+        GeoPoint::from_degrees(37.6184, 55.7512).unwrap()
+    }
+
+    #[test]
+    fn new_fails_for_out_of_range_coordinates() {
+        assert!(GeoPoint::new(PI + 1e-9, 0.).is_err());
+        assert!(GeoPoint::new(-PI - 1e-9, 0.).is_err());
+        assert!(GeoPoint::new(0., FRAC_PI_2 + 1e-9).is_err());
+        assert!(GeoPoint::new(0., -FRAC_PI_2 - 1e-9).is_err());
+        assert!(GeoPoint::new(f64::NAN, 0.).is_err());
+        assert!(GeoPoint::new(PI, FRAC_PI_2).is_ok());
+        assert!(GeoPoint::new(-PI, -FRAC_PI_2).is_ok());
+    }
+
+    #[test]
+    fn distance_to_is_correct_on_known_values() {
+        assert_approx_eq!(2_369_946., epfl().distance_to(&moscow()), 1.);
+        assert_approx_eq!(0., epfl().distance_to(&epfl()), 1e-6);
+    }
+
+    #[test]
+    fn distance_to_is_symmetric() {
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..500 {
+            let p1 = GeoPoint::new(rng.gen_range(-PI..PI), rng.gen_range(-1.5..1.5)).unwrap();
+            let p2 = GeoPoint::new(rng.gen_range(-PI..PI), rng.gen_range(-1.5..1.5)).unwrap();
+            assert_approx_eq!(p1.distance_to(&p2), p2.distance_to(&p1), 1e-6);
+        }
+    }
+
+    #[test]
+    fn azimuth_to_is_correct_on_known_values() {
+        assert_approx_eq!(52.9762, epfl().azimuth_to(&moscow()).degrees(), 1e-4);
+
+        let origin = GeoPoint::new(0., 0.).unwrap();
+        let north = GeoPoint::new(0., 0.1).unwrap();
+        let east = GeoPoint::new(0.1, 0.).unwrap();
+        assert_approx_eq!(0., origin.azimuth_to(&north).radians(), 1e-10);
+        assert_approx_eq!(90., origin.azimuth_to(&east).degrees(), 1e-10);
+        assert_approx_eq!(180., north.azimuth_to(&origin).degrees(), 1e-10);
+        assert_approx_eq!(270., east.azimuth_to(&origin).degrees(), 1e-10);
+    }
+
+    #[test]
+    fn destination_is_inverse_of_distance_and_azimuth() {
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..500 {
+            let p1 = GeoPoint::new(rng.gen_range(-PI..PI), rng.gen_range(-1.5..1.5)).unwrap();
+            let p2 = GeoPoint::new(rng.gen_range(-PI..PI), rng.gen_range(-1.5..1.5)).unwrap();
+            let p = p1.destination(p1.azimuth_to(&p2), p1.distance_to(&p2));
+            assert!(p.distance_to(&p2) < 1e-3);
+        }
+    }
+
+    #[test]
+    fn destination_is_correct_on_known_values() {
+        let origin = GeoPoint::new(0., 0.).unwrap();
+        let quarter = distance::to_meter(FRAC_PI_2);
+        let east = origin.destination(Azimuth::EAST, quarter);
+        assert_approx_eq!(FRAC_PI_2, east.longitude(), 1e-12);
+        assert_approx_eq!(0., east.latitude(), 1e-12);
+        let north = origin.destination(Azimuth::NORTH, quarter);
+        assert_approx_eq!(FRAC_PI_2, north.latitude(), 1e-12);
+        let west = GeoPoint::new(-3., 0.)
+            .unwrap()
+            .destination(Azimuth::WEST, quarter);
+        assert_approx_eq!(TAU - 3. - FRAC_PI_2, west.longitude(), 1e-12);
+    }
+
+    /// Parses an angle given in degrees, minutes and seconds.
+    fn dms(degrees: f64, minutes: f64, seconds: f64) -> f64 {
+        degrees.signum() * (degrees.abs() + minutes / 60. + seconds / 3600.)
+    }
+
+    #[test]
+    fn geodesic_distance_and_azimuth_match_vincenty_example() {
+        // Flinders Peak to Buninyong, from Vincenty's 1975 paper
+        let flinders =
+            GeoPoint::from_degrees(dms(144., 25., 29.5244), dms(-37., 57., 3.7203)).unwrap();
+        let buninyong =
+            GeoPoint::from_degrees(dms(143., 55., 35.3839), dms(-37., 39., 10.1561)).unwrap();
+        assert_approx_eq!(54972.271, flinders.geodesic_distance_to(&buninyong), 1e-3);
+        assert_approx_eq!(
+            dms(306., 52., 5.37),
+            flinders.geodesic_azimuth_to(&buninyong).degrees(),
+            1e-5
+        );
+    }
+
+    #[test]
+    fn geodesic_distance_is_correct_along_equator_and_meridian() {
+        let origin = GeoPoint::new(0., 0.).unwrap();
+        let east = GeoPoint::from_degrees(1., 0.).unwrap();
+        assert_approx_eq!(
+            distance::WGS84_A * 1_f64.to_radians(),
+            origin.geodesic_distance_to(&east),
+            1e-6
+        );
+        assert_eq!(Azimuth::EAST, origin.geodesic_azimuth_to(&east));
+
+        // length of the quarter meridian of WGS84
+        let pole = GeoPoint::new(0., FRAC_PI_2).unwrap();
+        assert_approx_eq!(10_001_965.729, origin.geodesic_distance_to(&pole), 1e-3);
+        assert_eq!(0., origin.geodesic_distance_to(&origin));
+    }
+
+    #[test]
+    fn geodesic_distance_is_close_to_spherical_distance() {
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..500 {
+            let p = GeoPoint::new(rng.gen_range(-PI..PI), rng.gen_range(-1.5..1.5)).unwrap();
+            let q = p.destination(Azimuth::new(rng.gen_range(0. ..TAU)), 200_000.);
+            let spherical = p.distance_to(&q);
+            assert_approx_eq!(spherical, p.geodesic_distance_to(&q), 0.006 * spherical);
+        }
+    }
+
+    #[test]
+    fn geodesic_distance_falls_back_on_antipodes() {
+        let p = GeoPoint::new(0., 0.).unwrap();
+        let antipode = GeoPoint::new(PI, 0.).unwrap();
+        assert_approx_eq!(
+            p.distance_to(&antipode),
+            p.geodesic_distance_to(&antipode),
+            1e-6
+        );
+    }
+
+    #[test]
+    fn to_dms_string_is_correct_on_known_values() {
+        assert_eq!("46°31′18″ N, 6°34′44″ E", epfl().to_dms_string(0));
+        assert_eq!("46°31′18.48″ N, 6°34′44.40″ E", epfl().to_dms_string(2));
+        let rio = GeoPoint::from_degrees(-43.1729, -22.9068).unwrap();
+        assert_eq!("22°54′24.5″ S, 43°10′22.4″ W", rio.to_dms_string(1));
+    }
+
+    #[test]
+    fn format_latitude_rounds_to_precision() {
+        assert_eq!("0°00′00″ N", format_latitude(-1e-9, 0));
+        assert_eq!(
+            "1°00′00.0″ S",
+            format_latitude(-(1. - 1e-7_f64).to_radians(), 1)
+        );
+        assert_eq!("90°00′00″ N", format_latitude(FRAC_PI_2, 0));
+        assert_eq!("180°00′00″ W", format_longitude(-PI, 0));
+    }
+
+    #[test]
+    fn display_is_in_degrees() {
+        assert_eq!("(6.5790,46.5218)", epfl().to_string());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trips_and_validates() {
+        let toml = toml::to_string(&epfl()).unwrap();
+        assert_eq!(epfl(), toml::from_str(&toml).unwrap());
+        assert!(toml::from_str::<GeoPoint>("longitude = 4.0\nlatitude = 0.0").is_err());
+    }
+}
@@ -0,0 +1,353 @@
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::Path,
+};
+
+use crate::{
+    dem::{ContinuousElevationModel, DiscreteElevationModel},
+    distance::{effective_radius, REFRACTION_COEFFICIENT},
+    geo::GeoPoint,
+    image::Image,
+    panorama::Panorama,
+};
+
+/// Color of tracks drawn on panoramas.
+const TRACK_COLOR: [u8; 4] = [230, 30, 30, 255];
+/// Radius of the dots drawing tracks, in pixels.
+const TRACK_RADIUS: f64 = 1.5;
+/// Distance before a point of a track at which the terrain seen is
+/// considered to be the ground of the track rather than hiding it, in meters,
+/// to which 2% of the distance of the point is added.
+const VISIBILITY_TOLERANCE: f64 = 200.;
+/// Maximal number of points drawn between two consecutive points of a track.
+const MAX_STEPS: usize = 1000;
+/// Length of the dashes and of the gaps between them drawing the parts of
+/// tracks hidden by the terrain, in pixels.
+const DASH_LENGTH: usize = 4;
+
+/// A point of a track, with its elevation in meters if known.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrackPoint {
+    pub position: GeoPoint,
+    pub elevation: Option<f64>,
+}
+
+/// GPS track, made of segments of consecutive points, like the tracks and
+/// routes of GPX files.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Track {
+    segments: Vec<Vec<TrackPoint>>,
+}
+
+impl Track {
+    pub fn new(segments: Vec<Vec<TrackPoint>>) -> Track {
+        Track { segments }
+    }
+
+    /// Track of the `trkseg` segments of the tracks and of the routes of the
+    /// GPX document `xml`.
+    pub fn parse_gpx(xml: &str) -> io::Result<Track> {
+        let invalid = |message: String| io::Error::new(ErrorKind::InvalidData, message);
+        let document = roxmltree::Document::parse(xml).map_err(|e| invalid(e.to_string()))?;
+
+        let mut segments = Vec::new();
+        for segment in document
+            .descendants()
+            .filter(|n| matches!(n.tag_name().name(), "trkseg" | "rte"))
+        {
+            let mut points = Vec::new();
+            for point in segment
+                .children()
+                .filter(|n| matches!(n.tag_name().name(), "trkpt" | "rtept"))
+            {
+                let coordinate = |name: &str| {
+                    point
+                        .attribute(name)
+                        .and_then(|v| v.trim().parse::<f64>().ok())
+                        .ok_or_else(|| invalid(format!("point without valid {}", name)))
+                };
+                let position = GeoPoint::from_degrees(coordinate("lon")?, coordinate("lat")?)
+                    .map_err(|e| invalid(e.to_string()))?;
+                let elevation = point
+                    .children()
+                    .find(|n| n.tag_name().name() == "ele")
+                    .and_then(|n| n.text())
+                    .and_then(|text| text.trim().parse().ok());
+                points.push(TrackPoint {
+                    position,
+                    elevation,
+                });
+            }
+            segments.push(points);
+        }
+
+        Ok(Track { segments })
+    }
+
+    /// Track of the GPX file at the given path, see
+    /// [`parse_gpx`](Track::parse_gpx).
+    pub fn load_gpx(path: impl AsRef<Path>) -> io::Result<Track> {
+        Track::parse_gpx(&fs::read_to_string(path)?)
+    }
+
+    pub fn segments(&self) -> &[Vec<TrackPoint>] {
+        &self.segments
+    }
+
+    /// Track whose points without elevation are on the ground of `cem`.
+    pub fn with_ground_elevation<D: DiscreteElevationModel>(
+        self,
+        cem: &ContinuousElevationModel<D>,
+    ) -> Track {
+        let segments = self
+            .segments
+            .into_iter()
+            .map(|points| {
+                points
+                    .into_iter()
+                    .map(|p| TrackPoint {
+                        elevation: p.elevation.or_else(|| Some(cem.elevation_at(&p.position))),
+                        ..p
+                    })
+                    .collect()
+            })
+            .collect();
+        Track { segments }
+    }
+
+    /// Pixels of `panorama` at which the track is seen, following its
+    /// segments about a pixel apart. The parts of the track hidden by the
+    /// terrain, beyond the maximum distance or out of the field of view are
+    /// left out, as are the segments joining points without elevation.
+    pub fn visible_pixels(&self, panorama: &Panorama) -> Vec<(usize, usize)> {
+        let mut pixels: Vec<(usize, usize)> = self
+            .pixels(panorama)
+            .into_iter()
+            .filter(|&(_, _, visible)| visible)
+            .map(|(x, y, _)| (x, y))
+            .collect();
+        pixels.dedup();
+        pixels
+    }
+
+    /// Pixels of `panorama` in the direction of the track, following its
+    /// segments about a pixel apart, and whether the track is seen there
+    /// rather than hidden by the terrain. The parts of the track beyond the
+    /// maximum distance or out of the field of view are left out, as are the
+    /// segments joining points without elevation.
+    fn pixels(&self, panorama: &Panorama) -> Vec<(usize, usize, bool)> {
+        let parameters = panorama.parameters();
+        let observer = parameters.observer_position();
+        let curvature = 1. / (2. * effective_radius(REFRACTION_COEFFICIENT));
+        let pixel = |position: GeoPoint, elevation: f64| {
+            let distance = observer.distance_to(&position);
+            if distance > parameters.max_distance() || distance < 1. {
+                return None;
+            }
+            // height of the point above the horizontal ray of the observer
+            let height =
+                elevation - distance * distance * curvature - parameters.observer_elevation();
+            let (x, y) = parameters
+                .pixel_for(observer.azimuth_to(&position), height.atan2(distance))
+                .ok()?;
+            // the pixel above the point sees either it or what hides it
+            let (x, y) = (x.round(), y.floor());
+            if x < 0. || y < 0. || x >= parameters.width() as f64 || y >= parameters.height() as f64
+            {
+                return None;
+            }
+            let (x, y) = (x as usize, y as usize);
+            let tolerance = VISIBILITY_TOLERANCE + 0.02 * distance;
+            Some((x, y, panorama.distance_at(x, y) >= distance - tolerance))
+        };
+
+        let mut pixels = Vec::new();
+        for points in &self.segments {
+            if let [point] = points.as_slice() {
+                pixels.extend(point.elevation.and_then(|e| pixel(point.position, e)));
+            }
+            for pair in points.windows(2) {
+                let (from, to) = (pair[0], pair[1]);
+                let (Some(from_elevation), Some(to_elevation)) = (from.elevation, to.elevation)
+                else {
+                    continue;
+                };
+                // steps of about a pixel at the nearest end
+                let nearest = observer
+                    .distance_to(&from.position)
+                    .min(observer.distance_to(&to.position))
+                    .max(1.);
+                let steps = (from.position.distance_to(&to.position)
+                    / (nearest * parameters.angle_per_pixel()))
+                .ceil()
+                .clamp(1., MAX_STEPS as f64) as usize;
+                for i in 0..=steps {
+                    let t = i as f64 / steps as f64;
+                    let lerp = |a: f64, b: f64| a + t * (b - a);
+                    let Ok(position) = GeoPoint::new(
+                        lerp(from.position.longitude(), to.position.longitude()),
+                        lerp(from.position.latitude(), to.position.latitude()),
+                    ) else {
+                        continue;
+                    };
+                    pixels.extend(pixel(position, lerp(from_elevation, to_elevation)));
+                }
+            }
+        }
+        pixels.dedup();
+        pixels
+    }
+
+    /// Draw the track onto `image`, the painted image of `panorama`, solid
+    /// where it is seen, see [`visible_pixels`](Track::visible_pixels), and
+    /// dashed where it is hidden by the terrain.
+    pub fn draw(&self, image: &mut Image, panorama: &Panorama) {
+        let r = TRACK_RADIUS.ceil() as isize;
+        // pixels since the track went behind the terrain
+        let mut hidden = 0;
+        for (x, y, visible) in self.pixels(panorama) {
+            if visible {
+                hidden = 0;
+            } else {
+                hidden += 1;
+                if (hidden - 1) % (2 * DASH_LENGTH) >= DASH_LENGTH {
+                    continue;
+                }
+            }
+            for dy in -r..=r {
+                for dx in -r..=r {
+                    let (px, py) = (x as isize + dx, y as isize + dy);
+                    if ((dx * dx + dy * dy) as f64).sqrt() <= TRACK_RADIUS
+                        && (0..image.width() as isize).contains(&px)
+                        && (0..image.height() as isize).contains(&py)
+                    {
+                        image.set(px as usize, py as usize, TRACK_COLOR);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod gpx_tests {
+    use super::*;
+    use crate::fixtures::{parallel_wall, parallel_wall_panorama as panorama};
+
+    const GPX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+        <gpx version="1.1" creator="test" xmlns="http://www.topografix.com/GPX/1/1">
+          <trk>
+            <name>Hike</name>
+            <trkseg>
+              <trkpt lat="46.6" lon="7.5"><ele>1000</ele></trkpt>
+              <trkpt lat="46.62" lon="7.5"><ele>1000.5</ele></trkpt>
+            </trkseg>
+            <trkseg>
+              <trkpt lat="46.7" lon="7.5"></trkpt>
+            </trkseg>
+          </trk>
+          <rte>
+            <rtept lat="46.5" lon="7.6"><ele>1200</ele></rtept>
+          </rte>
+        </gpx>"#;
+
+    #[test]
+    fn parse_gpx_reads_track_segments_and_routes() {
+        let track = Track::parse_gpx(GPX).unwrap();
+        let segments = track.segments();
+        assert_eq!(3, segments.len());
+        assert_eq!(2, segments[0].len());
+        assert_eq!(
+            GeoPoint::from_degrees(7.5, 46.62).unwrap(),
+            segments[0][1].position
+        );
+        assert_eq!(Some(1000.5), segments[0][1].elevation);
+        assert_eq!(None, segments[1][0].elevation);
+        assert_eq!(Some(1200.), segments[2][0].elevation);
+
+        assert!(Track::parse_gpx("<gpx><trk>").is_err());
+        assert!(Track::parse_gpx(r#"<gpx><rte><rtept lat="x" lon="7"/></rte></gpx>"#).is_err());
+    }
+
+    #[test]
+    fn with_ground_elevation_fills_missing_elevations() {
+        let track = Track::parse_gpx(GPX)
+            .unwrap()
+            .with_ground_elevation(&ContinuousElevationModel::new(parallel_wall()));
+        assert_eq!(Some(1000.5), track.segments()[0][1].elevation);
+        assert_eq!(Some(1000.), track.segments()[1][0].elevation);
+    }
+
+    #[test]
+    fn visible_pixels_leaves_out_the_track_behind_the_wall() {
+        let panorama = panorama();
+        let point = |lat: f64| TrackPoint {
+            position: GeoPoint::from_degrees(7.5, lat).unwrap(),
+            elevation: Some(1000.),
+        };
+
+        let before = Track::new(vec![vec![point(46.55), point(46.62)]]);
+        let pixels = before.visible_pixels(&panorama);
+        assert!(pixels.len() > 2);
+        assert!(pixels.iter().all(|&(x, _)| x == 100));
+        // farther points are seen higher
+        assert!(pixels.windows(2).all(|p| p[1].1 <= p[0].1));
+
+        let behind = Track::new(vec![vec![point(46.68), point(46.7)]]);
+        assert!(behind.visible_pixels(&panorama).is_empty());
+        let unknown = Track::new(vec![vec![TrackPoint {
+            elevation: None,
+            ..point(46.6)
+        }]]);
+        assert!(unknown.visible_pixels(&panorama).is_empty());
+    }
+
+    #[test]
+    fn draw_paints_dots_at_visible_pixels() {
+        let panorama = panorama();
+        let track = Track::new(vec![vec![TrackPoint {
+            position: GeoPoint::from_degrees(7.5, 46.6).unwrap(),
+            elevation: Some(1000.),
+        }]]);
+        let mut image = Image::new(201, 101);
+        track.draw(&mut image, &panorama);
+
+        let [(x, y)] = track.visible_pixels(&panorama)[..] else {
+            panic!("the point is not visible");
+        };
+        assert_eq!(TRACK_COLOR, image.get(x, y));
+        assert_eq!(TRACK_COLOR, image.get(x + 1, y + 1));
+        assert_eq!([0; 4], image.get(x + 2, y + 2));
+    }
+
+    #[test]
+    fn draw_dashes_the_track_behind_the_wall() {
+        let panorama = panorama();
+        let point = |lon: f64| TrackPoint {
+            position: GeoPoint::from_degrees(lon, 46.68).unwrap(),
+            elevation: Some(1000.),
+        };
+        let track = Track::new(vec![vec![point(7.47), point(7.53)]]);
+        assert!(track.visible_pixels(&panorama).is_empty());
+        let hidden = track.pixels(&panorama);
+        assert!(hidden.len() > 4 * DASH_LENGTH);
+
+        let mut image = Image::new(201, 101);
+        track.draw(&mut image, &panorama);
+        // dashes and gaps alternate along the track, starting with a dash
+        let drawn: Vec<bool> = hidden
+            .iter()
+            .map(|&(x, y, _)| image.get(x, y) == TRACK_COLOR)
+            .collect();
+        assert!(drawn[..DASH_LENGTH].iter().all(|&d| d));
+        assert!(drawn.contains(&false));
+        let (first, ..) = hidden[0];
+        let (last, ..) = hidden[hidden.len() - 1];
+        let y = hidden[0].1;
+        let columns = (first.min(last)..=first.max(last))
+            .filter(|&x| image.get(x, y) == TRACK_COLOR)
+            .count();
+        assert!(columns < first.abs_diff(last));
+    }
+}
@@ -0,0 +1,377 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Seek, Write},
+    path::Path,
+};
+
+use ::image::{
+    codecs::{jpeg::JpegEncoder, png::PngEncoder, webp::WebPEncoder},
+    ExtendedColorType, ImageEncoder,
+};
+use tiff::{encoder::TiffEncoder, tags::Tag, TiffResult};
+
+use crate::panorama::Panorama;
+
+/// Color of the sky in panoramas painted by [`Image::from_panorama`].
+const SKY: [u8; 4] = [135, 206, 235, 255];
+/// Width and height of the tiles of TIFF files, in pixels.
+const TIFF_TILE_SIDE: usize = 256;
+
+/// Format of image files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum ImageFormat {
+    #[default]
+    Png,
+    /// Lossy, without opacity.
+    Jpeg,
+    /// Lossless.
+    WebP,
+    /// Made of tiles of 256×256 pixels, which viewers of large images load
+    /// separately.
+    Tiff,
+}
+
+impl ImageFormat {
+    /// Format of the files with the extension of `path`, if known.
+    pub fn from_path(path: impl AsRef<Path>) -> Option<ImageFormat> {
+        let extension = path.as_ref().extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "png" => Some(ImageFormat::Png),
+            "jpg" | "jpeg" => Some(ImageFormat::Jpeg),
+            "webp" => Some(ImageFormat::WebP),
+            "tif" | "tiff" => Some(ImageFormat::Tiff),
+            _ => None,
+        }
+    }
+}
+
+/// Image of RGBA pixels, stored row by row from the top left corner.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<[u8; 4]>,
+}
+
+impl Image {
+    /// Transparent image of the given dimensions.
+    pub fn new(width: usize, height: usize) -> Image {
+        Image {
+            width,
+            height,
+            pixels: vec![[0; 4]; width * height],
+        }
+    }
+
+    /// Paint the terrain of `panorama` in shades of grey, lighter when
+    /// closer, over a blue sky.
+    pub fn from_panorama(panorama: &Panorama) -> Image {
+        let parameters = panorama.parameters();
+        let mut image = Image::new(parameters.width(), parameters.height());
+
+        for y in 0..image.height {
+            for x in 0..image.width {
+                let distance = panorama.distance_at(x, y);
+                let color = if distance.is_infinite() {
+                    SKY
+                } else {
+                    let t = (distance / parameters.max_distance()).min(1.);
+                    let grey = (255. * (1. - 0.8 * t)).round() as u8;
+                    [grey, grey, grey, 255]
+                };
+                image.set(x, y, color);
+            }
+        }
+
+        image
+    }
+
+    /// Read the image of the file at the given path, in any of the formats
+    /// of [`ImageFormat`].
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Image> {
+        let decoded = ::image::open(path)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+            .to_rgba8();
+        let (width, height) = (decoded.width() as usize, decoded.height() as usize);
+        Ok(Image {
+            width,
+            height,
+            pixels: decoded.pixels().map(|p| p.0).collect(),
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> [u8; 4] {
+        self.pixels[self.index(x, y)]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, rgba: [u8; 4]) {
+        let i = self.index(x, y);
+        self.pixels[i] = rgba;
+    }
+
+    /// Write the image to the PNG file at the given path.
+    pub fn save_png(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_png(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Write the image in the PNG format to `writer`, e.g. to send it over
+    /// the network.
+    pub fn write_png(&self, writer: impl Write) -> io::Result<()> {
+        PngEncoder::new(writer)
+            .write_image(
+                self.pixels.as_flattened(),
+                self.width as u32,
+                self.height as u32,
+                ExtendedColorType::Rgba8,
+            )
+            .map_err(io::Error::other)
+    }
+
+    /// Write the image to the JPEG file at the given path, with the given
+    /// quality in `[1, 100]`. The opacity of the pixels is dropped.
+    pub fn save_jpeg(&self, path: impl AsRef<Path>, quality: u8) -> io::Result<()> {
+        let rgb: Vec<u8> = self
+            .pixels
+            .iter()
+            .flat_map(|p| [p[0], p[1], p[2]])
+            .collect();
+        let mut writer = BufWriter::new(File::create(path)?);
+        JpegEncoder::new_with_quality(&mut writer, quality.clamp(1, 100))
+            .write_image(
+                &rgb,
+                self.width as u32,
+                self.height as u32,
+                ExtendedColorType::Rgb8,
+            )
+            .map_err(io::Error::other)?;
+        writer.flush()
+    }
+
+    /// Write the image to the lossless WebP file at the given path.
+    pub fn save_webp(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        WebPEncoder::new_lossless(&mut writer)
+            .write_image(
+                self.pixels.as_flattened(),
+                self.width as u32,
+                self.height as u32,
+                ExtendedColorType::Rgba8,
+            )
+            .map_err(io::Error::other)?;
+        writer.flush()
+    }
+
+    /// Write the image to the tiled TIFF file at the given path.
+    pub fn save_tiff(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_tiled_tiff(&mut writer)
+            .map_err(io::Error::other)?;
+        writer.flush()
+    }
+
+    /// Write the image to the file at the given path, in the given format,
+    /// with the given quality if it is lossy.
+    #[tracing::instrument(name = "encode", skip_all, fields(?format))]
+    pub fn save(&self, path: impl AsRef<Path>, format: ImageFormat, quality: u8) -> io::Result<()> {
+        match format {
+            ImageFormat::Png => self.save_png(path),
+            ImageFormat::Jpeg => self.save_jpeg(path, quality),
+            ImageFormat::WebP => self.save_webp(path),
+            ImageFormat::Tiff => self.save_tiff(path),
+        }
+    }
+
+    /// Write the image as a TIFF of uncompressed tiles, padded with
+    /// transparent pixels on the east and south edges.
+    fn write_tiled_tiff(&self, writer: impl Write + Seek) -> TiffResult<()> {
+        let mut encoder = TiffEncoder::new(writer)?;
+        let mut dir = encoder.image_directory()?;
+
+        let mut offsets = Vec::new();
+        let mut byte_counts = Vec::new();
+        for tile_y in (0..self.height).step_by(TIFF_TILE_SIDE) {
+            for tile_x in (0..self.width).step_by(TIFF_TILE_SIDE) {
+                let mut tile = vec![[0; 4]; TIFF_TILE_SIDE * TIFF_TILE_SIDE];
+                for y in tile_y..(tile_y + TIFF_TILE_SIDE).min(self.height) {
+                    let x_end = (tile_x + TIFF_TILE_SIDE).min(self.width);
+                    let row = (y - tile_y) * TIFF_TILE_SIDE;
+                    tile[row..row + x_end - tile_x].copy_from_slice(
+                        &self.pixels[self.index(tile_x, y)..=self.index(x_end - 1, y)],
+                    );
+                }
+                offsets.push(dir.write_data(tile.as_flattened())? as u32);
+                byte_counts.push(tile.as_flattened().len() as u32);
+            }
+        }
+
+        dir.write_tag(Tag::ImageWidth, self.width as u32)?;
+        dir.write_tag(Tag::ImageLength, self.height as u32)?;
+        dir.write_tag(Tag::BitsPerSample, &[8_u16; 4][..])?;
+        // no compression, RGB with unassociated alpha
+        dir.write_tag(Tag::Compression, 1_u16)?;
+        dir.write_tag(Tag::PhotometricInterpretation, 2_u16)?;
+        dir.write_tag(Tag::SamplesPerPixel, 4_u16)?;
+        dir.write_tag(Tag::ExtraSamples, 2_u16)?;
+        dir.write_tag(Tag::PlanarConfiguration, 1_u16)?;
+        dir.write_tag(Tag::TileWidth, TIFF_TILE_SIDE as u32)?;
+        dir.write_tag(Tag::TileLength, TIFF_TILE_SIDE as u32)?;
+        dir.write_tag(Tag::TileOffsets, &offsets[..])?;
+        dir.write_tag(Tag::TileByteCounts, &byte_counts[..])?;
+        dir.finish()
+    }
+
+    fn index(&self, x: usize, y: usize) -> usize {
+        assert!(
+            x < self.width && y < self.height,
+            "pixel ({}, {}) outside of image",
+            x,
+            y
+        );
+        y * self.width + x
+    }
+}
+
+#[cfg(test)]
+mod image_tests {
+    use super::*;
+    use crate::fixtures::flat_panorama;
+
+    #[test]
+    fn set_and_get_are_consistent() {
+        let mut image = Image::new(3, 2);
+        assert_eq!([0; 4], image.get(2, 1));
+        image.set(2, 1, [1, 2, 3, 4]);
+        assert_eq!([1, 2, 3, 4], image.get(2, 1));
+        assert_eq!([0; 4], image.get(1, 1));
+    }
+
+    #[test]
+    #[should_panic]
+    fn get_panics_outside_of_image() {
+        Image::new(3, 2).get(3, 0);
+    }
+
+    #[test]
+    fn from_panorama_paints_sky_and_terrain() {
+        let panorama = flat_panorama();
+        let image = Image::from_panorama(&panorama);
+
+        assert_eq!(SKY, image.get(5, 0));
+        let near = image.get(5, 10);
+        let far = image.get(5, 6);
+        assert_eq!(255, near[3]);
+        assert_eq!(near[0], near[2]);
+        assert!(near[0] > far[0]);
+    }
+
+    #[test]
+    fn save_png_writes_readable_file() {
+        let path = std::env::temp_dir()
+            .join("alpano_image_tests")
+            .join("image.png");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let mut image = Image::new(4, 3);
+        image.set(1, 2, [10, 20, 30, 255]);
+        image.save_png(&path).unwrap();
+
+        let png = ::image::open(&path).unwrap().to_rgba8();
+        assert_eq!((4, 3), png.dimensions());
+        assert_eq!([10, 20, 30, 255], png.get_pixel(1, 2).0);
+        assert_eq!([0; 4], png.get_pixel(0, 0).0);
+    }
+
+    #[test]
+    fn write_png_writes_readable_bytes() {
+        let image = gradient(20, 10);
+        let mut png = Vec::new();
+        image.write_png(&mut png).unwrap();
+
+        let decoded = ::image::load_from_memory(&png).unwrap().to_rgba8();
+        assert_eq!((20, 10), decoded.dimensions());
+        assert_eq!(image.get(13, 7), decoded.get_pixel(13, 7).0);
+    }
+
+    /// Image whose pixels all differ, with transparent ones.
+    fn gradient(width: usize, height: usize) -> Image {
+        let mut image = Image::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let alpha = if x % 7 == 0 { 0 } else { 255 };
+                image.set(x, y, [x as u8, y as u8, (x + y) as u8, alpha]);
+            }
+        }
+        image
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join("alpano_image_tests");
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join(name)
+    }
+
+    #[test]
+    fn from_path_uses_extension() {
+        assert_eq!(Some(ImageFormat::Png), ImageFormat::from_path("a/pano.png"));
+        assert_eq!(Some(ImageFormat::Jpeg), ImageFormat::from_path("pano.JPG"));
+        assert_eq!(Some(ImageFormat::Jpeg), ImageFormat::from_path("pano.jpeg"));
+        assert_eq!(Some(ImageFormat::WebP), ImageFormat::from_path("pano.webp"));
+        assert_eq!(Some(ImageFormat::Tiff), ImageFormat::from_path("pano.tif"));
+        assert_eq!(None, ImageFormat::from_path("pano.bmp"));
+        assert_eq!(None, ImageFormat::from_path("pano"));
+    }
+
+    #[test]
+    fn save_webp_and_tiff_are_lossless() {
+        // not a multiple of the tile side, to have partial tiles
+        let image = gradient(300, 20);
+        for (name, format) in [
+            ("image.webp", ImageFormat::WebP),
+            ("image.tif", ImageFormat::Tiff),
+        ] {
+            let path = temp_path(name);
+            image.save(&path, format, 90).unwrap();
+            let read = ::image::open(&path).unwrap().to_rgba8();
+            assert_eq!((300, 20), read.dimensions());
+            for (x, y, p) in read.enumerate_pixels() {
+                assert_eq!(
+                    image.get(x as usize, y as usize),
+                    p.0,
+                    "{} at ({}, {})",
+                    name,
+                    x,
+                    y
+                );
+            }
+            assert_eq!(image, Image::open(&path).unwrap());
+        }
+    }
+
+    #[test]
+    fn save_jpeg_depends_on_quality() {
+        let image = gradient(64, 64);
+        let (low, high) = (temp_path("low.jpg"), temp_path("high.jpg"));
+        image.save(&low, ImageFormat::Jpeg, 10).unwrap();
+        image.save(&high, ImageFormat::Jpeg, 95).unwrap();
+        assert!(std::fs::metadata(&low).unwrap().len() < std::fs::metadata(&high).unwrap().len());
+
+        let read = ::image::open(&high).unwrap().to_rgb8();
+        assert_eq!((64, 64), read.dimensions());
+        let [r, g, b] = read.get_pixel(40, 20).0;
+        assert!(r.abs_diff(40) < 8 && g.abs_diff(20) < 8 && b.abs_diff(60) < 8);
+    }
+}
@@ -0,0 +1,258 @@
+use std::fmt;
+
+/// Interval of consecutive integers, bounds included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Interval1D {
+    included_from: i32,
+    included_to: i32,
+}
+
+impl Interval1D {
+    /// Create the interval `[included_from..included_to]`, which must not be
+    /// empty.
+    pub fn new(included_from: i32, included_to: i32) -> Interval1D {
+        assert!(
+            included_from <= included_to,
+            "empty interval [{}..{}]",
+            included_from,
+            included_to
+        );
+        Interval1D {
+            included_from,
+            included_to,
+        }
+    }
+
+    pub fn included_from(&self) -> i32 {
+        self.included_from
+    }
+
+    pub fn included_to(&self) -> i32 {
+        self.included_to
+    }
+
+    pub fn contains(&self, v: i32) -> bool {
+        (self.included_from..=self.included_to).contains(&v)
+    }
+
+    /// Number of integers in the interval.
+    pub fn size(&self) -> usize {
+        (self.included_to as i64 - self.included_from as i64 + 1) as usize
+    }
+
+    /// Integers in both intervals, if any.
+    pub fn intersection(&self, that: &Interval1D) -> Option<Interval1D> {
+        let from = self.included_from.max(that.included_from);
+        let to = self.included_to.min(that.included_to);
+        (from <= to).then(|| Interval1D::new(from, to))
+    }
+
+    pub fn size_of_intersection_with(&self, that: &Interval1D) -> usize {
+        self.intersection(that).map_or(0, |i| i.size())
+    }
+
+    /// Smallest interval containing both intervals.
+    pub fn bounding_union(&self, that: &Interval1D) -> Interval1D {
+        Interval1D::new(
+            self.included_from.min(that.included_from),
+            self.included_to.max(that.included_to),
+        )
+    }
+
+    /// Whether the union of both intervals is an interval, i.e. whether they
+    /// overlap or are adjacent.
+    pub fn is_unionable_with(&self, that: &Interval1D) -> bool {
+        self.size() + that.size() - self.size_of_intersection_with(that)
+            == self.bounding_union(that).size()
+    }
+
+    /// Union of both intervals, if it is an interval.
+    pub fn union(&self, that: &Interval1D) -> Option<Interval1D> {
+        self.is_unionable_with(that)
+            .then(|| self.bounding_union(that))
+    }
+}
+
+impl fmt::Display for Interval1D {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}..{}]", self.included_from, self.included_to)
+    }
+}
+
+/// Rectangle of integer points, the cartesian product of two intervals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Interval2D {
+    x: Interval1D,
+    y: Interval1D,
+}
+
+impl Interval2D {
+    pub fn new(x: Interval1D, y: Interval1D) -> Interval2D {
+        Interval2D { x, y }
+    }
+
+    pub fn x(&self) -> Interval1D {
+        self.x
+    }
+
+    pub fn y(&self) -> Interval1D {
+        self.y
+    }
+
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        self.x.contains(x) && self.y.contains(y)
+    }
+
+    /// Number of points in the rectangle.
+    pub fn size(&self) -> usize {
+        self.x.size() * self.y.size()
+    }
+
+    /// Points in both rectangles, if any.
+    pub fn intersection(&self, that: &Interval2D) -> Option<Interval2D> {
+        Some(Interval2D::new(
+            self.x.intersection(&that.x)?,
+            self.y.intersection(&that.y)?,
+        ))
+    }
+
+    pub fn size_of_intersection_with(&self, that: &Interval2D) -> usize {
+        self.intersection(that).map_or(0, |i| i.size())
+    }
+
+    /// Smallest rectangle containing both rectangles.
+    pub fn bounding_union(&self, that: &Interval2D) -> Interval2D {
+        Interval2D::new(
+            self.x.bounding_union(&that.x),
+            self.y.bounding_union(&that.y),
+        )
+    }
+
+    /// Whether the union of both rectangles is a rectangle.
+    pub fn is_unionable_with(&self, that: &Interval2D) -> bool {
+        self.size() + that.size() - self.size_of_intersection_with(that)
+            == self.bounding_union(that).size()
+    }
+
+    /// Union of both rectangles, if it is a rectangle.
+    pub fn union(&self, that: &Interval2D) -> Option<Interval2D> {
+        self.is_unionable_with(that)
+            .then(|| self.bounding_union(that))
+    }
+}
+
+impl fmt::Display for Interval2D {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}×{}", self.x, self.y)
+    }
+}
+
+#[cfg(test)]
+mod interval_tests {
+    use super::*;
+    use rand::{rngs::ThreadRng, Rng};
+
+    fn next_interval(rng: &mut ThreadRng) -> Interval1D {
+        let from = rng.gen_range(-100..100);
+        Interval1D::new(from, from + rng.gen_range(0..50))
+    }
+
+    #[test]
+    #[should_panic]
+    fn new_panics_on_empty_interval() {
+        Interval1D::new(1, 0);
+    }
+
+    #[test]
+    fn size_counts_bounds() {
+        assert_eq!(1, Interval1D::new(3, 3).size());
+        assert_eq!(11, Interval1D::new(-5, 5).size());
+        assert_eq!(1 << 32, Interval1D::new(i32::MIN, i32::MAX).size());
+    }
+
+    #[test]
+    fn contains_includes_bounds() {
+        let i = Interval1D::new(-5, 5);
+        assert!(i.contains(-5) && i.contains(0) && i.contains(5));
+        assert!(!i.contains(-6) && !i.contains(6));
+    }
+
+    #[test]
+    fn intersection_is_correct_on_known_intervals() {
+        let i = Interval1D::new(0, 10);
+        assert_eq!(
+            Some(Interval1D::new(5, 10)),
+            i.intersection(&Interval1D::new(5, 20))
+        );
+        assert_eq!(
+            Some(Interval1D::new(10, 10)),
+            i.intersection(&Interval1D::new(10, 20))
+        );
+        assert_eq!(None, i.intersection(&Interval1D::new(11, 20)));
+        assert_eq!(0, i.size_of_intersection_with(&Interval1D::new(-20, -1)));
+        assert_eq!(3, i.size_of_intersection_with(&Interval1D::new(2, 4)));
+    }
+
+    #[test]
+    fn union_is_defined_for_overlapping_or_adjacent_intervals() {
+        let i = Interval1D::new(0, 10);
+        assert_eq!(
+            Some(Interval1D::new(0, 20)),
+            i.union(&Interval1D::new(11, 20))
+        );
+        assert_eq!(
+            Some(Interval1D::new(-5, 10)),
+            i.union(&Interval1D::new(-5, 3))
+        );
+        assert_eq!(None, i.union(&Interval1D::new(12, 20)));
+        assert_eq!(
+            Interval1D::new(0, 20),
+            i.bounding_union(&Interval1D::new(12, 20))
+        );
+    }
+
+    #[test]
+    fn operations_are_symmetric() {
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..500 {
+            let i1 = next_interval(&mut rng);
+            let i2 = next_interval(&mut rng);
+            assert_eq!(i1.intersection(&i2), i2.intersection(&i1));
+            assert_eq!(i1.bounding_union(&i2), i2.bounding_union(&i1));
+            assert_eq!(i1.union(&i2), i2.union(&i1));
+            let r1 = Interval2D::new(i1, i2);
+            let r2 = Interval2D::new(i2, i1);
+            assert_eq!(r1.intersection(&r2), r2.intersection(&r1));
+            assert_eq!(r1.union(&r2), r2.union(&r1));
+        }
+    }
+
+    #[test]
+    fn interval_2d_is_correct_on_known_rectangles() {
+        let r = Interval2D::new(Interval1D::new(0, 9), Interval1D::new(0, 4));
+        assert_eq!(50, r.size());
+        assert!(r.contains(9, 4));
+        assert!(!r.contains(10, 4));
+
+        let right = Interval2D::new(Interval1D::new(10, 19), Interval1D::new(0, 4));
+        assert_eq!(
+            Some(Interval2D::new(
+                Interval1D::new(0, 19),
+                Interval1D::new(0, 4)
+            )),
+            r.union(&right)
+        );
+        let diagonal = Interval2D::new(Interval1D::new(10, 19), Interval1D::new(5, 9));
+        assert_eq!(None, r.union(&diagonal));
+        assert_eq!(None, r.intersection(&diagonal));
+        let overlapping = Interval2D::new(Interval1D::new(5, 14), Interval1D::new(2, 4));
+        assert_eq!(15, r.size_of_intersection_with(&overlapping));
+    }
+
+    #[test]
+    fn display_shows_bounds() {
+        let r = Interval2D::new(Interval1D::new(-1, 2), Interval1D::new(3, 4));
+        assert_eq!("[-1..2]×[3..4]", r.to_string());
+    }
+}
@@ -0,0 +1,220 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Seek, Write},
+    path::Path,
+};
+
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+use crate::{geo::GeoPoint, labels::VisibleSummit, panorama::PanoramaParameters};
+
+/// Write the summits visible in the panorama of `parameters` to `writer` as
+/// a KML document, read by Google Earth and GIS software: a placemark for
+/// the observer, and one for each summit with its elevation, its distance
+/// and azimuth from the observer and the pixel at which it is seen.
+pub fn write_kml(
+    parameters: &PanoramaParameters,
+    summits: &[VisibleSummit],
+    mut writer: impl Write,
+) -> io::Result<()> {
+    let observer = parameters.observer_position();
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(writer, r#"<kml xmlns="http://www.opengis.net/kml/2.2">"#)?;
+    writeln!(writer, "<Document>")?;
+    writeln!(
+        writer,
+        "<name>Summits seen from {}</name>",
+        observer.to_dms_string(0)
+    )?;
+    write_placemark(
+        &mut writer,
+        "Observer",
+        observer,
+        parameters.observer_elevation(),
+        "",
+    )?;
+
+    for visible in summits {
+        let summit = visible.summit;
+        let distance = observer.distance_to(&summit.position);
+        let azimuth = observer.azimuth_to(&summit.position).degrees();
+        let details = format!(
+            "<description>{:.0} m, {:.1} km away at {:.1}°, pixel ({}, {})</description>\n\
+             <ExtendedData>\n\
+             <Data name=\"elevation\"><value>{:.0}</value></Data>\n\
+             <Data name=\"distance\"><value>{:.0}</value></Data>\n\
+             <Data name=\"azimuth\"><value>{:.1}</value></Data>\n\
+             <Data name=\"x\"><value>{}</value></Data>\n\
+             <Data name=\"y\"><value>{}</value></Data>\n\
+             </ExtendedData>\n",
+            summit.elevation,
+            distance / 1000.,
+            azimuth,
+            visible.x,
+            visible.y,
+            summit.elevation,
+            distance,
+            azimuth,
+            visible.x,
+            visible.y,
+        );
+        write_placemark(
+            &mut writer,
+            &summit.name,
+            summit.position,
+            summit.elevation,
+            &details,
+        )?;
+    }
+
+    writeln!(writer, "</Document>")?;
+    writeln!(writer, "</kml>")
+}
+
+/// Write the KML document of [`write_kml`] to `writer` as a KMZ archive,
+/// compressed as the `doc.kml` file of a zip archive.
+pub fn write_kmz(
+    parameters: &PanoramaParameters,
+    summits: &[VisibleSummit],
+    writer: impl Write + Seek,
+) -> io::Result<()> {
+    let mut kml = Vec::new();
+    write_kml(parameters, summits, &mut kml)?;
+    let mut zip = ZipWriter::new(writer);
+    zip.start_file(
+        "doc.kml",
+        SimpleFileOptions::default().compression_method(CompressionMethod::Deflated),
+    )
+    .map_err(io::Error::other)?;
+    zip.write_all(&kml)?;
+    zip.finish().map_err(io::Error::other)?;
+    Ok(())
+}
+
+/// Write the summits visible in the panorama of `parameters` to the file at
+/// the given path, as a KMZ archive if its extension is `kmz` and as a KML
+/// document otherwise.
+pub fn save(
+    parameters: &PanoramaParameters,
+    summits: &[VisibleSummit],
+    path: impl AsRef<Path>,
+) -> io::Result<()> {
+    let path = path.as_ref();
+    let mut writer = BufWriter::new(File::create(path)?);
+    if path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("kmz"))
+    {
+        write_kmz(parameters, summits, &mut writer)?;
+    } else {
+        write_kml(parameters, summits, &mut writer)?;
+    }
+    writer.flush()
+}
+
+/// Write a placemark named `name` at `position` and `elevation`, with the
+/// given KML `details`.
+fn write_placemark(
+    writer: &mut impl Write,
+    name: &str,
+    position: GeoPoint,
+    elevation: f64,
+    details: &str,
+) -> io::Result<()> {
+    write!(
+        writer,
+        "<Placemark>\n<name>{}</name>\n{}<Point>\n\
+         <altitudeMode>absolute</altitudeMode>\n\
+         <coordinates>{:.6},{:.6},{:.0}</coordinates>\n\
+         </Point>\n</Placemark>\n",
+        escape(name),
+        details,
+        position.longitude().to_degrees(),
+        position.latitude().to_degrees(),
+        elevation
+    )
+}
+
+/// `text` with the special characters of XML escaped.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod kml_tests {
+    use std::io::{Cursor, Read};
+
+    use super::*;
+    use crate::{azimuth::Azimuth, labels::Summit};
+
+    fn parameters() -> PanoramaParameters {
+        PanoramaParameters::new(
+            GeoPoint::from_degrees(7.5, 46.5).unwrap(),
+            1000.,
+            Azimuth::NORTH,
+            60_f64.to_radians(),
+            100_000.,
+            600,
+            200,
+        )
+        .unwrap()
+    }
+
+    fn summit() -> Summit {
+        Summit {
+            name: "Dent <Blanche> & co".to_string(),
+            position: GeoPoint::from_degrees(7.5, 46.6).unwrap(),
+            elevation: 3000.,
+        }
+    }
+
+    #[test]
+    fn write_kml_writes_a_placemark_per_summit() {
+        let summit = summit();
+        let visible = [VisibleSummit {
+            summit: &summit,
+            x: 300,
+            y: 80,
+        }];
+        let mut kml = Vec::new();
+        write_kml(&parameters(), &visible, &mut kml).unwrap();
+        let kml = String::from_utf8(kml).unwrap();
+
+        assert!(kml.starts_with("<?xml"));
+        assert_eq!(2, kml.matches("<Placemark>").count());
+        assert!(kml.contains("<coordinates>7.500000,46.500000,1000</coordinates>"));
+        assert!(kml.contains("<name>Dent &lt;Blanche&gt; &amp; co</name>"));
+        assert!(kml.contains("<coordinates>7.500000,46.600000,3000</coordinates>"));
+        assert!(kml.contains("<Data name=\"azimuth\"><value>0.0</value></Data>"));
+        assert!(kml.contains("<Data name=\"x\"><value>300</value></Data>"));
+        assert!(kml.contains("pixel (300, 80)"));
+        assert!(kml.trim_end().ends_with("</kml>"));
+    }
+
+    #[test]
+    fn write_kmz_compresses_the_kml_document() {
+        let summit = summit();
+        let visible = [VisibleSummit {
+            summit: &summit,
+            x: 300,
+            y: 80,
+        }];
+        let mut kml = Vec::new();
+        write_kml(&parameters(), &visible, &mut kml).unwrap();
+        let mut kmz = Cursor::new(Vec::new());
+        write_kmz(&parameters(), &visible, &mut kmz).unwrap();
+
+        let mut archive = zip::ZipArchive::new(kmz).unwrap();
+        assert_eq!(1, archive.len());
+        let mut doc = Vec::new();
+        archive
+            .by_name("doc.kml")
+            .unwrap()
+            .read_to_end(&mut doc)
+            .unwrap();
+        assert_eq!(kml, doc);
+    }
+}
@@ -0,0 +1,294 @@
+use std::collections::BTreeSet;
+
+use crate::{
+    dem::{ContinuousElevationModel, DiscreteElevationModel, ElevationProfile},
+    distance::{EarthModel, Sphere, REFRACTION_COEFFICIENT},
+    geo::GeoPoint,
+    math::first_interval_containing_root,
+    panorama::{ray_to_ground_distance, PanoramaComputer, PanoramaParameters},
+};
+
+/// Step used to search for the terrain hiding a summit, in meters.
+const SEARCH_STEP: f64 = 64.;
+/// Distance before a summit at which the terrain is considered to be the
+/// summit itself rather than hiding it, in meters.
+const VISIBILITY_TOLERANCE: f64 = 200.;
+/// Minimal row of a labelled summit, leaving room for the labels above.
+const MIN_SUMMIT_Y: usize = 170;
+/// Minimal distance of a labelled summit to the left and right edges, and
+/// between two labels, in pixels.
+const LABEL_SPACING: usize = 20;
+/// Rows between the topmost labelled summit and the labels.
+const LABEL_GAP: usize = 22;
+/// Angle between the text of labels and the horizontal, counterclockwise.
+pub const LABEL_ROTATION: f64 = std::f64::consts::FRAC_PI_3;
+
+/// A named summit.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Summit {
+    pub name: String,
+    pub position: GeoPoint,
+    /// Elevation in meters.
+    pub elevation: f64,
+}
+
+/// A summit visible in a panorama, at the given pixel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VisibleSummit<'a> {
+    pub summit: &'a Summit,
+    pub x: usize,
+    pub y: usize,
+}
+
+/// Label of a summit: its text is written from `(x, label_y)`, rotated by
+/// [`LABEL_ROTATION`], and a leader line joins `(x, label_y)` to the summit at
+/// `(x, summit_y)`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Label {
+    pub text: String,
+    pub x: usize,
+    pub label_y: usize,
+    pub summit_y: usize,
+}
+
+/// Determines which summits are visible in panoramas and places their
+/// labels.
+pub struct Labelizer<'a, D: DiscreteElevationModel> {
+    cem: &'a ContinuousElevationModel<D>,
+    earth_model: &'a dyn EarthModel,
+    refraction_coefficient: f64,
+    summits: Vec<Summit>,
+}
+
+impl<'a, D: DiscreteElevationModel> Labelizer<'a, D> {
+    /// Create a labelizer over `cem`, on a spherical earth and bending rays
+    /// with the standard refraction coefficient, like a [`PanoramaComputer`]
+    /// by default.
+    pub fn new(cem: &'a ContinuousElevationModel<D>, summits: Vec<Summit>) -> Labelizer<'a, D> {
+        Labelizer {
+            cem,
+            earth_model: &Sphere::EARTH,
+            refraction_coefficient: REFRACTION_COEFFICIENT,
+            summits,
+        }
+    }
+
+    /// Create a labelizer over the elevation model of `computer`, bending
+    /// rays over its earth model with its refraction coefficient, so that
+    /// summits are placed where it sees them.
+    pub fn for_computer(
+        computer: &'a PanoramaComputer<D>,
+        summits: Vec<Summit>,
+    ) -> Labelizer<'a, D> {
+        Labelizer::new(computer.cem(), summits)
+            .with_earth_model(computer.earth_model())
+            .with_refraction_coefficient(computer.refraction_coefficient())
+    }
+
+    /// Bend rays with refraction coefficient `k` instead, 0 for straight rays.
+    pub fn with_refraction_coefficient(self, k: f64) -> Labelizer<'a, D> {
+        Labelizer {
+            refraction_coefficient: k,
+            ..self
+        }
+    }
+
+    /// Curve rays over `earth_model` instead of the spherical earth.
+    pub fn with_earth_model(self, earth_model: &'a dyn EarthModel) -> Labelizer<'a, D> {
+        Labelizer {
+            earth_model,
+            ..self
+        }
+    }
+
+    pub fn summits(&self) -> &[Summit] {
+        &self.summits
+    }
+
+    /// Summits within the field of view and maximum distance of `parameters`
+    /// which are not hidden by the terrain, with the pixel at which they are
+    /// seen.
+    #[tracing::instrument(name = "label", skip_all, fields(summits = self.summits.len()))]
+    pub fn visible_summits(&self, parameters: &PanoramaParameters) -> Vec<VisibleSummit<'_>> {
+        let observer = parameters.observer_position();
+
+        self.summits
+            .iter()
+            .filter_map(|summit| {
+                let distance = observer.distance_to(&summit.position);
+                if distance > parameters.max_distance() {
+                    return None;
+                }
+                let azimuth = observer.azimuth_to(&summit.position);
+                let radius = self.earth_model.effective_radius(
+                    observer.latitude(),
+                    azimuth.radians(),
+                    self.refraction_coefficient,
+                );
+                let profile = ElevationProfile::new(self.cem, observer, azimuth, distance);
+                // height of the summit above the horizontal ray of the observer
+                let height =
+                    -ray_to_ground_distance(&profile, parameters.observer_elevation(), 0., radius)(
+                        distance,
+                    );
+                let (x, y) = parameters.pixel_for(azimuth, height.atan2(distance)).ok()?;
+
+                let ray = ray_to_ground_distance(
+                    &profile,
+                    parameters.observer_elevation(),
+                    height / distance,
+                    radius,
+                );
+                let hit = first_interval_containing_root(ray, 0., distance, SEARCH_STEP);
+                (hit >= distance - VISIBILITY_TOLERANCE).then(|| VisibleSummit {
+                    summit,
+                    x: x.round() as usize,
+                    y: y.round() as usize,
+                })
+            })
+            .collect()
+    }
+
+    /// Labels of the visible summits, all written on the same row above the
+    /// topmost one.
+    ///
+    /// Summits are labelled from the highest in the image, provided they are
+    /// low enough to leave room for the labels, not too close to the edges,
+    /// and not too close to an already labelled summit.
+    pub fn labels(&self, parameters: &PanoramaParameters) -> Vec<Label> {
+        let mut visible = self.visible_summits(parameters);
+        visible.sort_by(|s1, s2| {
+            s1.y.cmp(&s2.y)
+                .then(s2.summit.elevation.total_cmp(&s1.summit.elevation))
+        });
+
+        let mut taken = BTreeSet::new();
+        let labelled: Vec<&VisibleSummit> = visible
+            .iter()
+            .filter(|s| {
+                let fits = s.y >= MIN_SUMMIT_Y
+                    && s.x >= LABEL_SPACING
+                    && s.x + LABEL_SPACING <= parameters.width()
+                    && taken
+                        .range(s.x.saturating_sub(LABEL_SPACING - 1)..s.x + LABEL_SPACING)
+                        .next()
+                        .is_none();
+                if fits {
+                    taken.insert(s.x);
+                }
+                fits
+            })
+            .collect();
+
+        let label_y = match labelled.first() {
+            Some(topmost) => topmost.y - LABEL_GAP,
+            None => return Vec::new(),
+        };
+        labelled
+            .iter()
+            .map(|s| Label {
+                text: format!("{} ({:.0} m)", s.summit.name, s.summit.elevation),
+                x: s.x,
+                label_y,
+                summit_y: s.y,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod labels_tests {
+    use super::*;
+    use crate::{
+        distance::EARTH_RADIUS,
+        fixtures::{wall, wall_parameters},
+    };
+
+    fn summit(name: &str, longitude: f64, latitude: f64, elevation: f64) -> Summit {
+        Summit {
+            name: name.to_string(),
+            position: GeoPoint::from_degrees(longitude, latitude).unwrap(),
+            elevation,
+        }
+    }
+
+    fn names(visible: &[VisibleSummit]) -> Vec<String> {
+        visible.iter().map(|s| s.summit.name.clone()).collect()
+    }
+
+    #[test]
+    fn visible_summits_excludes_hidden_and_out_of_view_summits() {
+        let cem = ContinuousElevationModel::new(wall());
+        let labelizer = Labelizer::new(
+            &cem,
+            vec![
+                summit("Wall", 8., 46.5, 3000.),
+                summit("Behind", 8.5, 46.5, 1000.),
+                summit("South", 7., 46., 1000.),
+                summit("Far", 8.95, 46.5, 1000.),
+            ],
+        );
+        let visible = labelizer.visible_summits(&wall_parameters());
+        assert_eq!(vec!["Wall"], names(&visible));
+
+        // the top of the wall is seen about 1.1° above the horizon, slightly
+        // north of east as great circles bend
+        assert!((185..195).contains(&visible[0].y));
+        assert!((190..200).contains(&visible[0].x));
+    }
+
+    #[test]
+    fn for_computer_places_summits_where_the_computer_sees_them() {
+        // on an earth of half the radius, the wall drops about 500 m more
+        let computer = PanoramaComputer::new(ContinuousElevationModel::new(wall()))
+            .with_earth_model(Sphere {
+                radius: EARTH_RADIUS / 2.,
+            })
+            .with_refraction_coefficient(0.);
+        let panorama = computer.compute(&wall_parameters());
+        let summits = vec![summit("Wall", 8., 46.5, 3000.)];
+
+        let labelizer = Labelizer::for_computer(&computer, summits.clone());
+        let visible = labelizer.visible_summits(&wall_parameters());
+        let (x, y) = (visible[0].x, visible[0].y);
+        let top = (0..401)
+            .find(|&y| panorama.distance_at(x, y).is_finite())
+            .unwrap();
+        assert!(y.abs_diff(top) <= 1);
+
+        let standard = Labelizer::new(computer.cem(), summits);
+        assert!(standard.visible_summits(&wall_parameters())[0].y + 3 < y);
+    }
+
+    #[test]
+    fn labels_skip_summits_too_close_to_labelled_ones() {
+        let cem = ContinuousElevationModel::new(wall());
+        let labelizer = Labelizer::new(
+            &cem,
+            vec![
+                summit("Wall", 8., 46.5, 3000.),
+                summit("Next", 8., 46.52, 2900.),
+                summit("North", 8., 46.7, 3000.),
+            ],
+        );
+        assert_eq!(3, labelizer.visible_summits(&wall_parameters()).len());
+
+        let labels = labelizer.labels(&wall_parameters());
+        assert_eq!(2, labels.len());
+        assert!(labels
+            .iter()
+            .all(|l| l.label_y == labels[0].summit_y - LABEL_GAP));
+        assert!(labels.iter().any(|l| l.text == "North (3000 m)"));
+        assert!(labels.iter().all(|l| !l.text.starts_with("Next")));
+        assert!(labels[0].x.abs_diff(labels[1].x) >= LABEL_SPACING);
+    }
+
+    #[test]
+    fn labels_is_empty_without_visible_summits() {
+        let cem = ContinuousElevationModel::new(wall());
+        let labelizer = Labelizer::new(&cem, vec![summit("South", 7., 46., 1000.)]);
+        assert!(labelizer.labels(&wall_parameters()).is_empty());
+    }
+}
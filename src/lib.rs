@@ -0,0 +1,83 @@
+//! Computation and rendering of mountain panoramas, after the
+//! [Alpano](https://cs108.epfl.ch/archive/17/archive.html) project.
+//!
+//! Angles are in radians and distances in meters throughout the crate.
+
+/// Angles typed by their unit, radians or degrees.
+pub mod angle;
+/// Atmospheric extinction and sky colors.
+pub mod atmosphere;
+/// Azimuths: canonicalization, conversions, parsing and formatting.
+pub mod azimuth;
+/// Batch rendering of the panoramas seen by several observers.
+#[cfg(all(feature = "serde", not(target_arch = "wasm32")))]
+pub mod batch;
+/// Estimation of the framing of a photograph from known summits.
+pub mod calibration;
+/// Celestial coordinates and sidereal time.
+pub mod celestial;
+/// Charts of the terrain along elevation profiles.
+pub mod chart;
+/// Configuration files of render jobs.
+#[cfg(feature = "serde")]
+pub mod config;
+/// Discrete and continuous digital elevation models.
+pub mod dem;
+/// Distances on the surface of the earth and related geometry.
+pub mod distance;
+/// Errors of the crate.
+pub mod error;
+/// Elevation models and panoramas shared by the tests.
+#[cfg(test)]
+mod fixtures;
+/// Points on the surface of the earth.
+pub mod geo;
+/// GPS tracks and their overlay on panoramas.
+pub mod gpx;
+/// Images of panoramas and their output to files.
+pub mod image;
+/// Discrete intervals of integers, in one and two dimensions.
+pub mod interval;
+/// Export of the summits seen in panoramas to KML and KMZ files.
+pub mod kml;
+/// Visibility and labelling of summits in panoramas.
+pub mod labels;
+/// Depth and normal maps of the terrain seen in panoramas.
+pub mod maps;
+/// Interpolation, angular distance and root finding.
+pub mod math;
+/// Position and phase of the moon.
+pub mod moon;
+/// Bright stars and planets.
+pub mod night_sky;
+/// Marks and text drawn over the images of panoramas.
+pub mod overlay;
+/// Summits of OpenStreetMap from the Overpass API.
+#[cfg(feature = "serde")]
+pub mod overpass;
+/// Composable painters of images from panoramas.
+pub mod painter;
+/// Parameters and computation of panoramas.
+pub mod panorama;
+/// Rendering of the panoramas of configured jobs to images, in two stages:
+/// [`render::compute`] casts the rays of a panorama, which [`render::paint`]
+/// then paints with the output settings of any number of configurations.
+#[cfg(feature = "serde")]
+pub mod render;
+/// Rose diagrams of values binned per azimuth.
+pub mod rose;
+/// Memory held by DEM tiles, panoramas and tile caches.
+pub mod stats;
+/// Position of the sun, daylight and shadows.
+pub mod sun;
+/// Export of images as pyramids of web map tiles.
+pub mod tiles;
+/// Ranking of candidate viewpoints by the summits they see.
+pub mod viewpoints;
+/// Samples of the terrain visible from an observer.
+pub mod viewshed;
+/// JavaScript API of WebAssembly builds.
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use stats::stats;
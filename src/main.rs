@@ -1,13 +1,1173 @@
-mod utils;
-
-use std::f64;
-use utils::{distance, math};
-
-fn main() {
-    println!("{}", distance::to_rad(1000.));
-    println!("{}", distance::to_meter(f64::consts::PI));
-    println!("{}", math::haversin(2.0));
-    println!("{}", math::lerp(0.5, 0.0..=3.0));
-    println!("{}", math::bilerp(0.0, 1.0, 2.0, 3.0, 1.0, 1.0));
-    println!("{}", math::angular_distance(f64::consts::FRAC_PI_2, 0.));
+#[cfg(feature = "serve")]
+mod serve;
+
+use std::{
+    error::Error,
+    fs,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use alpano::{
+    azimuth::{self, AngleUnit, Azimuth, CompassResolution},
+    batch::{self, BatchTiles},
+    calibration::{calibrate, Correspondence},
+    chart::ProfileChart,
+    config::{
+        CameraConfig, DemConfig, DemFormat, HazeConfig, ObserverConfig, OutputConfig, PainterKind,
+        PaletteConfig, PalettePreset, PhotoConfig, RenderConfig, ShadowConfig, SkyConfig,
+    },
+    dem::{CompositeDem, ContinuousElevationModel, ElevationProfile, Interpolation},
+    distance::{self, Length},
+    geo::GeoPoint,
+    gpx::Track,
+    image::{Image, ImageFormat},
+    kml,
+    labels::{Label, Labelizer, Summit},
+    maps, overlay, overpass,
+    panorama::{Checkpoint, Panorama, PanoramaCache, PanoramaParameters, ProjectionKind},
+    sun::{self, CrossingKind},
+    tiles,
+    viewpoints::rank_viewpoints,
+    viewshed::Viewshed,
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use clap::{Parser, Subcommand};
+use indicatif::{ProgressBar, ProgressStyle};
+
+/// Compute and render mountain panoramas from SRTM elevation data.
+#[derive(Parser)]
+#[command(version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+    /// Print the time spent loading tiles, computing, painting, labelling
+    /// and encoding to the standard error.
+    #[cfg(feature = "trace")]
+    #[arg(long, global = true)]
+    trace: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Render the panorama seen from a point to an image file.
+    Render(Box<RenderArgs>),
+    /// Compute the samples of the terrain visible from a point, written as
+    /// an image overlay or an ESRI ASCII grid.
+    Viewshed(ViewshedArgs),
+    /// Chart the terrain seen from a point toward an azimuth, with the sight
+    /// line and the curvature drop, to a PNG or SVG image.
+    Profile(ProfileArgs),
+    /// Rank candidate viewpoints by the number of target summits each sees.
+    Viewpoints(ViewpointsArgs),
+    /// Print when and toward which azimuth the sun rises and sets over the
+    /// terrain horizon of a point on a day, and when a summit is lit.
+    Sun(SunArgs),
+    /// Serve PNG panoramas over HTTP at
+    /// GET /panorama?lat=..&lon=..&elevation=..&azimuth=.., with the optional
+    /// fov, max_distance, width, height, projection and painter parameters.
+    #[cfg(feature = "serve")]
+    Serve(ServeArgs),
+}
+
+/// Options of the render command, which override those of the configuration
+/// file if one is given.
+#[derive(clap::Args)]
+struct RenderArgs {
+    /// TOML file declaring the render job.
+    #[arg(long)]
+    config: Option<PathBuf>,
+    /// Panorama file written by --save-panorama to paint instead of
+    /// computing a panorama, whose observer and camera are then those of the
+    /// file.
+    #[arg(long)]
+    panorama: Option<PathBuf>,
+    /// CSV or JSON file of observers whose panoramas are all rendered with
+    /// the other options, sharing the loaded tiles, and written to outputs
+    /// suffixed with their names.
+    #[arg(long, conflicts_with_all = ["panorama", "checkpoint"])]
+    observers: Option<PathBuf>,
+    /// Latitude of the observer, in degrees.
+    #[arg(
+        long,
+        allow_negative_numbers = true,
+        required_unless_present_any = ["config", "panorama", "observers"],
+        conflicts_with = "observers"
+    )]
+    lat: Option<f64>,
+    /// Longitude of the observer, in degrees.
+    #[arg(
+        long,
+        allow_negative_numbers = true,
+        required_unless_present_any = ["config", "panorama", "observers"],
+        conflicts_with = "observers"
+    )]
+    lon: Option<f64>,
+    /// Elevation of the observer, in meters [default: that of the ground plus
+    /// the eye height].
+    #[arg(long)]
+    elevation: Option<f64>,
+    /// Height of the eyes of the observer above the ground, in meters, when
+    /// its elevation is not given [default: 2].
+    #[arg(long)]
+    eye_height: Option<f64>,
+    /// Azimuth of the center of the panorama, e.g. 135, 132.5°, SE or N45°E.
+    #[arg(long, required_unless_present_any = ["config", "panorama"])]
+    azimuth: Option<Azimuth>,
+    /// Horizontal field of view, in degrees [default: 60].
+    #[arg(long)]
+    fov: Option<f64>,
+    /// Maximum distance of the visible terrain, e.g. 100km or 60mi
+    /// [default: 100km].
+    #[arg(long)]
+    max_distance: Option<Length>,
+    /// Width of the image, in pixels [default: 2500].
+    #[arg(long)]
+    width: Option<usize>,
+    /// Height of the image, in pixels [default: 800].
+    #[arg(long)]
+    height: Option<usize>,
+    /// Projection of the image, equirectangular, cylindrical, rectilinear,
+    /// mercator, fisheye or stereographic [default: equirectangular].
+    #[arg(long, value_parser = parse_projection)]
+    projection: Option<ProjectionKind>,
+    /// Number of columns computed by each task of the parallel render
+    /// [default: chosen by rayon].
+    #[arg(long)]
+    chunk_size: Option<NonZeroUsize>,
+    /// Directory containing the tiles, named like N46E007.hgt or
+    /// N46E007.tif according to their format [default: .].
+    #[arg(long)]
+    dem_dir: Option<PathBuf>,
+    /// Format of the tiles, hgt or geotiff [default: hgt].
+    #[arg(long, value_parser = parse_dem_format)]
+    dem_format: Option<DemFormat>,
+    /// Interpolation between the samples of the tiles, bilinear or bicubic
+    /// [default: bilinear].
+    #[arg(long, value_parser = parse_interpolation)]
+    interpolation: Option<Interpolation>,
+    /// Memory taken by the tiles read in memory, in MiB, reading them again
+    /// when needed [default: all the tiles mapped in memory].
+    #[arg(long)]
+    memory_budget: Option<usize>,
+    /// Memory the render may take, in MiB, failing before computing the
+    /// panorama if its tiles, panorama and image would take more.
+    #[arg(long)]
+    max_memory: Option<usize>,
+    /// Path of the image file to write [default: pano.png].
+    #[arg(long)]
+    out: Option<PathBuf>,
+    /// Format of the image file, png, jpeg, webp or tiff [default: given by
+    /// the extension of its path, or png].
+    #[arg(long, value_parser = parse_format)]
+    format: Option<ImageFormat>,
+    /// GPX file whose tracks and routes are drawn over the image where they
+    /// are seen, skipping the points without elevation.
+    #[arg(long)]
+    gpx: Option<PathBuf>,
+    /// Directory to which the image is also written as web map tiles named
+    /// like {z}/{x}/{y}.png, with their metadata in tiles.json.
+    #[arg(long)]
+    tiles: Option<PathBuf>,
+    /// File to which the distances of the terrain are also written, as a
+    /// PFM image of meters if its extension is pfm, and otherwise as a
+    /// 16-bit PNG image scaled to the maximum distance.
+    #[arg(long)]
+    depth: Option<PathBuf>,
+    /// Image file to which the normals of the terrain are also written, in
+    /// the RGB encoding of normal maps.
+    #[arg(long)]
+    normals: Option<PathBuf>,
+    /// File of the summits to label, an Overpass API response in JSON for
+    /// natural=peak nodes [default: fetched from the Overpass API in builds
+    /// with the overpass feature].
+    #[arg(long)]
+    summits: Option<PathBuf>,
+    /// KML file to which the summits seen in the panorama are also written,
+    /// compressed if its extension is kmz.
+    #[arg(long)]
+    kml: Option<PathBuf>,
+    /// File to which the computed panorama is also written, to paint it
+    /// again with --panorama.
+    #[arg(long)]
+    save_panorama: Option<PathBuf>,
+    /// Directory of computed panoramas, from which a panorama computed
+    /// before with the same observer, camera and tiles is read instead of
+    /// computing it again, and to which it is written otherwise.
+    #[arg(long)]
+    cache: Option<PathBuf>,
+    /// Give bit-identical outputs for the same options and tiles, caching
+    /// panoramas at full precision and labelling only the summits of
+    /// --summits.
+    #[arg(long)]
+    deterministic: bool,
+    /// File to which the computed columns are saved periodically, so that
+    /// an interrupted render can continue with --resume.
+    #[arg(long)]
+    checkpoint: Option<PathBuf>,
+    /// Seconds between saves of the checkpoint [default: 60].
+    #[arg(long, requires = "checkpoint")]
+    checkpoint_interval: Option<u64>,
+    /// Continue the render interrupted at the point saved by --checkpoint,
+    /// which must be of the same panorama.
+    #[arg(long, requires = "checkpoint")]
+    resume: bool,
+    /// Quality of JPEG images, from 1 to 100 [default: 90].
+    #[arg(long, value_parser = clap::value_parser!(u8).range(1..=100))]
+    quality: Option<u8>,
+    /// Painting of the terrain, grey, classic, hillshade or palette
+    /// [default: grey].
+    #[arg(long, value_parser = parse_painter)]
+    painter: Option<PainterKind>,
+    /// Colors of the palette painter, colorblind or contrast, instead of
+    /// those of the configuration file [default: colorblind].
+    #[arg(long, value_parser = parse_palette)]
+    palette: Option<PalettePreset>,
+    /// Instant of the render, like 2024-06-21T19:30:00+02:00, placing the
+    /// sun lighting the hillshade painter at its actual position and
+    /// coloring the sky and haze for its elevation.
+    #[arg(long, value_parser = parse_time)]
+    time: Option<DateTime<Utc>>,
+    /// Azimuth of the sun lighting the hillshade painter, unless a time is
+    /// given [default: NW].
+    #[arg(long)]
+    sun_azimuth: Option<Azimuth>,
+    /// Altitude of the sun lighting the hillshade painter, in degrees, unless
+    /// a time is given [default: 45].
+    #[arg(long, allow_negative_numbers = true)]
+    sun_altitude: Option<f64>,
+    /// Paint a sky gradient behind the terrain of the classic and hillshade
+    /// painters, instead of leaving it transparent.
+    #[arg(long)]
+    sky: bool,
+    /// Darken the terrain of the classic and hillshade painters in the
+    /// shadow of the terrain, for the sun of the given time or angles.
+    #[arg(long)]
+    shadows: bool,
+    /// Veil the distant terrain of the classic and hillshade painters with
+    /// haze of the given visibility, e.g. 80km.
+    #[arg(long)]
+    visibility: Option<Length>,
+    /// Draw a compass ribbon along the bottom of the image.
+    #[arg(long)]
+    compass: bool,
+    /// Mark the sunrise and sunset over the terrain horizon on the day of
+    /// the given time, on the horizon and with their time on the compass
+    /// ribbon.
+    #[arg(long)]
+    sunrise: bool,
+    /// Draw the daily path of the sun on the day of the given time, with
+    /// its position at every whole hour.
+    #[arg(long)]
+    sun_path: bool,
+    /// Draw the moon in the sky at the given time, at its position and with
+    /// its apparent size and phase.
+    #[arg(long)]
+    moon: bool,
+    /// Draw the bright stars and planets in the night sky at the given time.
+    #[arg(long)]
+    stars: bool,
+    /// Label the summits seen in the panorama with their names and
+    /// elevations, among those of --summits or else fetched from the
+    /// Overpass API.
+    #[arg(long)]
+    labels: bool,
+}
+
+/// Options of the viewshed command.
+#[derive(clap::Args)]
+struct ViewshedArgs {
+    /// Latitude of the observer, in degrees.
+    #[arg(long, allow_negative_numbers = true)]
+    lat: f64,
+    /// Longitude of the observer, in degrees.
+    #[arg(long, allow_negative_numbers = true)]
+    lon: f64,
+    /// Elevation of the observer, in meters [default: that of the ground plus
+    /// the eye height].
+    #[arg(long)]
+    elevation: Option<f64>,
+    /// Height of the eyes of the observer above the ground, in meters, when
+    /// its elevation is not given.
+    #[arg(long, default_value_t = 2.)]
+    eye_height: f64,
+    /// Height of the targets above the terrain, in meters, e.g. that of a
+    /// receiving antenna.
+    #[arg(long, default_value_t = 0.)]
+    target_height: f64,
+    /// Distance up to which the terrain is analysed.
+    #[arg(long, default_value = "20km")]
+    max_distance: Length,
+    /// Directory containing the SRTM tiles, named like N46E007.hgt.
+    #[arg(long, default_value = ".")]
+    dem_dir: PathBuf,
+    /// Interpolation between the samples of the tiles, bilinear or bicubic.
+    #[arg(long, value_parser = parse_interpolation, default_value = "bilinear")]
+    interpolation: Interpolation,
+    /// File to write, an ESRI ASCII grid of the visibility of every sample
+    /// if its extension is asc, and otherwise an image of the visible
+    /// samples, north up.
+    #[arg(long, default_value = "viewshed.png")]
+    out: PathBuf,
+}
+
+/// Options of the profile command.
+#[derive(clap::Args)]
+struct ProfileArgs {
+    /// Latitude of the observer, in degrees.
+    #[arg(long, allow_negative_numbers = true)]
+    lat: f64,
+    /// Longitude of the observer, in degrees.
+    #[arg(long, allow_negative_numbers = true)]
+    lon: f64,
+    /// Elevation of the observer, in meters [default: that of the ground plus
+    /// the eye height].
+    #[arg(long)]
+    elevation: Option<f64>,
+    /// Height of the eyes of the observer above the ground, in meters, when
+    /// its elevation is not given.
+    #[arg(long, default_value_t = 2.)]
+    eye_height: f64,
+    /// Azimuth of the profile, e.g. 135, 132.5°, SE or N45°E.
+    #[arg(long)]
+    azimuth: Azimuth,
+    /// Length of the profile.
+    #[arg(long, default_value = "50km")]
+    max_distance: Length,
+    /// Directory containing the SRTM tiles, named like N46E007.hgt.
+    #[arg(long, default_value = ".")]
+    dem_dir: PathBuf,
+    /// Interpolation between the samples of the tiles, bilinear or bicubic.
+    #[arg(long, value_parser = parse_interpolation, default_value = "bilinear")]
+    interpolation: Interpolation,
+    /// Width of the chart, in pixels.
+    #[arg(long, default_value_t = 1000)]
+    width: usize,
+    /// Height of the chart, in pixels.
+    #[arg(long, default_value_t = 400)]
+    height: usize,
+    /// File to write, an SVG image if its extension is svg, and otherwise
+    /// an image in the format of its extension.
+    #[arg(long, default_value = "profile.png")]
+    out: PathBuf,
+}
+
+/// Options of the viewpoints command.
+#[derive(clap::Args)]
+struct ViewpointsArgs {
+    /// CSV or JSON file of the candidate viewpoints, named, like the
+    /// observers of render --observers, of which only the positions are
+    /// used.
+    #[arg(long)]
+    candidates: PathBuf,
+    /// File of the target summits, an Overpass API response in JSON.
+    #[arg(long)]
+    summits: PathBuf,
+    /// Height of the eyes of the observer above the ground, in meters.
+    #[arg(long, default_value_t = 2.)]
+    eye_height: f64,
+    /// Distance up to which the summits are seen.
+    #[arg(long, default_value = "20km")]
+    max_distance: Length,
+    /// Directory containing the SRTM tiles, named like N46E007.hgt.
+    #[arg(long, default_value = ".")]
+    dem_dir: PathBuf,
+    /// Interpolation between the samples of the tiles, bilinear or bicubic.
+    #[arg(long, value_parser = parse_interpolation, default_value = "bilinear")]
+    interpolation: Interpolation,
+}
+
+/// Options of the sun command.
+#[derive(clap::Args)]
+struct SunArgs {
+    /// Latitude of the observer, in degrees.
+    #[arg(long, allow_negative_numbers = true)]
+    lat: f64,
+    /// Longitude of the observer, in degrees.
+    #[arg(long, allow_negative_numbers = true)]
+    lon: f64,
+    /// Elevation of the observer, in meters [default: that of the ground plus
+    /// the eye height].
+    #[arg(long)]
+    elevation: Option<f64>,
+    /// Height of the eyes of the observer above the ground, in meters, when
+    /// its elevation is not given.
+    #[arg(long, default_value_t = 2.)]
+    eye_height: f64,
+    /// Day of the sunrise and sunset, like 2024-06-21, from midnight to
+    /// midnight of the mean solar time of the observer.
+    #[arg(long, value_parser = parse_date)]
+    date: NaiveDate,
+    /// Latitude of a summit whose periods in the sunlight are also printed,
+    /// in degrees.
+    #[arg(long, allow_negative_numbers = true, requires = "summit_lon")]
+    summit_lat: Option<f64>,
+    /// Longitude of the summit, in degrees.
+    #[arg(long, allow_negative_numbers = true, requires = "summit_lat")]
+    summit_lon: Option<f64>,
+    /// Distance up to which the terrain is analysed.
+    #[arg(long, default_value = "100km")]
+    max_distance: Length,
+    /// Directory containing the SRTM tiles, named like N46E007.hgt.
+    #[arg(long, default_value = ".")]
+    dem_dir: PathBuf,
+    /// Interpolation between the samples of the tiles, bilinear or bicubic.
+    #[arg(long, value_parser = parse_interpolation, default_value = "bilinear")]
+    interpolation: Interpolation,
+}
+
+/// Options of the serve command.
+#[cfg(feature = "serve")]
+#[derive(clap::Args)]
+struct ServeArgs {
+    /// Address to listen on.
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    addr: std::net::SocketAddr,
+    /// Directory containing the SRTM tiles, named like N46E007.hgt.
+    #[arg(long, default_value = ".")]
+    dem_dir: PathBuf,
+    /// Largest number of pixels of rendered images.
+    #[arg(long, default_value_t = 4_000_000)]
+    max_pixels: usize,
+    /// Largest maximum distance of rendered panoramas.
+    #[arg(long, default_value = "200km")]
+    max_distance: Length,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let cli = Cli::parse();
+    #[cfg(feature = "trace")]
+    if cli.trace {
+        // the busy and idle times of every span when it closes
+        tracing_subscriber::fmt()
+            .with_span_events(tracing_subscriber::fmt::format::FmtSpan::CLOSE)
+            .with_writer(std::io::stderr)
+            .init();
+    }
+    match cli.command {
+        Command::Render(args) => {
+            let saved = match &args.panorama {
+                Some(path) => Some(
+                    Panorama::load(path)
+                        .map_err(|e| format!("cannot read {}: {}", path.display(), e))?,
+                ),
+                None => None,
+            };
+            let checkpoint = args.checkpoint.as_ref().map(|path| {
+                let checkpoint = Checkpoint::new(path);
+                match args.checkpoint_interval {
+                    Some(seconds) => checkpoint.with_interval(Duration::from_secs(seconds)),
+                    None => checkpoint,
+                }
+            });
+            let config = render_config(&args, saved.as_ref())?;
+            if let Some(path) = &args.observers {
+                return render_batch(&config, path);
+            }
+            let (panorama, dem) = match saved {
+                Some(panorama) => (panorama, None),
+                None => compute(&config, checkpoint.as_ref(), args.resume, None)?,
+            };
+            render(&config, panorama, dem, None)
+        }
+        Command::Viewshed(args) => viewshed(&args),
+        Command::Profile(args) => profile(&args),
+        Command::Viewpoints(args) => viewpoints(&args),
+        Command::Sun(args) => sun(&args),
+        #[cfg(feature = "serve")]
+        Command::Serve(args) => serve::serve(
+            args.addr,
+            serve::ServeOptions {
+                dem_dir: args.dem_dir,
+                max_pixels: args.max_pixels,
+                max_distance: args.max_distance,
+            },
+        ),
+    }
+}
+
+/// Configuration of the file given in `args`, if any, or of the `saved`
+/// panorama, with the options of `args` applied over it.
+fn render_config(
+    args: &RenderArgs,
+    saved: Option<&Panorama>,
+) -> Result<RenderConfig, Box<dyn Error>> {
+    let mut config = match (&args.config, saved) {
+        (Some(path), _) => RenderConfig::load(path)
+            .map_err(|e| format!("cannot read {}: {}", path.display(), e))?,
+        (None, Some(panorama)) => RenderConfig::from_parameters(panorama.parameters()),
+        // clap requires the observer, unless given by the observers of a
+        // batch, and the azimuth without configuration file
+        (None, None) => RenderConfig {
+            observer: ObserverConfig::new(
+                args.lat.unwrap_or_default(),
+                args.lon.unwrap_or_default(),
+            ),
+            camera: CameraConfig::new(args.azimuth.unwrap()),
+            dem: DemConfig::default(),
+            output: OutputConfig::default(),
+        },
+    };
+
+    let observer = &mut config.observer;
+    observer.latitude = args.lat.unwrap_or(observer.latitude);
+    observer.longitude = args.lon.unwrap_or(observer.longitude);
+    observer.elevation = args.elevation.or(observer.elevation);
+    observer.eye_height = args.eye_height.unwrap_or(observer.eye_height);
+    let camera = &mut config.camera;
+    camera.azimuth = args.azimuth.unwrap_or(camera.azimuth);
+    camera.fov = args.fov.unwrap_or(camera.fov);
+    camera.max_distance = args.max_distance.unwrap_or(camera.max_distance);
+    camera.width = args.width.unwrap_or(camera.width);
+    camera.height = args.height.unwrap_or(camera.height);
+    camera.projection = args.projection.unwrap_or(camera.projection);
+    camera.chunk_size = args.chunk_size.or(camera.chunk_size);
+    camera.max_memory = args.max_memory.or(camera.max_memory);
+    if let Some(dir) = &args.dem_dir {
+        config.dem.dir = dir.clone();
+    }
+    config.dem.format = args.dem_format.unwrap_or(config.dem.format);
+    config.dem.interpolation = args.interpolation.unwrap_or(config.dem.interpolation);
+    config.dem.memory_budget = args.memory_budget.or(config.dem.memory_budget);
+    if let Some(path) = &args.out {
+        config.output.path = path.clone();
+    }
+    config.output.format = args.format.or(config.output.format);
+    config.output.quality = args.quality.unwrap_or(config.output.quality);
+    if let Some(path) = &args.gpx {
+        config.output.gpx = Some(path.clone());
+    }
+    if let Some(dir) = &args.tiles {
+        config.output.tiles = Some(dir.clone());
+    }
+    if let Some(path) = &args.depth {
+        config.output.depth = Some(path.clone());
+    }
+    if let Some(path) = &args.normals {
+        config.output.normals = Some(path.clone());
+    }
+    if let Some(path) = &args.summits {
+        config.output.summits = Some(path.clone());
+    }
+    if let Some(path) = &args.kml {
+        config.output.kml = Some(path.clone());
+    }
+    if let Some(path) = &args.save_panorama {
+        config.output.panorama = Some(path.clone());
+    }
+    if let Some(dir) = &args.cache {
+        config.output.cache = Some(dir.clone());
+    }
+    config.output.deterministic |= args.deterministic;
+    config.output.painter = args.painter.unwrap_or(config.output.painter);
+    if let Some(preset) = args.palette {
+        config.output.palette = PaletteConfig {
+            preset,
+            colors: None,
+        };
+    }
+    config.output.time = args.time.or(config.output.time);
+    config.output.sun_azimuth = args.sun_azimuth.unwrap_or(config.output.sun_azimuth);
+    config.output.sun_altitude = args.sun_altitude.unwrap_or(config.output.sun_altitude);
+    if args.sky && config.output.sky.is_none() {
+        config.output.sky = Some(SkyConfig::default());
+    }
+    if args.shadows && config.output.shadows.is_none() {
+        config.output.shadows = Some(ShadowConfig::default());
+    }
+    if let Some(visibility) = args.visibility {
+        let haze = config.output.haze.get_or_insert_with(HazeConfig::default);
+        haze.visibility = visibility;
+    }
+    let overlays = &mut config.output.overlays;
+    overlays.compass |= args.compass;
+    overlays.sunrise |= args.sunrise;
+    overlays.sun_path |= args.sun_path;
+    overlays.moon |= args.moon;
+    overlays.stars |= args.stars;
+    overlays.labels |= args.labels;
+
+    config
+        .validate()
+        .map_err(|e| format!("invalid configuration: {}", e))?;
+    for warning in config.warnings() {
+        eprintln!("warning: {}", warning);
+    }
+    Ok(config)
+}
+
+/// Paint `panorama` and write the outputs of `config`, labelling its summits
+/// over `dem` if its panorama was computed over it, or else over the DEM of
+/// `config`, made of the `tiles` of a batch if any.
+fn render(
+    config: &RenderConfig,
+    panorama: Panorama,
+    dem: Option<SharedDem>,
+    tiles: Option<&BatchTiles>,
+) -> Result<(), Box<dyn Error>> {
+    let mut image = alpano::render::paint(&panorama, config);
+    if let Some(path) = &config.output.gpx {
+        Track::load_gpx(path)
+            .map_err(|e| format!("cannot read {}: {}", path.display(), e))?
+            .draw(&mut image, &panorama);
+    }
+    let parameters = panorama.parameters();
+    // summits are placed where the computer of the panorama sees them
+    let computer = if config.output.overlays.labels || config.output.kml.is_some() {
+        let dem = match dem {
+            Some(dem) => dem,
+            None => load_dem(config, tiles)?,
+        };
+        let cem = ContinuousElevationModel::new(dem).with_interpolation(config.dem.interpolation);
+        Some(alpano::render::computer(config, cem))
+    } else {
+        None
+    };
+    let labelizer = match &computer {
+        Some(computer) => Some(Labelizer::for_computer(
+            computer,
+            summits(config, parameters)?,
+        )),
+        None => None,
+    };
+    let labels = match (config.output.overlays.labels, &labelizer) {
+        (true, Some(labelizer)) => labelizer.labels(parameters),
+        _ => Vec::new(),
+    };
+    overlay::labels(&mut image, &labels);
+
+    image.save(
+        &config.output.path,
+        config.output.format(),
+        config.output.quality,
+    )?;
+
+    println!("panorama written to {}", config.output.path.display());
+    if let Some(dir) = &config.output.tiles {
+        let pyramid = tiles::export_tiles(&image, dir)?;
+        println!(
+            "tiles of zoom 0 to {} written to {}",
+            pyramid.max_zoom(),
+            dir.display()
+        );
+    }
+    if let Some(path) = &config.output.depth {
+        maps::save_depth(&panorama, path)?;
+        println!("depth map written to {}", path.display());
+    }
+    if let Some(path) = &config.output.normals {
+        maps::normal_map(&panorama).save(
+            path,
+            ImageFormat::from_path(path).unwrap_or_default(),
+            config.output.quality,
+        )?;
+        println!("normal map written to {}", path.display());
+    }
+    if let (Some(path), Some(labelizer)) = (&config.output.kml, &labelizer) {
+        let visible = labelizer.visible_summits(parameters);
+        kml::save(parameters, &visible, path)?;
+        println!(
+            "{} visible summits written to {}",
+            visible.len(),
+            path.display()
+        );
+    }
+    if let Some(path) = &config.output.panorama {
+        panorama.save(path)?;
+        println!("panorama data written to {}", path.display());
+    }
+    if let Some(photo) = &config.output.photo {
+        composite(photo, &panorama, &labels, config.output.quality)?;
+        println!("photograph written to {}", photo.out.display());
+    }
+    Ok(())
+}
+
+/// Draw the skyline of `panorama` and its `labels` over the photograph of
+/// `photo`, calibrated by its points, and write it with the given quality if
+/// it is lossy.
+fn composite(
+    photo: &PhotoConfig,
+    panorama: &Panorama,
+    labels: &[Label],
+    quality: u8,
+) -> Result<(), Box<dyn Error>> {
+    let mut image = Image::open(&photo.path)
+        .map_err(|e| format!("cannot read {}: {}", photo.path.display(), e))?;
+    let points: Vec<Correspondence> = photo.points.iter().map(|p| p.correspondence()).collect();
+    let calibration = calibrate(&points, image.width() as u32, image.height() as u32)
+        .map_err(|e| format!("cannot calibrate {}: {}", photo.path.display(), e))?;
+    overlay::composite(&mut image, &calibration, panorama, labels, photo.opacity);
+    image.save(
+        &photo.out,
+        ImageFormat::from_path(&photo.out).unwrap_or_default(),
+        quality,
+    )?;
+    Ok(())
+}
+
+/// Summits to label in the panorama of `parameters`, read from the summits
+/// file of `config` or else fetched from the Overpass API, and cached, unless
+/// the render is deterministic.
+fn summits(
+    config: &RenderConfig,
+    parameters: &PanoramaParameters,
+) -> Result<Vec<Summit>, Box<dyn Error>> {
+    if let Some(path) = &config.output.summits {
+        let json = fs::read_to_string(path)
+            .map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+        return Ok(overpass::parse_summits(&json)
+            .map_err(|e| format!("cannot read {}: {}", path.display(), e))?);
+    }
+    if config.output.deterministic {
+        return Err("no summits to label: deterministic renders take them from --summits".into());
+    }
+    #[cfg(feature = "overpass")]
+    {
+        let cache = overpass::OverpassCache::new(std::env::temp_dir().join("alpano-overpass"));
+        Ok(cache.summits(
+            overpass::BoundingBox::of_panorama(parameters),
+            overpass::fetch,
+        )?)
+    }
+    #[cfg(not(feature = "overpass"))]
+    {
+        let _ = parameters;
+        Err(
+            "no summits to label: give them with --summits, or build with the overpass \
+             feature to fetch them"
+                .into(),
+        )
+    }
+}
+
+/// Render the panoramas of the observers of the file at `path`, each with
+/// the configuration `base` adapted by [`batch::BatchObserver::config`], over tiles
+/// loaded once for all of them, going on with the others when one fails.
+fn render_batch(base: &RenderConfig, path: &Path) -> Result<(), Box<dyn Error>> {
+    let observers = batch::load_observers(path)
+        .map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+    let configs: Vec<RenderConfig> = observers.iter().map(|o| o.config(base)).collect();
+    let tiles = BatchTiles::load(&base.dem, &configs)?;
+    println!(
+        "{} tiles loaded for {} observers",
+        tiles.tile_count(),
+        observers.len()
+    );
+
+    let mut failed = 0;
+    for (i, (observer, config)) in observers.iter().zip(&configs).enumerate() {
+        println!("[{}/{}] {}", i + 1, observers.len(), observer.name);
+        if let Err(e) = compute(config, None, false, Some(&tiles))
+            .and_then(|(panorama, dem)| render(config, panorama, dem, Some(&tiles)))
+        {
+            eprintln!("error: {}: {}", observer.name, e);
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        return Err(format!("{} of {} renders failed", failed, observers.len()).into());
+    }
+    Ok(())
+}
+
+/// DEM shared by the computation of a panorama and the labels of its summits.
+type SharedDem = Arc<CompositeDem>;
+
+/// DEM of the panorama of `config`, made of the `tiles` of a batch if any.
+fn load_dem(
+    config: &RenderConfig,
+    tiles: Option<&BatchTiles>,
+) -> Result<SharedDem, Box<dyn Error>> {
+    Ok(Arc::new(match tiles {
+        Some(tiles) => tiles.dem(config)?,
+        None => alpano::render::load_tiles(config)?,
+    }))
+}
+
+/// Compute the panorama of `config` over its DEM, made of the `tiles` of a
+/// batch if any, showing the progress of the computation, and saving it to
+/// `checkpoint` if any, from which it resumes if `resume`. With the cache
+/// directory of `config`, the panorama is read from it if it was computed
+/// before, and written to it otherwise.
+///
+/// Also gives the DEM, unless the panorama was read from the cache.
+fn compute(
+    config: &RenderConfig,
+    checkpoint: Option<&Checkpoint>,
+    resume: bool,
+    tiles: Option<&BatchTiles>,
+) -> Result<(Panorama, Option<SharedDem>), Box<dyn Error>> {
+    let cache = match &config.output.cache {
+        Some(dir) => {
+            let cache = PanoramaCache::new(dir);
+            let cache = if config.output.deterministic {
+                cache.with_full_precision()
+            } else {
+                cache
+            };
+            Some((cache, alpano::render::cache_key(config)?))
+        }
+        None => None,
+    };
+    if let Some((cache, key)) = &cache {
+        if let Some(panorama) = cache.load(*key)? {
+            println!(
+                "panorama read from the cache {}",
+                cache.path(*key).display()
+            );
+            return Ok((panorama, None));
+        }
+    }
+
+    println!(
+        "rendering the view from {} toward {}",
+        config.observer.position()?.to_dms_string(0),
+        azimuth::format(
+            config.camera.azimuth.radians(),
+            AngleUnit::DegreesMinutesSeconds,
+            0
+        )
+    );
+    let bar = ProgressBar::new(config.camera.width as u64).with_style(
+        ProgressStyle::with_template("{elapsed_precise} [{wide_bar}] {pos}/{len} columns")?,
+    );
+    let sink = bar.clone();
+    let progress = move |done, _| sink.set_position(done as u64);
+    let dem = load_dem(config, tiles)?;
+    let panorama = match checkpoint {
+        Some(checkpoint) => alpano::render::compute_resumable_dem(
+            config,
+            Arc::clone(&dem),
+            checkpoint,
+            resume,
+            progress,
+        )?,
+        None => alpano::render::compute_dem(config, Arc::clone(&dem), progress)?,
+    };
+    bar.finish_and_clear();
+    if let Some((cache, key)) = &cache {
+        cache.save(*key, &panorama)?;
+    }
+    Ok((panorama, Some(dem)))
+}
+
+fn viewshed(args: &ViewshedArgs) -> Result<(), Box<dyn Error>> {
+    let observer = GeoPoint::from_degrees(args.lon, args.lat)?;
+    let max_distance = args.max_distance.meters();
+    let dem = DemConfig {
+        dir: args.dem_dir.clone(),
+        ..DemConfig::default()
+    };
+    let dem = alpano::render::load_tiles_around(&dem, observer, max_distance)?;
+    let cem = ContinuousElevationModel::new(dem).with_interpolation(args.interpolation);
+
+    let elevation = args
+        .elevation
+        .unwrap_or_else(|| cem.elevation_at(&observer) + args.eye_height);
+
+    let viewshed = Viewshed::compute(&cem, observer, elevation, args.target_height, max_distance);
+    viewshed.save(&args.out)?;
+    println!(
+        "{} visible samples written to {}",
+        viewshed.visible_count(),
+        args.out.display()
+    );
+    Ok(())
+}
+
+fn profile(args: &ProfileArgs) -> Result<(), Box<dyn Error>> {
+    if args.width < 2 || args.height < 2 {
+        return Err("the chart must be at least 2 pixels wide and high".into());
+    }
+    let observer = GeoPoint::from_degrees(args.lon, args.lat)?;
+    let max_distance = args.max_distance.meters();
+    let dem = DemConfig {
+        dir: args.dem_dir.clone(),
+        ..DemConfig::default()
+    };
+    let dem = alpano::render::load_tiles_around(&dem, observer, max_distance)?;
+    let cem = ContinuousElevationModel::new(dem).with_interpolation(args.interpolation);
+
+    let elevation = args
+        .elevation
+        .unwrap_or_else(|| cem.elevation_at(&observer) + args.eye_height);
+    let profile = ElevationProfile::new(&cem, observer, args.azimuth, max_distance);
+    let chart = ProfileChart::new(
+        &profile,
+        elevation,
+        max_distance,
+        distance::effective_radius(distance::REFRACTION_COEFFICIENT),
+    );
+    chart.save(&args.out, args.width, args.height)?;
+    println!("profile chart written to {}", args.out.display());
+    Ok(())
+}
+
+/// Print the candidate viewpoints of `args`, from those seeing the most
+/// target summits, with the summits they see.
+fn viewpoints(args: &ViewpointsArgs) -> Result<(), Box<dyn Error>> {
+    let candidates = batch::load_observers(&args.candidates)
+        .map_err(|e| format!("cannot read {}: {}", args.candidates.display(), e))?;
+    let json = fs::read_to_string(&args.summits)
+        .map_err(|e| format!("cannot read {}: {}", args.summits.display(), e))?;
+    let targets = overpass::parse_summits(&json)
+        .map_err(|e| format!("cannot read {}: {}", args.summits.display(), e))?;
+    let positions = candidates
+        .iter()
+        .map(|c| GeoPoint::from_degrees(c.longitude, c.latitude))
+        .collect::<Result<Vec<GeoPoint>, _>>()?;
+
+    let max_distance = args.max_distance.meters();
+    let dem = DemConfig {
+        dir: args.dem_dir.clone(),
+        ..DemConfig::default()
+    };
+    let mut corners = Vec::new();
+    for &position in &positions {
+        for tile in alpano::render::tiles_around(position, max_distance) {
+            if !corners.contains(&tile) {
+                corners.push(tile);
+            }
+        }
+    }
+    let mut tiles = Vec::new();
+    for (south, west) in corners {
+        tiles.push(alpano::render::load_tile(&dem, south, west)?);
+    }
+    let cem = ContinuousElevationModel::new(CompositeDem::new(tiles)?)
+        .with_interpolation(args.interpolation);
+
+    let ranked = rank_viewpoints(&cem, &positions, args.eye_height, &targets, max_distance);
+    for (rank, viewpoint) in ranked.iter().enumerate() {
+        let names: Vec<&str> = viewpoint.visible.iter().map(|s| s.name.as_str()).collect();
+        print!(
+            "{}. {} at {:.0} m sees {} of {} summits",
+            rank + 1,
+            candidates[viewpoint.candidate].name,
+            viewpoint.elevation,
+            names.len(),
+            targets.len()
+        );
+        if names.is_empty() {
+            println!();
+        } else {
+            println!(": {}", names.join(", "));
+        }
+    }
+    Ok(())
+}
+
+/// Width of the panoramas whose terrain horizon the sun command computes,
+/// for a pixel every fifth of a degree.
+const HORIZON_WIDTH: usize = 1800;
+/// Height of the panoramas of the sun command, up to 30° above and below the
+/// horizon.
+const HORIZON_HEIGHT: usize = 301;
+/// Step at which the sun command searches the terrain shadowing the summit,
+/// in meters.
+const SUMMIT_SEARCH_STEP: f64 = 50.;
+
+fn sun(args: &SunArgs) -> Result<(), Box<dyn Error>> {
+    let config = RenderConfig {
+        observer: ObserverConfig {
+            elevation: args.elevation,
+            eye_height: args.eye_height,
+            ..ObserverConfig::new(args.lat, args.lon)
+        },
+        camera: CameraConfig {
+            fov: 360.,
+            max_distance: args.max_distance,
+            width: HORIZON_WIDTH,
+            height: HORIZON_HEIGHT,
+            ..CameraConfig::new(Azimuth::NORTH)
+        },
+        dem: DemConfig {
+            dir: args.dem_dir.clone(),
+            interpolation: args.interpolation,
+            ..DemConfig::default()
+        },
+        output: OutputConfig::default(),
+    };
+    let observer = config.observer.position()?;
+    let dem = Arc::new(alpano::render::load_tiles(&config)?);
+
+    println!(
+        "computing the terrain horizon of {}",
+        observer.to_dms_string(0)
+    );
+    let bar = ProgressBar::new(HORIZON_WIDTH as u64).with_style(ProgressStyle::with_template(
+        "{elapsed_precise} [{wide_bar}] {pos}/{len} columns",
+    )?);
+    let sink = bar.clone();
+    let panorama = alpano::render::compute_dem(&config, dem.clone(), move |done, _| {
+        sink.set_position(done as u64)
+    })?;
+    bar.finish_and_clear();
+
+    // noon of the mean solar time of the observer on the given day
+    let noon = args
+        .date
+        .and_hms_opt(12, 0, 0)
+        .expect("noon is valid")
+        .and_utc()
+        - chrono::Duration::seconds((args.lon * 240.).round() as i64);
+    println!("sun over the terrain horizon on {}, in UTC:", args.date);
+    let crossings = overlay::sun_crossings(&panorama, noon);
+    if crossings.is_empty() {
+        let (latitude, longitude) = (observer.latitude(), observer.longitude());
+        let up = sun::position(noon, latitude, longitude).elevation > 0.;
+        println!("  {}", if up { "up all day" } else { "down all day" });
+    }
+    for crossing in crossings {
+        println!(
+            "  {} {} toward {} ({})",
+            match crossing.kind {
+                CrossingKind::Rise => "rises",
+                CrossingKind::Set => "sets ",
+            },
+            crossing.time.format("%H:%M:%S"),
+            azimuth::format(crossing.azimuth, AngleUnit::Degrees, 1),
+            crossing.azimuth.to_compass_str(CompassResolution::Sixteen)
+        );
+    }
+
+    if let (Some(lat), Some(lon)) = (args.summit_lat, args.summit_lon) {
+        let summit = GeoPoint::from_degrees(lon, lat)?;
+        if observer.distance_to(&summit) > args.max_distance.meters() {
+            return Err("the summit is beyond the maximum distance of the observer".into());
+        }
+        let cem = ContinuousElevationModel::new(dem).with_interpolation(args.interpolation);
+        let elevation = cem.elevation_at(&summit);
+        let max_distance = args.max_distance.meters();
+        let radius = distance::effective_radius(distance::REFRACTION_COEFFICIENT);
+        // below the sea-level horizon of the summit, the earth hides the sun
+        let dip = -(2. * elevation.max(0.) / radius).sqrt();
+        let is_lit = |time| {
+            let position = sun::position(time, summit.latitude(), summit.longitude());
+            if position.elevation < dip {
+                return false;
+            }
+            let profile = ElevationProfile::new(&cem, summit, position.azimuth, max_distance);
+            !sun::is_in_shadow(
+                |d| profile.elevation_at(d),
+                elevation,
+                position.elevation,
+                radius,
+                max_distance,
+                SUMMIT_SEARCH_STEP,
+            )
+        };
+        let (start, end) = sun::solar_day(noon, summit.longitude());
+        println!(
+            "summit at {}, {:.0} m, in the sunlight:",
+            summit.to_dms_string(0),
+            elevation
+        );
+        let periods = sun::lit_periods(start, end, is_lit);
+        if periods.is_empty() {
+            println!("  never");
+        }
+        for (from, to) in periods {
+            println!(
+                "  from {} to {}",
+                from.format("%H:%M:%S"),
+                to.format("%H:%M:%S")
+            );
+        }
+    }
+    Ok(())
+}
+
+fn parse_date(s: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .map_err(|e| format!("invalid date: {}, expected e.g. 2024-06-21 ({})", s, e))
+}
+
+fn parse_time(s: &str) -> Result<DateTime<Utc>, String> {
+    DateTime::parse_from_rfc3339(s)
+        .map(|time| time.with_timezone(&Utc))
+        .map_err(|e| {
+            format!(
+                "invalid time: {}, expected e.g. 2024-06-21T19:30:00+02:00 ({})",
+                s, e
+            )
+        })
+}
+
+fn parse_interpolation(s: &str) -> Result<Interpolation, String> {
+    match s {
+        "bilinear" => Ok(Interpolation::Bilinear),
+        "bicubic" => Ok(Interpolation::Bicubic),
+        _ => Err(format!(
+            "invalid interpolation: {}, expected bilinear or bicubic",
+            s
+        )),
+    }
+}
+
+fn parse_dem_format(s: &str) -> Result<DemFormat, String> {
+    match s {
+        "hgt" => Ok(DemFormat::Hgt),
+        "geotiff" => Ok(DemFormat::GeoTiff),
+        _ => Err(format!(
+            "invalid DEM format: {}, expected hgt or geotiff",
+            s
+        )),
+    }
+}
+
+fn parse_projection(s: &str) -> Result<ProjectionKind, String> {
+    match s {
+        "equirectangular" => Ok(ProjectionKind::Equirectangular),
+        "cylindrical" => Ok(ProjectionKind::Cylindrical),
+        "rectilinear" => Ok(ProjectionKind::Rectilinear),
+        "mercator" => Ok(ProjectionKind::Mercator),
+        "fisheye" => Ok(ProjectionKind::Fisheye),
+        "stereographic" => Ok(ProjectionKind::Stereographic),
+        _ => Err(format!(
+            "invalid projection: {}, expected equirectangular, cylindrical, rectilinear, \
+             mercator, fisheye or stereographic",
+            s
+        )),
+    }
+}
+
+fn parse_format(s: &str) -> Result<ImageFormat, String> {
+    match s {
+        "png" => Ok(ImageFormat::Png),
+        "jpeg" | "jpg" => Ok(ImageFormat::Jpeg),
+        "webp" => Ok(ImageFormat::WebP),
+        "tiff" | "tif" => Ok(ImageFormat::Tiff),
+        _ => Err(format!(
+            "invalid format: {}, expected png, jpeg, webp or tiff",
+            s
+        )),
+    }
+}
+
+fn parse_palette(s: &str) -> Result<PalettePreset, String> {
+    match s {
+        "colorblind" => Ok(PalettePreset::Colorblind),
+        "contrast" => Ok(PalettePreset::Contrast),
+        _ => Err(format!(
+            "invalid palette: {}, expected colorblind or contrast",
+            s
+        )),
+    }
+}
+
+fn parse_painter(s: &str) -> Result<PainterKind, String> {
+    match s {
+        "grey" => Ok(PainterKind::Grey),
+        "classic" => Ok(PainterKind::Classic),
+        "hillshade" => Ok(PainterKind::Hillshade),
+        "palette" => Ok(PainterKind::Palette),
+        _ => Err(format!(
+            "invalid painter: {}, expected grey, classic, hillshade or palette",
+            s
+        )),
+    }
 }
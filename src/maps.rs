@@ -0,0 +1,175 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use ::image::{codecs::png::PngEncoder, ExtendedColorType, ImageEncoder};
+
+use crate::{image::Image, panorama::Panorama};
+
+/// Write the distances of the terrain seen in `panorama` to `writer` as a
+/// 16-bit greyscale PNG image, from 0 at the observer to 65535 at the
+/// maximum distance of the panorama and in the sky.
+pub fn write_depth_png(panorama: &Panorama, writer: impl Write) -> io::Result<()> {
+    let parameters = panorama.parameters();
+    let (width, height) = (parameters.width(), parameters.height());
+    let mut bytes = Vec::with_capacity(2 * width * height);
+    for &distance in panorama.distances() {
+        let t = (distance / parameters.max_distance()).min(1.);
+        bytes.extend_from_slice(&((t * 65535.).round() as u16).to_ne_bytes());
+    }
+    PngEncoder::new(writer)
+        .write_image(&bytes, width as u32, height as u32, ExtendedColorType::L16)
+        .map_err(io::Error::other)
+}
+
+/// Write the distances of the terrain seen in `panorama` to `writer` as a
+/// greyscale PFM image of little-endian floats, in meters, infinite in the
+/// sky. As PFM requires, rows are written from the bottom of the panorama
+/// up.
+pub fn write_depth_pfm(panorama: &Panorama, mut writer: impl Write) -> io::Result<()> {
+    let parameters = panorama.parameters();
+    let (width, height) = (parameters.width(), parameters.height());
+    // a negative scale declares little-endian values
+    write!(writer, "Pf\n{} {}\n-1.0\n", width, height)?;
+    for row in panorama.distances().chunks_exact(width).rev() {
+        let bytes: Vec<u8> = row.iter().flat_map(|&d| (d as f32).to_le_bytes()).collect();
+        writer.write_all(&bytes)?;
+    }
+    Ok(())
+}
+
+/// Write the depth map of `panorama` to the file at the given path, as a PFM
+/// image if its extension is `pfm` and as a 16-bit PNG image otherwise.
+pub fn save_depth(panorama: &Panorama, path: impl AsRef<Path>) -> io::Result<()> {
+    let path = path.as_ref();
+    let mut writer = BufWriter::new(File::create(path)?);
+    if path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("pfm"))
+    {
+        write_depth_pfm(panorama, &mut writer)?;
+    } else {
+        write_depth_png(panorama, &mut writer)?;
+    }
+    writer.flush()
+}
+
+/// Normals of the terrain seen in `panorama`, computed from its slope and
+/// aspect, as an image in the usual encoding of normal maps: the red, green
+/// and blue components map the coordinates of the normal from `[-1, 1]` to
+/// `[0, 255]`, along the axes pointing right, up and toward the observer
+/// across the direction of each pixel. The sky is transparent.
+pub fn normal_map(panorama: &Panorama) -> Image {
+    let parameters = panorama.parameters();
+    let mut image = Image::new(parameters.width(), parameters.height());
+
+    for y in 0..image.height() {
+        for x in 0..image.width() {
+            if panorama.distance_at(x, y).is_infinite() {
+                continue;
+            }
+            let (slope, aspect) = (panorama.slope_at(x, y), panorama.aspect_at(x, y).radians());
+            // east, north and up components, tilted toward the aspect
+            let normal = [
+                slope.sin() * aspect.sin(),
+                slope.sin() * aspect.cos(),
+                slope.cos(),
+            ];
+            let (azimuth, altitude) = parameters.direction_at(x as f64, y as f64);
+            let (sin_a, cos_a) = azimuth.radians().sin_cos();
+            let (sin_h, cos_h) = altitude.sin_cos();
+            let right = [cos_a, -sin_a, 0.];
+            let up = [-sin_a * sin_h, -cos_a * sin_h, cos_h];
+            let backward = [-sin_a * cos_h, -cos_a * cos_h, -sin_h];
+
+            let dot = |axis: [f64; 3]| (0..3).map(|i| normal[i] * axis[i]).sum::<f64>();
+            let byte = |c: f64| ((c.clamp(-1., 1.) + 1.) / 2. * 255.).round() as u8;
+            image.set(
+                x,
+                y,
+                [byte(dot(right)), byte(dot(up)), byte(dot(backward)), 255],
+            );
+        }
+    }
+
+    image
+}
+
+#[cfg(test)]
+mod maps_tests {
+    use super::*;
+    use crate::{
+        azimuth::Azimuth,
+        fixtures::{compute, flat_panorama, terrain},
+        geo::GeoPoint,
+        panorama::PanoramaParameters,
+    };
+
+    #[test]
+    fn depth_png_scales_distances_to_the_maximum_distance() {
+        let panorama = flat_panorama();
+        let mut png = Vec::new();
+        write_depth_png(&panorama, &mut png).unwrap();
+        let depth = ::image::load_from_memory(&png).unwrap().to_luma16();
+
+        assert_eq!((11, 11), depth.dimensions());
+        assert_eq!(65535, depth.get_pixel(5, 0).0[0]);
+        let expected = panorama.distance_at(5, 10) / 50_000. * 65535.;
+        assert_eq!(expected.round() as u16, depth.get_pixel(5, 10).0[0]);
+        assert!(depth.get_pixel(5, 10).0[0] < depth.get_pixel(5, 6).0[0]);
+    }
+
+    #[test]
+    fn depth_pfm_writes_rows_from_the_bottom_up() {
+        let panorama = flat_panorama();
+        let mut pfm = Vec::new();
+        write_depth_pfm(&panorama, &mut pfm).unwrap();
+
+        let header = b"Pf\n11 11\n-1.0\n";
+        assert_eq!(header, &pfm[..header.len()]);
+        assert_eq!(header.len() + 4 * 11 * 11, pfm.len());
+        let value = |x: usize, row: usize| {
+            let i = header.len() + 4 * (row * 11 + x);
+            f32::from_le_bytes(pfm[i..i + 4].try_into().unwrap())
+        };
+        assert_eq!(panorama.distance_at(3, 10) as f32, value(3, 0));
+        assert_eq!(f32::INFINITY, value(3, 10));
+    }
+
+    #[test]
+    fn normal_map_of_flat_terrain_points_up() {
+        let panorama = flat_panorama();
+        let image = normal_map(&panorama);
+
+        assert_eq!([0; 4], image.get(5, 0));
+        let (_, altitude) = panorama.parameters().direction_at(5., 10.);
+        let [r, g, b, a] = image.get(5, 10);
+        assert_eq!((128, 255), (r, a));
+        assert_eq!(((altitude.cos() + 1.) / 2. * 255.).round() as u8, g);
+        assert_eq!(((-altitude.sin() + 1.) / 2. * 255.).round() as u8, b);
+    }
+
+    #[test]
+    fn normal_map_of_terrain_descending_rightward_points_right() {
+        // terrain descending eastward, seen looking north
+        let dem = terrain(|x, _| (1000 - 50 * (x - 60)) as f64);
+        let parameters = PanoramaParameters::new(
+            GeoPoint::from_degrees(7.5, 46.5).unwrap(),
+            2000.,
+            Azimuth::NORTH,
+            10_f64.to_radians(),
+            50_000.,
+            11,
+            11,
+        )
+        .unwrap();
+        let panorama = compute(dem, &parameters);
+        let [r, g, b, _] = normal_map(&panorama).get(5, 10);
+
+        let slope = panorama.slope_at(5, 10);
+        assert_eq!(((slope.sin() + 1.) / 2. * 255.).round() as u8, r);
+        assert!(g > 128 && b > 128);
+    }
+}
@@ -0,0 +1,486 @@
+use std::{
+    f64::consts::{FRAC_PI_2, PI, TAU},
+    ops::RangeInclusive,
+};
+
+use num_traits::Float;
+use thiserror::Error;
+
+use crate::angle::Rad;
+
+pub fn haversin<F: Float>(v: F) -> F {
+    (v / (F::one() + F::one())).sin().powi(2)
+}
+
+pub fn lerp<F: Float>(v: F, range: RangeInclusive<F>) -> F {
+    *range.start() * (F::one() - v) + (*range.end() * v)
+}
+
+pub fn angular_distance(a1: impl Into<Rad>, a2: impl Into<Rad>) -> f64 {
+    let (a1, a2) = (a1.into().0, a2.into().0);
+    let diff = (a2 - a1 + PI) % TAU - PI;
+
+    if diff < -PI {
+        diff + TAU
+    } else {
+        diff
+    }
+}
+
+pub fn bilerp<F: Float>(z00: F, z10: F, z01: F, z11: F, x: F, y: F) -> F {
+    let x_0_1 = lerp(x, z00..=z10);
+    let x_1_2 = lerp(x, z01..=z11);
+    lerp(y, x_0_1..=x_1_2)
+}
+
+/// Bicubic interpolation at `(x, y)`, in `[0, 1]²`, of the 4×4 samples `z`,
+/// where `z[j][i]` is the sample at `(i - 1, j - 1)`, by Catmull-Rom splines.
+///
+/// Unlike [`bilerp`], its result has a continuous gradient across cells.
+pub fn bicubic<F: Float>(z: [[F; 4]; 4], x: F, y: F) -> F {
+    cubic(z.map(|row| cubic(row, x)), y)
+}
+
+/// Catmull-Rom interpolation at `t`, in `[0, 1]`, between `p[1]` and `p[2]`.
+fn cubic<F: Float>(p: [F; 4], t: F) -> F {
+    let c = |v: f64| F::from(v).unwrap();
+    p[1] + c(0.5)
+        * t
+        * (p[2] - p[0]
+            + t * (c(2.) * p[0] - c(5.) * p[1] + c(4.) * p[2] - p[3]
+                + t * (c(3.) * (p[1] - p[2]) + p[3] - p[0])))
+}
+
+/// Lower bound of the first interval `[x, x + dx]`, stepping from `min_x` up
+/// to `max_x`, over which `f` changes sign, or infinity if there is none.
+///
+/// `f` may be any closure, e.g. one capturing an elevation profile.
+#[allow(clippy::redundant_pattern_matching)]
+pub fn first_interval_containing_root(
+    f: impl Fn(f64) -> f64,
+    min_x: f64,
+    max_x: f64,
+    dx: f64,
+) -> f64 {
+    let mut i = min_x;
+
+    while i < max_x {
+        if let Ok(_) = improve_root(&f, i, i + dx, 1e-10) {
+            return i;
+        }
+
+        i += dx;
+    }
+
+    f64::INFINITY
+}
+
+/// Every root of `f` bracketed by an interval `[x, x + dx]`, stepping from
+/// `min_x` up to `max_x`, in increasing order and located to within `eps`.
+///
+/// Roots closer than `dx` to each other may cancel out and be missed.
+pub fn roots_in(
+    f: impl Fn(f64) -> f64,
+    min_x: f64,
+    max_x: f64,
+    dx: f64,
+    eps: f64,
+) -> impl Iterator<Item = f64> {
+    let steps = ((max_x - min_x) / dx).ceil().max(0.) as usize;
+    (0..steps)
+        .map(move |i| min_x + i as f64 * dx)
+        .filter_map(move |x| improve_root(&f, x, x + dx, eps).ok())
+}
+
+/// Errors of root finding.
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum RootError {
+    #[error("no root bracketed by [{0}, {1}]")]
+    NotBracketed(f64, f64),
+}
+
+/// Root of `f` in `[x1, x2]` located by bisection to within `eps`, failing
+/// unless `f` changes sign over the interval.
+pub fn improve_root(
+    f: impl Fn(f64) -> f64,
+    mut x1: f64,
+    mut x2: f64,
+    eps: f64,
+) -> Result<f64, RootError> {
+    if f(x1).signum() == f(x2).signum() || x1 > x2 {
+        return Err(RootError::NotBracketed(x1, x2));
+    }
+
+    while (x2 - x1) > eps {
+        let m = (x1 + x2) / 2.;
+        if f(m).signum() == f(x1).signum() {
+            x1 = m;
+        } else {
+            x2 = m;
+        }
+    }
+
+    Ok(x1)
+}
+
+/// Table of sines precomputed over a full turn, linearly interpolated.
+///
+/// With `n` samples, the absolute error is at most `(2π/n)² / 8`, i.e. below
+/// 3e-7 for the default table of 4096 samples.
+pub struct TrigTable {
+    sines: Vec<f64>,
+    step: f64,
+}
+
+impl TrigTable {
+    pub const DEFAULT_SIZE: usize = 4096;
+
+    pub fn new(size: usize) -> TrigTable {
+        assert!(size >= 4, "a trigonometric table needs at least 4 samples");
+        let step = TAU / size as f64;
+        TrigTable {
+            sines: (0..=size).map(|i| (i as f64 * step).sin()).collect(),
+            step,
+        }
+    }
+
+    pub fn sin(&self, x: f64) -> f64 {
+        let t = x.rem_euclid(TAU) / self.step;
+        let i = (t.floor() as usize).min(self.sines.len() - 2);
+        lerp(t - i as f64, self.sines[i]..=self.sines[i + 1])
+    }
+
+    pub fn cos(&self, x: f64) -> f64 {
+        self.sin(x + FRAC_PI_2)
+    }
+}
+
+#[cfg(feature = "trig-lut")]
+fn trig_table() -> &'static TrigTable {
+    static TABLE: std::sync::OnceLock<TrigTable> = std::sync::OnceLock::new();
+    TABLE.get_or_init(|| TrigTable::new(TrigTable::DEFAULT_SIZE))
+}
+
+/// Sine used by per-pixel angle conversions, i.e. the slopes of the rays
+/// cast for each pixel and the fisheye and stereographic projections: looked
+/// up in a [`TrigTable`] when the `trig-lut` feature is enabled, exact
+/// otherwise.
+pub fn fast_sin(x: f64) -> f64 {
+    #[cfg(feature = "trig-lut")]
+    return trig_table().sin(x);
+    #[cfg(not(feature = "trig-lut"))]
+    return x.sin();
+}
+
+/// Cosine counterpart of [`fast_sin`].
+pub fn fast_cos(x: f64) -> f64 {
+    #[cfg(feature = "trig-lut")]
+    return trig_table().cos(x);
+    #[cfg(not(feature = "trig-lut"))]
+    return x.cos();
+}
+
+/// Tolerance of the tests of results computed with [`fast_sin`] and
+/// [`fast_cos`]: `tolerance` itself, or with the `trig-lut` feature, at least
+/// enough for the error of its table, amplified near the poles of inverse
+/// functions.
+#[cfg(test)]
+pub(crate) fn fast_trig_tolerance(tolerance: f64) -> f64 {
+    if cfg!(feature = "trig-lut") {
+        tolerance.max(1e-4)
+    } else {
+        tolerance
+    }
+}
+
+#[cfg(test)]
+mod math_tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+    use rand::{rngs::ThreadRng, Rng};
+
+    fn next_angle(rng: &mut ThreadRng) -> f64 {
+        let random: f64 = rng.gen_range(-180.0..180.0);
+        random.to_radians()
+    }
+
+    #[test]
+    fn haversin_is_correct_on_random_values() {
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..500 {
+            let a = next_angle(&mut rng);
+            let h = (1. - a.cos()) / 2.;
+            assert_approx_eq!(h, haversin(a), 1e-10);
+        }
+    }
+
+    #[test]
+    fn haversin_and_lerp_work_on_f32() {
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..500 {
+            let a = next_angle(&mut rng);
+            assert_approx_eq!(haversin(a) as f32, haversin(a as f32), 1e-6);
+
+            let v1 = (rng.gen::<f32>() - 0.5) * 1000.;
+            let v2 = (rng.gen::<f32>() - 0.5) * 1000.;
+            let p = rng.gen::<f32>();
+            assert_approx_eq!(
+                lerp(p as f64, v1 as f64..=v2 as f64) as f32,
+                lerp(p, v1..=v2),
+                1e-3
+            );
+            assert_approx_eq!(v1, bilerp(v1, v2, v1, v2, 0., p), 1e-3);
+        }
+    }
+
+    #[test]
+    fn angular_distance_is_correct_on_known_angles() {
+        let data: Vec<f64> = vec![
+            0., 45., 45., 45., 0., -45., 0., 179., 179., 0., 181., -179., 181., 359., 178., 181.,
+            2., -179.,
+        ];
+
+        for i in (0..data.len()).step_by(3) {
+            let a1 = data[i].to_radians();
+            let a2 = data[i + 1].to_radians();
+            let expected = data[i + 2].to_radians();
+            let actual = angular_distance(a1, a2);
+            assert_approx_eq!(expected, actual, 1e-10);
+        }
+    }
+
+    #[test]
+    #[allow(clippy::manual_range_contains)]
+    fn angular_distance_is_in_expected_range() {
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..500 {
+            let a1 = next_angle(&mut rng);
+            let a2 = next_angle(&mut rng);
+            let d = angular_distance(a1, a2);
+            assert!(-PI <= d && d < PI);
+        }
+    }
+
+    #[test]
+    fn angular_distance_is_symmetric() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..500 {
+            let a1 = next_angle(&mut rng);
+            let a2 = next_angle(&mut rng);
+            assert_approx_eq!(
+                0.,
+                angular_distance(a1, a2) + angular_distance(a2, a1),
+                1e-10
+            );
+        }
+    }
+
+    #[test]
+    fn lerp_is_first_value_at_start() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..500 {
+            let v1 = (rng.gen::<f64>() - 0.5) * 1000.;
+            let v2 = (rng.gen::<f64>() - 0.5) * 1000.;
+            assert_approx_eq!(v1, lerp(0., v1..=v2), 1e-10);
+        }
+    }
+
+    #[test]
+    fn lerp_is_first_value_at_middle() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..500 {
+            let v1 = (rng.gen::<f64>() - 0.5) * 1000.;
+            let v2 = (rng.gen::<f64>() - 0.5) * 1000.;
+            assert_approx_eq!((v1 + v2) / 2., lerp(0.5, v1..=v2), 1e-10);
+        }
+    }
+
+    #[test]
+    fn lerp_is_first_value_at_end() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..500 {
+            let v1 = (rng.gen::<f64>() - 0.5) * 1000.;
+            let v2 = (rng.gen::<f64>() - 0.5) * 1000.;
+            assert_approx_eq!(v2, lerp(1.0, v1..=v2), 1e-10);
+        }
+    }
+
+    #[test]
+    fn lerp_is_in_expected_range() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..500 {
+            let v1 = (rng.gen::<f64>() - 0.5) * 1000.;
+            let v2 = (rng.gen::<f64>() - 0.5) * 1000.;
+            let p = rng.gen::<f64>();
+            let v = lerp(p, v1..=v2);
+            assert!(v1.min(v2) <= v && v <= v1.max(v2));
+        }
+    }
+
+    #[test]
+    fn bilerp_is_in_expected_range() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..500 {
+            let v1 = (rng.gen::<f64>() - 0.5) * 1000.;
+            let v2 = (rng.gen::<f64>() - 0.5) * 1000.;
+            let v3 = (rng.gen::<f64>() - 0.5) * 1000.;
+            let v4 = (rng.gen::<f64>() - 0.5) * 1000.;
+            let x = rng.gen::<f64>();
+            let y = rng.gen::<f64>();
+            let v = bilerp(v1, v2, v3, v4, x, y);
+            assert!(v1.min(v2).min(v3).min(v4) <= v && v <= v1.max(v2).max(v3).max(v4));
+        }
+    }
+
+    #[test]
+    fn bilerp_is_correct_in_corners() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..500 {
+            let v1 = rng.gen::<f64>();
+            let v2 = rng.gen::<f64>();
+            let v3 = rng.gen::<f64>();
+            let v4 = rng.gen::<f64>();
+            assert_approx_eq!(v1, bilerp(v1, v2, v3, v4, 0., 0.), 1e-10);
+            assert_approx_eq!(v2, bilerp(v2, v2, v3, v4, 1., 0.), 1e-10);
+            assert_approx_eq!(v3, bilerp(v3, v2, v3, v4, 0., 1.), 1e-10);
+            assert_approx_eq!(v4, bilerp(v4, v2, v3, v4, 1., 1.), 1e-10);
+        }
+    }
+
+    #[test]
+    fn bilerp_is_correct_along_sides() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..500 {
+            let v1 = rng.gen::<f64>();
+            let v2 = rng.gen::<f64>();
+            let v3 = rng.gen::<f64>();
+            let v4 = rng.gen::<f64>();
+            assert_approx_eq!((v1 + v2) / 2., bilerp(v1, v2, v3, v4, 0.5, 0.), 1e-10);
+            assert_approx_eq!((v1 + v3) / 2., bilerp(v1, v2, v3, v4, 0., 0.5), 1e-10);
+            assert_approx_eq!((v3 + v4) / 2., bilerp(v1, v2, v3, v4, 0.5, 1.), 1e-10);
+            assert_approx_eq!((v2 + v4) / 2., bilerp(v1, v2, v3, v4, 1., 0.5), 1e-10);
+        }
+    }
+
+    #[test]
+    fn bicubic_is_correct_on_samples() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..500 {
+            let z: [[f64; 4]; 4] = rng.gen();
+            assert_approx_eq!(z[1][1], bicubic(z, 0., 0.), 1e-10);
+            assert_approx_eq!(z[1][2], bicubic(z, 1., 0.), 1e-10);
+            assert_approx_eq!(z[2][1], bicubic(z, 0., 1.), 1e-10);
+            assert_approx_eq!(z[2][2], bicubic(z, 1., 1.), 1e-10);
+        }
+    }
+
+    #[test]
+    fn bicubic_reproduces_quadratic_surfaces() {
+        let f = |x: f64, y: f64| 3. * x * x - 2. * x * y + y * y - x + 5.;
+        let mut z = [[0.; 4]; 4];
+        for (j, row) in z.iter_mut().enumerate() {
+            for (i, v) in row.iter_mut().enumerate() {
+                *v = f(i as f64 - 1., j as f64 - 1.);
+            }
+        }
+
+        let mut rng = rand::thread_rng();
+        for _ in 0..500 {
+            let x = rng.gen::<f64>();
+            let y = rng.gen::<f64>();
+            assert_approx_eq!(f(x, y), bicubic(z, x, y), 1e-10);
+        }
+    }
+
+    #[test]
+    fn first_interval_containing_root_works_on_sin() {
+        let i1 = first_interval_containing_root(|x| x.sin(), -1., 1., 0.1 + 1e-11);
+        assert_approx_eq!(-0.1, i1, 1e-10);
+
+        let i2 = first_interval_containing_root(|x| x.sin(), 1., 4., 1.);
+        assert_approx_eq!(3., i2, f64::EPSILON);
+    }
+
+    #[test]
+    fn root_finding_accepts_capturing_closures() {
+        let profile = [0., 10., 20., 30., 40.];
+        let ray = |x: f64| 27. - lerp(x / 4., profile[0]..=profile[4]);
+
+        let interval = first_interval_containing_root(ray, 0., 4., 0.5);
+        assert_eq!(2.5, interval);
+        let root = improve_root(ray, interval, interval + 0.5, 1e-10).unwrap();
+        assert_approx_eq!(2.7, root, 1e-9);
+    }
+
+    #[test]
+    fn roots_in_finds_every_root_of_sin() {
+        let roots: Vec<f64> = roots_in(|x| x.sin(), -1., 10., 0.1 + 1e-11, 1e-10).collect();
+        assert_eq!(4, roots.len());
+        for (i, root) in roots.into_iter().enumerate() {
+            assert_approx_eq!(i as f64 * PI, root, 1e-9);
+        }
+    }
+
+    #[test]
+    fn roots_in_is_lazy_and_empty_without_roots() {
+        assert_eq!(0, roots_in(|x| x.sin(), 0.1, 3., 0.1, 1e-10).count());
+        assert_eq!(0, roots_in(|x| x.sin(), 1., -1., 0.1, 1e-10).count());
+
+        let first = roots_in(|x| x.cos(), 0., 1e9, 1., 1e-10).next().unwrap();
+        assert_approx_eq!(PI / 2., first, 1e-9);
+    }
+
+    #[test]
+    #[allow(clippy::assertions_on_constants)]
+    fn improve_root_fails_when_interval_does_not_contains_root() {
+        match improve_root(|x| x.sin(), 1., 2., 1e-10) {
+            Ok(_) => assert!(false),
+            Err(_) => assert!(true),
+        }
+    }
+
+    #[test]
+    fn improve_root_works_on_sin() {
+        let pi = improve_root(|x| x.sin(), 3.1, 3.2, 1e-10).unwrap();
+        assert_approx_eq!(PI, pi, 1e-10);
+
+        let m_pi = improve_root(|x| x.sin(), -4., -3.1, 1e-10).unwrap();
+        assert_approx_eq!(-PI, m_pi, 1e-10);
+    }
+
+    #[test]
+    fn trig_table_is_exact_on_samples() {
+        let table = TrigTable::new(360);
+        for deg in 0..360 {
+            let x = (deg as f64).to_radians();
+            assert_approx_eq!(x.sin(), table.sin(x), 1e-12);
+            assert_approx_eq!(x.cos(), table.cos(x), 1e-12);
+        }
+    }
+
+    #[test]
+    fn trig_table_is_within_error_bound_on_random_angles() {
+        let mut rng = rand::thread_rng();
+        let table = TrigTable::new(TrigTable::DEFAULT_SIZE);
+
+        for _ in 0..500 {
+            let x: f64 = rng.gen_range(-100.0..100.0);
+            assert_approx_eq!(x.sin(), table.sin(x), 3e-7);
+            assert_approx_eq!(x.cos(), table.cos(x), 3e-7);
+        }
+    }
+
+    #[test]
+    fn fast_sin_and_cos_are_accurate() {
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..500 {
+            let x = next_angle(&mut rng);
+            assert_approx_eq!(x.sin(), fast_sin(x), 3e-7);
+            assert_approx_eq!(x.cos(), fast_cos(x), 3e-7);
+        }
+    }
+}
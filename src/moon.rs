@@ -0,0 +1,137 @@
+use std::f64::consts::TAU;
+
+use chrono::{DateTime, Utc};
+
+use super::{
+    azimuth::Azimuth,
+    celestial::{ecliptic_to_equatorial, equatorial_to_horizontal, julian_centuries, obliquity},
+    distance::EARTH_RADIUS,
+    sun,
+};
+
+/// Mean radius of the moon, in meters.
+pub const MOON_RADIUS: f64 = 1737400.0;
+
+/// Position and phase of the moon in the sky of an observer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LunarPosition {
+    pub azimuth: Azimuth,
+    /// Topocentric elevation of the center of the moon above the astronomical
+    /// horizon, in radians (without atmospheric refraction).
+    pub elevation: f64,
+    /// Distance between the centers of the earth and the moon, in meters.
+    pub distance: f64,
+    /// Apparent angular diameter of the disk, in radians.
+    pub angular_diameter: f64,
+    /// Fraction (in `[0, 1]`) of the disk which is lit.
+    pub illuminated_fraction: f64,
+    /// Fraction (in `[0, 1[`) of the synodic month elapsed since new moon,
+    /// 0.5 being the full moon.
+    pub phase: f64,
+}
+
+impl LunarPosition {
+    pub fn is_waxing(&self) -> bool {
+        self.phase < 0.5
+    }
+}
+
+/// Position and phase of the moon at the given instant, seen from the given
+/// latitude and longitude (in radians, longitude positive to the east).
+///
+/// Uses the low-precision formulae of the Astronomical Almanac, accurate to
+/// about 0.3° in position.
+pub fn position(date_time: DateTime<Utc>, lat: f64, lon: f64) -> LunarPosition {
+    let t = julian_centuries(date_time);
+    let term = |a: f64, b: f64| (a + b * t).to_radians();
+
+    let longitude = (218.32 + 481267.881 * t + 6.29 * term(135.0, 477198.87).sin()
+        - 1.27 * term(259.3, -413335.36).sin()
+        + 0.66 * term(235.7, 890534.22).sin()
+        + 0.21 * term(269.9, 954397.74).sin()
+        - 0.19 * term(357.5, 35999.05).sin()
+        - 0.11 * term(186.5, 966404.03).sin())
+    .to_radians();
+    let latitude = (5.13 * term(93.3, 483202.02).sin() + 0.28 * term(228.2, 960400.89).sin()
+        - 0.28 * term(318.3, 6003.15).sin()
+        - 0.17 * term(217.6, -407332.21).sin())
+    .to_radians();
+    let parallax = (0.9508
+        + 0.0518 * term(135.0, 477198.87).cos()
+        + 0.0095 * term(259.3, -413335.36).cos()
+        + 0.0078 * term(235.7, 890534.22).cos()
+        + 0.0028 * term(269.9, 954397.74).cos())
+    .to_radians();
+
+    let (ra, dec) = ecliptic_to_equatorial(longitude, latitude, obliquity(date_time));
+    let (azimuth, geocentric_elevation) = equatorial_to_horizontal(ra, dec, date_time, lat, lon);
+    let distance = EARTH_RADIUS / parallax.sin();
+
+    let sun_longitude = sun::ecliptic_longitude(date_time);
+    let elongation = (latitude.cos() * (longitude - sun_longitude).cos()).acos();
+    let phase_angle = TAU / 2. - elongation;
+
+    LunarPosition {
+        azimuth,
+        elevation: geocentric_elevation - parallax * geocentric_elevation.cos(),
+        distance,
+        angular_diameter: 2. * (MOON_RADIUS / distance).asin(),
+        illuminated_fraction: (1. + phase_angle.cos()) / 2.,
+        phase: (longitude - sun_longitude).rem_euclid(TAU) / TAU,
+    }
+}
+
+#[cfg(test)]
+mod moon_tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+    use chrono::TimeZone;
+
+    fn utc(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, mo, d, h, mi, 0).unwrap()
+    }
+
+    #[test]
+    fn moon_is_full_at_known_full_moon() {
+        let p = position(utc(2024, 1, 25, 17, 54), 0.8, 0.1);
+        assert!(p.illuminated_fraction > 0.99);
+        assert_approx_eq!(0.5, p.phase, 0.01);
+    }
+
+    #[test]
+    fn moon_is_new_at_known_new_moon() {
+        let p = position(utc(2024, 1, 11, 11, 57), 0.8, 0.1);
+        assert!(p.illuminated_fraction < 0.01);
+        assert!(p.phase < 0.01 || p.phase > 0.99);
+    }
+
+    #[test]
+    fn moon_is_waxing_after_new_moon() {
+        let p = position(utc(2024, 1, 18, 3, 53), 0.8, 0.1);
+        assert!(p.is_waxing());
+        // first quarter
+        assert_approx_eq!(0.5, p.illuminated_fraction, 0.05);
+        assert!(!position(utc(2024, 2, 2, 23, 18), 0.8, 0.1).is_waxing());
+    }
+
+    #[test]
+    fn moon_distance_and_size_are_in_expected_range() {
+        for day in 1..=28 {
+            let p = position(utc(2024, 2, day, 0, 0), 0.8, 0.1);
+            assert!((356e6..407e6).contains(&p.distance));
+            assert_approx_eq!(0.52, p.angular_diameter.to_degrees(), 0.05);
+        }
+    }
+
+    #[test]
+    fn full_moon_is_opposite_to_the_sun() {
+        let lat = 46.5_f64.to_radians();
+        let lon = 7_f64.to_radians();
+        for hour in 0..24 {
+            let t = utc(2024, 1, 25, hour, 0);
+            let m = position(t, lat, lon);
+            let s = sun::position(t, lat, lon);
+            assert_approx_eq!(-s.elevation.to_degrees(), m.elevation.to_degrees(), 7.);
+        }
+    }
+}
@@ -0,0 +1,304 @@
+use chrono::{DateTime, Utc};
+
+use super::{
+    azimuth::Azimuth,
+    celestial::{ecliptic_to_equatorial, equatorial_to_horizontal, julian_centuries},
+};
+
+/// A star or planet in the sky of an observer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SkyObject {
+    pub name: &'static str,
+    pub azimuth: Azimuth,
+    /// Elevation above the astronomical horizon, in radians (without
+    /// atmospheric refraction).
+    pub elevation: f64,
+    /// Apparent visual magnitude (approximate and fixed for planets).
+    pub magnitude: f64,
+}
+
+/// Brightest stars: name, J2000 right ascension and declination (degrees)
+/// and visual magnitude.
+const STARS: [(&str, f64, f64, f64); 23] = [
+    ("Sirius", 101.287, -16.716, -1.46),
+    ("Canopus", 95.988, -52.696, -0.74),
+    ("Rigil Kentaurus", 219.902, -60.834, -0.27),
+    ("Arcturus", 213.915, 19.182, -0.05),
+    ("Vega", 279.235, 38.784, 0.03),
+    ("Capella", 79.172, 45.998, 0.08),
+    ("Rigel", 78.634, -8.202, 0.13),
+    ("Procyon", 114.825, 5.225, 0.34),
+    ("Achernar", 24.429, -57.237, 0.46),
+    ("Betelgeuse", 88.793, 7.407, 0.50),
+    ("Hadar", 210.956, -60.373, 0.61),
+    ("Altair", 297.696, 8.868, 0.76),
+    ("Acrux", 186.650, -63.099, 0.76),
+    ("Aldebaran", 68.980, 16.509, 0.86),
+    ("Antares", 247.352, -26.432, 0.96),
+    ("Spica", 201.298, -11.161, 0.97),
+    ("Pollux", 116.329, 28.026, 1.14),
+    ("Fomalhaut", 344.413, -29.622, 1.16),
+    ("Deneb", 310.358, 45.280, 1.25),
+    ("Mimosa", 191.930, -59.689, 1.25),
+    ("Regulus", 152.093, 11.967, 1.35),
+    ("Castor", 113.650, 31.888, 1.58),
+    ("Polaris", 37.955, 89.264, 1.98),
+];
+
+/// Keplerian elements of a planet (JPL approximate positions, 1800-2050):
+/// each pair is the J2000 value and its rate per Julian century, for the
+/// semi-major axis (au), eccentricity, inclination (°), mean longitude (°),
+/// longitude of perihelion (°) and longitude of ascending node (°).
+struct Planet {
+    name: &'static str,
+    magnitude: f64,
+    elements: [(f64, f64); 6],
+}
+
+const EARTH: Planet = Planet {
+    name: "Earth",
+    magnitude: 0.,
+    elements: [
+        (1.00000261, 0.00000562),
+        (0.01671123, -0.00004392),
+        (-0.00001531, -0.01294668),
+        (100.46457166, 35999.37244981),
+        (102.93768193, 0.32327364),
+        (0.0, 0.0),
+    ],
+};
+
+const PLANETS: [Planet; 5] = [
+    Planet {
+        name: "Mercury",
+        magnitude: 0.,
+        elements: [
+            (0.38709927, 0.00000037),
+            (0.20563593, 0.00001906),
+            (7.00497902, -0.00594749),
+            (252.25032350, 149472.67411175),
+            (77.45779628, 0.16047689),
+            (48.33076593, -0.12534081),
+        ],
+    },
+    Planet {
+        name: "Venus",
+        magnitude: -4.4,
+        elements: [
+            (0.72333566, 0.00000390),
+            (0.00677672, -0.00004107),
+            (3.39467605, -0.00078890),
+            (181.97909950, 58517.81538729),
+            (131.60246718, 0.00268329),
+            (76.67984255, -0.27769418),
+        ],
+    },
+    Planet {
+        name: "Mars",
+        magnitude: 0.7,
+        elements: [
+            (1.52371034, 0.00001847),
+            (0.09339410, 0.00007882),
+            (1.84969142, -0.00813131),
+            (-4.55343205, 19140.30268499),
+            (-23.94362959, 0.44441088),
+            (49.55953891, -0.29257343),
+        ],
+    },
+    Planet {
+        name: "Jupiter",
+        magnitude: -2.4,
+        elements: [
+            (5.20288700, -0.00011607),
+            (0.04838624, -0.00013253),
+            (1.30439695, -0.00183714),
+            (34.39644051, 3034.74612775),
+            (14.72847983, 0.21252668),
+            (100.47390909, 0.20469106),
+        ],
+    },
+    Planet {
+        name: "Saturn",
+        magnitude: 0.5,
+        elements: [
+            (9.53667594, -0.00125060),
+            (0.05386179, -0.00050991),
+            (2.48599187, 0.00193609),
+            (49.95424423, 1222.49362201),
+            (92.59887831, -0.41897216),
+            (113.66242448, -0.28867794),
+        ],
+    },
+];
+
+/// Obliquity of the ecliptic at J2000.0, the reference frame of the catalog.
+const J2000_OBLIQUITY: f64 = 23.43928;
+
+impl Planet {
+    /// Heliocentric ecliptic coordinates (au) `t` Julian centuries after J2000.
+    fn heliocentric(&self, t: f64) -> [f64; 3] {
+        let [a, e, i, l, w, o] = self.elements.map(|(v, rate)| v + rate * t);
+        let (i, l, w, o) = (
+            i.to_radians(),
+            l.to_radians(),
+            w.to_radians(),
+            o.to_radians(),
+        );
+
+        // solve Kepler's equation for the eccentric anomaly
+        let m = l - w;
+        let mut ecc_anom = m + e * m.sin();
+        for _ in 0..10 {
+            ecc_anom -= (ecc_anom - e * ecc_anom.sin() - m) / (1. - e * ecc_anom.cos());
+        }
+
+        let x = a * (ecc_anom.cos() - e);
+        let y = a * (1. - e * e).sqrt() * ecc_anom.sin();
+
+        let (sin_w, cos_w) = (w - o).sin_cos();
+        let (sin_o, cos_o) = o.sin_cos();
+        let (sin_i, cos_i) = i.sin_cos();
+        [
+            (cos_w * cos_o - sin_w * sin_o * cos_i) * x
+                - (sin_w * cos_o + cos_w * sin_o * cos_i) * y,
+            (cos_w * sin_o + sin_w * cos_o * cos_i) * x
+                + (cos_w * cos_o * cos_i - sin_w * sin_o) * y,
+            sin_w * sin_i * x + cos_w * sin_i * y,
+        ]
+    }
+
+    /// Geocentric ecliptic longitude and latitude (radians) `t` Julian
+    /// centuries after J2000.
+    fn geocentric(&self, t: f64) -> (f64, f64) {
+        let p = self.heliocentric(t);
+        let e = EARTH.heliocentric(t);
+        let (x, y, z) = (p[0] - e[0], p[1] - e[1], p[2] - e[2]);
+        (y.atan2(x), z.atan2(x.hypot(y)))
+    }
+}
+
+/// Bright stars of the built-in catalog, seen at the given instant from the
+/// given latitude and longitude (in radians), whether above the horizon or not.
+///
+/// Precession since J2000.0 is neglected, which is accurate to a few tenths
+/// of a degree for the coming decades.
+pub fn stars(date_time: DateTime<Utc>, lat: f64, lon: f64) -> Vec<SkyObject> {
+    STARS
+        .iter()
+        .map(|&(name, ra, dec, magnitude)| {
+            let (azimuth, elevation) =
+                equatorial_to_horizontal(ra.to_radians(), dec.to_radians(), date_time, lat, lon);
+            SkyObject {
+                name,
+                azimuth,
+                elevation,
+                magnitude,
+            }
+        })
+        .collect()
+}
+
+/// Naked-eye planets seen at the given instant from the given latitude and
+/// longitude (in radians), whether above the horizon or not.
+pub fn planets(date_time: DateTime<Utc>, lat: f64, lon: f64) -> Vec<SkyObject> {
+    let t = julian_centuries(date_time);
+    PLANETS
+        .iter()
+        .map(|planet| {
+            let (longitude, latitude) = planet.geocentric(t);
+            let (ra, dec) =
+                ecliptic_to_equatorial(longitude, latitude, J2000_OBLIQUITY.to_radians());
+            let (azimuth, elevation) = equatorial_to_horizontal(ra, dec, date_time, lat, lon);
+            SkyObject {
+                name: planet.name,
+                azimuth,
+                elevation,
+                magnitude: planet.magnitude,
+            }
+        })
+        .collect()
+}
+
+/// Stars and planets above the astronomical horizon at the given instant,
+/// brightest first.
+pub fn visible_objects(date_time: DateTime<Utc>, lat: f64, lon: f64) -> Vec<SkyObject> {
+    let mut objects: Vec<SkyObject> = stars(date_time, lat, lon)
+        .into_iter()
+        .chain(planets(date_time, lat, lon))
+        .filter(|o| o.elevation > 0.)
+        .collect();
+    objects.sort_by(|a, b| a.magnitude.total_cmp(&b.magnitude));
+    objects
+}
+
+#[cfg(test)]
+mod night_sky_tests {
+    use super::*;
+    use crate::{math::angular_distance, sun};
+    use assert_approx_eq::assert_approx_eq;
+    use chrono::{Duration, TimeZone};
+
+    fn utc(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, mo, d, h, mi, 0).unwrap()
+    }
+
+    fn planet_longitude(name: &str, t: DateTime<Utc>) -> f64 {
+        let planet = PLANETS.iter().find(|p| p.name == name).unwrap();
+        planet.geocentric(julian_centuries(t)).0
+    }
+
+    #[test]
+    fn polaris_stays_near_latitude_elevation() {
+        let lat = 46.5_f64.to_radians();
+        for hour in 0..24 {
+            let objects = stars(utc(2024, 1, 1, hour, 0), lat, 0.1);
+            let polaris = objects.iter().find(|o| o.name == "Polaris").unwrap();
+            assert_approx_eq!(46.5, polaris.elevation.to_degrees(), 1.);
+        }
+    }
+
+    #[test]
+    fn sirius_culminates_at_expected_elevation() {
+        let lat = 46.5_f64.to_radians();
+        let highest = (0..24 * 60)
+            .map(|m| stars(utc(2024, 1, 1, 0, 0) + Duration::minutes(m), lat, 0.1)[0])
+            .max_by(|a, b| a.elevation.total_cmp(&b.elevation))
+            .unwrap();
+        assert_eq!("Sirius", highest.name);
+        assert_approx_eq!(90. - 46.5 - 16.716, highest.elevation.to_degrees(), 0.1);
+        assert_approx_eq!(180., highest.azimuth.degrees(), 1.);
+    }
+
+    #[test]
+    fn outer_planets_are_opposite_to_the_sun_at_opposition() {
+        let oppositions = [
+            ("Mars", utc(2022, 12, 8, 5, 0)),
+            ("Jupiter", utc(2023, 11, 3, 5, 0)),
+            ("Saturn", utc(2023, 8, 27, 8, 0)),
+        ];
+        for (name, t) in oppositions {
+            let d = angular_distance(sun::ecliptic_longitude(t), planet_longitude(name, t));
+            assert_approx_eq!(180., d.abs().to_degrees(), 1.);
+        }
+    }
+
+    #[test]
+    fn inner_planets_stay_close_to_the_sun() {
+        for day in (0..3650).step_by(7) {
+            let t = utc(2020, 1, 1, 0, 0) + Duration::days(day);
+            let sun_longitude = sun::ecliptic_longitude(t);
+            let mercury = angular_distance(sun_longitude, planet_longitude("Mercury", t));
+            let venus = angular_distance(sun_longitude, planet_longitude("Venus", t));
+            assert!(mercury.abs().to_degrees() < 28.5);
+            assert!(venus.abs().to_degrees() < 47.5);
+        }
+    }
+
+    #[test]
+    fn visible_objects_are_above_horizon_and_sorted_by_brightness() {
+        let objects = visible_objects(utc(2024, 1, 1, 22, 0), 46.5_f64.to_radians(), 0.1);
+        assert!(!objects.is_empty());
+        assert!(objects.iter().all(|o| o.elevation > 0.));
+        assert!(objects.windows(2).all(|w| w[0].magnitude <= w[1].magnitude));
+    }
+}
@@ -0,0 +1,981 @@
+use std::f64::consts::TAU;
+
+use ab_glyph::{point, Font, FontRef, PxScale, ScaleFont};
+use chrono::{DateTime, Duration, Utc};
+
+use crate::{
+    azimuth::Azimuth,
+    calibration::Calibration,
+    image::Image,
+    labels::{Label, LABEL_ROTATION},
+    math::angular_distance,
+    moon, night_sky,
+    panorama::{Panorama, PanoramaParameters},
+    sun::{self, CrossingKind, HorizonCrossing},
+};
+
+/// Height of text written over images, in pixels.
+pub const TEXT_SIZE: f64 = 14.;
+/// Color of the marks and text of the compass ribbon.
+const COMPASS_COLOR: [u8; 4] = [255, 255, 255, 255];
+/// Color of the band behind the compass ribbon.
+const RIBBON_COLOR: [u8; 4] = [0, 0, 0, 180];
+/// Height of the compass ribbon at the bottom of images, in pixels: a row
+/// of ticks, a row of azimuths and a row of sunrise and sunset times.
+const RIBBON_HEIGHT: usize = 44;
+/// Color of the sunrise and sunset markers.
+const SUN_COLOR: [u8; 4] = [255, 170, 30, 255];
+/// Radius of the sunrise and sunset markers on the horizon, in pixels.
+const SUN_MARKER_RADIUS: f64 = 4.;
+/// Width of the arc of the daily path of the sun, in pixels.
+const SUN_PATH_WIDTH: f64 = 1.5;
+/// Time between the points of the arc of the daily path of the sun, in
+/// minutes.
+const SUN_PATH_STEP: i64 = 5;
+/// Color of the labels of summits and of their leader lines.
+const LABEL_COLOR: [u8; 4] = [0, 0, 0, 255];
+/// Color of the skyline drawn over photographs.
+const SKYLINE_COLOR: [u8; 4] = [255, 60, 40, 255];
+/// Width of the skyline drawn over photographs, in pixels.
+const SKYLINE_WIDTH: f64 = 2.;
+/// Color of the lit part of the moon.
+const MOON_COLOR: [u8; 4] = [245, 243, 225, 255];
+/// Color of the part of the moon in the shadow, faintly lit by the earth.
+const MOON_SHADOW_COLOR: [u8; 4] = [80, 85, 100, 140];
+/// Smallest radius of the moon, in pixels, keeping it visible in images of
+/// wide fields of view.
+const MIN_MOON_RADIUS: f64 = 3.;
+/// Color of the stars and planets.
+const STAR_COLOR: [u8; 4] = [255, 250, 235, 255];
+/// Elevation of the sun below which stars are drawn, in degrees, at the end
+/// of civil twilight.
+const STARS_MAX_SUN_ELEVATION: f64 = -6.;
+
+/// Blend `color` over the pixel `(x, y)` of `image`, if inside it, with its
+/// opacity multiplied by `coverage`, in `[0, 1]`.
+pub fn blend(image: &mut Image, x: isize, y: isize, color: [u8; 4], coverage: f64) {
+    if x < 0 || y < 0 || x >= image.width() as isize || y >= image.height() as isize {
+        return;
+    }
+    let (x, y) = (x as usize, y as usize);
+    let top = color[3] as f64 / 255. * coverage.clamp(0., 1.);
+    if top == 0. {
+        return;
+    }
+    let below = image.get(x, y);
+    let bottom = below[3] as f64 / 255.;
+    let opacity = top + bottom * (1. - top);
+    let mix = |i: usize| {
+        let c = (color[i] as f64 * top + below[i] as f64 * bottom * (1. - top)) / opacity;
+        c.round() as u8
+    };
+    image.set(
+        x,
+        y,
+        [mix(0), mix(1), mix(2), (opacity * 255.).round() as u8],
+    );
+}
+
+/// Draw the segment from `(x0, y0)` to `(x1, y1)` with the given width, in
+/// pixels, antialiased.
+pub fn line(
+    image: &mut Image,
+    (x0, y0): (f64, f64),
+    (x1, y1): (f64, f64),
+    width: f64,
+    color: [u8; 4],
+) {
+    let reach = width / 2. + 1.;
+    let (dx, dy) = (x1 - x0, y1 - y0);
+    let length2 = dx * dx + dy * dy;
+    for y in (y0.min(y1) - reach).floor() as isize..=(y0.max(y1) + reach).ceil() as isize {
+        for x in (x0.min(x1) - reach).floor() as isize..=(x0.max(x1) + reach).ceil() as isize {
+            let (px, py) = (x as f64, y as f64);
+            // distance from the pixel to the nearest point of the segment
+            let t = if length2 == 0. {
+                0.
+            } else {
+                (((px - x0) * dx + (py - y0) * dy) / length2).clamp(0., 1.)
+            };
+            let distance = (px - x0 - t * dx).hypot(py - y0 - t * dy);
+            blend(image, x, y, color, width / 2. + 0.5 - distance);
+        }
+    }
+}
+
+/// Draw the disk of the given center and radius, in pixels, antialiased.
+pub fn disk(image: &mut Image, (cx, cy): (f64, f64), radius: f64, color: [u8; 4]) {
+    for y in (cy - radius - 1.).floor() as isize..=(cy + radius + 1.).ceil() as isize {
+        for x in (cx - radius - 1.).floor() as isize..=(cx + radius + 1.).ceil() as isize {
+            let distance = (x as f64 - cx).hypot(y as f64 - cy);
+            blend(image, x, y, color, radius + 0.5 - distance);
+        }
+    }
+}
+
+/// Fill the rectangle of the pixels from `(x0, y0)` included to `(x1, y1)`
+/// excluded.
+pub fn rectangle(
+    image: &mut Image,
+    (x0, y0): (isize, isize),
+    (x1, y1): (isize, isize),
+    color: [u8; 4],
+) {
+    for y in y0..y1 {
+        for x in x0..x1 {
+            blend(image, x, y, color, 1.);
+        }
+    }
+}
+
+/// Font of the text written over images.
+fn font() -> FontRef<'static> {
+    FontRef::try_from_slice(epaint_default_fonts::UBUNTU_LIGHT).expect("embedded font is valid")
+}
+
+/// Coverage of the pixels of a text written along the x axis from the
+/// origin, its baseline, over the pixels from `(left, top)`.
+struct Coverage {
+    left: f64,
+    top: f64,
+    width: usize,
+    height: usize,
+    values: Vec<f64>,
+}
+
+impl Coverage {
+    fn of(text: &str, size: f64) -> Coverage {
+        let font = font();
+        let scaled = font.as_scaled(PxScale::from(size as f32));
+        let mut caret = 0.;
+        let mut previous = None;
+        let mut outlines = Vec::new();
+        for c in text.chars() {
+            let id = scaled.glyph_id(c);
+            if let Some(previous) = previous {
+                caret += scaled.kern(previous, id);
+            }
+            outlines.extend(
+                font.outline_glyph(id.with_scale_and_position(scaled.scale(), point(caret, 0.))),
+            );
+            caret += scaled.h_advance(id);
+            previous = Some(id);
+        }
+
+        let bounds: Vec<_> = outlines.iter().map(|o| o.px_bounds()).collect();
+        let left = bounds.iter().map(|b| b.min.x).fold(f32::INFINITY, f32::min);
+        let top = bounds.iter().map(|b| b.min.y).fold(f32::INFINITY, f32::min);
+        let right = bounds.iter().map(|b| b.max.x).fold(left, f32::max);
+        let bottom = bounds.iter().map(|b| b.max.y).fold(top, f32::max);
+        let (width, height) = ((right - left) as usize, (bottom - top) as usize);
+
+        let mut values = vec![0_f64; width * height];
+        for (outline, bounds) in outlines.iter().zip(&bounds) {
+            let (dx, dy) = (
+                (bounds.min.x - left) as usize,
+                (bounds.min.y - top) as usize,
+            );
+            outline.draw(|x, y, c| {
+                let (x, y) = (dx + x as usize, dy + y as usize);
+                if x < width && y < height {
+                    let value = &mut values[y * width + x];
+                    *value = value.max(c as f64);
+                }
+            });
+        }
+        Coverage {
+            left: left as f64,
+            top: top as f64,
+            width,
+            height,
+            values,
+        }
+    }
+
+    /// Coverage at the (possibly fractional) point `(x, y)` relative to the
+    /// origin, interpolated between the pixels around it.
+    fn at(&self, x: f64, y: f64) -> f64 {
+        let (x, y) = (x - self.left - 0.5, y - self.top - 0.5);
+        let (x0, y0) = (x.floor(), y.floor());
+        let value = |x: f64, y: f64| {
+            if x < 0. || y < 0. || x >= self.width as f64 || y >= self.height as f64 {
+                0.
+            } else {
+                self.values[y as usize * self.width + x as usize]
+            }
+        };
+        let (tx, ty) = (x - x0, y - y0);
+        let top = value(x0, y0) * (1. - tx) + value(x0 + 1., y0) * tx;
+        let bottom = value(x0, y0 + 1.) * (1. - tx) + value(x0 + 1., y0 + 1.) * tx;
+        top * (1. - ty) + bottom * ty
+    }
+}
+
+/// Width of `text` written with the given height, in pixels.
+pub fn text_width(text: &str, size: f64) -> f64 {
+    let font = font();
+    let scaled = font.as_scaled(PxScale::from(size as f32));
+    let mut width = 0.;
+    let mut previous = None;
+    for c in text.chars() {
+        let id = scaled.glyph_id(c);
+        if let Some(previous) = previous {
+            width += scaled.kern(previous, id);
+        }
+        width += scaled.h_advance(id);
+        previous = Some(id);
+    }
+    width as f64
+}
+
+/// Write `text` with the given height, in pixels, along a baseline starting
+/// at `(x, y)` and rotated by `rotation` (in radians) counterclockwise from
+/// the horizontal.
+pub fn text(
+    image: &mut Image,
+    text: &str,
+    (x, y): (f64, f64),
+    size: f64,
+    rotation: f64,
+    color: [u8; 4],
+) {
+    let coverage = Coverage::of(text, size);
+    if coverage.values.is_empty() {
+        return;
+    }
+    let (sin, cos) = rotation.sin_cos();
+    // pixels of the image covered by the rotated bounds of the text
+    let corners = [
+        (coverage.left, coverage.top),
+        (coverage.left + coverage.width as f64, coverage.top),
+        (coverage.left, coverage.top + coverage.height as f64),
+        (
+            coverage.left + coverage.width as f64,
+            coverage.top + coverage.height as f64,
+        ),
+    ]
+    .map(|(u, v)| (x + u * cos + v * sin, y - u * sin + v * cos));
+    let min = |i: fn(&(f64, f64)) -> f64| corners.iter().map(i).fold(f64::INFINITY, f64::min);
+    let max = |i: fn(&(f64, f64)) -> f64| corners.iter().map(i).fold(f64::NEG_INFINITY, f64::max);
+
+    for py in min(|c| c.1).floor() as isize..=max(|c| c.1).ceil() as isize {
+        for px in min(|c| c.0).floor() as isize..=max(|c| c.0).ceil() as isize {
+            // center of the pixel in the frame of the text
+            let (dx, dy) = (px as f64 + 0.5 - x, py as f64 + 0.5 - y);
+            let (u, v) = (dx * cos - dy * sin, dx * sin + dy * cos);
+            blend(image, px, py, color, coverage.at(u, v));
+        }
+    }
+}
+
+/// Elevation angle of the terrain horizon of `panorama` in every azimuth, in
+/// radians: that of the highest pixel of the terrain seen in that azimuth,
+/// or 0 in the azimuths where no terrain is seen, like those out of the
+/// field of view. The horizon of columns where the terrain reaches the top
+/// of the image is that of the top row.
+pub fn terrain_horizon(panorama: &Panorama) -> impl Fn(Azimuth) -> f64 {
+    let parameters = panorama.parameters();
+    let bins = (TAU / parameters.angle_per_pixel()).ceil() as usize;
+    let bin = move |azimuth: Azimuth| (azimuth.radians() / TAU * bins as f64) as usize % bins;
+
+    let mut horizon = vec![f64::NEG_INFINITY; bins];
+    for x in 0..parameters.width() {
+        for y in 0..parameters.height() {
+            // only the upper edges of the terrain can be its horizon
+            if panorama.distance_at(x, y).is_infinite()
+                || (y > 0 && panorama.distance_at(x, y - 1).is_finite())
+            {
+                continue;
+            }
+            let (azimuth, altitude) = parameters.direction_at(x as f64, y as f64);
+            let b = bin(azimuth);
+            horizon[b] = horizon[b].max(altitude);
+        }
+    }
+    move |azimuth| match horizon[bin(azimuth)] {
+        altitude if altitude.is_finite() => altitude,
+        _ => 0.,
+    }
+}
+
+/// Instants of the solar day of `time` at which the sun rises above and
+/// sets below the terrain horizon of `panorama`, see
+/// [`terrain_horizon`] and [`sun::horizon_crossings`].
+pub fn sun_crossings(panorama: &Panorama, time: DateTime<Utc>) -> Vec<HorizonCrossing> {
+    let observer = panorama.parameters().observer_position();
+    let (start, end) = sun::solar_day(time, observer.longitude());
+    sun::horizon_crossings(
+        start,
+        end,
+        observer.latitude(),
+        observer.longitude(),
+        terrain_horizon(panorama),
+    )
+}
+
+/// Draw a compass ribbon along the bottom of `image`, the image of the
+/// panorama of `parameters`, with ticks every 10° of azimuth under the
+/// horizon, and the names of the octants or else the azimuths of the ticks
+/// when there is room for them.
+///
+/// The given crossings of the horizon by the sun, if any, are marked on the
+/// ribbon with their time, and on the terrain horizon of `panorama`.
+pub fn compass(image: &mut Image, panorama: &Panorama, crossings: &[HorizonCrossing]) {
+    let parameters = panorama.parameters();
+    let (width, height) = (image.width() as isize, image.height() as isize);
+    let top = height - RIBBON_HEIGHT as isize;
+    rectangle(image, (0, top), (width, height), RIBBON_COLOR);
+
+    let column = |azimuth: Azimuth| Some(parameters.pixel_for(azimuth, 0.).ok()?.0);
+    // room for the azimuth of every tick
+    let spacing = 10_f64.to_radians() / parameters.angle_per_pixel();
+    let names = spacing >= text_width("360°", TEXT_SIZE) + 8.;
+    for degrees in (0..360).step_by(10) {
+        let azimuth = Azimuth::from_degrees(degrees as f64);
+        let Some(x) = column(azimuth) else {
+            continue;
+        };
+        let octant = degrees % 45 == 0;
+        let length = if octant { 10. } else { 6. };
+        line(
+            image,
+            (x, top as f64),
+            (x, top as f64 + length),
+            if octant { 2. } else { 1. },
+            COMPASS_COLOR,
+        );
+        let label = match (octant, names) {
+            (true, _) => azimuth.to_octant_str("N", "E", "S", "W"),
+            (false, true) => format!("{}°", degrees),
+            (false, false) => continue,
+        };
+        let x = x - text_width(&label, TEXT_SIZE) / 2.;
+        text(
+            image,
+            &label,
+            (x, top as f64 + 26.),
+            TEXT_SIZE,
+            0.,
+            COMPASS_COLOR,
+        );
+    }
+
+    draw_sun_crossings(
+        image,
+        parameters,
+        crossings,
+        &terrain_horizon(panorama),
+        top as f64,
+    );
+}
+
+/// Mark the given crossings on the terrain horizon of the panorama of
+/// `parameters`, and on the compass ribbon starting at row `top` of `image`,
+/// above the image if it is shorter than the ribbon.
+fn draw_sun_crossings(
+    image: &mut Image,
+    parameters: &PanoramaParameters,
+    crossings: &[HorizonCrossing],
+    horizon: &impl Fn(Azimuth) -> f64,
+    top: f64,
+) {
+    for crossing in crossings {
+        let Ok((x, y)) = parameters.pixel_for(crossing.azimuth, horizon(crossing.azimuth)) else {
+            continue;
+        };
+        disk(image, (x, y), SUN_MARKER_RADIUS, SUN_COLOR);
+        line(image, (x, top), (x, top + 12.), 2., SUN_COLOR);
+        let label = format!(
+            "{} {}",
+            match crossing.kind {
+                CrossingKind::Rise => "rise",
+                CrossingKind::Set => "set",
+            },
+            crossing.time.format("%H:%M UTC")
+        );
+        let x = x - text_width(&label, TEXT_SIZE) / 2.;
+        text(image, &label, (x, top + 40.), TEXT_SIZE, 0., SUN_COLOR);
+    }
+}
+
+/// Draw the daily path of the sun on the solar day of `time` across
+/// `image`, the image of the panorama of `parameters`, as an arc with a tick
+/// and the UTC hour at every whole hour, see [`sun::path`]. The arc is drawn
+/// over the terrain too, to show where the sun goes behind it.
+pub fn sun_path(image: &mut Image, parameters: &PanoramaParameters, time: DateTime<Utc>) {
+    let observer = parameters.observer_position();
+    let (latitude, longitude) = (observer.latitude(), observer.longitude());
+    let (start, end) = sun::solar_day(time, longitude);
+    let pixel = |position: &sun::SolarPosition| {
+        parameters
+            .pixel_for(position.azimuth, position.elevation)
+            .ok()
+    };
+
+    let path = sun::path(
+        start,
+        end + Duration::minutes(SUN_PATH_STEP),
+        Duration::minutes(SUN_PATH_STEP),
+        latitude,
+        longitude,
+    );
+    for pair in path.windows(2) {
+        let (Some(p0), Some(p1)) = (pixel(&pair[0].1), pixel(&pair[1].1)) else {
+            continue;
+        };
+        // no segment across the seam of full circle panoramas
+        if (p1.0 - p0.0).abs() < image.width() as f64 / 2. {
+            line(image, p0, p1, SUN_PATH_WIDTH, SUN_COLOR);
+        }
+    }
+
+    let first_hour = DateTime::from_timestamp(start.timestamp().div_euclid(3600) * 3600 + 3600, 0)
+        .expect("the hour after a valid time is valid");
+    for (hour, position) in sun::path(first_hour, end, Duration::hours(1), latitude, longitude) {
+        let Some((x, y)) = pixel(&position) else {
+            continue;
+        };
+        disk(image, (x, y), SUN_PATH_WIDTH + 1.5, SUN_COLOR);
+        text(
+            image,
+            &hour.format("%Hh").to_string(),
+            (x + 5., y - 5.),
+            TEXT_SIZE,
+            0.,
+            SUN_COLOR,
+        );
+    }
+}
+
+/// Draw `labels` over `image`, each with a leader line from its summit up
+/// to the row of the labels, where its text starts, see
+/// [`Labelizer::labels`](crate::labels::Labelizer::labels).
+pub fn labels(image: &mut Image, labels: &[Label]) {
+    for l in labels {
+        let x = l.x as f64;
+        label(
+            image,
+            &l.text,
+            (x, l.summit_y as f64),
+            (x, l.label_y as f64),
+        );
+    }
+}
+
+/// Draw the label `text` written from `at`, and its leader line to the summit
+/// at `summit`.
+fn label(image: &mut Image, text: &str, summit: (f64, f64), at: (f64, f64)) {
+    line(image, summit, at, 1., LABEL_COLOR);
+    self::text(
+        image,
+        text,
+        (at.0 + 2., at.1 - 2.),
+        TEXT_SIZE,
+        LABEL_ROTATION,
+        LABEL_COLOR,
+    );
+}
+
+/// Draw the skyline of `panorama` and its `labels` over `photo`, a photograph
+/// taken from its observer and framed by `calibration`, with the given
+/// opacity, in `[0, 1]`.
+///
+/// The skyline joins the highest pixels of the terrain of the columns of the
+/// panorama, which must thus cover the field of view of the photograph and
+/// the terrain seen in it.
+pub fn composite(
+    photo: &mut Image,
+    calibration: &Calibration,
+    panorama: &Panorama,
+    labels: &[Label],
+    opacity: f64,
+) {
+    let parameters = panorama.parameters();
+    let (width, height) = (photo.width() as u32, photo.height() as u32);
+    let to_photo = |x: f64, y: f64| {
+        let (azimuth, altitude) = parameters.direction_at(x, y);
+        calibration.pixel_for(azimuth, altitude, width, height)
+    };
+
+    // marks drawn opaque, then blended over the photograph at once, so that
+    // where they overlap they are not more opaque
+    let mut layer = Image::new(photo.width(), photo.height());
+    let mut previous = None;
+    for x in 0..parameters.width() {
+        let top = (0..parameters.height()).find(|&y| panorama.distance_at(x, y).is_finite());
+        let point = top.map(|y| to_photo(x as f64, y as f64));
+        if let (Some(p0), Some(p1)) = (previous, point) {
+            line(&mut layer, p0, p1, SKYLINE_WIDTH, SKYLINE_COLOR);
+        }
+        previous = point;
+    }
+    for l in labels {
+        let x = l.x as f64;
+        label(
+            &mut layer,
+            &l.text,
+            to_photo(x, l.summit_y as f64),
+            to_photo(x, l.label_y as f64),
+        );
+    }
+
+    for y in 0..photo.height() {
+        for x in 0..photo.width() {
+            blend(photo, x as isize, y as isize, layer.get(x, y), opacity);
+        }
+    }
+}
+
+/// Draw the moon at `time` in the sky of `image`, the image of `panorama`,
+/// at its position and with its apparent size, lit on the side of the sun
+/// according to its phase, see [`moon::position`]. The terrain hides it.
+pub fn moon(image: &mut Image, panorama: &Panorama, time: DateTime<Utc>) {
+    let parameters = panorama.parameters();
+    let observer = parameters.observer_position();
+    let (latitude, longitude) = (observer.latitude(), observer.longitude());
+    let moon = moon::position(time, latitude, longitude);
+    let Ok((cx, cy)) = parameters.pixel_for(moon.azimuth, moon.elevation) else {
+        return;
+    };
+    let radius = (moon.angular_diameter / 2. / parameters.angle_per_pixel()).max(MIN_MOON_RADIUS);
+
+    // direction of the sun from the moon, x to the right and y up
+    let sun = sun::position(time, latitude, longitude);
+    let (sx, sy) = (
+        angular_distance(moon.azimuth, sun.azimuth) * moon.elevation.cos(),
+        sun.elevation - moon.elevation,
+    );
+    let norm = sx.hypot(sy);
+    let (sx, sy) = if norm > 0. {
+        (sx / norm, sy / norm)
+    } else {
+        (1., 0.)
+    };
+    // the terminator is a half ellipse whose semi-minor axis along the
+    // direction of the sun is the cosine of the phase angle
+    let cos_phase_angle = 2. * moon.illuminated_fraction - 1.;
+
+    let (width, height) = (image.width() as isize, image.height() as isize);
+    for y in ((cy - radius - 1.).floor() as isize).max(0)..((cy + radius + 2.) as isize).min(height)
+    {
+        for x in
+            ((cx - radius - 1.).floor() as isize).max(0)..((cx + radius + 2.) as isize).min(width)
+        {
+            if panorama.distance_at(x as usize, y as usize).is_finite() {
+                continue;
+            }
+            let (u, v) = ((x as f64 - cx) / radius, (cy - y as f64) / radius);
+            let (along, across) = (u * sx + v * sy, v * sx - u * sy);
+            let lit = along > -cos_phase_angle * (1. - across * across).max(0.).sqrt();
+            blend(
+                image,
+                x,
+                y,
+                if lit { MOON_COLOR } else { MOON_SHADOW_COLOR },
+                radius + 0.5 - u.hypot(v) * radius,
+            );
+        }
+    }
+}
+
+/// Draw the bright stars and planets in the sky of `image`, the image of
+/// `panorama`, at `time`, as disks larger for brighter objects, see
+/// [`night_sky::visible_objects`]. The terrain hides them, and nothing is
+/// drawn before the end of civil twilight.
+pub fn stars(image: &mut Image, panorama: &Panorama, time: DateTime<Utc>) {
+    let parameters = panorama.parameters();
+    let observer = parameters.observer_position();
+    let (latitude, longitude) = (observer.latitude(), observer.longitude());
+    if sun::position(time, latitude, longitude).elevation > STARS_MAX_SUN_ELEVATION.to_radians() {
+        return;
+    }
+
+    let (width, height) = (image.width() as isize, image.height() as isize);
+    for object in night_sky::visible_objects(time, latitude, longitude) {
+        let Ok((cx, cy)) = parameters.pixel_for(object.azimuth, object.elevation) else {
+            continue;
+        };
+        let radius = (1.6 - 0.4 * object.magnitude).clamp(0.7, 3.);
+        for y in
+            ((cy - radius - 1.).floor() as isize).max(0)..((cy + radius + 2.) as isize).min(height)
+        {
+            for x in ((cx - radius - 1.).floor() as isize).max(0)
+                ..((cx + radius + 2.) as isize).min(width)
+            {
+                if panorama.distance_at(x as usize, y as usize).is_infinite() {
+                    let distance = (x as f64 - cx).hypot(y as f64 - cy);
+                    blend(image, x, y, STAR_COLOR, radius + 0.5 - distance);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod overlay_tests {
+    use super::*;
+    use crate::{
+        dem::ContinuousElevationModel,
+        fixtures::{compute, flat, panorama, wall},
+        geo::GeoPoint,
+        labels::{Labelizer, Summit},
+    };
+    use assert_approx_eq::assert_approx_eq;
+    use chrono::{Duration, TimeZone};
+
+    /// Panorama of 10° toward `azimuth` over flat terrain at 1000 m, seen
+    /// from 100 m above it.
+    fn flat_panorama(azimuth: Azimuth) -> Panorama {
+        let parameters = PanoramaParameters::new(
+            GeoPoint::from_degrees(7., 46.5).unwrap(),
+            1100.,
+            azimuth,
+            10_f64.to_radians(),
+            50_000.,
+            801,
+            401,
+        )
+        .unwrap();
+        compute(flat(1000.), &parameters)
+    }
+
+    fn covered(image: &Image) -> usize {
+        (0..image.height())
+            .flat_map(|y| (0..image.width()).map(move |x| (x, y)))
+            .filter(|&(x, y)| image.get(x, y)[3] > 0)
+            .count()
+    }
+
+    #[test]
+    fn blend_composites_by_coverage() {
+        let mut image = Image::new(2, 1);
+        image.set(0, 0, [0, 0, 0, 255]);
+        blend(&mut image, 0, 0, [255, 255, 255, 255], 0.5);
+        assert_eq!([128, 128, 128, 255], image.get(0, 0));
+        blend(&mut image, 1, 0, [255, 0, 0, 255], 1.);
+        assert_eq!([255, 0, 0, 255], image.get(1, 0));
+        // outside of the image
+        blend(&mut image, 2, 0, [255, 0, 0, 255], 1.);
+        blend(&mut image, -1, 0, [255, 0, 0, 255], 1.);
+    }
+
+    #[test]
+    fn line_and_disk_cover_their_pixels() {
+        let mut image = Image::new(20, 20);
+        line(&mut image, (2., 10.), (17., 10.), 1., [255; 4]);
+        assert_eq!(255, image.get(10, 10)[3]);
+        assert_eq!(0, image.get(10, 12)[3]);
+        assert_eq!(0, image.get(0, 10)[3]);
+
+        let mut image = Image::new(20, 20);
+        disk(&mut image, (10., 10.), 3., [255; 4]);
+        assert_eq!(255, image.get(12, 10)[3]);
+        assert_eq!(0, image.get(14, 10)[3]);
+        // about the area of the disk
+        assert!((28..45).contains(&covered(&image)));
+    }
+
+    #[test]
+    fn text_is_written_along_its_rotated_baseline() {
+        let width = text_width("Niesen", TEXT_SIZE);
+        assert!(width > 30. && width < 60.);
+        assert!(text_width("Niesen (2362 m)", TEXT_SIZE) > width);
+
+        let mut image = Image::new(100, 100);
+        text(&mut image, "Niesen", (10., 50.), TEXT_SIZE, 0., [255; 4]);
+        let rows: Vec<usize> = (0..100)
+            .filter(|&y| (0..100).any(|x| image.get(x, y)[3] > 0))
+            .collect();
+        // above the baseline, with the cap height of the font
+        assert!(*rows.last().unwrap() <= 50);
+        assert!(*rows.first().unwrap() >= 36);
+
+        let mut rotated = Image::new(100, 100);
+        text(
+            &mut rotated,
+            "Niesen",
+            (10., 90.),
+            TEXT_SIZE,
+            std::f64::consts::FRAC_PI_2,
+            [255; 4],
+        );
+        let columns: Vec<usize> = (0..100)
+            .filter(|&x| (0..100).any(|y| rotated.get(x, y)[3] > 0))
+            .collect();
+        // written upward, left of the baseline
+        assert!(*columns.last().unwrap() <= 11);
+        assert!((0..40).all(|y| (0..100).all(|x| rotated.get(x, y)[3] == 0)));
+        assert_approx_eq!(covered(&image) as f64, covered(&rotated) as f64, 30.);
+    }
+
+    #[test]
+    fn terrain_horizon_is_the_top_of_the_terrain() {
+        let panorama = panorama();
+        let horizon = terrain_horizon(&panorama);
+        // the top of the wall is seen about 1.1° above the horizon
+        let wall = horizon(Azimuth::EAST).to_degrees();
+        assert!((0.9..1.3).contains(&wall));
+        // nothing is seen behind the observer
+        assert_eq!(0., horizon(Azimuth::WEST));
+    }
+
+    #[test]
+    fn sun_crossings_are_those_of_the_terrain_horizon() {
+        let panorama = panorama();
+        let time = Utc.with_ymd_and_hms(2024, 3, 20, 12, 0, 0).unwrap();
+        let crossings = sun_crossings(&panorama, time);
+        assert_eq!(2, crossings.len());
+        let (rise, set) = (crossings[0], crossings[1]);
+        assert_eq!(CrossingKind::Rise, rise.kind);
+        assert_eq!(CrossingKind::Set, set.kind);
+        // around the equinox, the sun rises in the east behind the wall,
+        // later than over the sea-level horizon, at about 5:30 UTC
+        assert!((80. ..95.).contains(&rise.azimuth.degrees()));
+        let sea_level = sun::horizon_crossings(
+            rise.time - Duration::hours(2),
+            rise.time,
+            46.5_f64.to_radians(),
+            7_f64.to_radians(),
+            |_| 0.,
+        );
+        assert_eq!(1, sea_level.len());
+        assert!(rise.time - sea_level[0].time > Duration::minutes(3));
+        assert!((260. ..280.).contains(&set.azimuth.degrees()));
+        assert_eq!(
+            sun::solar_day(time, 7_f64.to_radians()),
+            sun::solar_day(rise.time, 7_f64.to_radians())
+        );
+    }
+
+    #[test]
+    fn compass_draws_a_ribbon_with_the_crossings() {
+        let panorama = panorama();
+        let mut image = Image::new(401, 401);
+        let time = Utc.with_ymd_and_hms(2024, 3, 20, 12, 0, 0).unwrap();
+        compass(&mut image, &panorama, &sun_crossings(&panorama, time));
+
+        // the ribbon covers the bottom of the image
+        assert_eq!(0, image.get(200, 401 - RIBBON_HEIGHT - 3)[3]);
+        assert!(image.get(5, 400)[3] > 0);
+        // the tick of the east in the middle
+        assert_eq!(
+            COMPASS_COLOR[..3],
+            image.get(200, 401 - RIBBON_HEIGHT + 2)[..3]
+        );
+        // the sunrise marker on the wall
+        let rise = sun_crossings(&panorama, time)[0];
+        let (x, y) = panorama
+            .parameters()
+            .pixel_for(rise.azimuth, terrain_horizon(&panorama)(rise.azimuth))
+            .unwrap();
+        assert_eq!(SUN_COLOR, image.get(x.round() as usize, y.round() as usize));
+    }
+
+    #[test]
+    fn compass_fills_images_shorter_than_the_ribbon() {
+        let panorama = panorama();
+        let mut image = Image::new(401, 30);
+        let time = Utc.with_ymd_and_hms(2024, 3, 20, 12, 0, 0).unwrap();
+        compass(&mut image, &panorama, &sun_crossings(&panorama, time));
+        assert!(image.get(5, 0)[3] > 0);
+        assert!(image.get(5, 29)[3] > 0);
+    }
+
+    #[test]
+    fn sun_path_is_drawn_through_the_positions_of_the_sun() {
+        let (latitude, longitude) = (46.5_f64.to_radians(), 7_f64.to_radians());
+        let at = |hour, minute| Utc.with_ymd_and_hms(2024, 12, 21, hour, minute, 0).unwrap();
+        let time = at(12, 0);
+
+        // the sun stays below 20° at the winter solstice, out of a narrow
+        // field of view around the horizon
+        let mut image = Image::new(801, 401);
+        sun_path(&mut image, flat_panorama(Azimuth::SOUTH).parameters(), time);
+        assert_eq!(0, covered(&image));
+
+        let parameters = PanoramaParameters::new(
+            GeoPoint::from_degrees(7., 46.5).unwrap(),
+            1100.,
+            Azimuth::SOUTH,
+            90_f64.to_radians(),
+            50_000.,
+            901,
+            601,
+        )
+        .unwrap();
+        let mut image = Image::new(901, 601);
+        sun_path(&mut image, &parameters, time);
+        let pixel = |time| {
+            let position = sun::position(time, latitude, longitude);
+            let (x, y) = parameters
+                .pixel_for(position.azimuth, position.elevation)
+                .unwrap();
+            image.get(x.round() as usize, y.round() as usize)
+        };
+        // the ticks of the whole hours, and the arc between them
+        assert_eq!(SUN_COLOR, pixel(at(11, 0)));
+        assert_eq!(SUN_COLOR, pixel(at(12, 0)));
+        assert!(pixel(at(11, 32))[3] > 100);
+        // nothing far from the arc
+        assert_eq!(0, image.get(450, 10)[3]);
+    }
+
+    #[test]
+    fn labels_of_visible_summits_are_drawn_above_them() {
+        let panorama = panorama();
+        let cem = ContinuousElevationModel::new(wall());
+        let summit = Summit {
+            name: "Wall".to_string(),
+            position: GeoPoint::from_degrees(8., 46.5).unwrap(),
+            elevation: 3000.,
+        };
+        let placed = Labelizer::new(&cem, vec![summit]).labels(panorama.parameters());
+        assert_eq!(1, placed.len());
+        let label = &placed[0];
+
+        let mut image = Image::new(401, 401);
+        labels(&mut image, &placed);
+        // the leader line from the summit up to the labels
+        for y in label.label_y..=label.summit_y {
+            assert_eq!(LABEL_COLOR, image.get(label.x, y));
+        }
+        // the text, up and to the right of the line
+        let text: Vec<(usize, usize)> = (0..label.label_y)
+            .flat_map(|y| (label.x + 1..401).map(move |x| (x, y)))
+            .filter(|&(x, y)| image.get(x, y)[3] > 0)
+            .collect();
+        assert!(text.len() > 50);
+        assert!(text.iter().all(|&(_, y)| y + 100 > label.label_y));
+        // nothing below the summit
+        assert!((label.summit_y + 3..401).all(|y| (0..401).all(|x| image.get(x, y)[3] == 0)));
+    }
+
+    #[test]
+    fn composite_draws_the_skyline_and_labels_over_photos() {
+        let panorama = flat_panorama(Azimuth::NORTH);
+        let top = (0..401)
+            .find(|&y| panorama.distance_at(400, y).is_finite())
+            .unwrap();
+        // half of the panorama, at the same scale, a bit above the horizon
+        let calibration = Calibration {
+            heading: Azimuth::NORTH,
+            fov: 5_f64.to_radians(),
+            tilt: 50. * panorama.parameters().angle_per_pixel(),
+        };
+        let grey = [100, 100, 100, 255];
+        let mut photo = Image::new(401, 201);
+        for (x, y) in (0..201).flat_map(|y| (0..401).map(move |x| (x, y))) {
+            photo.set(x, y, grey);
+        }
+        let labels = [Label {
+            text: "Summit".to_string(),
+            x: 400,
+            label_y: top - 80,
+            summit_y: top,
+        }];
+
+        let mut unchanged = photo.clone();
+        composite(&mut unchanged, &calibration, &panorama, &labels, 0.);
+        assert_eq!(photo, unchanged);
+
+        composite(&mut photo, &calibration, &panorama, &labels, 0.5);
+        // the skyline, 50 rows higher in the photograph
+        let skyline = top - 50;
+        for x in [10, 100, 300, 390] {
+            let [r, g, b, a] = photo.get(x, skyline);
+            assert_eq!(255, a);
+            assert_eq!((178, 80, 70), (r, g, b));
+            assert_eq!(grey, photo.get(x, skyline + 5));
+            assert_eq!(grey, photo.get(x, skyline - 5));
+        }
+        // the leader line of the label, from the summit up
+        for y in skyline - 80..skyline {
+            assert_eq!([50, 50, 50, 255], photo.get(200, y));
+        }
+    }
+
+    #[test]
+    fn moon_is_drawn_in_the_sky_lit_by_the_sun() {
+        let (latitude, longitude) = (46.5_f64.to_radians(), 7_f64.to_radians());
+        // the waxing gibbous moon setting in the north-west in the night of
+        // March 19, 2024
+        let start = Utc.with_ymd_and_hms(2024, 3, 19, 0, 0, 0).unwrap();
+        let time = (0..24 * 60)
+            .map(|m| start + Duration::minutes(m))
+            .find(|&t| {
+                let elevation = moon::position(t, latitude, longitude).elevation;
+                (0.8..1.2).contains(&elevation.to_degrees())
+            })
+            .unwrap();
+        let position = moon::position(time, latitude, longitude);
+        let panorama = flat_panorama(position.azimuth);
+        let mut image = Image::new(801, 401);
+        moon(&mut image, &panorama, time);
+
+        let (cx, cy) = panorama
+            .parameters()
+            .pixel_for(position.azimuth, position.elevation)
+            .unwrap();
+        let radius = position.angular_diameter / 2. / panorama.parameters().angle_per_pixel();
+        assert!((15. ..30.).contains(&radius));
+        let pixels: Vec<(usize, usize)> = (0..401)
+            .flat_map(|y| (0..801).map(move |x| (x, y)))
+            .filter(|&(x, y)| image.get(x, y)[3] > 0)
+            .collect();
+        assert!(pixels
+            .iter()
+            .all(|&(x, y)| panorama.distance_at(x, y).is_infinite()));
+        assert!(pixels
+            .iter()
+            .all(|&(x, y)| { (x as f64 - cx).hypot(y as f64 - cy) < radius + 1.5 }));
+
+        let lit: Vec<&(usize, usize)> = pixels
+            .iter()
+            .filter(|&&(x, y)| image.get(x, y) == MOON_COLOR)
+            .collect();
+        let fraction = lit.len() as f64 / (std::f64::consts::PI * radius * radius);
+        assert_approx_eq!(position.illuminated_fraction, fraction, 0.1);
+        // the sun, below the horizon in the north-east, is to the right of
+        // the moon
+        let mean_x = lit.iter().map(|&&(x, _)| x as f64).sum::<f64>() / lit.len() as f64;
+        assert!(mean_x > cx + 1.);
+    }
+
+    #[test]
+    fn stars_are_drawn_in_the_night_sky() {
+        let (latitude, longitude) = (46.5_f64.to_radians(), 7_f64.to_radians());
+        // Sirius rising in the south-east on the evening of January 15, 2024
+        let start = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        let sirius = |t| {
+            night_sky::stars(t, latitude, longitude)
+                .into_iter()
+                .find(|o| o.name == "Sirius")
+                .unwrap()
+        };
+        let night = (0..12 * 60)
+            .map(|m| start + Duration::minutes(m))
+            .find(|&t| (0.8..1.2).contains(&sirius(t).elevation.to_degrees()))
+            .unwrap();
+        let sirius = sirius(night);
+        let panorama = flat_panorama(sirius.azimuth);
+        let (x, y) = panorama
+            .parameters()
+            .pixel_for(sirius.azimuth, sirius.elevation)
+            .unwrap();
+
+        let mut image = Image::new(801, 401);
+        stars(&mut image, &panorama, night);
+        assert_eq!(
+            STAR_COLOR,
+            image.get(x.round() as usize, y.round() as usize)
+        );
+        assert!((0..401)
+            .flat_map(|y| (0..801).map(move |x| (x, y)))
+            .filter(|&(x, y)| image.get(x, y)[3] > 0)
+            .all(|(x, y)| panorama.distance_at(x, y).is_infinite()));
+
+        let mut day = Image::new(801, 401);
+        stars(&mut day, &panorama, night - Duration::hours(10));
+        assert_eq!(0, covered(&day));
+    }
+}
@@ -0,0 +1,267 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::{azimuth::Azimuth, geo::GeoPoint, labels::Summit, panorama::PanoramaParameters};
+
+/// Interpreter of the main instance of the Overpass API.
+pub const OVERPASS_URL: &str = "https://overpass-api.de/api/interpreter";
+/// Lines per degree of the grid to which cached bounding boxes are enlarged,
+/// so that nearby panoramas share their summits.
+const CACHE_GRID: f64 = 10.;
+
+/// Bounding box of points, in degrees.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub south: f64,
+    pub west: f64,
+    pub north: f64,
+    pub east: f64,
+}
+
+impl BoundingBox {
+    /// Bounding box of the terrain seen in panoramas of `parameters`: the
+    /// sector of their horizontal field of view around the observer, up to
+    /// their maximum distance.
+    pub fn of_panorama(parameters: &PanoramaParameters) -> BoundingBox {
+        let observer = parameters.observer_position();
+        let fov = parameters.horizontal_field_of_view();
+        let first = parameters.center_azimuth().radians() - fov / 2.;
+        let steps = fov.to_degrees().ceil().max(1.) as usize;
+        let arc = (0..=steps).map(|i| {
+            let azimuth = Azimuth::new(first + fov * i as f64 / steps as f64);
+            observer.destination(azimuth, parameters.max_distance())
+        });
+
+        let degrees = |p: GeoPoint| (p.longitude().to_degrees(), p.latitude().to_degrees());
+        let (lon, lat) = degrees(observer);
+        let start = BoundingBox {
+            south: lat,
+            west: lon,
+            north: lat,
+            east: lon,
+        };
+        arc.map(degrees)
+            .fold(start, |bbox, (lon, lat)| BoundingBox {
+                south: bbox.south.min(lat),
+                west: bbox.west.min(lon),
+                north: bbox.north.max(lat),
+                east: bbox.east.max(lon),
+            })
+    }
+
+    /// Smallest box containing this one with sides on the cache grid.
+    fn snapped(self) -> BoundingBox {
+        let down = |v: f64| (v * CACHE_GRID).floor() / CACHE_GRID;
+        let up = |v: f64| (v * CACHE_GRID).ceil() / CACHE_GRID;
+        BoundingBox {
+            south: down(self.south),
+            west: down(self.west),
+            north: up(self.north),
+            east: up(self.east),
+        }
+    }
+}
+
+/// Overpass QL query of the named peaks of OpenStreetMap within `bbox`,
+/// answered in JSON.
+pub fn query(bbox: BoundingBox) -> String {
+    format!(
+        "[out:json][timeout:90];node[\"natural\"=\"peak\"][\"name\"]({},{},{},{});out;",
+        bbox.south, bbox.west, bbox.north, bbox.east
+    )
+}
+
+#[derive(Deserialize)]
+struct Response {
+    elements: Vec<Element>,
+}
+
+#[derive(Deserialize)]
+struct Element {
+    lat: f64,
+    lon: f64,
+    #[serde(default)]
+    tags: HashMap<String, String>,
+}
+
+/// Summits of an Overpass JSON response to a [`query`], skipping the peaks
+/// without a name or a numeric elevation in meters.
+pub fn parse_summits(json: &str) -> io::Result<Vec<Summit>> {
+    let response: Response =
+        serde_json::from_str(json).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+    Ok(response
+        .elements
+        .into_iter()
+        .filter_map(|element| {
+            let name = element.tags.get("name")?;
+            let ele = element.tags.get("ele")?.trim();
+            let elevation = ele.strip_suffix('m').unwrap_or(ele).trim().parse().ok()?;
+            Some(Summit {
+                name: name.clone(),
+                position: GeoPoint::from_degrees(element.lon, element.lat).ok()?,
+                elevation,
+            })
+        })
+        .collect())
+}
+
+/// Directory caching the responses of the Overpass API, one file per
+/// bounding box.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OverpassCache {
+    dir: PathBuf,
+}
+
+impl OverpassCache {
+    pub fn new(dir: impl Into<PathBuf>) -> OverpassCache {
+        OverpassCache { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Named peaks within `bbox` enlarged to the cache grid, read from the
+    /// cache or else fetched by calling `fetch` with their [`query`] and
+    /// cached if the response is valid.
+    pub fn summits(
+        &self,
+        bbox: BoundingBox,
+        fetch: impl FnOnce(&str) -> io::Result<String>,
+    ) -> io::Result<Vec<Summit>> {
+        let bbox = bbox.snapped();
+        let path = self.dir.join(format!(
+            "peaks_{:.1}_{:.1}_{:.1}_{:.1}.json",
+            bbox.south, bbox.west, bbox.north, bbox.east
+        ));
+        match fs::read_to_string(&path) {
+            Ok(json) => return parse_summits(&json),
+            Err(e) if e.kind() != ErrorKind::NotFound => return Err(e),
+            Err(_) => {}
+        }
+
+        let json = fetch(&query(bbox))?;
+        let summits = parse_summits(&json)?;
+        fs::create_dir_all(&self.dir)?;
+        let mut partial = path.clone().into_os_string();
+        partial.push(".partial");
+        fs::write(&partial, json)?;
+        fs::rename(partial, path)?;
+        Ok(summits)
+    }
+}
+
+/// Response of the Overpass API at [`OVERPASS_URL`] to `query`.
+#[cfg(feature = "overpass")]
+pub fn fetch(query: &str) -> io::Result<String> {
+    ureq::post(OVERPASS_URL)
+        .send_form(&[("data", query)])
+        .map_err(io::Error::other)?
+        .into_string()
+}
+
+#[cfg(test)]
+mod overpass_tests {
+    use super::*;
+    use crate::distance;
+
+    const RESPONSE: &str = r#"{
+        "version": 0.6,
+        "elements": [
+            {"type": "node", "id": 1, "lat": 46.5376, "lon": 7.9628,
+             "tags": {"natural": "peak", "name": "Jungfrau", "ele": "4158"}},
+            {"type": "node", "id": 2, "lat": 46.5775, "lon": 8.0053,
+             "tags": {"natural": "peak", "name": "Eiger", "ele": "3967 m"}},
+            {"type": "node", "id": 3, "lat": 46.55, "lon": 7.9,
+             "tags": {"natural": "peak", "name": "Unknown"}},
+            {"type": "node", "id": 4, "lat": 46.56, "lon": 7.95}
+        ]
+    }"#;
+
+    #[test]
+    fn parse_summits_skips_peaks_without_name_or_elevation() {
+        let summits = parse_summits(RESPONSE).unwrap();
+        assert_eq!(2, summits.len());
+        assert_eq!("Jungfrau", summits[0].name);
+        assert_eq!(4158., summits[0].elevation);
+        assert_eq!(
+            GeoPoint::from_degrees(7.9628, 46.5376).unwrap(),
+            summits[0].position
+        );
+        assert_eq!(3967., summits[1].elevation);
+        assert!(parse_summits("<osm/>").is_err());
+    }
+
+    #[test]
+    fn query_is_ordered_south_west_north_east() {
+        let bbox = BoundingBox {
+            south: 46.,
+            west: 7.,
+            north: 47.5,
+            east: 8.,
+        };
+        assert!(query(bbox).ends_with("node[\"natural\"=\"peak\"][\"name\"](46,7,47.5,8);out;"));
+    }
+
+    #[test]
+    fn of_panorama_bounds_the_field_of_view() {
+        let parameters = |azimuth: f64, fov: f64| {
+            PanoramaParameters::new(
+                GeoPoint::from_degrees(7.5, 46.5).unwrap(),
+                1000.,
+                Azimuth::from_degrees(azimuth),
+                fov.to_radians(),
+                50_000.,
+                100,
+                50,
+            )
+            .unwrap()
+        };
+        let d_lat = 50_000. / distance::meters_per_deg_lat();
+
+        let all_around = BoundingBox::of_panorama(&parameters(0., 360.));
+        assert!((all_around.north - 46.5 - d_lat).abs() < 1e-3);
+        assert!((46.5 - all_around.south - d_lat).abs() < 1e-3);
+        assert!(all_around.west < 7. && all_around.east > 8.);
+
+        let north = BoundingBox::of_panorama(&parameters(0., 60.));
+        assert_eq!(46.5, north.south);
+        assert!((north.north - 46.5 - d_lat).abs() < 1e-3);
+        assert!(north.west < 7.5 && north.east > 7.5);
+    }
+
+    #[test]
+    fn summits_are_fetched_once_and_cached() {
+        let dir = std::env::temp_dir().join("alpano_overpass_tests");
+        let _ = fs::remove_dir_all(&dir);
+        let cache = OverpassCache::new(&dir);
+        let bbox = BoundingBox {
+            south: 46.52,
+            west: 7.91,
+            north: 46.61,
+            east: 8.07,
+        };
+
+        let failed = cache.summits(bbox, |_| Err(io::Error::other("offline")));
+        assert!(failed.is_err());
+        let mut queried = String::new();
+        let summits = cache
+            .summits(bbox, |query| {
+                queried = query.to_string();
+                Ok(RESPONSE.to_string())
+            })
+            .unwrap();
+        assert!(queried.contains("(46.5,7.9,46.7,8.1)"));
+        assert_eq!(2, summits.len());
+        let cached = cache
+            .summits(bbox, |_| panic!("cached summits fetched again"))
+            .unwrap();
+        assert_eq!(summits, cached);
+    }
+}
@@ -0,0 +1,660 @@
+use std::f64::consts::FRAC_PI_2;
+
+use crate::{
+    atmosphere::Atmosphere,
+    azimuth::Azimuth,
+    image::Image,
+    math::lerp,
+    panorama::{Panorama, PanoramaParameters},
+};
+
+/// Value of one channel (hue, saturation, brightness, opacity...) of every
+/// pixel of an image.
+///
+/// Closures taking the coordinates of a pixel are channel painters, and
+/// painters can be combined with the provided methods.
+pub trait ChannelPainter {
+    fn value_at(&self, x: usize, y: usize) -> f64;
+
+    fn add(self, that: impl ChannelPainter) -> impl ChannelPainter
+    where
+        Self: Sized,
+    {
+        move |x, y| self.value_at(x, y) + that.value_at(x, y)
+    }
+
+    fn sub(self, that: impl ChannelPainter) -> impl ChannelPainter
+    where
+        Self: Sized,
+    {
+        move |x, y| self.value_at(x, y) - that.value_at(x, y)
+    }
+
+    fn mul(self, that: impl ChannelPainter) -> impl ChannelPainter
+    where
+        Self: Sized,
+    {
+        move |x, y| self.value_at(x, y) * that.value_at(x, y)
+    }
+
+    fn div(self, that: impl ChannelPainter) -> impl ChannelPainter
+    where
+        Self: Sized,
+    {
+        move |x, y| self.value_at(x, y) / that.value_at(x, y)
+    }
+
+    fn map(self, f: impl Fn(f64) -> f64) -> impl ChannelPainter
+    where
+        Self: Sized,
+    {
+        move |x, y| f(self.value_at(x, y))
+    }
+
+    /// Values clamped to `[0, 1]`.
+    fn clamped(self) -> impl ChannelPainter
+    where
+        Self: Sized,
+    {
+        self.map(|v| v.clamp(0., 1.))
+    }
+
+    /// Fractional part of the values, which cycle through `[0, 1)`.
+    fn cycling(self) -> impl ChannelPainter
+    where
+        Self: Sized,
+    {
+        self.map(|v| v.rem_euclid(1.))
+    }
+
+    /// Values `v` replaced by `1 - v`.
+    fn inverted(self) -> impl ChannelPainter
+    where
+        Self: Sized,
+    {
+        self.map(|v| 1. - v)
+    }
+}
+
+impl<F: Fn(usize, usize) -> f64> ChannelPainter for F {
+    fn value_at(&self, x: usize, y: usize) -> f64 {
+        self(x, y)
+    }
+}
+
+/// Color of every pixel of an image, as RGBA.
+pub trait ImagePainter {
+    fn color_at(&self, x: usize, y: usize) -> [u8; 4];
+
+    /// Paint an image of the given dimensions.
+    fn paint(&self, width: usize, height: usize) -> Image {
+        let mut image = Image::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                image.set(x, y, self.color_at(x, y));
+            }
+        }
+        image
+    }
+
+    /// Colors composited over those of `background`, according to their
+    /// opacity.
+    fn over(self, background: impl ImagePainter) -> impl ImagePainter
+    where
+        Self: Sized,
+    {
+        move |x, y| {
+            let top = self.color_at(x, y).map(|c| c as f64 / 255.);
+            let bottom = background.color_at(x, y).map(|c| c as f64 / 255.);
+            let opacity = top[3] + bottom[3] * (1. - top[3]);
+            if opacity == 0. {
+                return [0; 4];
+            }
+            let mix =
+                |i: usize| (top[i] * top[3] + bottom[i] * bottom[3] * (1. - top[3])) / opacity;
+            [
+                to_byte(mix(0)),
+                to_byte(mix(1)),
+                to_byte(mix(2)),
+                to_byte(opacity),
+            ]
+        }
+    }
+}
+
+impl<F: Fn(usize, usize) -> [u8; 4]> ImagePainter for F {
+    fn color_at(&self, x: usize, y: usize) -> [u8; 4] {
+        self(x, y)
+    }
+}
+
+/// Painter whose value is `v` everywhere.
+pub fn constant(v: f64) -> impl ChannelPainter {
+    move |_, _| v
+}
+
+/// Painter of the colors of the given hue (in degrees), saturation,
+/// brightness and opacity, the last three in `[0, 1]`.
+pub fn hsb(
+    hue: impl ChannelPainter,
+    saturation: impl ChannelPainter,
+    brightness: impl ChannelPainter,
+    opacity: impl ChannelPainter,
+) -> impl ImagePainter {
+    move |x, y| {
+        let [r, g, b] = hsb_to_rgb(
+            hue.value_at(x, y),
+            saturation.value_at(x, y),
+            brightness.value_at(x, y),
+        );
+        [r, g, b, to_byte(opacity.value_at(x, y))]
+    }
+}
+
+/// Painter of the shades of grey of the given brightness and opacity, both in
+/// `[0, 1]`.
+pub fn gray(brightness: impl ChannelPainter, opacity: impl ChannelPainter) -> impl ImagePainter {
+    move |x, y| {
+        let v = to_byte(brightness.value_at(x, y));
+        [v, v, v, to_byte(opacity.value_at(x, y))]
+    }
+}
+
+/// Painter of the classic Alpano look: the hue cycles with the distance, the
+/// colors fade with the distance and darken with the slope, and the sky is
+/// transparent.
+pub fn classic(panorama: &Panorama) -> impl ImagePainter + '_ {
+    hsb(
+        distance(panorama)
+            .div(constant(100_000.))
+            .cycling()
+            .mul(constant(360.)),
+        distance(panorama)
+            .div(constant(200_000.))
+            .clamped()
+            .inverted(),
+        slope(panorama)
+            .mul(constant(2. / std::f64::consts::PI))
+            .inverted()
+            .mul(constant(0.7))
+            .add(constant(0.3)),
+        distance(panorama).map(|d| if d.is_infinite() { 0. } else { 1. }),
+    )
+}
+
+/// Colors of the terrain by distance, as RGB in `[0, 1]`, evenly spread from
+/// the observer to the maximum distance, see [`palette`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Palette {
+    colors: Vec<[f64; 3]>,
+}
+
+impl Palette {
+    /// Palette of the given colors, from the nearest to the farthest.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `colors` is empty.
+    pub fn new(colors: Vec<[f64; 3]>) -> Palette {
+        assert!(!colors.is_empty(), "a palette needs at least one color");
+        Palette { colors }
+    }
+
+    /// Viridis, from yellow near to dark blue far: its lightness changes
+    /// steadily, which keeps distances apart for colorblind viewers and in
+    /// greyscale.
+    pub fn colorblind() -> Palette {
+        Palette::new(vec![
+            [0.993, 0.906, 0.144],
+            [0.369, 0.789, 0.383],
+            [0.128, 0.567, 0.551],
+            [0.229, 0.322, 0.546],
+            [0.267, 0.005, 0.329],
+        ])
+    }
+
+    /// From black near to bright yellow far, for the strongest contrast
+    /// between the near and the far terrain.
+    pub fn high_contrast() -> Palette {
+        Palette::new(vec![[0., 0., 0.], [0.45, 0.35, 0.], [1., 0.9, 0.]])
+    }
+
+    pub fn colors(&self) -> &[[f64; 3]] {
+        &self.colors
+    }
+
+    /// Color at `t`, in `[0, 1]` from the first color to the last,
+    /// interpolated between the two nearest.
+    pub fn color_at(&self, t: f64) -> [f64; 3] {
+        let position = t.clamp(0., 1.) * (self.colors.len() - 1) as f64;
+        let i = (position.floor() as usize).min(self.colors.len().saturating_sub(2));
+        let Some(next) = self.colors.get(i + 1) else {
+            return self.colors[i];
+        };
+        let v = position - i as f64;
+        [0, 1, 2].map(|j| lerp(v, self.colors[i][j]..=next[j]))
+    }
+
+    /// Contrast ratio between the nearest and the farthest colors, from 1
+    /// for equally light colors to 21 for black and white, as defined by the
+    /// Web Content Accessibility Guidelines.
+    pub fn contrast_ratio(&self) -> f64 {
+        let first = relative_luminance(self.colors[0]);
+        let last = relative_luminance(self.colors[self.colors.len() - 1]);
+        (first.max(last) + 0.05) / (first.min(last) + 0.05)
+    }
+}
+
+/// Relative luminance of the sRGB color `rgb`, in `[0, 1]`, as defined by
+/// the Web Content Accessibility Guidelines.
+pub fn relative_luminance(rgb: [f64; 3]) -> f64 {
+    let [r, g, b] = rgb.map(|c| {
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    });
+    0.2126 * r + 0.7152 * g + 0.0722 * b
+}
+
+/// Painter of the colors of `palette` by distance, darkened with the slope
+/// like [`classic`], and a transparent sky.
+pub fn palette<'a>(panorama: &'a Panorama, palette: &'a Palette) -> impl ImagePainter + 'a {
+    let max_distance = panorama.parameters().max_distance();
+    let brightness = slope(panorama)
+        .mul(constant(2. / std::f64::consts::PI))
+        .inverted()
+        .mul(constant(0.7))
+        .add(constant(0.3));
+    move |x, y| {
+        let d = panorama.distance_at(x, y);
+        if d.is_infinite() {
+            return [0; 4];
+        }
+        let b = brightness.value_at(x, y);
+        let [r, g, b] = palette.color_at(d / max_distance).map(|c| to_byte(c * b));
+        [r, g, b, 255]
+    }
+}
+
+/// Painter of shaded relief: shades of grey lit by the sun at the given
+/// azimuth and altitude (in radians), see [`hillshade`], and a transparent
+/// sky.
+pub fn shaded_relief(
+    panorama: &Panorama,
+    sun_azimuth: Azimuth,
+    sun_altitude: f64,
+) -> impl ImagePainter + '_ {
+    gray(
+        hillshade(panorama, sun_azimuth, sun_altitude),
+        distance(panorama).map(|d| if d.is_infinite() { 0. } else { 1. }),
+    )
+}
+
+/// Colors of `painter` blended toward the color of haze, as RGB in `[0, 1]`,
+/// as the light of the terrain seen at each pixel of `panorama` is
+/// attenuated by `atmosphere` over its distance. The sky is left unchanged.
+pub fn haze<'a>(
+    painter: impl ImagePainter + 'a,
+    panorama: &'a Panorama,
+    atmosphere: Atmosphere,
+    color: [f64; 3],
+) -> impl ImagePainter + 'a {
+    move |x, y| {
+        let [r, g, b, a] = painter.color_at(x, y);
+        let d = panorama.distance_at(x, y);
+        if d.is_infinite() {
+            return [r, g, b, a];
+        }
+        let t = atmosphere.transmittance(d);
+        let mix = |c: u8, h: f64| to_byte(lerp(t, h..=c as f64 / 255.));
+        [mix(r, color[0]), mix(g, color[1]), mix(b, color[2]), a]
+    }
+}
+
+/// Colors of `painter` darkened by `darkness`, in `[0, 1]`, on the terrain
+/// of `panorama` in the shadow of the sun, see
+/// [`PanoramaComputer::with_shadows`](crate::panorama::PanoramaComputer::with_shadows).
+pub fn shadows<'a>(
+    painter: impl ImagePainter + 'a,
+    panorama: &'a Panorama,
+    darkness: f64,
+) -> impl ImagePainter + 'a {
+    move |x, y| {
+        let [r, g, b, a] = painter.color_at(x, y);
+        if !panorama.is_in_shadow_at(x, y) {
+            return [r, g, b, a];
+        }
+        let darken = |c: u8| to_byte(c as f64 / 255. * (1. - darkness));
+        [darken(r), darken(g), darken(b), a]
+    }
+}
+
+/// Painter of an opaque sky, whose color goes from `horizon` at and below the
+/// horizon to `zenith` at the zenith with the altitude of the rays of the
+/// pixels, the horizon color fading quickly like its glow. Colors are RGB in
+/// `[0, 1]`, like those of
+/// [`atmosphere::sky_colors`](crate::atmosphere::sky_colors).
+pub fn sky(
+    parameters: &PanoramaParameters,
+    horizon: [f64; 3],
+    zenith: [f64; 3],
+) -> impl ImagePainter {
+    let height = altitude(parameters)
+        .div(constant(FRAC_PI_2))
+        .clamped()
+        .map(f64::sqrt);
+    move |x, y| {
+        let t = height.value_at(x, y);
+        let [r, g, b] = [0, 1, 2].map(|i| to_byte(lerp(t, horizon[i]..=zenith[i])));
+        [r, g, b, 255]
+    }
+}
+
+/// Altitude of the ray of each pixel of a panorama with the given parameters,
+/// in radians.
+pub fn altitude(parameters: &PanoramaParameters) -> impl ChannelPainter {
+    let parameters = *parameters;
+    move |x, y| parameters.direction_at(x as f64, y as f64).1
+}
+
+/// Distance to the terrain seen at each pixel of `panorama`.
+pub fn distance(panorama: &Panorama) -> impl ChannelPainter + '_ {
+    move |x, y| panorama.distance_at(x, y)
+}
+
+/// Elevation of the terrain seen at each pixel of `panorama`.
+pub fn elevation(panorama: &Panorama) -> impl ChannelPainter + '_ {
+    move |x, y| panorama.elevation_at(x, y)
+}
+
+/// Slope of the terrain seen at each pixel of `panorama`.
+pub fn slope(panorama: &Panorama) -> impl ChannelPainter + '_ {
+    move |x, y| panorama.slope_at(x, y)
+}
+
+/// Aspect of the terrain seen at each pixel of `panorama`, in radians.
+pub fn aspect(panorama: &Panorama) -> impl ChannelPainter + '_ {
+    move |x, y| panorama.aspect_at(x, y).radians()
+}
+
+/// Illumination in `[0, 1]` of the terrain seen at each pixel of `panorama`
+/// by the sun at the given azimuth and altitude (in radians): the cosine of
+/// the angle between the sun and the normal of the terrain, 0 on the terrain
+/// facing away from the sun. Shadows cast by the terrain are ignored.
+pub fn hillshade(
+    panorama: &Panorama,
+    sun_azimuth: Azimuth,
+    sun_altitude: f64,
+) -> impl ChannelPainter + '_ {
+    let zenith = FRAC_PI_2 - sun_altitude;
+    let illumination = move |x, y| {
+        let slope = panorama.slope_at(x, y);
+        let aspect = panorama.aspect_at(x, y);
+        zenith.cos() * slope.cos()
+            + zenith.sin() * slope.sin() * (sun_azimuth.radians() - aspect.radians()).cos()
+    };
+    illumination.clamped()
+}
+
+/// Largest difference between the distance of each pixel of `panorama` and
+/// those of its neighbors, which is large on the ridges.
+pub fn max_distance_to_neighbors(panorama: &Panorama) -> impl ChannelPainter + '_ {
+    let parameters = panorama.parameters();
+    // neighbors outside of the image are infinitely far, like the sky
+    let distance_or_sky = move |x: isize, y: isize| {
+        if parameters.sample_grid().contains(x as i32, y as i32) {
+            panorama.distance_at(x as usize, y as usize)
+        } else {
+            f64::INFINITY
+        }
+    };
+
+    move |x, y| {
+        let (x, y) = (x as isize, y as isize);
+        let d = distance_or_sky(x, y);
+        [(-1, 0), (1, 0), (0, -1), (0, 1)]
+            .iter()
+            .map(|(dx, dy)| distance_or_sky(x + dx, y + dy) - d)
+            .fold(f64::NEG_INFINITY, f64::max)
+    }
+}
+
+/// Convert a color given by its hue (in degrees), saturation and brightness
+/// to RGB.
+fn hsb_to_rgb(hue: f64, saturation: f64, brightness: f64) -> [u8; 3] {
+    let h = hue.rem_euclid(360.) / 60.;
+    let s = saturation.clamp(0., 1.);
+    let v = brightness.clamp(0., 1.);
+
+    let c = v * s;
+    let x = c * (1. - (h % 2. - 1.).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (c, x, 0.),
+        1 => (x, c, 0.),
+        2 => (0., c, x),
+        3 => (0., x, c),
+        4 => (x, 0., c),
+        _ => (c, 0., x),
+    };
+    let m = v - c;
+    [to_byte(r + m), to_byte(g + m), to_byte(b + m)]
+}
+
+fn to_byte(v: f64) -> u8 {
+    (v.clamp(0., 1.) * 255.).round() as u8
+}
+
+#[cfg(test)]
+mod painter_tests {
+    use super::*;
+    use crate::{
+        dem::ContinuousElevationModel,
+        fixtures::{compute, flat, flat_panorama, flat_parameters, terrain},
+        geo::GeoPoint,
+        panorama::{PanoramaComputer, PanoramaParameters},
+    };
+    use assert_approx_eq::assert_approx_eq;
+    use rand::Rng;
+
+    #[test]
+    fn combinators_are_correct() {
+        let x = |x: usize, _y: usize| x as f64;
+        let y = |_x: usize, y: usize| y as f64;
+        assert_eq!(7., x.add(y).value_at(3, 4));
+        assert_eq!(-1., x.sub(y).value_at(3, 4));
+        assert_eq!(12., x.mul(y).value_at(3, 4));
+        assert_eq!(0.75, x.div(y).value_at(3, 4));
+        assert_eq!(9., x.map(|v| v * v).value_at(3, 4));
+        assert_eq!(1., x.clamped().value_at(3, 4));
+        assert_eq!(0., x.clamped().value_at(0, 4));
+        assert_approx_eq!(0.5, x.div(constant(2.)).cycling().value_at(3, 4), 1e-12);
+        assert_eq!(-2., x.inverted().value_at(3, 4));
+    }
+
+    #[test]
+    fn hsb_to_rgb_is_correct_on_known_values() {
+        assert_eq!([255, 0, 0], hsb_to_rgb(0., 1., 1.));
+        assert_eq!([0, 255, 0], hsb_to_rgb(120., 1., 1.));
+        assert_eq!([0, 0, 255], hsb_to_rgb(240., 1., 1.));
+        assert_eq!([255, 0, 0], hsb_to_rgb(360., 1., 1.));
+        assert_eq!([255, 255, 0], hsb_to_rgb(60., 1., 1.));
+        assert_eq!([128, 128, 128], hsb_to_rgb(200., 0., 0.5));
+        assert_eq!([0, 0, 0], hsb_to_rgb(200., 1., 0.));
+    }
+
+    #[test]
+    fn hsb_to_rgb_keeps_brightness_as_max_component() {
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..500 {
+            let b: f64 = rng.gen();
+            let rgb = hsb_to_rgb(rng.gen_range(0. ..360.), rng.gen(), b);
+            assert_eq!(to_byte(b), *rgb.iter().max().unwrap());
+        }
+    }
+
+    #[test]
+    fn paint_uses_every_channel() {
+        let painter = hsb(
+            constant(120.),
+            constant(1.),
+            |x: usize, _y: usize| x as f64,
+            constant(0.5),
+        );
+        let image = painter.paint(2, 3);
+        assert_eq!((2, 3), (image.width(), image.height()));
+        assert_eq!([0, 0, 0, 128], image.get(0, 2));
+        assert_eq!([0, 255, 0, 128], image.get(1, 2));
+
+        let image = gray(|_x: usize, y: usize| y as f64 / 2., constant(1.)).paint(2, 3);
+        assert_eq!([128, 128, 128, 255], image.get(0, 1));
+    }
+
+    #[test]
+    fn palette_interpolates_its_colors() {
+        let palette = Palette::new(vec![[0., 0., 0.], [1., 0.5, 0.], [1., 1., 1.]]);
+        assert_eq!([0., 0., 0.], palette.color_at(0.));
+        assert_eq!([0.5, 0.25, 0.], palette.color_at(0.25));
+        assert_eq!([1., 0.5, 0.], palette.color_at(0.5));
+        assert_eq!([1., 1., 1.], palette.color_at(1.));
+        assert_eq!([1., 1., 1.], palette.color_at(1.5));
+        assert_eq!([0., 0., 0.], palette.color_at(-1.));
+        assert_eq!([0.2; 3], Palette::new(vec![[0.2; 3]]).color_at(0.7));
+    }
+
+    #[test]
+    fn contrast_ratio_follows_the_accessibility_guidelines() {
+        let black_and_white = Palette::new(vec![[0.; 3], [0.5; 3], [1.; 3]]);
+        assert_approx_eq!(21., black_and_white.contrast_ratio(), 1e-9);
+        assert_approx_eq!(1., Palette::new(vec![[0.3; 3]]).contrast_ratio(), 1e-9);
+        assert_approx_eq!(0.2126, relative_luminance([1., 0., 0.]), 1e-9);
+        // the presets keep the near and far terrain apart
+        assert!(Palette::colorblind().contrast_ratio() > 4.5);
+        assert!(Palette::high_contrast().contrast_ratio() > 15.);
+    }
+
+    #[test]
+    fn palette_paints_the_terrain_by_distance() {
+        let panorama = flat_panorama();
+        let colors = Palette::new(vec![[1., 0., 0.], [0., 0., 1.]]);
+        let image = palette(&panorama, &colors).paint(11, 11);
+        assert_eq!(0, image.get(5, 0)[3]);
+        let (near, far) = (image.get(5, 10), image.get(5, 6));
+        assert_eq!((255, 255), (near[3], far[3]));
+        assert!(near[0] > far[0] && near[2] < far[2]);
+        assert_eq!(0, near[1]);
+    }
+
+    #[test]
+    fn hillshade_lights_terrain_facing_the_sun() {
+        // terrain descending eastward, seen looking north
+        let dem = terrain(|x, _| (1000 - 50 * (x - 60)) as f64);
+        let parameters = PanoramaParameters::new(
+            GeoPoint::from_degrees(7.5, 46.5).unwrap(),
+            2000.,
+            Azimuth::NORTH,
+            10_f64.to_radians(),
+            50_000.,
+            11,
+            11,
+        )
+        .unwrap();
+        let panorama = compute(dem, &parameters);
+        assert!(panorama.distance_at(5, 10).is_finite());
+        assert_approx_eq!(
+            Azimuth::EAST.radians(),
+            aspect(&panorama).value_at(5, 10),
+            1e-6
+        );
+
+        let altitude = 30_f64.to_radians();
+        let east = hillshade(&panorama, Azimuth::EAST, altitude).value_at(5, 10);
+        let west = hillshade(&panorama, Azimuth::WEST, altitude).value_at(5, 10);
+        let north = hillshade(&panorama, Azimuth::NORTH, altitude).value_at(5, 10);
+        assert!(east > north && north > west);
+        assert_approx_eq!(altitude.sin() * panorama.slope_at(5, 10).cos(), north, 1e-9);
+
+        let image = shaded_relief(&panorama, Azimuth::EAST, altitude).paint(11, 11);
+        assert_eq!(0, image.get(5, 0)[3]);
+        assert_eq!(255, image.get(5, 10)[3]);
+    }
+
+    fn parameters(width: usize, height: usize) -> PanoramaParameters {
+        PanoramaParameters::new(
+            GeoPoint::from_degrees(7.5, 46.5).unwrap(),
+            2000.,
+            Azimuth::NORTH,
+            std::f64::consts::TAU,
+            50_000.,
+            width,
+            height,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn sky_goes_from_horizon_to_zenith() {
+        // 181 rows of 1°, from 90° to -90° of altitude
+        let p = parameters(361, 181);
+        let sky = sky(&p, [1., 0.5, 0.], [0., 0., 1.]);
+        assert_eq!([0, 0, 255, 255], sky.color_at(0, 0));
+        assert_eq!([255, 128, 0, 255], sky.color_at(0, 90));
+        assert_eq!([255, 128, 0, 255], sky.color_at(0, 180));
+        let mid = sky.color_at(0, 45);
+        assert!(mid[0] < 128 && mid[2] > 128);
+
+        for y in 1..=90 {
+            assert!(sky.color_at(0, y - 1)[2] >= sky.color_at(0, y)[2]);
+        }
+    }
+
+    #[test]
+    fn over_composites_according_to_opacity() {
+        let background = |_x: usize, _y: usize| [0, 0, 255, 255];
+        let top = |x: usize, _y: usize| [255, 0, 0, [0, 128, 255][x]];
+        let image = top.over(background).paint(3, 1);
+        assert_eq!([0, 0, 255, 255], image.get(0, 0));
+        assert_eq!([128, 0, 127, 255], image.get(1, 0));
+        assert_eq!([255, 0, 0, 255], image.get(2, 0));
+
+        let transparent = |_x: usize, _y: usize| [0; 4];
+        assert_eq!([0; 4], transparent.over(transparent).color_at(0, 0));
+        assert_eq!([255, 0, 0, 128], top.over(transparent).color_at(1, 0));
+    }
+
+    #[test]
+    fn haze_veils_distant_terrain() {
+        let panorama = flat_panorama();
+        let black = |_x: usize, _y: usize| [0, 0, 0, 255];
+        let white = [1., 1., 1.];
+
+        let clear = haze(black, &panorama, Atmosphere::new(0.), white);
+        assert_eq!([0, 0, 0, 255], clear.color_at(5, 10));
+        let hazy = haze(black, &panorama, Atmosphere::from_visibility_km(10.), white);
+        let (near, far) = (hazy.color_at(5, 10), hazy.color_at(5, 6));
+        assert!(0 < near[0] && near[0] < far[0]);
+        let t = Atmosphere::from_visibility_km(10.).transmittance(panorama.distance_at(5, 10));
+        assert_eq!(to_byte(1. - t), near[0]);
+        assert_eq!([0, 0, 0, 255], hazy.color_at(5, 0));
+    }
+
+    #[test]
+    fn shadows_darken_shadowed_terrain() {
+        let parameters = flat_parameters();
+        let white = |_x: usize, _y: usize| [255, 255, 255, 255];
+
+        let day = PanoramaComputer::new(ContinuousElevationModel::new(flat(0.)))
+            .with_shadows(Azimuth::WEST, 30_f64.to_radians())
+            .compute(&parameters);
+        assert_eq!([255; 4], shadows(white, &day, 0.6).color_at(5, 10));
+        // everything is in shadow once the sun has set
+        let night = PanoramaComputer::new(ContinuousElevationModel::new(flat(0.)))
+            .with_shadows(Azimuth::WEST, -5_f64.to_radians())
+            .compute(&parameters);
+        let painter = shadows(white, &night, 0.6);
+        assert_eq!([102, 102, 102, 255], painter.color_at(5, 10));
+        assert_eq!([255; 4], painter.color_at(5, 0));
+    }
+}
@@ -0,0 +1,154 @@
+use std::{
+    fs,
+    io::{ErrorKind, Result},
+    path::{Path, PathBuf},
+};
+
+use super::Panorama;
+
+/// Directory of computed panoramas, saved in the format of
+/// [`Panorama::save`] under the hash of everything their computation
+/// depends on, so that painting a panorama again with other options does not
+/// compute it again, see [`PanoramaCache::key`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PanoramaCache {
+    dir: PathBuf,
+    full_precision: bool,
+}
+
+impl PanoramaCache {
+    pub fn new(dir: impl Into<PathBuf>) -> PanoramaCache {
+        PanoramaCache {
+            dir: dir.into(),
+            full_precision: false,
+        }
+    }
+
+    /// Save the panoramas at full precision instead, so that a cached
+    /// panorama is the same as the computed one, see
+    /// [`Panorama::save_full_precision`]. The keys of such panoramas should
+    /// differ from those of the others.
+    pub fn with_full_precision(self) -> PanoramaCache {
+        PanoramaCache {
+            full_precision: true,
+            ..self
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn is_full_precision(&self) -> bool {
+        self.full_precision
+    }
+
+    /// Path of the file of the panorama cached under `key`.
+    pub fn path(&self, key: u64) -> PathBuf {
+        self.dir.join(format!("{:016x}.panorama", key))
+    }
+
+    /// Panorama cached under `key`, or `None` if there is none.
+    pub fn load(&self, key: u64) -> Result<Option<Panorama>> {
+        match Panorama::load(self.path(key)) {
+            Ok(panorama) => Ok(Some(panorama)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Cache `panorama` under `key`, replacing the previous panorama cached
+    /// under it only once it is fully written.
+    pub fn save(&self, key: u64, panorama: &Panorama) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.path(key);
+        let mut partial = path.clone().into_os_string();
+        partial.push(".partial");
+        if self.full_precision {
+            panorama.save_full_precision(&partial)?;
+        } else {
+            panorama.save(&partial)?;
+        }
+        fs::rename(partial, path)
+    }
+
+    /// Hash of `parts` identifying a panorama in a [`PanoramaCache`], the same
+    /// across runs and platforms: the 64-bit FNV-1a hash of the parts, each
+    /// followed by its length so that moving bytes from one part to the next
+    /// changes it.
+    pub fn key<'a>(parts: impl IntoIterator<Item = &'a [u8]>) -> u64 {
+        const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+        const PRIME: u64 = 0x0100_0000_01b3;
+        let mut hash = OFFSET_BASIS;
+        for part in parts {
+            for &byte in part.iter().chain(&(part.len() as u64).to_le_bytes()) {
+                hash = (hash ^ byte as u64).wrapping_mul(PRIME);
+            }
+        }
+        hash
+    }
+}
+
+#[cfg(test)]
+mod cache_tests {
+    use super::*;
+    use crate::{
+        azimuth::Azimuth,
+        fixtures::{compute, wall},
+        geo::GeoPoint,
+        panorama::PanoramaParameters,
+    };
+
+    #[test]
+    fn key_is_fnv1a_of_the_parts_and_their_lengths() {
+        assert_eq!(0xcbf2_9ce4_8422_2325, PanoramaCache::key([]));
+        assert_eq!(
+            PanoramaCache::key([b"ab".as_slice()]),
+            PanoramaCache::key([b"ab".as_slice()])
+        );
+        assert_ne!(
+            PanoramaCache::key([b"ab".as_slice()]),
+            PanoramaCache::key([b"ba".as_slice()])
+        );
+        assert_ne!(
+            PanoramaCache::key([b"a".as_slice(), b"b".as_slice()]),
+            PanoramaCache::key([b"ab".as_slice(), b"".as_slice()])
+        );
+    }
+
+    #[test]
+    fn cached_panoramas_are_loaded_by_key() {
+        let dir = std::env::temp_dir().join("alpano_panorama_cache_tests");
+        let _ = fs::remove_dir_all(&dir);
+        let cache = PanoramaCache::new(&dir);
+        assert!(cache.load(1).unwrap().is_none());
+
+        let parameters = PanoramaParameters::new(
+            GeoPoint::from_degrees(7., 46.5).unwrap(),
+            1100.,
+            Azimuth::EAST,
+            40_f64.to_radians(),
+            150_000.,
+            40,
+            20,
+        )
+        .unwrap();
+        let panorama = compute(wall(), &parameters);
+        cache.save(1, &panorama).unwrap();
+        assert!(cache.path(1).exists());
+        let cached = cache.load(1).unwrap().unwrap();
+        assert_eq!(&parameters, cached.parameters());
+        // saved as single precision floats
+        assert_eq!(
+            panorama.distance_at(20, 10) as f32 as f64,
+            cached.distance_at(20, 10)
+        );
+        assert!(cache.load(2).unwrap().is_none());
+
+        let cache = cache.with_full_precision();
+        cache.save(2, &panorama).unwrap();
+        let cached = cache.load(2).unwrap().unwrap();
+        assert_eq!(panorama.distances(), cached.distances());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
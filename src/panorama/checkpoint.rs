@@ -0,0 +1,87 @@
+use std::{
+    fs::{self, File},
+    io::{BufReader, BufWriter, Error, ErrorKind, Read, Result, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use super::{Panorama, PanoramaParameters};
+
+/// File to which the columns of a panorama are saved as they are computed,
+/// so that an interrupted computation can resume from them, see
+/// [`PanoramaComputer::compute_resumable`](super::PanoramaComputer::compute_resumable).
+///
+/// Checkpoint files hold the panorama in the format of
+/// [`Panorama::write_full_precision_to`], so that resumed computations match
+/// uninterrupted ones exactly, its columns not yet computed being empty,
+/// followed by a byte per column, 1 if it was computed and 0 otherwise.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checkpoint {
+    path: PathBuf,
+    interval: Duration,
+}
+
+impl Checkpoint {
+    /// Checkpoint saved to the file at `path` every minute.
+    pub fn new(path: impl Into<PathBuf>) -> Checkpoint {
+        Checkpoint {
+            path: path.into(),
+            interval: Duration::from_secs(60),
+        }
+    }
+
+    /// Save the checkpoint at least `interval` apart instead.
+    pub fn with_interval(self, interval: Duration) -> Checkpoint {
+        Checkpoint { interval, ..self }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Panorama of `parameters` saved to the checkpoint, with whether each
+    /// of its columns was computed, or `None` if there is no checkpoint
+    /// file. Fails if the checkpoint is of another panorama.
+    pub fn load(&self, parameters: &PanoramaParameters) -> Result<Option<(Panorama, Vec<bool>)>> {
+        let mut reader = match File::open(&self.path) {
+            Ok(file) => BufReader::new(file),
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let panorama = Panorama::read_from(&mut reader)?;
+        if panorama.parameters() != parameters {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("checkpoint {} is of another panorama", self.path.display()),
+            ));
+        }
+        let mut done = vec![0; parameters.width()];
+        reader.read_exact(&mut done)?;
+        Ok(Some((panorama, done.into_iter().map(|d| d != 0).collect())))
+    }
+
+    /// Save `panorama`, whose computed columns are those flagged in `done`,
+    /// replacing the previous checkpoint only once it is fully written.
+    pub fn save(&self, panorama: &Panorama, done: &[bool]) -> Result<()> {
+        let mut partial = self.path.clone().into_os_string();
+        partial.push(".partial");
+        let mut writer = BufWriter::new(File::create(&partial)?);
+        panorama.write_full_precision_to(&mut writer)?;
+        writer.write_all(&done.iter().map(|&d| d as u8).collect::<Vec<_>>())?;
+        writer.flush()?;
+        drop(writer);
+        fs::rename(partial, &self.path)
+    }
+
+    /// Remove the checkpoint file, if any.
+    pub fn remove(&self) -> Result<()> {
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,981 @@
+use std::{
+    num::NonZeroUsize,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use super::Checkpoint;
+use super::{PanoramaParameters, ProgressSink, Projection};
+use crate::{
+    azimuth::Azimuth,
+    dem::{ContinuousElevationModel, DiscreteElevationModel, ElevationProfile},
+    distance::{EarthModel, Sphere, REFRACTION_COEFFICIENT},
+    geo::GeoPoint,
+    math::{fast_cos, fast_sin, first_interval_containing_root, improve_root},
+    stats::Held,
+    sun,
+};
+
+/// Step used to search for the terrain along a ray, in meters.
+const SEARCH_STEP: f64 = 64.;
+/// Precision with which the terrain is located along a ray, in meters.
+const SEARCH_PRECISION: f64 = 4.;
+/// Elevation of the highest summit of the earth, above which the rays
+/// toward the sun cannot be shadowed, in meters.
+const HIGHEST_SUMMIT: f64 = 8849.;
+/// Number of columns computed between checks of the time elapsed since the
+/// last checkpoint.
+#[cfg(not(target_arch = "wasm32"))]
+const CHECKPOINT_COLUMNS: usize = 64;
+
+/// Terrain seen at each pixel of a panorama. Pixels where no terrain is seen
+/// are at an infinite distance, have longitude, latitude, elevation, slope
+/// and aspect 0, and are not in shadow.
+pub struct Panorama {
+    pub(super) parameters: PanoramaParameters,
+    pub(super) distance: Vec<f64>,
+    pub(super) longitude: Vec<f64>,
+    pub(super) latitude: Vec<f64>,
+    pub(super) elevation: Vec<f64>,
+    pub(super) slope: Vec<f64>,
+    pub(super) aspect: Vec<f64>,
+    pub(super) shadow: Vec<bool>,
+    held: Held,
+}
+
+impl Panorama {
+    /// Bytes of the planes of a pixel.
+    pub const BYTES_PER_PIXEL: usize = 6 * size_of::<f64>() + size_of::<bool>();
+
+    /// Panorama in which no terrain is seen.
+    pub(super) fn empty(parameters: &PanoramaParameters) -> Panorama {
+        let size = parameters.width() * parameters.height();
+        Panorama {
+            held: Held::panorama_planes(size * Panorama::BYTES_PER_PIXEL),
+            parameters: *parameters,
+            distance: vec![f64::INFINITY; size],
+            longitude: vec![0.; size],
+            latitude: vec![0.; size],
+            elevation: vec![0.; size],
+            slope: vec![0.; size],
+            aspect: vec![0.; size],
+            shadow: vec![false; size],
+        }
+    }
+
+    /// Store the terrain seen in column `x`, from the bottom of the image up.
+    fn set_column(&mut self, x: usize, hits: Vec<Hit>) {
+        let height = self.parameters.height();
+        for (i, hit) in hits.into_iter().enumerate() {
+            let j = self.parameters.linear_sample_index(x, height - 1 - i);
+            self.distance[j] = hit.distance;
+            self.longitude[j] = hit.longitude;
+            self.latitude[j] = hit.latitude;
+            self.elevation[j] = hit.elevation;
+            self.slope[j] = hit.slope;
+            self.aspect[j] = hit.aspect;
+            self.shadow[j] = hit.in_shadow;
+        }
+    }
+
+    pub fn parameters(&self) -> &PanoramaParameters {
+        &self.parameters
+    }
+
+    /// Bytes of memory held by the planes of the panorama, counted in
+    /// [`stats`](crate::stats::stats).
+    pub fn memory_size(&self) -> usize {
+        self.held.bytes()
+    }
+
+    /// Distances of the terrain seen at every pixel, see
+    /// [`distance_at`](Panorama::distance_at). Like the other planes of the
+    /// panorama, they are stored row by row from the top left corner, the
+    /// value of pixel `(x, y)` being at
+    /// [`linear_sample_index`](PanoramaParameters::linear_sample_index).
+    pub fn distances(&self) -> &[f64] {
+        &self.distance
+    }
+
+    /// Longitudes of the terrain seen at every pixel.
+    pub fn longitudes(&self) -> &[f64] {
+        &self.longitude
+    }
+
+    /// Latitudes of the terrain seen at every pixel.
+    pub fn latitudes(&self) -> &[f64] {
+        &self.latitude
+    }
+
+    /// Elevations of the terrain seen at every pixel.
+    pub fn elevations(&self) -> &[f64] {
+        &self.elevation
+    }
+
+    /// Slopes of the terrain seen at every pixel.
+    pub fn slopes(&self) -> &[f64] {
+        &self.slope
+    }
+
+    /// Aspects of the terrain seen at every pixel, in radians.
+    pub fn aspects(&self) -> &[f64] {
+        &self.aspect
+    }
+
+    /// Whether the terrain seen at every pixel is in shadow, see
+    /// [`is_in_shadow_at`](Panorama::is_in_shadow_at).
+    pub fn shadows(&self) -> &[bool] {
+        &self.shadow
+    }
+
+    /// Distance from the observer to the terrain seen at pixel `(x, y)`,
+    /// along the ray, in meters.
+    pub fn distance_at(&self, x: usize, y: usize) -> f64 {
+        self.distances()[self.parameters.linear_sample_index(x, y)]
+    }
+
+    pub fn longitude_at(&self, x: usize, y: usize) -> f64 {
+        self.longitudes()[self.parameters.linear_sample_index(x, y)]
+    }
+
+    pub fn latitude_at(&self, x: usize, y: usize) -> f64 {
+        self.latitudes()[self.parameters.linear_sample_index(x, y)]
+    }
+
+    /// Point of the terrain seen at pixel `(x, y)`, or `None` if no terrain
+    /// is seen there.
+    pub fn geopoint_at(&self, x: usize, y: usize) -> Option<GeoPoint> {
+        if self.distance_at(x, y).is_infinite() {
+            return None;
+        }
+        GeoPoint::new(self.longitude_at(x, y), self.latitude_at(x, y)).ok()
+    }
+
+    /// Elevation of the terrain seen at pixel `(x, y)`, in meters.
+    pub fn elevation_at(&self, x: usize, y: usize) -> f64 {
+        self.elevations()[self.parameters.linear_sample_index(x, y)]
+    }
+
+    /// Slope of the terrain seen at pixel `(x, y)`.
+    pub fn slope_at(&self, x: usize, y: usize) -> f64 {
+        self.slopes()[self.parameters.linear_sample_index(x, y)]
+    }
+
+    /// Aspect of the terrain seen at pixel `(x, y)`, i.e. the azimuth of its
+    /// steepest descent.
+    pub fn aspect_at(&self, x: usize, y: usize) -> Azimuth {
+        Azimuth::new(self.aspects()[self.parameters.linear_sample_index(x, y)])
+    }
+
+    /// Whether the terrain seen at pixel `(x, y)` is in the shadow of the
+    /// terrain, false unless computed with shadows, see
+    /// [`PanoramaComputer::with_shadows`].
+    pub fn is_in_shadow_at(&self, x: usize, y: usize) -> bool {
+        self.shadows()[self.parameters.linear_sample_index(x, y)]
+    }
+}
+
+/// Computes panoramas by casting, for every column of the image, rays of
+/// increasing altitude over the elevation profile in its direction, or for
+/// projections whose columns are not seen at a single azimuth, a ray per
+/// pixel over the elevation profile in its own direction.
+pub struct PanoramaComputer<D: DiscreteElevationModel> {
+    cem: ContinuousElevationModel<D>,
+    earth_model: Box<dyn EarthModel>,
+    refraction_coefficient: f64,
+    progress: Option<Box<dyn ProgressSink>>,
+    sun: Option<(Azimuth, f64)>,
+    chunk_size: Option<NonZeroUsize>,
+}
+
+impl<D: DiscreteElevationModel> PanoramaComputer<D> {
+    /// Create a computer over `cem`, on a spherical earth and bending rays
+    /// with the standard refraction coefficient.
+    pub fn new(cem: ContinuousElevationModel<D>) -> PanoramaComputer<D> {
+        PanoramaComputer {
+            cem,
+            earth_model: Box::new(Sphere::EARTH),
+            refraction_coefficient: REFRACTION_COEFFICIENT,
+            progress: None,
+            sun: None,
+            chunk_size: None,
+        }
+    }
+
+    /// Bend rays with refraction coefficient `k` instead, 0 for straight rays.
+    pub fn with_refraction_coefficient(self, k: f64) -> PanoramaComputer<D> {
+        PanoramaComputer {
+            refraction_coefficient: k,
+            ..self
+        }
+    }
+
+    /// Curve rays over `earth_model` instead of the spherical earth.
+    pub fn with_earth_model(self, earth_model: impl EarthModel + 'static) -> PanoramaComputer<D> {
+        PanoramaComputer {
+            earth_model: Box::new(earth_model),
+            ..self
+        }
+    }
+
+    /// Report the progress of computations to `progress`.
+    pub fn with_progress(self, progress: impl ProgressSink + 'static) -> PanoramaComputer<D> {
+        PanoramaComputer {
+            progress: Some(Box::new(progress)),
+            ..self
+        }
+    }
+
+    /// Cast a secondary ray from the terrain seen at each pixel toward the
+    /// sun at the given azimuth and altitude (in radians), to find whether
+    /// it is in the shadow of the terrain.
+    pub fn with_shadows(self, sun_azimuth: Azimuth, sun_altitude: f64) -> PanoramaComputer<D> {
+        PanoramaComputer {
+            sun: Some((sun_azimuth, sun_altitude)),
+            ..self
+        }
+    }
+
+    /// Split the columns of parallel computations into tasks of about
+    /// `columns` columns instead of letting rayon split them adaptively:
+    /// small tasks balance the load of columns of sky and columns of
+    /// terrain better, large ones cost less to schedule.
+    pub fn with_chunk_size(self, columns: NonZeroUsize) -> PanoramaComputer<D> {
+        PanoramaComputer {
+            chunk_size: Some(columns),
+            ..self
+        }
+    }
+
+    pub fn refraction_coefficient(&self) -> f64 {
+        self.refraction_coefficient
+    }
+
+    pub fn earth_model(&self) -> &dyn EarthModel {
+        self.earth_model.as_ref()
+    }
+
+    pub fn cem(&self) -> &ContinuousElevationModel<D> {
+        &self.cem
+    }
+
+    /// Number of columns of the tasks of parallel computations, if not
+    /// chosen by rayon.
+    pub fn chunk_size(&self) -> Option<NonZeroUsize> {
+        self.chunk_size
+    }
+
+    pub fn compute(&self, parameters: &PanoramaParameters) -> Panorama {
+        let done = AtomicUsize::new(0);
+        let mut panorama = Panorama::empty(parameters);
+        for x in 0..parameters.width() {
+            panorama.set_column(x, self.compute_column(parameters, x, &done));
+        }
+        panorama
+    }
+
+    /// Like [`compute`](PanoramaComputer::compute), but computing the columns
+    /// in parallel on the rayon thread pool, see
+    /// [`with_chunk_size`](PanoramaComputer::with_chunk_size).
+    #[cfg(feature = "parallel")]
+    pub fn compute_parallel(&self, parameters: &PanoramaParameters) -> Panorama {
+        let done = AtomicUsize::new(0);
+        let all: Vec<usize> = (0..parameters.width()).collect();
+        let columns = self.compute_columns_parallel(parameters, &all, &done);
+
+        let mut panorama = Panorama::empty(parameters);
+        for (x, column) in columns.into_iter().enumerate() {
+            panorama.set_column(x, column);
+        }
+        panorama
+    }
+
+    /// Like [`compute`](PanoramaComputer::compute), in parallel with the
+    /// `parallel` feature, but saving the computed columns to `checkpoint`
+    /// as they are computed, and first resuming from it if `resume` is true
+    /// and it exists. The checkpoint is removed once the panorama is
+    /// computed.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn compute_resumable(
+        &self,
+        parameters: &PanoramaParameters,
+        checkpoint: &Checkpoint,
+        resume: bool,
+    ) -> std::io::Result<Panorama> {
+        let saved = if resume {
+            checkpoint.load(parameters)?
+        } else {
+            None
+        };
+        let (mut panorama, mut done) =
+            saved.unwrap_or_else(|| (Panorama::empty(parameters), vec![false; parameters.width()]));
+
+        let todo: Vec<usize> = (0..parameters.width()).filter(|&x| !done[x]).collect();
+        let counter = AtomicUsize::new(parameters.width() - todo.len());
+        let mut last_save = std::time::Instant::now();
+        for columns in todo.chunks(CHECKPOINT_COLUMNS) {
+            #[cfg(feature = "parallel")]
+            let hits = self.compute_columns_parallel(parameters, columns, &counter);
+            #[cfg(not(feature = "parallel"))]
+            let hits: Vec<Vec<Hit>> = columns
+                .iter()
+                .map(|&x| self.compute_column(parameters, x, &counter))
+                .collect();
+
+            for (&x, hits) in columns.iter().zip(hits) {
+                panorama.set_column(x, hits);
+                done[x] = true;
+            }
+            if last_save.elapsed() >= checkpoint.interval() {
+                checkpoint.save(&panorama, &done)?;
+                last_save = std::time::Instant::now();
+            }
+        }
+
+        checkpoint.remove()?;
+        Ok(panorama)
+    }
+
+    /// Hits of the given columns, computed in tasks of the chunk size if any.
+    #[cfg(feature = "parallel")]
+    fn compute_columns_parallel(
+        &self,
+        parameters: &PanoramaParameters,
+        columns: &[usize],
+        done: &AtomicUsize,
+    ) -> Vec<Vec<Hit>> {
+        use rayon::prelude::*;
+
+        let (min, max) = self
+            .chunk_size
+            .map_or((1, usize::MAX), |n| (n.get(), n.get()));
+        columns
+            .par_iter()
+            .with_min_len(min)
+            .with_max_len(max)
+            .map(|&x| self.compute_column(parameters, x, done))
+            .collect()
+    }
+
+    /// Terrain seen in column `x`, from the bottom of the image up to the
+    /// last row where terrain is seen. `done` counts the computed columns.
+    fn compute_column(
+        &self,
+        parameters: &PanoramaParameters,
+        x: usize,
+        done: &AtomicUsize,
+    ) -> Vec<Hit> {
+        let mut hits = if parameters.projection().keeps_azimuth_along_columns() {
+            self.cast_column(parameters, x)
+        } else {
+            (0..parameters.height())
+                .rev()
+                .map(|y| self.cast_pixel(parameters, x, y).unwrap_or(Hit::NONE))
+                .collect()
+        };
+        if let Some((sun_azimuth, sun_altitude)) = self.sun {
+            for hit in hits.iter_mut().filter(|hit| hit.distance.is_finite()) {
+                hit.in_shadow = self.is_in_shadow(hit, parameters, sun_azimuth, sun_altitude);
+            }
+        }
+
+        if let Some(progress) = &self.progress {
+            let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+            progress.columns_done(done, parameters.width());
+        }
+        hits
+    }
+
+    /// Terrain seen in column `x` of a projection keeping its azimuth
+    /// constant, over a single elevation profile.
+    fn cast_column(&self, parameters: &PanoramaParameters, x: usize) -> Vec<Hit> {
+        let (azimuth, _) = parameters.direction_at(x as f64, 0.);
+        let profile = ElevationProfile::new(
+            &self.cem,
+            parameters.observer_position(),
+            azimuth,
+            parameters.max_distance(),
+        );
+        let radius = self.effective_radius(parameters, azimuth);
+
+        // rays of increasing altitude meet the terrain further away, so each
+        // search starts from the previous intersection
+        let mut hits = Vec::new();
+        let mut ray_start = 0.;
+        for y in (0..parameters.height()).rev() {
+            let (_, altitude) = parameters.direction_at(x as f64, y as f64);
+            match cast_ray(&profile, parameters, altitude, radius, ray_start) {
+                Some((d, hit)) => {
+                    ray_start = d;
+                    hits.push(hit);
+                }
+                None => break,
+            }
+        }
+        hits
+    }
+
+    /// Terrain seen at pixel `(x, y)`, over the elevation profile in its own
+    /// direction.
+    fn cast_pixel(&self, parameters: &PanoramaParameters, x: usize, y: usize) -> Option<Hit> {
+        let (azimuth, altitude) = parameters.direction_at(x as f64, y as f64);
+        let profile = ElevationProfile::new(
+            &self.cem,
+            parameters.observer_position(),
+            azimuth,
+            parameters.max_distance(),
+        );
+        let radius = self.effective_radius(parameters, azimuth);
+        cast_ray(&profile, parameters, altitude, radius, 0.).map(|(_, hit)| hit)
+    }
+
+    /// Whether the terrain of `hit` is in the shadow of the terrain within
+    /// the maximum distance, for the sun at the given azimuth and altitude.
+    fn is_in_shadow(
+        &self,
+        hit: &Hit,
+        parameters: &PanoramaParameters,
+        sun_azimuth: Azimuth,
+        sun_altitude: f64,
+    ) -> bool {
+        let radius = self.earth_model.effective_radius(
+            hit.latitude,
+            sun_azimuth.radians(),
+            self.refraction_coefficient,
+        );
+        let reach = shadow_reach(
+            hit.elevation,
+            sun_altitude,
+            radius,
+            parameters.max_distance(),
+        );
+        let origin = GeoPoint::new(hit.longitude, hit.latitude).expect("terrain is on the earth");
+        let profile = ElevationProfile::new(&self.cem, origin, sun_azimuth, reach.max(SEARCH_STEP));
+        sun::is_in_shadow(
+            |d| profile.elevation_at(d),
+            hit.elevation,
+            sun_altitude,
+            radius,
+            reach,
+            SEARCH_STEP,
+        )
+    }
+
+    fn effective_radius(&self, parameters: &PanoramaParameters, azimuth: Azimuth) -> f64 {
+        self.earth_model.effective_radius(
+            parameters.observer_position().latitude(),
+            azimuth.radians(),
+            self.refraction_coefficient,
+        )
+    }
+}
+
+/// Distance from terrain at `elevation` beyond which no terrain can shadow
+/// it from the sun at `sun_altitude`, over an earth of the given effective
+/// radius, at most `max_distance`.
+fn shadow_reach(
+    elevation: f64,
+    sun_altitude: f64,
+    effective_radius: f64,
+    max_distance: f64,
+) -> f64 {
+    // the ray toward the sun rises above the highest summit of the earth, as
+    // the earth also curves away below it, at the positive root of
+    // c d² + tan(h) d + e - HIGHEST_SUMMIT = 0 with c = 1 / 2r, written to
+    // hold on a flat earth, where c = 0
+    let curvature = 1. / (2. * effective_radius);
+    let slope = sun_altitude.tan();
+    let discriminant = slope * slope + 4. * curvature * (HIGHEST_SUMMIT - elevation);
+    let root = 2. * (HIGHEST_SUMMIT - elevation) / (slope + discriminant.max(0.).sqrt());
+    root.max(0.).min(max_distance)
+}
+
+/// Horizontal distance at which the ray of the given altitude leaving the
+/// observer over `profile` meets the terrain, searching from `ray_start`, and
+/// the terrain seen there, if any within the maximum distance.
+fn cast_ray<D: DiscreteElevationModel>(
+    profile: &ElevationProfile<D>,
+    parameters: &PanoramaParameters,
+    altitude: f64,
+    radius: f64,
+    ray_start: f64,
+) -> Option<(f64, Hit)> {
+    let (sin, cos) = (fast_sin(altitude), fast_cos(altitude));
+    let ray = ray_to_ground_distance(profile, parameters.observer_elevation(), sin / cos, radius);
+
+    let interval =
+        first_interval_containing_root(&ray, ray_start, parameters.max_distance(), SEARCH_STEP);
+    if interval.is_infinite() {
+        return None;
+    }
+    let d = improve_root(&ray, interval, interval + SEARCH_STEP, SEARCH_PRECISION)
+        .expect("interval contains a root");
+
+    let position = profile.position_at(d);
+    let hit = Hit {
+        distance: d / cos,
+        longitude: position.longitude(),
+        latitude: position.latitude(),
+        elevation: profile.elevation_at(d),
+        slope: profile.slope_at(d),
+        aspect: profile.aspect_at(d).radians(),
+        in_shadow: false,
+    };
+    Some((d, hit))
+}
+
+/// Terrain seen at one pixel.
+struct Hit {
+    distance: f64,
+    longitude: f64,
+    latitude: f64,
+    elevation: f64,
+    slope: f64,
+    aspect: f64,
+    in_shadow: bool,
+}
+
+impl Hit {
+    /// Pixel where no terrain is seen.
+    const NONE: Hit = Hit {
+        distance: f64::INFINITY,
+        longitude: 0.,
+        latitude: 0.,
+        elevation: 0.,
+        slope: 0.,
+        aspect: 0.,
+        in_shadow: false,
+    };
+}
+
+/// Height of the ray leaving the observer at `ray_elevation` with the given
+/// slope above the terrain, as a function of the horizontal distance, taking
+/// the curvature of the earth into account through its `effective_radius`,
+/// which includes the refraction of the ray.
+pub(crate) fn ray_to_ground_distance<'a, D: DiscreteElevationModel>(
+    profile: &'a ElevationProfile<D>,
+    ray_elevation: f64,
+    ray_slope: f64,
+    effective_radius: f64,
+) -> impl Fn(f64) -> f64 + 'a {
+    let curvature = 1. / (2. * effective_radius);
+    move |x| ray_elevation + x * ray_slope - profile.elevation_at(x) + x * x * curvature
+}
+
+#[cfg(test)]
+mod computer_tests {
+    use super::*;
+    use crate::{
+        azimuth::Azimuth,
+        distance::{effective_radius, Wgs84, EARTH_RADIUS},
+        fixtures::wall,
+        geo::GeoPoint,
+        panorama::{Checkpoint, ProjectionKind},
+    };
+    use assert_approx_eq::assert_approx_eq;
+
+    fn parameters(azimuth: Azimuth) -> PanoramaParameters {
+        PanoramaParameters::new(
+            GeoPoint::from_degrees(7., 46.5).unwrap(),
+            1100.,
+            azimuth,
+            20_f64.to_radians(),
+            100_000.,
+            21,
+            21,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn compute_finds_flat_terrain_below_horizon() {
+        let computer = PanoramaComputer::new(ContinuousElevationModel::new(wall()))
+            .with_refraction_coefficient(0.);
+        let parameters = parameters(Azimuth::SOUTH);
+        let panorama = computer.compute(&parameters);
+
+        for y in 11..21 {
+            let (_, altitude) = parameters.direction_at(10., y as f64);
+            let d = panorama.distance_at(10, y) * altitude.cos();
+            // the ray is 100 m above the terrain at the observer
+            let drop = d * altitude.tan() + d * d / (2. * EARTH_RADIUS);
+            assert_approx_eq!(-100., drop, 1.);
+            assert_approx_eq!(1000., panorama.elevation_at(10, y), 1e-6);
+            assert_approx_eq!(0., panorama.slope_at(10, y), 1e-9);
+            assert_approx_eq!(7_f64.to_radians(), panorama.longitude_at(10, y), 1e-9);
+            assert!(panorama.latitude_at(10, y) < 46.5_f64.to_radians());
+        }
+        // beyond the horizon of the observer, but not of the flat terrain
+        for y in 0..=10 {
+            assert!(panorama.distance_at(10, y).is_infinite());
+            assert_eq!(0., panorama.elevation_at(10, y));
+        }
+    }
+
+    #[test]
+    fn planes_hold_the_values_of_the_pixels_row_by_row() {
+        let parameters = parameters(Azimuth::SOUTH);
+        let panorama = PanoramaComputer::new(ContinuousElevationModel::new(wall()))
+            .with_shadows(Azimuth::NORTH, 0.1)
+            .compute(&parameters);
+
+        for plane in [
+            panorama.distances(),
+            panorama.longitudes(),
+            panorama.latitudes(),
+            panorama.elevations(),
+            panorama.slopes(),
+            panorama.aspects(),
+        ] {
+            assert_eq!(21 * 21, plane.len());
+        }
+        assert_eq!(21 * 21, panorama.shadows().len());
+        for (x, y) in [(0, 0), (10, 15), (20, 15), (3, 20)] {
+            let i = y * 21 + x;
+            assert_eq!(panorama.distance_at(x, y), panorama.distances()[i]);
+            assert_eq!(panorama.longitude_at(x, y), panorama.longitudes()[i]);
+            assert_eq!(panorama.latitude_at(x, y), panorama.latitudes()[i]);
+            assert_eq!(panorama.elevation_at(x, y), panorama.elevations()[i]);
+            assert_eq!(panorama.slope_at(x, y), panorama.slopes()[i]);
+            assert_eq!(
+                panorama.aspect_at(x, y),
+                Azimuth::new(panorama.aspects()[i])
+            );
+            assert_eq!(panorama.is_in_shadow_at(x, y), panorama.shadows()[i]);
+        }
+    }
+
+    #[test]
+    fn geopoint_at_is_the_terrain_seen() {
+        let parameters = parameters(Azimuth::SOUTH);
+        let panorama =
+            PanoramaComputer::new(ContinuousElevationModel::new(wall())).compute(&parameters);
+
+        let point = panorama.geopoint_at(10, 15).unwrap();
+        assert_eq!(panorama.longitude_at(10, 15), point.longitude());
+        assert_eq!(panorama.latitude_at(10, 15), point.latitude());
+        let observer = parameters.observer_position();
+        let (_, altitude) = parameters.direction_at(10., 15.);
+        assert_approx_eq!(
+            panorama.distance_at(10, 15) * altitude.cos(),
+            observer.distance_to(&point),
+            100.
+        );
+        assert_approx_eq!(180., observer.azimuth_to(&point).degrees(), 1e-6);
+        assert_eq!(None, panorama.geopoint_at(10, 5));
+    }
+
+    #[test]
+    fn compute_finds_wall_ahead() {
+        let computer = PanoramaComputer::new(ContinuousElevationModel::new(wall()))
+            .with_refraction_coefficient(0.);
+        let parameters = parameters(Azimuth::EAST);
+        let panorama = computer.compute(&parameters);
+        let wall = GeoPoint::from_degrees(7., 46.5)
+            .unwrap()
+            .distance_to(&GeoPoint::from_degrees(8., 46.5).unwrap());
+
+        // the top of the wall is at about 1° above the horizon
+        let top = ((1900. - wall * wall / (2. * EARTH_RADIUS)) / wall).atan();
+        let y = parameters.pixel_for(Azimuth::EAST, top).unwrap().1.ceil() as usize + 1;
+        let (_, altitude) = parameters.direction_at(10., y as f64);
+        assert!(panorama.distance_at(10, y) * altitude.cos() < wall);
+        assert!(panorama.distance_at(10, y) * altitude.cos() > wall - 10_000.);
+        assert!(panorama.elevation_at(10, y) > 1100.);
+        assert!(panorama.slope_at(10, y) > 0.1);
+        assert!(panorama.distance_at(10, y - 2).is_infinite());
+    }
+
+    #[test]
+    fn compute_casts_a_ray_per_pixel_of_little_planets() {
+        let computer = PanoramaComputer::new(ContinuousElevationModel::new(wall()))
+            .with_refraction_coefficient(0.);
+        let parameters = PanoramaParameters::new_with_projection(
+            GeoPoint::from_degrees(7., 46.5).unwrap(),
+            1100.,
+            Azimuth::SOUTH,
+            270_f64.to_radians(),
+            100_000.,
+            21,
+            21,
+            ProjectionKind::Stereographic,
+        )
+        .unwrap();
+        let panorama = computer.compute(&parameters);
+
+        for y in 0..21 {
+            for x in 0..21 {
+                let (azimuth, altitude) = parameters.direction_at(x as f64, y as f64);
+                if (-1.5..-0.01).contains(&altitude) {
+                    let d = panorama.distance_at(x, y) * altitude.cos();
+                    let drop = d * altitude.tan() + d * d / (2. * EARTH_RADIUS);
+                    // steep rays meet the terrain with less vertical precision
+                    assert_approx_eq!(-100., drop, 1. - SEARCH_PRECISION * altitude.tan());
+                    assert_approx_eq!(1000., panorama.elevation_at(x, y), 1e-6);
+                } else if altitude > 0.01 && azimuth.radians().sin() < 0. {
+                    // only the wall to the east is above the horizon
+                    assert!(panorama.distance_at(x, y).is_infinite());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn compute_accounts_for_refraction() {
+        let parameters = parameters(Azimuth::SOUTH);
+        let straight = PanoramaComputer::new(ContinuousElevationModel::new(wall()))
+            .with_refraction_coefficient(0.)
+            .compute(&parameters);
+        let refracted =
+            PanoramaComputer::new(ContinuousElevationModel::new(wall())).compute(&parameters);
+
+        let radius = effective_radius(REFRACTION_COEFFICIENT);
+        for y in 11..21 {
+            let (_, altitude) = parameters.direction_at(10., y as f64);
+            let d = refracted.distance_at(10, y) * altitude.cos();
+            assert_approx_eq!(-100., d * altitude.tan() + d * d / (2. * radius), 1.);
+        }
+        // the terrain bends away less under refracted rays
+        assert!(refracted.distance_at(10, 11) < straight.distance_at(10, 11) - 10.);
+    }
+
+    #[test]
+    fn compute_curves_rays_over_earth_model() {
+        let parameters = parameters(Azimuth::SOUTH);
+        let computer = PanoramaComputer::new(ContinuousElevationModel::new(wall()))
+            .with_earth_model(Wgs84)
+            .with_refraction_coefficient(0.);
+        let panorama = computer.compute(&parameters);
+
+        // the meridian is less curved than the mean sphere at 46.5°
+        let radius = Wgs84.radius(46.5_f64.to_radians(), Azimuth::SOUTH.radians());
+        assert!(radius < EARTH_RADIUS);
+        for y in 11..21 {
+            let (_, altitude) = parameters.direction_at(10., y as f64);
+            let d = panorama.distance_at(10, y) * altitude.cos();
+            assert_approx_eq!(-100., d * altitude.tan() + d * d / (2. * radius), 1.);
+        }
+
+        let flat = PanoramaComputer::new(ContinuousElevationModel::new(wall()))
+            .with_earth_model(Sphere {
+                radius: f64::INFINITY,
+            })
+            .compute(&parameters);
+        // the terrain never bends away from rays over a flat earth
+        for y in 11..21 {
+            let (_, altitude) = parameters.direction_at(10., y as f64);
+            let d = flat.distance_at(10, y) * altitude.cos();
+            assert_approx_eq!(-100., d * altitude.tan(), 1.);
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn compute_parallel_matches_compute() {
+        let computer = PanoramaComputer::new(ContinuousElevationModel::new(wall()));
+        let parameters = parameters(Azimuth::from_degrees(100.));
+        let sequential = computer.compute(&parameters);
+        let parallel = computer.compute_parallel(&parameters);
+
+        for x in 0..parameters.width() {
+            for y in 0..parameters.height() {
+                assert_eq!(sequential.distance_at(x, y), parallel.distance_at(x, y));
+                assert_eq!(sequential.longitude_at(x, y), parallel.longitude_at(x, y));
+                assert_eq!(sequential.latitude_at(x, y), parallel.latitude_at(x, y));
+                assert_eq!(sequential.elevation_at(x, y), parallel.elevation_at(x, y));
+                assert_eq!(sequential.slope_at(x, y), parallel.slope_at(x, y));
+            }
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn compute_parallel_matches_compute_whatever_the_chunk_size() {
+        let parameters = parameters(Azimuth::from_degrees(100.));
+        let sequential =
+            PanoramaComputer::new(ContinuousElevationModel::new(wall())).compute(&parameters);
+        for columns in [1, 7, 1000] {
+            let computer = PanoramaComputer::new(ContinuousElevationModel::new(wall()))
+                .with_chunk_size(NonZeroUsize::new(columns).unwrap());
+            assert_eq!(NonZeroUsize::new(columns), computer.chunk_size());
+            let parallel = computer.compute_parallel(&parameters);
+            for x in 0..parameters.width() {
+                for y in 0..parameters.height() {
+                    assert_eq!(sequential.distance_at(x, y), parallel.distance_at(x, y));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn with_shadows_finds_terrain_in_the_shadow_of_the_wall() {
+        let parameters = parameters(Azimuth::EAST);
+        let in_shadow = |panorama: &Panorama| -> Vec<bool> {
+            (0..21).map(|y| panorama.is_in_shadow_at(10, y)).collect()
+        };
+        let computer = || PanoramaComputer::new(ContinuousElevationModel::new(wall()));
+
+        let unshadowed = computer().compute(&parameters);
+        assert!(!in_shadow(&unshadowed).contains(&true));
+        let morning = computer()
+            .with_shadows(Azimuth::EAST, 5_f64.to_radians())
+            .compute(&parameters);
+        let morning = in_shadow(&morning);
+        // the near terrain is lit, the face of the wall toward the observer
+        // is not
+        assert!(!morning[20]);
+        assert!(morning.contains(&true));
+        let evening = computer()
+            .with_shadows(Azimuth::WEST, 30_f64.to_radians())
+            .compute(&parameters);
+        assert!(!in_shadow(&evening).contains(&true));
+    }
+
+    #[test]
+    fn with_shadows_follows_the_earth_model_and_refraction_of_the_computer() {
+        // rows 0.1° apart, seeing the terrain up to 57 km away
+        let parameters = PanoramaParameters::new(
+            GeoPoint::from_degrees(7., 46.5).unwrap(),
+            1100.,
+            Azimuth::EAST,
+            2_f64.to_radians(),
+            100_000.,
+            21,
+            21,
+        )
+        .unwrap();
+        let sun_altitude = (2000_f64 / 60_000.).atan();
+        // without refraction the rays are straight over a flat earth, where
+        // the wall shadows the terrain up to 60 km west of it
+        let flat = PanoramaComputer::new(ContinuousElevationModel::new(wall()))
+            .with_refraction_coefficient(1.)
+            .with_shadows(Azimuth::EAST, sun_altitude)
+            .compute(&parameters);
+
+        let mut shadowed = 0;
+        for y in 0..21 {
+            if !flat.distance_at(10, y).is_finite() || flat.elevation_at(10, y) > 1500. {
+                continue;
+            }
+            let latitude = flat.latitude_at(10, y);
+            let terrain = GeoPoint::new(flat.longitude_at(10, y), latitude).unwrap();
+            let wall = GeoPoint::new(8_f64.to_radians(), latitude).unwrap();
+            let to_wall = terrain.distance_to(&wall);
+            if (to_wall - 60_000.).abs() > 1000. {
+                assert_eq!(to_wall < 60_000., flat.is_in_shadow_at(10, y));
+                shadowed += flat.is_in_shadow_at(10, y) as usize;
+            }
+        }
+        // including terrain beyond the 54 km of the shadow on the curved earth
+        assert!(shadowed > 0);
+    }
+
+    #[test]
+    fn shadow_reach_is_where_the_ray_to_the_sun_rises_above_the_highest_summit() {
+        // with the sun on the horizon, the ray only rises as the earth curves
+        // away, by d² / 2r = 7849 m at d = sqrt(2 × 6371 km / 0.87 × 7849 m)
+        let radius = effective_radius(REFRACTION_COEFFICIENT);
+        assert_approx_eq!(339_052., shadow_reach(1000., 0., radius, 1e9), 1.);
+        // 1000 + 43772 tan(10°) + 43772² / 2r = 1000 + 7718.3 + 130.8 m
+        let reach = shadow_reach(1000., 10_f64.to_radians(), radius, 1e9);
+        assert_approx_eq!(43_772., reach, 1.);
+        // farther for the sun below the horizon, within the maximum distance
+        assert!(shadow_reach(1000., -5_f64.to_radians(), radius, 1e9) > 1_000_000.);
+        let reach = shadow_reach(1000., -5_f64.to_radians(), radius, 200_000.);
+        assert_eq!(200_000., reach);
+        assert_eq!(
+            0.,
+            shadow_reach(HIGHEST_SUMMIT, 10_f64.to_radians(), radius, 1e9)
+        );
+        // on a flat earth, the ray only rises with the sun
+        let flat = shadow_reach(1000., 10_f64.to_radians(), f64::INFINITY, 1e9);
+        assert_approx_eq!(7849. / 10_f64.to_radians().tan(), flat, 1e-6);
+        assert_eq!(1e9, shadow_reach(1000., 0., f64::INFINITY, 1e9));
+    }
+
+    #[test]
+    fn compute_reports_every_column() {
+        use std::sync::{Arc, Mutex};
+
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&reports);
+        let computer = PanoramaComputer::new(ContinuousElevationModel::new(wall()))
+            .with_progress(move |done, total| sink.lock().unwrap().push((done, total)));
+        computer.compute(&parameters(Azimuth::EAST));
+
+        let expected: Vec<(usize, usize)> = (1..=21).map(|done| (done, 21)).collect();
+        assert_eq!(expected, *reports.lock().unwrap());
+    }
+
+    #[test]
+    fn compute_resumable_matches_compute_and_removes_checkpoint() {
+        let path = std::env::temp_dir().join("alpano_compute_resumable.checkpoint");
+        let checkpoint = Checkpoint::new(&path).with_interval(std::time::Duration::ZERO);
+        let computer = PanoramaComputer::new(ContinuousElevationModel::new(wall()));
+        let parameters = parameters(Azimuth::from_degrees(100.));
+        let panorama = computer
+            .compute_resumable(&parameters, &checkpoint, true)
+            .unwrap();
+
+        let expected = computer.compute(&parameters);
+        for x in 0..parameters.width() {
+            for y in 0..parameters.height() {
+                assert_eq!(expected.distance_at(x, y), panorama.distance_at(x, y));
+            }
+        }
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn compute_resumable_keeps_the_columns_of_the_checkpoint() {
+        use std::sync::{Arc, Mutex};
+
+        let path = std::env::temp_dir().join("alpano_resume.checkpoint");
+        let checkpoint = Checkpoint::new(&path);
+        let other = parameters(Azimuth::WEST);
+        let parameters = parameters(Azimuth::EAST);
+        // columns of straight rays, told apart from those of bent rays
+        let straight = PanoramaComputer::new(ContinuousElevationModel::new(wall()))
+            .with_refraction_coefficient(0.)
+            .compute(&parameters);
+        let done: Vec<bool> = (0..21).map(|x| x < 10).collect();
+        checkpoint.save(&straight, &done).unwrap();
+        assert!(checkpoint.load(&other).is_err());
+
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&reports);
+        let computer = PanoramaComputer::new(ContinuousElevationModel::new(wall()))
+            .with_progress(move |done, _| sink.lock().unwrap().push(done));
+        let resumed = computer
+            .compute_resumable(&parameters, &checkpoint, true)
+            .unwrap();
+
+        let bent =
+            PanoramaComputer::new(ContinuousElevationModel::new(wall())).compute(&parameters);
+        assert!((0..21).any(|y| straight.distance_at(5, y) != bent.distance_at(5, y)));
+        for y in 0..21 {
+            assert_eq!(straight.distance_at(5, y), resumed.distance_at(5, y));
+            assert_eq!(bent.distance_at(15, y), resumed.distance_at(15, y));
+        }
+        assert_eq!((11..=21).collect::<Vec<_>>(), *reports.lock().unwrap());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn compute_resumable_from_a_checkpoint_matches_an_uninterrupted_computation() {
+        let path = std::env::temp_dir().join("alpano_resume_exactly.checkpoint");
+        let checkpoint = Checkpoint::new(&path);
+        let computer = PanoramaComputer::new(ContinuousElevationModel::new(wall()));
+        let parameters = parameters(Azimuth::from_degrees(80.));
+        let fresh = computer.compute(&parameters);
+        let done: Vec<bool> = (0..21).map(|x| x % 2 == 0).collect();
+        checkpoint.save(&fresh, &done).unwrap();
+
+        let resumed = computer
+            .compute_resumable(&parameters, &checkpoint, true)
+            .unwrap();
+        assert_eq!(fresh.planes(), resumed.planes());
+        assert_eq!(fresh.shadow, resumed.shadow);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,315 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Error, ErrorKind, Read, Result, Write},
+    path::Path,
+};
+
+use super::{Panorama, PanoramaParameters, ProjectionKind};
+use crate::{azimuth::Azimuth, geo::GeoPoint};
+
+/// First bytes of panorama files.
+const MAGIC: &[u8; 6] = b"ALPANO";
+/// Version of the format of panorama files, incremented when it changes.
+const VERSION: u8 = 3;
+/// Last version of the format storing the planes as `f32` without giving
+/// the size of their values, still read.
+const SINGLE_PRECISION_VERSION: u8 = 2;
+/// Largest number of pixels of the panoramas read, bounding the memory
+/// allocated for corrupt files before their planes are read.
+const MAX_PIXELS: usize = 1 << 28;
+/// Projections, by their number in panorama files.
+const PROJECTIONS: [ProjectionKind; 6] = [
+    ProjectionKind::Equirectangular,
+    ProjectionKind::Cylindrical,
+    ProjectionKind::Rectilinear,
+    ProjectionKind::Mercator,
+    ProjectionKind::Fisheye,
+    ProjectionKind::Stereographic,
+];
+
+/// Panorama files start with [`MAGIC`] and [`VERSION`], followed by the
+/// parameters of the panorama: the longitude and latitude of the observer in
+/// radians, its elevation, the center azimuth, the horizontal field of view
+/// and the maximum distance as little-endian `f64`, the width and height as
+/// little-endian `u32`, the number of the projection in [`PROJECTIONS`] as a
+/// byte, and the size in bytes of the values of the planes as a byte, 4 or
+/// 8. Then come the distance, longitude, latitude, elevation, slope and
+/// aspect of every pixel, each as a plane of little-endian `f32` or `f64`
+/// stored row by row from the top left corner, and a plane of bytes, 1 where
+/// the terrain is in shadow and 0 elsewhere.
+impl Panorama {
+    /// Write the panorama to `writer` in the binary format of panorama
+    /// files, storing the values of its pixels as `f32`.
+    pub fn write_to(&self, writer: impl Write) -> Result<()> {
+        self.write_planes_to(writer, size_of::<f32>())
+    }
+
+    /// Like [`write_to`](Panorama::write_to), but storing the values of the
+    /// pixels as `f64`, so that the panorama read back is the same.
+    pub fn write_full_precision_to(&self, writer: impl Write) -> Result<()> {
+        self.write_planes_to(writer, size_of::<f64>())
+    }
+
+    /// Write the panorama to `writer`, storing the values of its pixels
+    /// with `value_size` bytes.
+    fn write_planes_to(&self, mut writer: impl Write, value_size: usize) -> Result<()> {
+        let parameters = &self.parameters;
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION])?;
+        for value in [
+            parameters.observer_position().longitude(),
+            parameters.observer_position().latitude(),
+            parameters.observer_elevation(),
+            parameters.center_azimuth().radians(),
+            parameters.horizontal_field_of_view(),
+            parameters.max_distance(),
+        ] {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        writer.write_all(&(parameters.width() as u32).to_le_bytes())?;
+        writer.write_all(&(parameters.height() as u32).to_le_bytes())?;
+        let projection = PROJECTIONS
+            .iter()
+            .position(|&p| p == parameters.projection())
+            .unwrap();
+        writer.write_all(&[projection as u8, value_size as u8])?;
+
+        for plane in self.planes() {
+            let bytes: Vec<u8> = if value_size == size_of::<f64>() {
+                plane.iter().flat_map(|&v| v.to_le_bytes()).collect()
+            } else {
+                plane
+                    .iter()
+                    .flat_map(|&v| (v as f32).to_le_bytes())
+                    .collect()
+            };
+            writer.write_all(&bytes)?;
+        }
+        let shadow: Vec<u8> = self.shadows().iter().map(|&s| s as u8).collect();
+        writer.write_all(&shadow)?;
+        Ok(())
+    }
+
+    /// Read a panorama written by [`write_to`](Panorama::write_to) or
+    /// [`write_full_precision_to`](Panorama::write_full_precision_to) from
+    /// `reader`, failing if it is not a valid panorama file.
+    pub fn read_from(mut reader: impl Read) -> Result<Panorama> {
+        let mut magic = [0; 7];
+        reader.read_exact(&mut magic)?;
+        if &magic[..6] != MAGIC {
+            return Err(invalid_data("not a panorama file".to_string()));
+        }
+        let version = magic[6];
+        if version != VERSION && version != SINGLE_PRECISION_VERSION {
+            return Err(invalid_data(format!(
+                "unsupported panorama file version: {}",
+                magic[6]
+            )));
+        }
+
+        let mut values = [0.; 6];
+        for value in &mut values {
+            *value = f64::from_le_bytes(read_bytes(&mut reader)?);
+        }
+        let [longitude, latitude, elevation, azimuth, fov, max_distance] = values;
+        let width = u32::from_le_bytes(read_bytes(&mut reader)?) as usize;
+        let height = u32::from_le_bytes(read_bytes(&mut reader)?) as usize;
+        let [projection] = read_bytes(&mut reader)?;
+        let projection = *PROJECTIONS
+            .get(projection as usize)
+            .ok_or_else(|| invalid_data(format!("unknown projection: {}", projection)))?;
+        let value_size = match version {
+            SINGLE_PRECISION_VERSION => size_of::<f32>(),
+            _ => match read_bytes(&mut reader)? {
+                [4] => size_of::<f32>(),
+                [8] => size_of::<f64>(),
+                [size] => return Err(invalid_data(format!("invalid value size: {}", size))),
+            },
+        };
+        let plane_size = width
+            .checked_mul(height)
+            .filter(|&pixels| pixels <= MAX_PIXELS)
+            .and_then(|pixels| pixels.checked_mul(value_size))
+            .ok_or_else(|| {
+                invalid_data(format!(
+                    "image of {}×{} pixels exceeds the limit of {} pixels",
+                    width, height, MAX_PIXELS
+                ))
+            })?;
+        let parameters = PanoramaParameters::new_with_projection(
+            GeoPoint::new(longitude, latitude).map_err(|e| invalid_data(e.to_string()))?,
+            elevation,
+            Azimuth::try_new(azimuth).map_err(|e| invalid_data(e.to_string()))?,
+            fov,
+            max_distance,
+            width,
+            height,
+            projection,
+        )
+        .map_err(|e| invalid_data(e.to_string()))?;
+
+        let mut panorama = Panorama::empty(&parameters);
+        let mut bytes = vec![0; plane_size];
+        for plane in panorama.planes_mut() {
+            reader.read_exact(&mut bytes)?;
+            for (value, bytes) in plane.iter_mut().zip(bytes.chunks_exact(value_size)) {
+                *value = if value_size == size_of::<f64>() {
+                    f64::from_le_bytes(bytes.try_into().unwrap())
+                } else {
+                    f32::from_le_bytes(bytes.try_into().unwrap()) as f64
+                };
+            }
+        }
+        let mut shadow = vec![0; width * height];
+        reader.read_exact(&mut shadow)?;
+        panorama.shadow = shadow.into_iter().map(|s| s != 0).collect();
+        Ok(panorama)
+    }
+
+    /// Write the panorama to the file at the given path, see
+    /// [`write_to`](Panorama::write_to).
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Like [`save`](Panorama::save), but at full precision, see
+    /// [`write_full_precision_to`](Panorama::write_full_precision_to).
+    pub fn save_full_precision(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_full_precision_to(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Read the panorama of the file at the given path, see
+    /// [`read_from`](Panorama::read_from).
+    pub fn load(path: impl AsRef<Path>) -> Result<Panorama> {
+        Panorama::read_from(BufReader::new(File::open(path)?))
+    }
+
+    pub(super) fn planes(&self) -> [&[f64]; 6] {
+        [
+            self.distances(),
+            self.longitudes(),
+            self.latitudes(),
+            self.elevations(),
+            self.slopes(),
+            self.aspects(),
+        ]
+    }
+
+    fn planes_mut(&mut self) -> [&mut Vec<f64>; 6] {
+        [
+            &mut self.distance,
+            &mut self.longitude,
+            &mut self.latitude,
+            &mut self.elevation,
+            &mut self.slope,
+            &mut self.aspect,
+        ]
+    }
+}
+
+fn read_bytes<const N: usize>(reader: &mut impl Read) -> Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn invalid_data(message: String) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod file_tests {
+    use super::*;
+    use crate::{dem::ContinuousElevationModel, fixtures::terrain, panorama::PanoramaComputer};
+
+    fn panorama() -> Panorama {
+        let dem = terrain(|x, y| (20 * x + 30 * y) as f64 - 14_000.);
+        let parameters = PanoramaParameters::new(
+            GeoPoint::from_degrees(7.5, 46.5).unwrap(),
+            1000.,
+            Azimuth::from_degrees(200.),
+            30_f64.to_radians(),
+            50_000.,
+            21,
+            11,
+        )
+        .unwrap()
+        .with_projection(ProjectionKind::Rectilinear)
+        .unwrap();
+        PanoramaComputer::new(ContinuousElevationModel::new(dem))
+            .with_shadows(Azimuth::from_degrees(100.), 5_f64.to_radians())
+            .compute(&parameters)
+    }
+
+    #[test]
+    fn read_from_reads_what_write_to_writes() {
+        let panorama = panorama();
+        let mut bytes = Vec::new();
+        panorama.write_to(&mut bytes).unwrap();
+        assert_eq!(7 + 6 * 8 + 2 * 4 + 2 + 25 * 21 * 11, bytes.len());
+        let read = Panorama::read_from(bytes.as_slice()).unwrap();
+
+        assert_eq!(panorama.parameters(), read.parameters());
+        assert!(read.distance_at(10, 0).is_infinite());
+        for (original, read) in panorama.planes().into_iter().zip(read.planes()) {
+            for (&o, &r) in original.iter().zip(read) {
+                assert_eq!(o as f32 as f64, r);
+            }
+        }
+        assert_eq!(panorama.shadow, read.shadow);
+    }
+
+    #[test]
+    fn read_from_reads_full_precision_panoramas_exactly() {
+        let panorama = panorama();
+        let mut bytes = Vec::new();
+        panorama.write_full_precision_to(&mut bytes).unwrap();
+        assert_eq!(7 + 6 * 8 + 2 * 4 + 2 + 49 * 21 * 11, bytes.len());
+        let read = Panorama::read_from(bytes.as_slice()).unwrap();
+        assert_eq!(panorama.parameters(), read.parameters());
+        assert_eq!(panorama.planes(), read.planes());
+        assert_eq!(panorama.shadow, read.shadow);
+    }
+
+    #[test]
+    fn read_from_reads_files_of_the_previous_version() {
+        let panorama = panorama();
+        let mut bytes = Vec::new();
+        panorama.write_to(&mut bytes).unwrap();
+        // without the size of the values
+        bytes[6] = SINGLE_PRECISION_VERSION;
+        bytes.remove(7 + 6 * 8 + 2 * 4 + 1);
+        let read = Panorama::read_from(bytes.as_slice()).unwrap();
+        assert_eq!(panorama.parameters(), read.parameters());
+        assert_eq!(
+            panorama.distance_at(3, 8) as f32 as f64,
+            read.distance_at(3, 8)
+        );
+    }
+
+    #[test]
+    fn read_from_fails_on_invalid_files() {
+        let mut bytes = Vec::new();
+        panorama().write_to(&mut bytes).unwrap();
+
+        let kind = |bytes: &[u8]| Panorama::read_from(bytes).err().unwrap().kind();
+        assert_eq!(ErrorKind::InvalidData, kind(b"PANORAMA"));
+        let mut version = bytes.clone();
+        version[6] = VERSION + 1;
+        assert_eq!(ErrorKind::InvalidData, kind(&version));
+        let mut projection = bytes.clone();
+        projection[7 + 6 * 8 + 2 * 4] = 6;
+        assert_eq!(ErrorKind::InvalidData, kind(&projection));
+        let mut value_size = bytes.clone();
+        value_size[7 + 6 * 8 + 2 * 4 + 1] = 2;
+        assert_eq!(ErrorKind::InvalidData, kind(&value_size));
+        let mut size = bytes.clone();
+        size[7 + 6 * 8..7 + 6 * 8 + 2 * 4].fill(0xff);
+        assert_eq!(ErrorKind::InvalidData, kind(&size));
+        assert_eq!(ErrorKind::UnexpectedEof, kind(&bytes[..bytes.len() - 1]));
+    }
+}
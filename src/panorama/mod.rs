@@ -0,0 +1,21 @@
+#[cfg(not(target_arch = "wasm32"))]
+mod cache;
+#[cfg(not(target_arch = "wasm32"))]
+mod checkpoint;
+mod computer;
+mod file;
+mod parameters;
+mod progress;
+mod projection;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use cache::PanoramaCache;
+#[cfg(not(target_arch = "wasm32"))]
+pub use checkpoint::Checkpoint;
+pub(crate) use computer::ray_to_ground_distance;
+pub use computer::{Panorama, PanoramaComputer};
+pub use parameters::{PanoramaParameters, RenderError};
+pub use progress::ProgressSink;
+pub use projection::{
+    Cylindrical, Equirectangular, Mercator, Projection, ProjectionKind, Rectilinear,
+};
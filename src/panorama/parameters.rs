@@ -0,0 +1,698 @@
+use std::f64::consts::TAU;
+
+use thiserror::Error;
+
+use super::{Projection, ProjectionKind};
+use crate::{
+    azimuth::Azimuth,
+    geo::GeoPoint,
+    interval::{Interval1D, Interval2D},
+    math::angular_distance,
+};
+
+/// Errors on panorama parameters.
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum RenderError {
+    #[error("invalid observer elevation: {0}")]
+    InvalidObserverElevation(f64),
+    #[error("observer elevation is not given")]
+    UnknownObserverElevation,
+    #[error("horizontal field of view {0} is not in (0, 2π] or not supported by the projection")]
+    InvalidFieldOfView(f64),
+    #[error("maximum distance {0} is not positive")]
+    InvalidMaxDistance(f64),
+    #[error("image of {0}×{1} pixels is too small")]
+    ImageTooSmall(usize, usize),
+    #[error("vertical field of view {0} is too large for the projection")]
+    VerticalFieldOfViewTooLarge(f64),
+    #[error("azimuth {0} is outside of the field of view")]
+    AzimuthOutOfView(Azimuth),
+    #[error("altitude {0} is outside of the field of view")]
+    AltitudeOutOfView(f64),
+    #[error("the render needs {0} MiB of memory, more than the maximum of {1} MiB")]
+    MemoryExceeded(usize, usize),
+}
+
+/// Parameters of a panorama: where it is seen from, in which direction, how
+/// far, and the size of its image.
+///
+/// Directions are projected on the image by a [`Projection`], equirectangular
+/// by default. Pixels are square at the center of the image: the vertical
+/// field of view follows from the horizontal one, the image dimensions and
+/// the projection, and is centered on the horizontal plane.
+///
+/// With a horizontal field of view of `2π` and a projection keeping a single
+/// azimuth in each column, the panorama goes full circle:
+/// the first column follows the last one, without seam, as in equirectangular
+/// images.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "UncheckedParameters")
+)]
+pub struct PanoramaParameters {
+    observer_position: GeoPoint,
+    observer_elevation: f64,
+    center_azimuth: Azimuth,
+    horizontal_field_of_view: f64,
+    max_distance: f64,
+    width: usize,
+    height: usize,
+    projection: ProjectionKind,
+}
+
+/// Parameters validated by [`PanoramaParameters::new_with_projection`] when
+/// deserialized.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct UncheckedParameters {
+    observer_position: GeoPoint,
+    observer_elevation: f64,
+    center_azimuth: Azimuth,
+    horizontal_field_of_view: f64,
+    max_distance: f64,
+    width: usize,
+    height: usize,
+    #[serde(default)]
+    projection: ProjectionKind,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<UncheckedParameters> for PanoramaParameters {
+    type Error = RenderError;
+
+    fn try_from(p: UncheckedParameters) -> Result<PanoramaParameters, RenderError> {
+        PanoramaParameters::new_with_projection(
+            p.observer_position,
+            p.observer_elevation,
+            p.center_azimuth,
+            p.horizontal_field_of_view,
+            p.max_distance,
+            p.width,
+            p.height,
+            p.projection,
+        )
+    }
+}
+
+impl PanoramaParameters {
+    /// Create parameters, failing unless the field of view is in `(0, 2π]`,
+    /// the maximum distance is positive, the image is at least 2×2 pixels and
+    /// the vertical field of view does not exceed `π`.
+    pub fn new(
+        observer_position: GeoPoint,
+        observer_elevation: f64,
+        center_azimuth: Azimuth,
+        horizontal_field_of_view: f64,
+        max_distance: f64,
+        width: usize,
+        height: usize,
+    ) -> Result<PanoramaParameters, RenderError> {
+        PanoramaParameters::new_with_projection(
+            observer_position,
+            observer_elevation,
+            center_azimuth,
+            horizontal_field_of_view,
+            max_distance,
+            width,
+            height,
+            ProjectionKind::default(),
+        )
+    }
+
+    /// Like [`new`](PanoramaParameters::new), but projecting directions with
+    /// `projection`, which must support the fields of view.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_with_projection(
+        observer_position: GeoPoint,
+        observer_elevation: f64,
+        center_azimuth: Azimuth,
+        horizontal_field_of_view: f64,
+        max_distance: f64,
+        width: usize,
+        height: usize,
+        projection: ProjectionKind,
+    ) -> Result<PanoramaParameters, RenderError> {
+        if !observer_elevation.is_finite() {
+            return Err(RenderError::InvalidObserverElevation(observer_elevation));
+        }
+        if horizontal_field_of_view < f64::MIN_POSITIVE {
+            return Err(RenderError::InvalidFieldOfView(horizontal_field_of_view));
+        }
+        if !(f64::MIN_POSITIVE..=f64::MAX).contains(&max_distance) {
+            return Err(RenderError::InvalidMaxDistance(max_distance));
+        }
+        if width < 2 || height < 2 {
+            return Err(RenderError::ImageTooSmall(width, height));
+        }
+
+        PanoramaParameters {
+            observer_position,
+            observer_elevation,
+            center_azimuth,
+            horizontal_field_of_view,
+            max_distance,
+            width,
+            height,
+            projection,
+        }
+        .validated()
+    }
+
+    /// Project directions with `projection` instead, failing unless it
+    /// supports the fields of view.
+    pub fn with_projection(
+        self,
+        projection: ProjectionKind,
+    ) -> Result<PanoramaParameters, RenderError> {
+        PanoramaParameters { projection, ..self }.validated()
+    }
+
+    /// Parameters, failing if their fields of view are invalid.
+    fn validated(self) -> Result<PanoramaParameters, RenderError> {
+        if !self
+            .projection
+            .supports_field_of_view(self.horizontal_field_of_view)
+        {
+            return Err(RenderError::InvalidFieldOfView(
+                self.horizontal_field_of_view,
+            ));
+        }
+        if !self
+            .projection
+            .supports_vertical_field_of_view(self.vertical_field_of_view())
+        {
+            return Err(RenderError::VerticalFieldOfViewTooLarge(
+                self.vertical_field_of_view(),
+            ));
+        }
+        Ok(self)
+    }
+
+    /// Create the parameters of a full circle panorama covering the whole
+    /// sphere, from the nadir to the zenith, like the equirectangular images
+    /// of panorama viewers, which are twice as wide as high.
+    pub fn equirectangular(
+        observer_position: GeoPoint,
+        observer_elevation: f64,
+        center_azimuth: Azimuth,
+        max_distance: f64,
+        width: usize,
+    ) -> Result<PanoramaParameters, RenderError> {
+        PanoramaParameters::new(
+            observer_position,
+            observer_elevation,
+            center_azimuth,
+            TAU,
+            max_distance,
+            width,
+            width / 2,
+        )
+    }
+
+    pub fn observer_position(&self) -> GeoPoint {
+        self.observer_position
+    }
+
+    /// Elevation of the observer, in meters.
+    pub fn observer_elevation(&self) -> f64 {
+        self.observer_elevation
+    }
+
+    pub fn center_azimuth(&self) -> Azimuth {
+        self.center_azimuth
+    }
+
+    pub fn horizontal_field_of_view(&self) -> f64 {
+        self.horizontal_field_of_view
+    }
+
+    /// Whether the panorama goes full circle, its last column being followed
+    /// by its first one.
+    pub fn is_full_circle(&self) -> bool {
+        self.horizontal_field_of_view == TAU && self.projection.keeps_azimuth_along_columns()
+    }
+
+    pub fn vertical_field_of_view(&self) -> f64 {
+        let top = (self.height - 1) as f64 / 2. * self.angle_per_pixel();
+        self.projection.vertical_field_of_view(top)
+    }
+
+    pub fn projection(&self) -> ProjectionKind {
+        self.projection
+    }
+
+    /// Maximum distance of the visible terrain, in meters.
+    pub fn max_distance(&self) -> f64 {
+        self.max_distance
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Angle between two adjacent pixels at the center of the image,
+    /// horizontally or vertically.
+    pub fn angle_per_pixel(&self) -> f64 {
+        if self.is_full_circle() {
+            // the last column is adjacent to the first one
+            TAU / self.width as f64
+        } else {
+            2. * self
+                .projection
+                .horizontal_extent(self.horizontal_field_of_view)
+                / (self.width - 1) as f64
+        }
+    }
+
+    /// Azimuth and altitude of the direction seen at the (possibly
+    /// fractional) pixel `(x, y)`, which must be in the image.
+    pub fn direction_at(&self, x: f64, y: f64) -> (Azimuth, f64) {
+        assert!(
+            (0. ..=(self.width - 1) as f64).contains(&x)
+                && (0. ..=(self.height - 1) as f64).contains(&y),
+            "pixel ({}, {}) outside of image",
+            x,
+            y
+        );
+        let u = (x - (self.width - 1) as f64 / 2.) * self.angle_per_pixel();
+        let v = ((self.height - 1) as f64 / 2. - y) * self.angle_per_pixel();
+        let (azimuth, altitude) = self.projection.unproject(u, v);
+        (self.center_azimuth + azimuth, altitude)
+    }
+
+    /// Pixel at which the direction of the given azimuth and altitude is
+    /// seen, failing if it is outside of the field of view. In full circle
+    /// panoramas, azimuths between the last and the first columns are at the
+    /// nearest of them.
+    pub fn pixel_for(&self, azimuth: Azimuth, altitude: f64) -> Result<(f64, f64), RenderError> {
+        let offset = angular_distance(self.center_azimuth.radians(), azimuth.radians());
+        let (u, v) = self
+            .projection
+            .project(offset, altitude)
+            .ok_or(RenderError::AzimuthOutOfView(azimuth))?;
+        let x = (self.width - 1) as f64 / 2. + u / self.angle_per_pixel();
+        let y = (self.height - 1) as f64 / 2. - v / self.angle_per_pixel();
+
+        let x = if self.is_full_circle() {
+            let x = x.rem_euclid(self.width as f64);
+            let last = (self.width - 1) as f64;
+            if x <= last {
+                x
+            } else if x - last < 0.5 {
+                last
+            } else {
+                0.
+            }
+        } else {
+            clamp_to_image(x, self.width).ok_or(RenderError::AzimuthOutOfView(azimuth))?
+        };
+        let y = clamp_to_image(y, self.height).ok_or(RenderError::AltitudeOutOfView(altitude))?;
+        Ok((x, y))
+    }
+
+    /// Indices of the samples of the panorama, from `(0, 0)` at the top left
+    /// to `(width - 1, height - 1)` at the bottom right.
+    pub fn sample_grid(&self) -> Interval2D {
+        Interval2D::new(
+            Interval1D::new(0, self.width as i32 - 1),
+            Interval1D::new(0, self.height as i32 - 1),
+        )
+    }
+
+    pub fn is_valid_sample_index(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.height
+    }
+
+    /// Index of the pixel `(x, y)` in buffers stored row by row.
+    pub fn linear_sample_index(&self, x: usize, y: usize) -> usize {
+        assert!(self.is_valid_sample_index(x, y));
+        y * self.width + x
+    }
+}
+
+/// Clamp the coordinate `v` to `[0, size - 1]`, tolerating rounding errors
+/// on the edges of the image but failing beyond them.
+fn clamp_to_image(v: f64, size: usize) -> Option<f64> {
+    const EPSILON: f64 = 1e-6;
+    let max = (size - 1) as f64;
+    if (-EPSILON..=max + EPSILON).contains(&v) {
+        Some(v.clamp(0., max))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod parameters_tests {
+    use super::*;
+    use crate::math::fast_trig_tolerance;
+    use assert_approx_eq::assert_approx_eq;
+    use rand::Rng;
+    use std::f64::consts::{FRAC_PI_2, PI};
+
+    fn niesen() -> PanoramaParameters {
+        PanoramaParameters::new(
+            GeoPoint::from_degrees(7.6496, 46.6456).unwrap(),
+            2362.,
+            Azimuth::from_degrees(180.),
+            60_f64.to_radians(),
+            100_000.,
+            2501,
+            801,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn new_fails_for_invalid_parameters() {
+        let p = GeoPoint::from_degrees(7.6496, 46.6456).unwrap();
+        let az = Azimuth::NORTH;
+        assert!(PanoramaParameters::new(p, 2362., az, 0., 100_000., 2501, 801).is_err());
+        assert!(PanoramaParameters::new(p, 2362., az, TAU + 1e-9, 100_000., 2501, 801).is_err());
+        assert!(PanoramaParameters::new(p, 2362., az, 1., 0., 2501, 801).is_err());
+        assert!(PanoramaParameters::new(p, 2362., az, 1., 100_000., 1, 801).is_err());
+        assert!(PanoramaParameters::new(p, 2362., az, 1., 100_000., 2501, 1).is_err());
+        assert!(PanoramaParameters::new(p, f64::NAN, az, 1., 100_000., 2501, 801).is_err());
+        // vertical field of view of 4 rad
+        assert!(PanoramaParameters::new(p, 2362., az, 1., 100_000., 11, 41).is_err());
+        assert!(PanoramaParameters::new(p, 2362., az, TAU, 100_000., 3601, 1800).is_ok());
+    }
+
+    #[test]
+    fn new_reports_the_invalid_parameter() {
+        let p = GeoPoint::from_degrees(7.6496, 46.6456).unwrap();
+        let az = Azimuth::NORTH;
+        assert_eq!(
+            Err(RenderError::InvalidFieldOfView(0.)),
+            PanoramaParameters::new(p, 2362., az, 0., 100_000., 2501, 801)
+        );
+        assert_eq!(
+            Err(RenderError::ImageTooSmall(1, 801)),
+            PanoramaParameters::new(p, 2362., az, 1., 100_000., 1, 801)
+        );
+        assert_eq!(
+            Err(RenderError::VerticalFieldOfViewTooLarge(4.)),
+            PanoramaParameters::new(p, 2362., az, 1., 100_000., 11, 41)
+        );
+    }
+
+    #[test]
+    fn derived_values_are_correct() {
+        let p = niesen();
+        assert_approx_eq!(60_f64.to_radians() / 2500., p.angle_per_pixel(), 1e-15);
+        assert_approx_eq!(19.2_f64.to_radians(), p.vertical_field_of_view(), 1e-12);
+        assert_eq!(2501 * 3 + 4, p.linear_sample_index(4, 3));
+        assert!(p.is_valid_sample_index(2500, 800));
+        assert!(!p.is_valid_sample_index(2501, 0));
+        assert_eq!(2501 * 801, p.sample_grid().size());
+    }
+
+    #[test]
+    fn direction_at_is_correct_on_known_values() {
+        let p = niesen();
+        assert_approx_eq!(150., p.direction_at(0., 400.).0.degrees(), 1e-9);
+        assert_approx_eq!(180., p.direction_at(1250., 400.).0.degrees(), 1e-9);
+        assert_approx_eq!(210., p.direction_at(2500., 400.).0.degrees(), 1e-9);
+        assert_approx_eq!(9.6, p.direction_at(1250., 0.).1.to_degrees(), 1e-9);
+        assert_approx_eq!(0., p.direction_at(1250., 400.).1, 1e-15);
+        assert_approx_eq!(-9.6, p.direction_at(1250., 800.).1.to_degrees(), 1e-9);
+    }
+
+    #[test]
+    fn direction_at_wraps_around_north() {
+        let p = PanoramaParameters::new(
+            GeoPoint::new(0., 0.).unwrap(),
+            0.,
+            Azimuth::NORTH,
+            FRAC_PI_2,
+            1000.,
+            101,
+            11,
+        )
+        .unwrap();
+        assert_approx_eq!(315., p.direction_at(0., 5.).0.degrees(), 1e-9);
+        assert_approx_eq!(45., p.direction_at(100., 5.).0.degrees(), 1e-9);
+        assert_approx_eq!(
+            0.,
+            p.pixel_for(Azimuth::from_degrees(315.), 0.).unwrap().0,
+            1e-9
+        );
+    }
+
+    #[test]
+    fn full_circle_has_no_seam() {
+        let p = PanoramaParameters::new(
+            GeoPoint::new(0., 0.).unwrap(),
+            0.,
+            Azimuth::SOUTH,
+            TAU,
+            1000.,
+            360,
+            100,
+        )
+        .unwrap();
+        assert!(p.is_full_circle());
+        assert!(!niesen().is_full_circle());
+        assert_approx_eq!(1_f64.to_radians(), p.angle_per_pixel(), 1e-12);
+        let (first, _) = p.direction_at(0., 0.);
+        let (last, _) = p.direction_at(359., 0.);
+        assert_approx_eq!(
+            1.,
+            angular_distance(last.radians(), first.radians()).to_degrees(),
+            1e-9
+        );
+
+        let mut rng = rand::thread_rng();
+        for _ in 0..500 {
+            let x = rng.gen_range(0. ..=359.);
+            let (azimuth, altitude) = p.direction_at(x, 50.);
+            assert_approx_eq!(x, p.pixel_for(azimuth, altitude).unwrap().0, 1e-6);
+            let azimuth = Azimuth::from_degrees(rng.gen_range(0. ..360.));
+            assert!(p.pixel_for(azimuth, 0.).is_ok());
+        }
+        // in the seam, nearer to the last column then to the first one
+        let seam = first.radians() - 0.6_f64.to_radians();
+        assert_approx_eq!(359., p.pixel_for(Azimuth::new(seam), 0.).unwrap().0, 1e-9);
+        let seam = first.radians() - 0.4_f64.to_radians();
+        assert_eq!(0., p.pixel_for(Azimuth::new(seam), 0.).unwrap().0);
+    }
+
+    #[test]
+    fn equirectangular_covers_the_whole_sphere() {
+        let p = PanoramaParameters::equirectangular(
+            GeoPoint::new(0., 0.).unwrap(),
+            0.,
+            Azimuth::NORTH,
+            1000.,
+            720,
+        )
+        .unwrap();
+        assert_eq!((720, 360), (p.width(), p.height()));
+        // rows are centered on pixels half a pixel from the poles
+        assert_approx_eq!(89.75, p.direction_at(0., 0.).1.to_degrees(), 1e-9);
+        assert_approx_eq!(-89.75, p.direction_at(0., 359.).1.to_degrees(), 1e-9);
+        assert_approx_eq!(
+            180.,
+            (p.vertical_field_of_view() + p.angle_per_pixel()).to_degrees(),
+            1e-9
+        );
+    }
+
+    #[test]
+    fn pixel_for_is_inverse_of_direction_at() {
+        let mut rng = rand::thread_rng();
+
+        for projection in [
+            ProjectionKind::Equirectangular,
+            ProjectionKind::Cylindrical,
+            ProjectionKind::Rectilinear,
+            ProjectionKind::Mercator,
+        ] {
+            let p = niesen().with_projection(projection).unwrap();
+            for _ in 0..500 {
+                let x = rng.gen_range(0. ..=2500.);
+                let y = rng.gen_range(0. ..=800.);
+                let (azimuth, altitude) = p.direction_at(x, y);
+                let (x1, y1) = p.pixel_for(azimuth, altitude).unwrap();
+                assert_approx_eq!(x, x1, 1e-6);
+                assert_approx_eq!(y, y1, 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn pixel_for_fails_outside_of_field_of_view() {
+        let p = niesen();
+        assert!(p.pixel_for(Azimuth::from_degrees(149.9), 0.).is_err());
+        assert!(p.pixel_for(Azimuth::from_degrees(210.1), 0.).is_err());
+        assert_eq!(
+            Err(RenderError::AzimuthOutOfView(Azimuth::NORTH)),
+            p.pixel_for(Azimuth::NORTH, 0.)
+        );
+        assert_eq!(
+            Err(RenderError::AltitudeOutOfView(0.2)),
+            p.pixel_for(Azimuth::SOUTH, 0.2)
+        );
+        let rectilinear = niesen()
+            .with_projection(ProjectionKind::Rectilinear)
+            .unwrap();
+        assert!(rectilinear.pixel_for(Azimuth::NORTH, 0.).is_err());
+    }
+
+    #[test]
+    fn projections_keep_the_center_and_the_edges_of_the_field_of_view() {
+        for projection in [
+            ProjectionKind::Cylindrical,
+            ProjectionKind::Rectilinear,
+            ProjectionKind::Mercator,
+        ] {
+            let p = niesen().with_projection(projection).unwrap();
+            assert_eq!(projection, p.projection());
+            assert_approx_eq!(150., p.direction_at(0., 400.).0.degrees(), 1e-9);
+            assert_approx_eq!(210., p.direction_at(2500., 400.).0.degrees(), 1e-9);
+            assert_approx_eq!(0., p.direction_at(1250., 400.).1, 1e-15);
+            // rows are not evenly spaced in altitude
+            let (_, top) = p.direction_at(1250., 0.);
+            assert!((top - p.vertical_field_of_view() / 2.).abs() < 1e-12);
+            assert!((top - 9.6_f64.to_radians()).abs() > 1e-4);
+        }
+    }
+
+    #[test]
+    fn rectilinear_keeps_horizon_straight_and_bends_other_rows() {
+        let p = niesen()
+            .with_projection(ProjectionKind::Rectilinear)
+            .unwrap();
+        assert_approx_eq!(0., p.direction_at(0., 400.).1, 1e-15);
+        assert!(p.direction_at(0., 0.).1 < p.direction_at(1250., 0.).1);
+    }
+
+    #[test]
+    fn little_planet_sees_the_nadir_at_the_center_and_the_horizon_as_a_circle() {
+        let p = PanoramaParameters::new_with_projection(
+            GeoPoint::new(0., 0.).unwrap(),
+            0.,
+            Azimuth::EAST,
+            270_f64.to_radians(),
+            1000.,
+            1001,
+            1001,
+            ProjectionKind::Stereographic,
+        )
+        .unwrap();
+        assert!(!p.is_full_circle());
+        assert_approx_eq!(270_f64.to_radians(), p.vertical_field_of_view(), 1e-9);
+        assert_approx_eq!(-FRAC_PI_2, p.direction_at(500., 500.).1, 1e-12);
+        assert_approx_eq!(90., p.direction_at(500., 0.).0.degrees(), 1e-9);
+        assert_approx_eq!(270., p.direction_at(500., 1000.).0.degrees(), 1e-9);
+        assert_approx_eq!(180., p.direction_at(1000., 500.).0.degrees(), 1e-9);
+
+        // trigonometric tables err by a hundredth of a pixel far from the
+        // nadir, where pixels span large angles
+        let pixel_tolerance = if cfg!(feature = "trig-lut") {
+            1e-2
+        } else {
+            1e-6
+        };
+        let mut rng = rand::thread_rng();
+        for _ in 0..500 {
+            let azimuth = Azimuth::from_degrees(rng.gen_range(0. ..360.));
+            let (x, y) = p.pixel_for(azimuth, 0.).unwrap();
+            let r = (x - 500.).hypot(y - 500.) * p.angle_per_pixel();
+            assert_approx_eq!(2., r, fast_trig_tolerance(1e-9));
+            let (x, y) = (rng.gen_range(0. ..=1000.), rng.gen_range(0. ..=1000.));
+            let (azimuth, altitude) = p.direction_at(x, y);
+            let (x1, y1) = p.pixel_for(azimuth, altitude).unwrap();
+            assert_approx_eq!(x, x1, pixel_tolerance);
+            assert_approx_eq!(y, y1, pixel_tolerance);
+        }
+        // the zenith is never seen
+        assert!(p.pixel_for(Azimuth::NORTH, FRAC_PI_2).is_err());
+    }
+
+    #[test]
+    fn fisheye_does_not_go_full_circle() {
+        let p = PanoramaParameters::new_with_projection(
+            GeoPoint::new(0., 0.).unwrap(),
+            0.,
+            Azimuth::NORTH,
+            TAU,
+            1000.,
+            101,
+            91,
+            ProjectionKind::Fisheye,
+        )
+        .unwrap();
+        assert!(!p.is_full_circle());
+        assert_approx_eq!(324_f64.to_radians(), p.vertical_field_of_view(), 1e-9);
+        // straight up and behind, over the zenith
+        let tolerance = fast_trig_tolerance(1e-9);
+        assert_approx_eq!(FRAC_PI_2, p.direction_at(50., 20.).1, tolerance);
+        assert_approx_eq!(180., p.direction_at(50., 10.).0.degrees(), tolerance);
+        assert_approx_eq!(54_f64.to_radians(), p.direction_at(50., 10.).1, tolerance);
+        assert!(PanoramaParameters::new(
+            GeoPoint::new(0., 0.).unwrap(),
+            0.,
+            Azimuth::NORTH,
+            TAU,
+            1000.,
+            101,
+            101,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn with_projection_fails_for_unsupported_field_of_view() {
+        let p = PanoramaParameters::new(
+            GeoPoint::new(0., 0.).unwrap(),
+            0.,
+            Azimuth::NORTH,
+            PI,
+            1000.,
+            101,
+            11,
+        )
+        .unwrap();
+        assert_eq!(
+            Err(RenderError::InvalidFieldOfView(PI)),
+            p.with_projection(ProjectionKind::Rectilinear)
+        );
+        assert!(p.with_projection(ProjectionKind::Mercator).is_ok());
+        // the vertical field of view of a cylindrical projection is below π
+        assert!(PanoramaParameters::equirectangular(
+            GeoPoint::new(0., 0.).unwrap(),
+            0.,
+            Azimuth::NORTH,
+            1000.,
+            720,
+        )
+        .unwrap()
+        .with_projection(ProjectionKind::Cylindrical)
+        .is_ok());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trips_and_validates() {
+        let toml = toml::to_string(&niesen()).unwrap();
+        assert_eq!(niesen(), toml::from_str(&toml).unwrap());
+
+        let invalid = toml.replace("width = 2501", "width = 1");
+        assert!(toml::from_str::<PanoramaParameters>(&invalid).is_err());
+        let invalid = toml.replace("center_azimuth = ", "center_azimuth = -");
+        assert!(toml::from_str::<PanoramaParameters>(&invalid).is_err());
+    }
+
+    #[test]
+    #[should_panic]
+    fn direction_at_panics_outside_of_image() {
+        niesen().direction_at(2500.5, 0.);
+    }
+}
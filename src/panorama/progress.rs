@@ -0,0 +1,15 @@
+/// Receives the progress of panorama computations.
+///
+/// Closures taking the number of computed columns and the total number of
+/// columns are progress sinks.
+pub trait ProgressSink: Send + Sync {
+    /// Called after each computed column. With parallel computations, calls
+    /// may come from several threads and out of order.
+    fn columns_done(&self, done: usize, total: usize);
+}
+
+impl<F: Fn(usize, usize) + Send + Sync> ProgressSink for F {
+    fn columns_done(&self, done: usize, total: usize) {
+        self(done, total)
+    }
+}
@@ -0,0 +1,422 @@
+use std::f64::consts::{FRAC_PI_2, PI, TAU};
+
+use crate::math::{fast_cos, fast_sin};
+
+/// Projection of the directions seen from the observer on the image plane of
+/// panoramas.
+///
+/// Directions are given by their azimuth relative to the center of the
+/// panorama, positive to the right, and their altitude. Points of the image
+/// plane are given by their coordinates relative to its center, rightward and
+/// upward, scaled so that they match angles at the center.
+///
+/// The default methods suit projections which, like the equirectangular one,
+/// see the horizon along the middle row and a single azimuth in each column.
+pub trait Projection {
+    /// Point of the image plane at which the given direction is seen, if it
+    /// can be projected.
+    fn project(&self, azimuth: f64, altitude: f64) -> Option<(f64, f64)>;
+
+    /// Azimuth and altitude of the direction seen at the point `(u, v)` of the
+    /// image plane.
+    fn unproject(&self, u: f64, v: f64) -> (f64, f64);
+
+    /// Whether a panorama can have the given horizontal field of view.
+    fn supports_field_of_view(&self, horizontal_field_of_view: f64) -> bool {
+        horizontal_field_of_view <= TAU
+    }
+
+    /// Whether a panorama can have the given vertical field of view.
+    fn supports_vertical_field_of_view(&self, vertical_field_of_view: f64) -> bool {
+        vertical_field_of_view <= PI
+    }
+
+    /// Coordinate `u` of the right edge of images of the given horizontal
+    /// field of view, which must be supported.
+    fn horizontal_extent(&self, horizontal_field_of_view: f64) -> f64 {
+        self.project(horizontal_field_of_view / 2., 0.)
+            .expect("field of view is supported by the projection")
+            .0
+    }
+
+    /// Vertical field of view of images whose top edge is at coordinate `v`.
+    fn vertical_field_of_view(&self, v: f64) -> f64 {
+        2. * self.unproject(0., v).1
+    }
+
+    /// Whether all the points of a column of the image plane are seen at
+    /// the same azimuth.
+    fn keeps_azimuth_along_columns(&self) -> bool {
+        true
+    }
+}
+
+/// Projection whose coordinates are the angles themselves, distorting
+/// neither azimuths nor altitudes: equal angles take equal numbers of pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Equirectangular;
+
+impl Projection for Equirectangular {
+    fn project(&self, azimuth: f64, altitude: f64) -> Option<(f64, f64)> {
+        Some((azimuth, altitude))
+    }
+
+    fn unproject(&self, u: f64, v: f64) -> (f64, f64) {
+        (u, v)
+    }
+}
+
+/// Projection on a cylinder around the observer, which keeps vertical lines
+/// straight but stretches altitudes away from the horizon.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cylindrical;
+
+impl Projection for Cylindrical {
+    fn project(&self, azimuth: f64, altitude: f64) -> Option<(f64, f64)> {
+        Some((azimuth, altitude.tan()))
+    }
+
+    fn unproject(&self, u: f64, v: f64) -> (f64, f64) {
+        (u, v.atan())
+    }
+}
+
+/// Projection of a pinhole camera, which keeps all straight lines straight
+/// like photographs, for fields of view narrower than `π`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rectilinear;
+
+impl Projection for Rectilinear {
+    fn project(&self, azimuth: f64, altitude: f64) -> Option<(f64, f64)> {
+        (azimuth.abs() < FRAC_PI_2).then(|| (azimuth.tan(), altitude.tan() / azimuth.cos()))
+    }
+
+    fn unproject(&self, u: f64, v: f64) -> (f64, f64) {
+        (u.atan(), (v / u.hypot(1.)).atan())
+    }
+
+    fn supports_field_of_view(&self, horizontal_field_of_view: f64) -> bool {
+        horizontal_field_of_view < PI
+    }
+}
+
+/// Projection stretching altitudes like the Mercator projection stretches
+/// latitudes, so that shapes are locally preserved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mercator;
+
+impl Projection for Mercator {
+    fn project(&self, azimuth: f64, altitude: f64) -> Option<(f64, f64)> {
+        Some((azimuth, altitude.tan().asinh()))
+    }
+
+    fn unproject(&self, u: f64, v: f64) -> (f64, f64) {
+        (u, v.sinh().atan())
+    }
+}
+
+/// Azimuthal equidistant projection centered on the center of the panorama,
+/// like fisheye lenses: distances from the center of the image are
+/// proportional to angles from the center of the view, up to the whole
+/// sphere in a circle of radius `π`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fisheye;
+
+impl Projection for Fisheye {
+    fn project(&self, azimuth: f64, altitude: f64) -> Option<(f64, f64)> {
+        // rightward, upward and forward coordinates of the direction
+        let x = fast_cos(altitude) * fast_sin(azimuth);
+        let y = fast_sin(altitude);
+        let z = fast_cos(altitude) * fast_cos(azimuth);
+        let r = x.hypot(y);
+        let angle = r.atan2(z);
+        if r == 0. {
+            return Some((angle, 0.));
+        }
+        Some((angle * x / r, angle * y / r))
+    }
+
+    fn unproject(&self, u: f64, v: f64) -> (f64, f64) {
+        let angle = u.hypot(v);
+        if angle == 0. {
+            return (0., 0.);
+        }
+        let (x, y) = (fast_sin(angle) * u / angle, fast_sin(angle) * v / angle);
+        (x.atan2(fast_cos(angle)), y.clamp(-1., 1.).asin())
+    }
+
+    fn supports_vertical_field_of_view(&self, vertical_field_of_view: f64) -> bool {
+        vertical_field_of_view <= TAU
+    }
+
+    fn vertical_field_of_view(&self, v: f64) -> f64 {
+        2. * v
+    }
+
+    fn keeps_azimuth_along_columns(&self) -> bool {
+        false
+    }
+}
+
+/// Stereographic projection centered on the nadir, which renders all-around
+/// views as "little planets": the terrain below the observer is at the
+/// center of the image, the horizon is a circle of radius 2 around it, the
+/// sky is outside of it, and the center azimuth of the panorama is upward.
+///
+/// Fields of view are measured across the center of the image, from the
+/// nadir, and must be below `2π`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stereographic;
+
+impl Projection for Stereographic {
+    fn project(&self, azimuth: f64, altitude: f64) -> Option<(f64, f64)> {
+        // the zenith is infinitely far from the center
+        (altitude < FRAC_PI_2).then(|| {
+            let r = 2. * ((altitude + FRAC_PI_2) / 2.).tan();
+            (r * fast_sin(azimuth), r * fast_cos(azimuth))
+        })
+    }
+
+    fn unproject(&self, u: f64, v: f64) -> (f64, f64) {
+        let r = u.hypot(v);
+        let azimuth = if r == 0. { 0. } else { u.atan2(v) };
+        (azimuth, 2. * (r / 2.).atan() - FRAC_PI_2)
+    }
+
+    fn supports_field_of_view(&self, horizontal_field_of_view: f64) -> bool {
+        horizontal_field_of_view < TAU
+    }
+
+    fn supports_vertical_field_of_view(&self, vertical_field_of_view: f64) -> bool {
+        vertical_field_of_view < TAU
+    }
+
+    fn horizontal_extent(&self, horizontal_field_of_view: f64) -> f64 {
+        2. * (horizontal_field_of_view / 4.).tan()
+    }
+
+    fn vertical_field_of_view(&self, v: f64) -> f64 {
+        4. * (v / 2.).atan()
+    }
+
+    fn keeps_azimuth_along_columns(&self) -> bool {
+        false
+    }
+}
+
+/// Projections of panoramas.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum ProjectionKind {
+    /// See [`Equirectangular`].
+    #[default]
+    Equirectangular,
+    /// See [`Cylindrical`].
+    Cylindrical,
+    /// See [`Rectilinear`].
+    Rectilinear,
+    /// See [`Mercator`].
+    Mercator,
+    /// See [`Fisheye`].
+    Fisheye,
+    /// See [`Stereographic`].
+    Stereographic,
+}
+
+impl ProjectionKind {
+    fn projection(self) -> &'static dyn Projection {
+        match self {
+            ProjectionKind::Equirectangular => &Equirectangular,
+            ProjectionKind::Cylindrical => &Cylindrical,
+            ProjectionKind::Rectilinear => &Rectilinear,
+            ProjectionKind::Mercator => &Mercator,
+            ProjectionKind::Fisheye => &Fisheye,
+            ProjectionKind::Stereographic => &Stereographic,
+        }
+    }
+}
+
+impl Projection for ProjectionKind {
+    fn project(&self, azimuth: f64, altitude: f64) -> Option<(f64, f64)> {
+        self.projection().project(azimuth, altitude)
+    }
+
+    fn unproject(&self, u: f64, v: f64) -> (f64, f64) {
+        self.projection().unproject(u, v)
+    }
+
+    fn supports_field_of_view(&self, horizontal_field_of_view: f64) -> bool {
+        self.projection()
+            .supports_field_of_view(horizontal_field_of_view)
+    }
+
+    fn supports_vertical_field_of_view(&self, vertical_field_of_view: f64) -> bool {
+        self.projection()
+            .supports_vertical_field_of_view(vertical_field_of_view)
+    }
+
+    fn horizontal_extent(&self, horizontal_field_of_view: f64) -> f64 {
+        self.projection()
+            .horizontal_extent(horizontal_field_of_view)
+    }
+
+    fn vertical_field_of_view(&self, v: f64) -> f64 {
+        self.projection().vertical_field_of_view(v)
+    }
+
+    fn keeps_azimuth_along_columns(&self) -> bool {
+        self.projection().keeps_azimuth_along_columns()
+    }
+}
+
+#[cfg(test)]
+mod projection_tests {
+    use super::*;
+    use crate::math::fast_trig_tolerance;
+    use assert_approx_eq::assert_approx_eq;
+    use rand::Rng;
+
+    const ALL: [ProjectionKind; 6] = [
+        ProjectionKind::Equirectangular,
+        ProjectionKind::Cylindrical,
+        ProjectionKind::Rectilinear,
+        ProjectionKind::Mercator,
+        ProjectionKind::Fisheye,
+        ProjectionKind::Stereographic,
+    ];
+
+    #[test]
+    fn unproject_is_inverse_of_project() {
+        let mut rng = rand::thread_rng();
+
+        for projection in ALL {
+            for _ in 0..500 {
+                let azimuth = rng.gen_range(-1.5..1.5);
+                let altitude = rng.gen_range(-1.5..1.5);
+                let (u, v) = projection.project(azimuth, altitude).unwrap();
+                let (a, h) = projection.unproject(u, v);
+                assert_approx_eq!(azimuth, a, fast_trig_tolerance(1e-9));
+                assert_approx_eq!(altitude, h, fast_trig_tolerance(1e-9));
+            }
+        }
+    }
+
+    #[test]
+    fn projections_match_angles_at_center() {
+        // the error of trigonometric tables is relative near 0
+        let tolerance = if cfg!(feature = "trig-lut") {
+            1e-12
+        } else {
+            1e-15
+        };
+        for projection in &ALL[..5] {
+            let (u, v) = projection.project(1e-6, 1e-6).unwrap();
+            assert_approx_eq!(1e-6, u, tolerance);
+            assert_approx_eq!(1e-6, v, tolerance);
+        }
+    }
+
+    #[test]
+    fn projections_are_correct_on_known_values() {
+        let d = 45_f64.to_radians();
+        let (u, v) = Cylindrical.project(d, d).unwrap();
+        assert_approx_eq!(d, u, 1e-12);
+        assert_approx_eq!(1., v, 1e-12);
+
+        let (u, v) = Rectilinear.project(d, d).unwrap();
+        assert_approx_eq!(1., u, 1e-12);
+        assert_approx_eq!(2_f64.sqrt(), v, 1e-12);
+        assert_eq!(None, Rectilinear.project(FRAC_PI_2, 0.));
+
+        let (_, v) = Mercator.project(0., d).unwrap();
+        assert_approx_eq!((1. + 2_f64.sqrt()).ln(), v, 1e-12);
+
+        let (u, v) = Fisheye.project(FRAC_PI_2, 0.).unwrap();
+        assert_approx_eq!(FRAC_PI_2, u, 1e-12);
+        assert_approx_eq!(0., v, 1e-12);
+        let (u, v) = Fisheye.project(0., -FRAC_PI_2).unwrap();
+        assert_approx_eq!(0., u, 1e-12);
+        assert_approx_eq!(-FRAC_PI_2, v, 1e-12);
+        let (u, _) = Fisheye.project(PI, 0.).unwrap();
+        assert_approx_eq!(PI, u, 1e-12);
+    }
+
+    #[test]
+    fn stereographic_sees_the_horizon_as_a_circle_around_the_nadir() {
+        let mut rng = rand::thread_rng();
+
+        assert_eq!(Some((0., 0.)), Stereographic.project(0., -FRAC_PI_2));
+        assert_eq!(None, Stereographic.project(0., FRAC_PI_2));
+        for _ in 0..500 {
+            let azimuth = rng.gen_range(-PI..PI);
+            let (u, v) = Stereographic.project(azimuth, 0.).unwrap();
+            assert_approx_eq!(2., u.hypot(v), fast_trig_tolerance(1e-12));
+            assert_approx_eq!(
+                azimuth,
+                Stereographic.unproject(u, v).0,
+                fast_trig_tolerance(1e-9)
+            );
+        }
+        // the center azimuth is upward
+        let (u, v) = Stereographic.project(0., 0.).unwrap();
+        assert_approx_eq!(0., u, 1e-12);
+        assert_approx_eq!(2., v, 1e-12);
+    }
+
+    #[test]
+    fn extents_match_fields_of_view() {
+        let mut rng = rand::thread_rng();
+
+        for projection in ALL {
+            for _ in 0..500 {
+                let fov = rng.gen_range(0.1..3.);
+                let u = projection.horizontal_extent(fov);
+                let (azimuth, altitude) = projection.unproject(u, 0.);
+                if projection == ProjectionKind::Stereographic {
+                    // measured from the nadir
+                    assert_approx_eq!(FRAC_PI_2, azimuth, 1e-9);
+                    assert_approx_eq!(fov / 2. - FRAC_PI_2, altitude, 1e-9);
+                } else {
+                    assert_approx_eq!(fov / 2., azimuth, 1e-9);
+                }
+                if projection.keeps_azimuth_along_columns() {
+                    let (_, altitude) = projection.unproject(0., u);
+                    assert_approx_eq!(altitude * 2., projection.vertical_field_of_view(u), 1e-9);
+                } else {
+                    assert_approx_eq!(fov, projection.vertical_field_of_view(u), 1e-9);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rectilinear_keeps_lines_straight() {
+        // points of the horizontal line 1 unit above and in front of the
+        // observer
+        for x in [-2., -0.5, 0., 1., 3.] {
+            let azimuth = f64::atan2(x, 1.);
+            let altitude = f64::atan2(1., f64::hypot(x, 1.));
+            let (u, v) = Rectilinear.project(azimuth, altitude).unwrap();
+            assert_approx_eq!(x, u, 1e-12);
+            assert_approx_eq!(1., v, 1e-12);
+        }
+    }
+
+    #[test]
+    fn supports_field_of_view_is_correct() {
+        assert!(ProjectionKind::Equirectangular.supports_field_of_view(TAU));
+        assert!(ProjectionKind::Cylindrical.supports_field_of_view(TAU));
+        assert!(!ProjectionKind::Mercator.supports_field_of_view(TAU + 1e-9));
+        assert!(ProjectionKind::Rectilinear.supports_field_of_view(3.));
+        assert!(!ProjectionKind::Rectilinear.supports_field_of_view(PI));
+        assert!(ProjectionKind::Fisheye.supports_field_of_view(TAU));
+        assert!(ProjectionKind::Fisheye.supports_vertical_field_of_view(TAU));
+        assert!(!ProjectionKind::Stereographic.supports_field_of_view(TAU));
+        assert!(ProjectionKind::Stereographic.supports_vertical_field_of_view(4.));
+        assert!(!ProjectionKind::Mercator.supports_vertical_field_of_view(4.));
+    }
+}
@@ -0,0 +1,604 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::{fs, io, iter, path::Path, time::UNIX_EPOCH};
+
+use crate::{
+    atmosphere,
+    config::{CameraConfig, PainterKind, RenderConfig},
+    dem::{ContinuousElevationModel, DiscreteElevationModel},
+    error::AlpanoError,
+    image::Image,
+    overlay,
+    painter::{self, ImagePainter},
+    panorama::{Panorama, PanoramaComputer, PanoramaParameters, ProgressSink, RenderError},
+    stats::stats,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{
+    config::{DemConfig, DemFormat},
+    dem::{CompositeDem, GeoTiffDem, HgtDem, TileCache},
+    distance,
+    geo::GeoPoint,
+    panorama::{Checkpoint, PanoramaCache},
+};
+
+/// Render the panorama of `config` to an image, over the tiles of its DEM
+/// directory, reporting the progress of its computation to `progress`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn render(
+    config: &RenderConfig,
+    progress: impl ProgressSink + 'static,
+) -> Result<Image, AlpanoError> {
+    Ok(paint(&compute(config, progress)?, config))
+}
+
+/// Render the panorama of `config` to an image, over `dem` instead of the
+/// tiles of its DEM directory, without accessing files.
+pub fn render_dem(
+    config: &RenderConfig,
+    dem: impl DiscreteElevationModel,
+    progress: impl ProgressSink + 'static,
+) -> Result<Image, AlpanoError> {
+    Ok(paint(&compute_dem(config, dem, progress)?, config))
+}
+
+/// Compute the panorama of `config`, over the tiles of its DEM directory,
+/// reporting the progress of its computation to `progress`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn compute(
+    config: &RenderConfig,
+    progress: impl ProgressSink + 'static,
+) -> Result<Panorama, AlpanoError> {
+    compute_dem(config, load_tiles(config)?, progress)
+}
+
+/// Compute the panorama of `config`, over `dem` instead of the tiles of its
+/// DEM directory.
+pub fn compute_dem(
+    config: &RenderConfig,
+    dem: impl DiscreteElevationModel,
+    progress: impl ProgressSink + 'static,
+) -> Result<Panorama, AlpanoError> {
+    let _span = tracing::info_span!(
+        "compute",
+        width = config.camera.width,
+        height = config.camera.height
+    )
+    .entered();
+    let cem = ContinuousElevationModel::new(dem).with_interpolation(config.dem.interpolation);
+    let parameters = config.parameters_over(&cem)?;
+    check_memory(config, &parameters)?;
+    let computer = computer(config, cem).with_progress(progress);
+    #[cfg(feature = "parallel")]
+    let panorama = computer.compute_parallel(&parameters);
+    #[cfg(not(feature = "parallel"))]
+    let panorama = computer.compute(&parameters);
+    Ok(panorama)
+}
+
+/// Like [`compute`], but saving the computed columns to `checkpoint` as they
+/// are computed, and first resuming from it if `resume` is true, see
+/// [`PanoramaComputer::compute_resumable`].
+#[cfg(not(target_arch = "wasm32"))]
+pub fn compute_resumable(
+    config: &RenderConfig,
+    checkpoint: &Checkpoint,
+    resume: bool,
+    progress: impl ProgressSink + 'static,
+) -> Result<Panorama, AlpanoError> {
+    compute_resumable_dem(config, load_tiles(config)?, checkpoint, resume, progress)
+}
+
+/// Like [`compute_resumable`], but over `dem` instead of the tiles of its
+/// DEM directory.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn compute_resumable_dem(
+    config: &RenderConfig,
+    dem: impl DiscreteElevationModel,
+    checkpoint: &Checkpoint,
+    resume: bool,
+    progress: impl ProgressSink + 'static,
+) -> Result<Panorama, AlpanoError> {
+    let cem = ContinuousElevationModel::new(dem).with_interpolation(config.dem.interpolation);
+    let _span = tracing::info_span!(
+        "compute",
+        width = config.camera.width,
+        height = config.camera.height
+    )
+    .entered();
+    let parameters = config.parameters_over(&cem)?;
+    check_memory(config, &parameters)?;
+    Ok(computer(config, cem)
+        .with_progress(progress)
+        .compute_resumable(&parameters, checkpoint, resume)?)
+}
+
+/// Fail if the memory held by the crate, see [`stats`], and the memory of
+/// the panorama of `parameters` and of its image would exceed the maximum
+/// memory of `config`, if any.
+fn check_memory(config: &RenderConfig, parameters: &PanoramaParameters) -> Result<(), RenderError> {
+    let Some(max_memory) = config.camera.max_memory_bytes() else {
+        return Ok(());
+    };
+    let pixels = parameters.width() * parameters.height();
+    let needed = stats().total() + pixels * (Panorama::BYTES_PER_PIXEL + size_of::<[u8; 4]>());
+    if needed > max_memory {
+        Err(RenderError::MemoryExceeded(
+            needed.div_ceil(1 << 20),
+            max_memory >> 20,
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Key of the panorama of `config` in a [`PanoramaCache`]: the hash of the
+/// settings its computation depends on and of whether it is deterministic,
+/// and of the name, size and time of modification of the tiles of its DEM
+/// directory it is computed over, which are not read.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn cache_key(config: &RenderConfig) -> Result<u64, AlpanoError> {
+    let settings = format!(
+        "{:?} {:?} {:?} {:?} {}",
+        config.observer,
+        // the chunk size changes how the panorama is computed, not what
+        CameraConfig {
+            chunk_size: None,
+            max_memory: None,
+            ..config.camera.clone()
+        },
+        config.dem.interpolation,
+        config.output.shadows.map(|_| config.sun_direction()),
+        // deterministic panoramas are cached at full precision
+        config.output.deterministic
+    );
+    let mut tiles = Vec::new();
+    for (south, west) in tiles_around(
+        config.observer.position()?,
+        config.camera.max_distance.meters(),
+    ) {
+        let name = config.dem.format.file_name(south, west);
+        let path = config.dem.dir.join(&name);
+        let metadata = fs::metadata(&path).map_err(|e| {
+            io::Error::new(e.kind(), format!("cannot read {}: {}", path.display(), e))
+        })?;
+        let modified = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        tiles.push(format!(
+            "{} {} {}",
+            name,
+            metadata.len(),
+            modified.as_nanos()
+        ));
+    }
+    Ok(PanoramaCache::key(
+        iter::once(settings.as_bytes()).chain(tiles.iter().map(|t| t.as_bytes())),
+    ))
+}
+
+/// Computer of panoramas over `cem` with the settings of `config`, also
+/// placing the labels of its summits, see
+/// [`Labelizer::for_computer`](crate::labels::Labelizer::for_computer).
+pub fn computer<D: DiscreteElevationModel>(
+    config: &RenderConfig,
+    cem: ContinuousElevationModel<D>,
+) -> PanoramaComputer<D> {
+    let mut computer = PanoramaComputer::new(cem);
+    if let Some(columns) = config.camera.chunk_size {
+        computer = computer.with_chunk_size(columns);
+    }
+    match config.output.shadows {
+        Some(_) => {
+            let (sun_azimuth, sun_altitude) = config.sun_direction();
+            computer.with_shadows(sun_azimuth, sun_altitude)
+        }
+        None => computer,
+    }
+}
+
+/// Load the tiles of the DEM directory of `config` covering every point
+/// within the maximum distance of the observer, through a [`TileCache`] if
+/// the DEM has a memory budget.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_tiles(config: &RenderConfig) -> Result<CompositeDem, AlpanoError> {
+    let position = config.observer.position()?;
+    let max_distance = config.camera.max_distance.meters();
+    match config.dem.memory_budget_bytes() {
+        Some(budget) => {
+            let cache = tile_cache(&config.dem, &tiles_around(position, max_distance), budget)?;
+            let tiles: Vec<Box<dyn DiscreteElevationModel>> = vec![Box::new(cache)];
+            Ok(CompositeDem::new(tiles)?)
+        }
+        None => load_tiles_around(&config.dem, position, max_distance),
+    }
+}
+
+/// Load the tiles of the DEM directory of `dem` covering every point within
+/// `max_distance` (in meters) of `center`.
+#[cfg(not(target_arch = "wasm32"))]
+#[tracing::instrument(name = "load_tiles", skip_all)]
+pub fn load_tiles_around(
+    dem: &DemConfig,
+    center: GeoPoint,
+    max_distance: f64,
+) -> Result<CompositeDem, AlpanoError> {
+    let mut tiles = Vec::new();
+    for (south, west) in tiles_around(center, max_distance) {
+        tiles.push(load_tile(dem, south, west)?);
+    }
+    Ok(CompositeDem::new(tiles)?)
+}
+
+/// Latitudes and longitudes (in degrees) of the south-west corners of the
+/// tiles covering every point within `max_distance` (in meters) of `center`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn tiles_around(center: GeoPoint, max_distance: f64) -> Vec<(i32, i32)> {
+    let d_lat = max_distance / distance::meters_per_deg_lat();
+    let d_lon = max_distance / distance::meters_per_deg_lon(center.latitude());
+    let lat = center.latitude().to_degrees();
+    let lon = center.longitude().to_degrees();
+
+    let mut tiles = Vec::new();
+    for south in (lat - d_lat).floor().max(-90.) as i32..=(lat + d_lat).floor().min(89.) as i32 {
+        for west in (lon - d_lon).floor().max(-180.) as i32..=(lon + d_lon).floor().min(179.) as i32
+        {
+            tiles.push((south, west));
+        }
+    }
+    tiles
+}
+
+/// Load the tile of the DEM directory of `dem` whose south-west corner is
+/// at the given latitude and longitude, in degrees, mapping it in memory if
+/// its format allows.
+#[cfg(not(target_arch = "wasm32"))]
+#[tracing::instrument(skip(dem))]
+pub fn load_tile(
+    dem: &DemConfig,
+    south: i32,
+    west: i32,
+) -> Result<Box<dyn DiscreteElevationModel>, AlpanoError> {
+    let path = dem.dir.join(dem.format.file_name(south, west));
+    let tile = match dem.format {
+        DemFormat::Hgt => {
+            HgtDem::map(&path).map(|t| Box::new(t) as Box<dyn DiscreteElevationModel>)
+        }
+        DemFormat::GeoTiff => read_tile(dem.format, &path),
+    };
+    Ok(tile
+        .map_err(|e| io::Error::new(e.kind(), format!("cannot load {}: {}", path.display(), e)))?)
+}
+
+/// Cache of the tiles of the DEM directory of `dem` whose south-west corners
+/// are at the given latitudes and longitudes (in degrees), keeping at most
+/// `memory_budget` bytes of them in memory (but at least one tile).
+///
+/// Fails unless all the tiles exist, reading only the first one to find
+/// their resolution.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn tile_cache(
+    dem: &DemConfig,
+    tiles: &[(i32, i32)],
+    memory_budget: usize,
+) -> Result<TileCache, AlpanoError> {
+    for &(south, west) in tiles {
+        let path = dem.dir.join(dem.format.file_name(south, west));
+        fs::metadata(&path).map_err(|e| {
+            io::Error::new(e.kind(), format!("cannot load {}: {}", path.display(), e))
+        })?;
+    }
+    let &(south, west) = tiles
+        .first()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no tiles to load"))?;
+    let samples_per_degree = load_tile(dem, south, west)?.samples_per_degree();
+
+    let side = samples_per_degree as usize + 1;
+    let sample_size = match dem.format {
+        DemFormat::Hgt => 2,
+        DemFormat::GeoTiff => 4,
+    };
+    let south = tiles.iter().map(|t| t.0);
+    let west = tiles.iter().map(|t| t.1);
+    let (dir, format) = (dem.dir.clone(), dem.format);
+    Ok(TileCache::with_loader(
+        south.clone().min().unwrap()..=south.max().unwrap(),
+        west.clone().min().unwrap()..=west.max().unwrap(),
+        samples_per_degree,
+        memory_budget / (side * side * sample_size),
+        move |south, west| read_tile(format, &dir.join(format.file_name(south, west))),
+    ))
+}
+
+/// Read the tile of the given format at `path` in memory.
+#[cfg(not(target_arch = "wasm32"))]
+fn read_tile(format: DemFormat, path: &Path) -> io::Result<Box<dyn DiscreteElevationModel>> {
+    Ok(match format {
+        DemFormat::Hgt => Box::new(HgtDem::open(path)?),
+        DemFormat::GeoTiff => Box::new(GeoTiffDem::open(path)?),
+    })
+}
+
+/// Paint `panorama` with the painter of `config`, and draw its overlays over
+/// the image.
+#[tracing::instrument(skip_all)]
+pub fn paint(panorama: &Panorama, config: &RenderConfig) -> Image {
+    let mut image = paint_painter(panorama, config);
+    paint_overlays(&mut image, panorama, config);
+    image
+}
+
+/// Paint `panorama` with the painter of `config`.
+fn paint_painter(panorama: &Panorama, config: &RenderConfig) -> Image {
+    match config.output.painter {
+        PainterKind::Grey => Image::from_panorama(panorama),
+        PainterKind::Classic => paint_terrain(painter::classic(panorama), panorama, config),
+        PainterKind::Hillshade => {
+            let (sun_azimuth, sun_altitude) = config.sun_direction();
+            paint_terrain(
+                painter::shaded_relief(panorama, sun_azimuth, sun_altitude),
+                panorama,
+                config,
+            )
+        }
+        PainterKind::Palette => {
+            let palette = config.output.palette.palette();
+            paint_terrain(painter::palette(panorama, &palette), panorama, config)
+        }
+    }
+}
+
+/// Paint the image of the terrain of `panorama` painted by `terrain`,
+/// darkened by the shadows, veiled by the haze and over the sky of the
+/// configuration if any.
+fn paint_terrain(terrain: impl ImagePainter, panorama: &Panorama, config: &RenderConfig) -> Image {
+    match config.output.shadows {
+        Some(shadows) => paint_haze(
+            painter::shadows(terrain, panorama, shadows.darkness),
+            panorama,
+            config,
+        ),
+        None => paint_haze(terrain, panorama, config),
+    }
+}
+
+/// Paint the image of the terrain of `panorama` painted by `terrain`, veiled
+/// by the haze and over the sky of the configuration if any.
+fn paint_haze(terrain: impl ImagePainter, panorama: &Panorama, config: &RenderConfig) -> Image {
+    match config.output.haze {
+        Some(haze) => {
+            // haze takes the color of the horizon at the time of the render
+            let color = match config.sun_position() {
+                Some(sun) => atmosphere::sky_colors(sun.elevation).0,
+                None => haze.color,
+            };
+            paint_over_sky(
+                painter::haze(terrain, panorama, haze.atmosphere(), color),
+                panorama.parameters(),
+                config,
+            )
+        }
+        None => paint_over_sky(terrain, panorama.parameters(), config),
+    }
+}
+
+/// Paint the image painted by `terrain`, over the sky of the configuration
+/// if any, colored for the elevation of the sun at the time of the render if
+/// given.
+fn paint_over_sky(
+    terrain: impl ImagePainter,
+    parameters: &PanoramaParameters,
+    config: &RenderConfig,
+) -> Image {
+    let (width, height) = (parameters.width(), parameters.height());
+    match config.output.sky {
+        Some(sky) => {
+            let (horizon, zenith) = match config.sun_position() {
+                Some(sun) => atmosphere::sky_colors(sun.elevation),
+                None => (sky.horizon, sky.zenith),
+            };
+            terrain
+                .over(painter::sky(parameters, horizon, zenith))
+                .paint(width, height)
+        }
+        None => terrain.paint(width, height),
+    }
+}
+
+/// Draw the overlays of `config` over `image`, the painted image of
+/// `panorama`, leaving out those depending on the time of the render
+/// without it.
+fn paint_overlays(image: &mut Image, panorama: &Panorama, config: &RenderConfig) {
+    let overlays = &config.output.overlays;
+    let crossings = match config.output.time {
+        Some(time) if overlays.sunrise => overlay::sun_crossings(panorama, time),
+        _ => Vec::new(),
+    };
+    if overlays.compass || overlays.sunrise {
+        overlay::compass(image, panorama, &crossings);
+    }
+    if let (true, Some(time)) = (overlays.sun_path, config.output.time) {
+        overlay::sun_path(image, panorama.parameters(), time);
+    }
+    if let (true, Some(time)) = (overlays.stars, config.output.time) {
+        overlay::stars(image, panorama, time);
+    }
+    if let (true, Some(time)) = (overlays.moon, config.output.time) {
+        overlay::moon(image, panorama, time);
+    }
+}
+
+#[cfg(test)]
+mod render_tests {
+    use super::*;
+    use crate::{
+        azimuth::Azimuth,
+        config::{DemConfig, ObserverConfig, OutputConfig},
+        fixtures::wall,
+        interval::{Interval1D, Interval2D},
+    };
+
+    #[test]
+    fn computed_panorama_is_painted_by_any_painter() {
+        let config = RenderConfig {
+            observer: ObserverConfig {
+                elevation: Some(1100.),
+                ..ObserverConfig::new(46.5, 7.)
+            },
+            camera: CameraConfig {
+                fov: 40.,
+                width: 100,
+                height: 50,
+                ..CameraConfig::new(Azimuth::EAST)
+            },
+            dem: DemConfig::default(),
+            output: OutputConfig::default(),
+        };
+        let panorama = compute_dem(&config, wall(), |_, _| {}).unwrap();
+
+        let images: Vec<Image> = [
+            PainterKind::Grey,
+            PainterKind::Classic,
+            PainterKind::Hillshade,
+        ]
+        .into_iter()
+        .map(|painter| {
+            let mut config = config.clone();
+            config.output.painter = painter;
+            paint(&panorama, &config)
+        })
+        .collect();
+        for image in &images {
+            assert_eq!((100, 50), (image.width(), image.height()));
+        }
+        assert_ne!(images[0], images[1]);
+        assert_ne!(images[1], images[2]);
+        // painting again paints the same image
+        assert_eq!(images[0], paint(&panorama, &config));
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn renders_are_identical_whatever_the_number_of_threads() {
+        let config = RenderConfig {
+            observer: ObserverConfig {
+                elevation: Some(1100.),
+                ..ObserverConfig::new(46.5, 7.)
+            },
+            camera: CameraConfig {
+                fov: 40.,
+                width: 120,
+                height: 60,
+                ..CameraConfig::new(Azimuth::EAST)
+            },
+            dem: DemConfig::default(),
+            output: OutputConfig {
+                painter: PainterKind::Hillshade,
+                deterministic: true,
+                ..OutputConfig::default()
+            },
+        };
+        let compute_on = |threads| {
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap()
+                .install(|| compute_dem(&config, wall(), |_, _| {}).unwrap())
+        };
+        let panorama = compute_on(1);
+        let image = paint(&panorama, &config);
+        for threads in [2, 5] {
+            let parallel = compute_on(threads);
+            assert_eq!(panorama.distances(), parallel.distances());
+            assert_eq!(panorama.elevations(), parallel.elevations());
+            assert_eq!(image, paint(&parallel, &config));
+        }
+
+        // and to the panorama computed sequentially
+        let cem = ContinuousElevationModel::new(wall());
+        let parameters = config.parameters_over(&cem).unwrap();
+        let sequential = computer(&config, cem).compute(&parameters);
+        assert_eq!(panorama.distances(), sequential.distances());
+        assert_eq!(image, paint(&sequential, &config));
+    }
+
+    #[test]
+    fn compute_fails_beyond_the_maximum_memory() {
+        let mut config = RenderConfig {
+            observer: ObserverConfig {
+                elevation: Some(1100.),
+                ..ObserverConfig::new(46.5, 7.)
+            },
+            camera: CameraConfig {
+                fov: 40.,
+                width: 100,
+                height: 50,
+                max_memory: Some(usize::MAX >> 20),
+                ..CameraConfig::new(Azimuth::EAST)
+            },
+            dem: DemConfig::default(),
+            output: OutputConfig::default(),
+        };
+        assert!(compute_dem(&config, wall(), |_, _| {}).is_ok());
+
+        // the planes alone take 100 × 50 × 49 bytes
+        config.camera.max_memory = Some(0);
+        assert!(matches!(
+            compute_dem(&config, wall(), |_, _| {}),
+            Err(AlpanoError::Render(RenderError::MemoryExceeded(_, 0)))
+        ));
+    }
+
+    #[test]
+    fn tiles_are_loaded_in_the_format_of_the_dem() {
+        use tiff::{
+            encoder::{colortype, TiffEncoder},
+            tags::Tag,
+        };
+
+        // the tile of 7°E 46°N at 10 samples per degree, with the index of
+        // each sample as elevation
+        let dir = std::env::temp_dir().join("alpano_render_tests_geotiff");
+        fs::create_dir_all(&dir).unwrap();
+        let mut file = fs::File::create(dir.join("N46E007.tif")).unwrap();
+        let mut encoder = TiffEncoder::new(&mut file).unwrap();
+        let mut image = encoder.new_image::<colortype::Gray32Float>(11, 11).unwrap();
+        let tags = image.encoder();
+        tags.write_tag(Tag::ModelPixelScaleTag, &[0.1, 0.1, 0.][..])
+            .unwrap();
+        tags.write_tag(Tag::ModelTiepointTag, &[0., 0., 0., 7., 47., 0.][..])
+            .unwrap();
+        tags.write_tag(Tag::GeoKeyDirectoryTag, &[1u16, 1, 0, 1, 1025, 0, 1, 2][..])
+            .unwrap();
+        let samples: Vec<f32> = (0..121).map(|i| i as f32).collect();
+        image.write_data(&samples).unwrap();
+
+        let mut config = RenderConfig {
+            observer: ObserverConfig::new(46.5, 7.5),
+            camera: CameraConfig {
+                max_distance: "5km".parse().unwrap(),
+                ..CameraConfig::new(Azimuth::EAST)
+            },
+            dem: DemConfig {
+                dir,
+                format: DemFormat::GeoTiff,
+                ..DemConfig::default()
+            },
+            output: OutputConfig::default(),
+        };
+        let dem = load_tiles(&config).unwrap();
+        assert_eq!(
+            Interval2D::new(Interval1D::new(70, 80), Interval1D::new(460, 470)),
+            dem.extent()
+        );
+        assert_eq!(60., dem.elevation_sample(75, 465));
+        assert!(cache_key(&config).is_ok());
+
+        config.dem.memory_budget = Some(1);
+        assert_eq!(60., load_tiles(&config).unwrap().elevation_sample(75, 465));
+
+        config.dem.format = DemFormat::Hgt;
+        assert!(load_tiles(&config).is_err());
+        assert!(cache_key(&config).is_err());
+    }
+}
@@ -0,0 +1,144 @@
+use std::f64::consts::TAU;
+
+use super::azimuth::Azimuth;
+
+/// Rose diagram of a value (visible-terrain distance, horizon altitude, ...)
+/// binned per azimuth sector, giving a fingerprint of a viewpoint's openness.
+pub struct Rose {
+    sums: Vec<f64>,
+    counts: Vec<usize>,
+}
+
+impl Rose {
+    /// Create an empty rose with `bin_count` sectors of equal width, the first
+    /// one being centered on north.
+    pub fn new(bin_count: usize) -> Rose {
+        assert!(bin_count > 0, "a rose needs at least one bin");
+        Rose {
+            sums: vec![0.; bin_count],
+            counts: vec![0; bin_count],
+        }
+    }
+
+    pub fn bin_count(&self) -> usize {
+        self.sums.len()
+    }
+
+    /// Index of the sector containing the given azimuth.
+    pub fn bin_of(&self, azimuth: Azimuth) -> usize {
+        let width = TAU / self.bin_count() as f64;
+        let bin = ((azimuth + width / 2.).radians() / width).floor() as usize;
+        bin.min(self.bin_count() - 1)
+    }
+
+    /// Add a sample observed in the direction of `azimuth`.
+    pub fn add(&mut self, azimuth: Azimuth, value: f64) {
+        let bin = self.bin_of(azimuth);
+        self.sums[bin] += value;
+        self.counts[bin] += 1;
+    }
+
+    /// Mean of the samples of the given bin, if it has any.
+    pub fn mean(&self, bin: usize) -> Option<f64> {
+        match self.counts[bin] {
+            0 => None,
+            n => Some(self.sums[bin] / n as f64),
+        }
+    }
+
+    /// Render the rose as a `size`×`size` grayscale image, stored row by row,
+    /// with north up. Each sector is drawn in black as a petal whose length is
+    /// proportional to its mean value, on a white background.
+    pub fn render(&self, size: usize) -> Vec<u8> {
+        let means: Vec<f64> = (0..self.bin_count())
+            .map(|b| self.mean(b).unwrap_or(0.).max(0.))
+            .collect();
+        let max = means.iter().cloned().fold(0., f64::max);
+        let center = size as f64 / 2.;
+
+        let mut pixels = vec![255; size * size];
+        if max == 0. {
+            return pixels;
+        }
+
+        for y in 0..size {
+            for x in 0..size {
+                let east = x as f64 + 0.5 - center;
+                let north = center - (y as f64 + 0.5);
+                let r = east.hypot(north) / center;
+                let azimuth = Azimuth::new(east.atan2(north));
+                if r <= means[self.bin_of(azimuth)] / max {
+                    pixels[y * size + x] = 0;
+                }
+            }
+        }
+
+        pixels
+    }
+}
+
+#[cfg(test)]
+mod rose_tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+    use rand::Rng;
+
+    #[test]
+    fn bin_of_centers_first_bin_on_north() {
+        let rose = Rose::new(4);
+        assert_eq!(0, rose.bin_of(Azimuth::NORTH));
+        assert_eq!(0, rose.bin_of(Azimuth::new(-0.1)));
+        assert_eq!(0, rose.bin_of(Azimuth::new(TAU - 0.1)));
+        assert_eq!(1, rose.bin_of(Azimuth::EAST));
+        assert_eq!(2, rose.bin_of(Azimuth::SOUTH));
+        assert_eq!(3, rose.bin_of(Azimuth::WEST));
+    }
+
+    #[test]
+    fn bin_of_is_in_range_for_random_azimuths() {
+        let mut rng = rand::thread_rng();
+        let rose = Rose::new(36);
+
+        for _ in 0..500 {
+            let azimuth = Azimuth::new(rng.gen_range(-100.0..100.0));
+            assert!(rose.bin_of(azimuth) < 36);
+        }
+    }
+
+    #[test]
+    fn mean_averages_samples_of_a_bin() {
+        let mut rose = Rose::new(8);
+        rose.add(Azimuth::new(0.01), 1000.);
+        rose.add(Azimuth::new(-0.01), 3000.);
+        rose.add(Azimuth::EAST, 500.);
+
+        assert_approx_eq!(2000., rose.mean(0).unwrap(), 1e-10);
+        assert_approx_eq!(500., rose.mean(2).unwrap(), 1e-10);
+        assert_eq!(None, rose.mean(1));
+    }
+
+    #[test]
+    fn render_draws_petals_in_the_right_direction() {
+        let mut rose = Rose::new(4);
+        rose.add(Azimuth::NORTH, 10.);
+        rose.add(Azimuth::SOUTH, 5.);
+
+        let size = 100;
+        let pixels = rose.render(size);
+        assert_eq!(size * size, pixels.len());
+
+        // north petal reaches the top edge, south petal half way down
+        assert_eq!(0, pixels[size + size / 2]);
+        assert_eq!(0, pixels[(size * 7 / 10) * size + size / 2]);
+        assert_eq!(255, pixels[(size - 2) * size + size / 2]);
+        // east and west have no samples
+        assert_eq!(255, pixels[(size / 2) * size + size - 10]);
+        assert_eq!(255, pixels[(size / 2) * size + 10]);
+    }
+
+    #[test]
+    fn render_is_blank_without_samples() {
+        let pixels = Rose::new(16).render(10);
+        assert!(pixels.iter().all(|p| *p == 255));
+    }
+}
@@ -0,0 +1,222 @@
+//! HTTP service rendering panoramas on demand.
+
+use std::{error::Error, io::ErrorKind, net::SocketAddr, path::PathBuf, sync::Arc};
+
+use alpano::{
+    azimuth::Azimuth,
+    config::{CameraConfig, DemConfig, ObserverConfig, OutputConfig, PainterKind, RenderConfig},
+    distance::Length,
+    error::AlpanoError,
+    panorama::ProjectionKind,
+};
+use axum::{
+    extract::{Query, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    routing::get,
+    Router,
+};
+use serde::Deserialize;
+
+/// Settings of the service, shared by all requests.
+pub struct ServeOptions {
+    /// Directory containing the SRTM tiles.
+    pub dem_dir: PathBuf,
+    /// Largest number of pixels of rendered images.
+    pub max_pixels: usize,
+    /// Largest maximum distance of rendered panoramas.
+    pub max_distance: Length,
+}
+
+/// Error response, with its status and message.
+type HttpError = (StatusCode, String);
+
+/// Panorama requested by the query string of `GET /panorama`, with the
+/// defaults of the configuration files.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PanoramaQuery {
+    lat: f64,
+    lon: f64,
+    elevation: Option<f64>,
+    azimuth: String,
+    fov: Option<f64>,
+    max_distance: Option<String>,
+    width: Option<usize>,
+    height: Option<usize>,
+    projection: Option<ProjectionKind>,
+    painter: Option<PainterKind>,
+}
+
+impl PanoramaQuery {
+    /// Configuration rendering the requested panorama, failing if it is
+    /// invalid or exceeds the limits of `options`.
+    fn config(&self, options: &ServeOptions) -> Result<RenderConfig, String> {
+        let mut camera =
+            CameraConfig::new(self.azimuth.parse::<Azimuth>().map_err(|e| e.to_string())?);
+        camera.fov = self.fov.unwrap_or(camera.fov);
+        if let Some(max_distance) = &self.max_distance {
+            camera.max_distance = max_distance.parse::<Length>().map_err(|e| e.to_string())?;
+        }
+        camera.width = self.width.unwrap_or(camera.width);
+        camera.height = self.height.unwrap_or(camera.height);
+        camera.projection = self.projection.unwrap_or(camera.projection);
+
+        if camera.max_distance.meters() > options.max_distance.meters() {
+            return Err(format!(
+                "maximum distance {} exceeds the limit of {}",
+                camera.max_distance, options.max_distance
+            ));
+        }
+        match camera.width.checked_mul(camera.height) {
+            Some(pixels) if pixels <= options.max_pixels => {}
+            _ => {
+                return Err(format!(
+                    "image of {}×{} pixels exceeds the limit of {} pixels",
+                    camera.width, camera.height, options.max_pixels
+                ))
+            }
+        }
+
+        let config = RenderConfig {
+            observer: ObserverConfig {
+                elevation: self.elevation,
+                ..ObserverConfig::new(self.lat, self.lon)
+            },
+            camera,
+            dem: DemConfig {
+                dir: options.dem_dir.clone(),
+                ..DemConfig::default()
+            },
+            output: OutputConfig {
+                painter: self.painter.unwrap_or(PainterKind::Classic),
+                ..OutputConfig::default()
+            },
+        };
+        config.validate().map_err(|e| e.to_string())?;
+        Ok(config)
+    }
+}
+
+/// Serve panoramas at `GET /panorama` on `addr` until the process is
+/// stopped.
+pub fn serve(addr: SocketAddr, options: ServeOptions) -> Result<(), Box<dyn Error>> {
+    let app = Router::new()
+        .route("/panorama", get(panorama))
+        .with_state(Arc::new(options));
+
+    tokio::runtime::Runtime::new()?.block_on(async {
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        println!("serving panoramas at http://{}/panorama", addr);
+        axum::serve(listener, app).await?;
+        Ok(())
+    })
+}
+
+async fn panorama(
+    State(options): State<Arc<ServeOptions>>,
+    Query(query): Query<PanoramaQuery>,
+) -> Result<impl IntoResponse, HttpError> {
+    let config = query
+        .config(&options)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    // rendering takes seconds, away from the threads serving requests
+    let png = tokio::task::spawn_blocking(move || render_png(&config))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))??;
+    Ok(([(header::CONTENT_TYPE, "image/png")], png))
+}
+
+/// PNG image of the panorama of `config`.
+fn render_png(config: &RenderConfig) -> Result<Vec<u8>, HttpError> {
+    let image = alpano::render::render(config, |_, _| {}).map_err(|e| match e {
+        AlpanoError::Io(e) if e.kind() == ErrorKind::NotFound => {
+            (StatusCode::NOT_FOUND, e.to_string())
+        }
+        AlpanoError::Io(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        e => (StatusCode::BAD_REQUEST, e.to_string()),
+    })?;
+
+    let mut png = Vec::new();
+    image
+        .write_png(&mut png)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(png)
+}
+
+#[cfg(test)]
+mod serve_tests {
+    use super::*;
+
+    fn options() -> ServeOptions {
+        ServeOptions {
+            dem_dir: PathBuf::from("srtm"),
+            max_pixels: 1000 * 500,
+            max_distance: Length::from_meters(100_000.),
+        }
+    }
+
+    fn request() -> PanoramaQuery {
+        PanoramaQuery {
+            lat: 46.5,
+            lon: 6.6,
+            elevation: Some(600.),
+            azimuth: "S".to_string(),
+            fov: Some(60.),
+            max_distance: Some("80km".to_string()),
+            width: Some(1000),
+            height: Some(500),
+            projection: None,
+            painter: None,
+        }
+    }
+
+    #[test]
+    fn config_builds_the_requested_panorama() {
+        let config = request().config(&options()).unwrap();
+        assert_eq!(Azimuth::SOUTH, config.camera.azimuth);
+        assert_eq!(80_000., config.camera.max_distance.meters());
+        assert_eq!((1000, 500), (config.camera.width, config.camera.height));
+        assert_eq!(Some(600.), config.observer.elevation);
+        assert_eq!(PathBuf::from("srtm"), config.dem.dir);
+    }
+
+    #[test]
+    fn config_fails_beyond_the_limits() {
+        let query = PanoramaQuery {
+            width: Some(1001),
+            ..request()
+        };
+        assert!(query.config(&options()).is_err());
+        let query = PanoramaQuery {
+            width: Some(usize::MAX),
+            height: Some(2),
+            ..query
+        };
+        assert!(query.config(&options()).is_err());
+        let query = PanoramaQuery {
+            max_distance: Some("101km".to_string()),
+            ..request()
+        };
+        assert!(query.config(&options()).is_err());
+    }
+
+    #[test]
+    fn config_fails_on_invalid_values() {
+        let query = PanoramaQuery {
+            azimuth: "up".to_string(),
+            ..request()
+        };
+        assert!(query.config(&options()).is_err());
+        let query = PanoramaQuery {
+            max_distance: Some("far".to_string()),
+            ..request()
+        };
+        assert!(query.config(&options()).is_err());
+        let query = PanoramaQuery {
+            max_distance: Some("10 parsecs".to_string()),
+            ..request()
+        };
+        assert!(query.config(&options()).is_err());
+    }
+}
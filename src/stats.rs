@@ -0,0 +1,142 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Bytes of the samples of the DEM tiles in memory, outside of tile caches.
+static DEM_TILES: AtomicUsize = AtomicUsize::new(0);
+/// Bytes of the planes of the panoramas.
+static PANORAMA_PLANES: AtomicUsize = AtomicUsize::new(0);
+/// Bytes of the samples of the tiles kept in memory by tile caches.
+static CACHES: AtomicUsize = AtomicUsize::new(0);
+
+/// Memory held by the data of the crate alive in the process, in bytes, see
+/// [`stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryStats {
+    /// Samples of the DEM tiles read in memory, outside of tile caches. The
+    /// samples of tiles mapped from disk are not counted: the system reads
+    /// them when needed and can drop them at any time.
+    pub dem_tiles: usize,
+    /// Planes of the panoramas, see
+    /// [`Panorama::distances`](crate::panorama::Panorama::distances).
+    pub panorama_planes: usize,
+    /// Samples of the tiles kept in memory by tile caches, see
+    /// [`TileCache`](crate::dem::TileCache).
+    pub caches: usize,
+}
+
+impl MemoryStats {
+    pub fn total(&self) -> usize {
+        self.dem_tiles + self.panorama_planes + self.caches
+    }
+}
+
+/// Memory currently held by DEM tiles, panoramas and tile caches.
+pub fn stats() -> MemoryStats {
+    MemoryStats {
+        dem_tiles: DEM_TILES.load(Ordering::Relaxed),
+        panorama_planes: PANORAMA_PLANES.load(Ordering::Relaxed),
+        caches: CACHES.load(Ordering::Relaxed),
+    }
+}
+
+/// Memory counted in [`stats`] for as long as it is held.
+#[derive(Debug)]
+pub(crate) struct Held {
+    counter: &'static AtomicUsize,
+    bytes: usize,
+}
+
+impl Held {
+    /// Samples of a DEM tile.
+    pub(crate) fn dem_tile(bytes: usize) -> Held {
+        Held::new(&DEM_TILES, bytes)
+    }
+
+    /// Planes of a panorama.
+    pub(crate) fn panorama_planes(bytes: usize) -> Held {
+        Held::new(&PANORAMA_PLANES, bytes)
+    }
+
+    fn new(counter: &'static AtomicUsize, bytes: usize) -> Held {
+        counter.fetch_add(bytes, Ordering::Relaxed);
+        Held { counter, bytes }
+    }
+
+    pub(crate) fn bytes(&self) -> usize {
+        self.bytes
+    }
+}
+
+impl Drop for Held {
+    fn drop(&mut self) {
+        self.counter.fetch_sub(self.bytes, Ordering::Relaxed);
+    }
+}
+
+/// Samples of a DEM tile counted in the caches rather than the DEM tiles of
+/// [`stats`] while it is kept by a tile cache. It must be dropped before the
+/// tile.
+#[derive(Debug)]
+pub(crate) struct Cached {
+    bytes: usize,
+}
+
+impl Cached {
+    pub(crate) fn new(bytes: usize) -> Cached {
+        CACHES.fetch_add(bytes, Ordering::Relaxed);
+        DEM_TILES.fetch_sub(bytes, Ordering::Relaxed);
+        Cached { bytes }
+    }
+}
+
+impl Drop for Cached {
+    fn drop(&mut self) {
+        DEM_TILES.fetch_add(self.bytes, Ordering::Relaxed);
+        CACHES.fetch_sub(self.bytes, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod stats_tests {
+    use super::*;
+    use crate::{
+        dem::{DiscreteElevationModel, HgtDem, MemoryDem, TileCache},
+        fixtures::flat_panorama,
+        interval::{Interval1D, Interval2D},
+    };
+
+    // the counters are shared by the tests running in parallel, which only
+    // hold memory while running: the counters are at least what a test holds
+
+    #[test]
+    fn stats_count_the_planes_of_panoramas_while_they_are_alive() {
+        let panorama = flat_panorama();
+        assert_eq!(11 * 11 * (6 * 8 + 1), panorama.memory_size());
+        assert!(stats().panorama_planes >= panorama.memory_size());
+        drop(panorama);
+    }
+
+    #[test]
+    fn stats_count_the_samples_of_tiles_read_in_memory() {
+        let tile = HgtDem::from_bytes(46, 7, vec![0; 1201 * 1201 * 2]).unwrap();
+        assert_eq!(1201 * 1201 * 2, tile.memory_size());
+        assert!(stats().dem_tiles >= 1201 * 1201 * 2);
+        drop(tile);
+    }
+
+    #[test]
+    fn stats_count_the_tiles_of_caches_as_caches() {
+        let extent = |south, west| {
+            Interval2D::new(
+                Interval1D::new(100 * west, 100 * (west + 1)),
+                Interval1D::new(100 * south, 100 * (south + 1)),
+            )
+        };
+        let cache = TileCache::with_loader(46..=46, 7..=8, 100, 1, move |south, west| {
+            Ok(MemoryDem::from_fn(100, extent(south, west), |_, _| 0.))
+        });
+        cache.elevation_sample(750, 4650);
+        assert!(stats().caches >= 101 * 101 * 4);
+        // the tile is dropped with the cache, and no longer counted
+        drop(cache);
+    }
+}
@@ -0,0 +1,583 @@
+use chrono::{DateTime, Duration, Utc};
+
+use super::{azimuth::Azimuth, celestial::julian_centuries};
+
+/// Julian day number of the Unix epoch.
+const UNIX_EPOCH_JD: f64 = 2440587.5;
+
+/// Position of the sun in the sky of an observer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SolarPosition {
+    pub azimuth: Azimuth,
+    /// Geometric elevation of the center of the sun above the astronomical
+    /// horizon, in radians (without atmospheric refraction).
+    pub elevation: f64,
+}
+
+/// Fractional Julian day of the given instant.
+pub fn julian_day(date_time: DateTime<Utc>) -> f64 {
+    let seconds = date_time.timestamp() as f64 + date_time.timestamp_subsec_nanos() as f64 * 1e-9;
+    seconds / 86400. + UNIX_EPOCH_JD
+}
+
+/// Position of the sun at the given instant, seen from the given latitude and
+/// longitude (in radians, longitude positive to the east).
+///
+/// Uses the NOAA solar calculator algorithm, accurate to about a hundredth
+/// of a degree for dates between 1800 and 2100.
+pub fn position(date_time: DateTime<Utc>, lat: f64, lon: f64) -> SolarPosition {
+    let (declination, equation_of_time) = declination_and_equation_of_time(date_time);
+
+    let day_fraction = (julian_day(date_time) + 0.5).fract();
+    let hour_angle = (day_fraction * 360. - 180.).to_radians() + equation_of_time + lon;
+
+    let (sin_lat, cos_lat) = lat.sin_cos();
+    let (sin_dec, cos_dec) = declination.sin_cos();
+    let (sin_ha, cos_ha) = hour_angle.sin_cos();
+
+    let elevation = (sin_lat * sin_dec + cos_lat * cos_dec * cos_ha).asin();
+    let azimuth = (-sin_ha * cos_dec).atan2(cos_lat * sin_dec - sin_lat * cos_dec * cos_ha);
+
+    SolarPosition {
+        azimuth: Azimuth::new(azimuth),
+        elevation,
+    }
+}
+
+/// Part of the day, according to the elevation of the sun.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Daylight {
+    /// Sun more than 6° above the horizon.
+    Day,
+    /// Sun between the horizon and 6° above it.
+    GoldenHour,
+    /// Sun less than 6° below the horizon.
+    CivilTwilight,
+    /// Sun between 6° and 12° below the horizon.
+    NauticalTwilight,
+    /// Sun between 12° and 18° below the horizon.
+    AstronomicalTwilight,
+    /// Sun more than 18° below the horizon.
+    Night,
+}
+
+impl Daylight {
+    /// Part of the day for the given elevation of the sun (in radians).
+    pub fn from_elevation(elevation: f64) -> Daylight {
+        match elevation.to_degrees() {
+            e if e >= 6. => Daylight::Day,
+            e if e >= 0. => Daylight::GoldenHour,
+            e if e >= -6. => Daylight::CivilTwilight,
+            e if e >= -12. => Daylight::NauticalTwilight,
+            e if e >= -18. => Daylight::AstronomicalTwilight,
+            _ => Daylight::Night,
+        }
+    }
+}
+
+/// Positions of the sun every `step` from `start` (included) to `end`
+/// (excluded), e.g. to draw its daily path with hour ticks.
+pub fn path(
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    step: Duration,
+    lat: f64,
+    lon: f64,
+) -> Vec<(DateTime<Utc>, SolarPosition)> {
+    assert!(step > Duration::zero(), "step must be positive");
+
+    let mut positions = Vec::new();
+    let mut t = start;
+    while t < end {
+        positions.push((t, position(t, lat, lon)));
+        t += step;
+    }
+
+    positions
+}
+
+/// Start and end of the mean solar day at the given longitude (in radians)
+/// containing `time`, from midnight to midnight.
+pub fn solar_day(time: DateTime<Utc>, longitude: f64) -> (DateTime<Utc>, DateTime<Utc>) {
+    let offset = Duration::seconds((longitude.to_degrees() * 240.).round() as i64);
+    let midnight = (time + offset)
+        .date_naive()
+        .and_hms_opt(0, 0, 0)
+        .expect("midnight is valid")
+        .and_utc()
+        - offset;
+    (midnight, midnight + Duration::days(1))
+}
+
+/// Whether the sun appears or disappears when crossing the horizon.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrossingKind {
+    Rise,
+    Set,
+}
+
+/// Instant at which the center of the sun crosses the horizon.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HorizonCrossing {
+    pub kind: CrossingKind,
+    pub time: DateTime<Utc>,
+    pub azimuth: Azimuth,
+}
+
+/// All instants between `start` and `end` at which the sun crosses the
+/// horizon seen from the given latitude and longitude (in radians).
+///
+/// The horizon is given as its elevation (in radians) for every azimuth, so
+/// that the actual terrain horizon can be used instead of the sea-level one.
+/// With a jagged horizon the sun can set and rise again several times in a
+/// day, so every crossing is returned, in chronological order and to the
+/// second. Crossings less than a minute apart may be missed.
+pub fn horizon_crossings(
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    lat: f64,
+    lon: f64,
+    horizon: impl Fn(Azimuth) -> f64,
+) -> Vec<HorizonCrossing> {
+    let above = |t: DateTime<Utc>| {
+        let p = position(t, lat, lon);
+        p.elevation - horizon(p.azimuth)
+    };
+
+    let mut crossings = Vec::new();
+    let mut t1 = start;
+    let mut h1 = above(t1);
+    while t1 < end {
+        let t2 = (t1 + Duration::minutes(1)).min(end);
+        let h2 = above(t2);
+
+        if (h1 >= 0.) != (h2 >= 0.) {
+            let (mut lo, mut hi) = (t1, t2);
+            while hi - lo > Duration::seconds(1) {
+                let m = lo + (hi - lo) / 2;
+                if (above(m) >= 0.) == (h1 >= 0.) {
+                    lo = m;
+                } else {
+                    hi = m;
+                }
+            }
+
+            crossings.push(HorizonCrossing {
+                kind: if h2 >= 0. {
+                    CrossingKind::Rise
+                } else {
+                    CrossingKind::Set
+                },
+                time: hi,
+                azimuth: position(hi, lat, lon).azimuth,
+            });
+        }
+
+        t1 = t2;
+        h1 = h2;
+    }
+
+    crossings
+}
+
+/// Periods between `start` and `end` during which `is_lit` holds, e.g. for a
+/// point of the terrain out of its shadow, in chronological order and to the
+/// second. Periods or breaks shorter than a minute may be missed.
+pub fn lit_periods(
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    is_lit: impl Fn(DateTime<Utc>) -> bool,
+) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+    let mut periods = Vec::new();
+    let mut t1 = start;
+    let mut lit1 = is_lit(t1);
+    let mut since = lit1.then_some(start);
+    while t1 < end {
+        let t2 = (t1 + Duration::minutes(1)).min(end);
+        let lit2 = is_lit(t2);
+
+        if lit1 != lit2 {
+            let (mut lo, mut hi) = (t1, t2);
+            while hi - lo > Duration::seconds(1) {
+                let m = lo + (hi - lo) / 2;
+                if is_lit(m) == lit1 {
+                    lo = m;
+                } else {
+                    hi = m;
+                }
+            }
+            match since.take() {
+                Some(from) => periods.push((from, hi)),
+                None => since = Some(hi),
+            }
+        }
+
+        t1 = t2;
+        lit1 = lit2;
+    }
+    periods.extend(since.map(|from| (from, end)));
+
+    periods
+}
+
+/// Whether a point of the terrain at the given elevation is in the shadow of
+/// the terrain for a sun at the given elevation angle (in radians).
+///
+/// `terrain` gives the elevation of the terrain at a distance (in meters)
+/// from the point, in the direction of the sun's azimuth. The secondary ray
+/// toward the sun is marched every `step` meters up to `max_distance`,
+/// accounting for the curvature and refraction of an earth of the given
+/// effective radius, see [`effective_radius`](crate::distance::effective_radius).
+pub fn is_in_shadow(
+    terrain: impl Fn(f64) -> f64,
+    elevation: f64,
+    sun_elevation: f64,
+    effective_radius: f64,
+    max_distance: f64,
+    step: f64,
+) -> bool {
+    let slope = sun_elevation.tan();
+
+    let mut d = step;
+    while d <= max_distance {
+        let ray = elevation + d * slope + d * d / (2. * effective_radius);
+        if terrain(d) > ray {
+            return true;
+        }
+        d += step;
+    }
+
+    false
+}
+
+/// Apparent ecliptic longitude of the sun at the given instant, in radians.
+pub fn ecliptic_longitude(date_time: DateTime<Utc>) -> f64 {
+    ecliptic_longitude_and_obliquity(julian_centuries(date_time)).0
+}
+
+/// Declination of the sun and equation of time, both in radians.
+fn declination_and_equation_of_time(date_time: DateTime<Utc>) -> (f64, f64) {
+    let jc = julian_centuries(date_time);
+    let (apparent_long, obliquity) = ecliptic_longitude_and_obliquity(jc);
+
+    let mean_long = mean_longitude(jc);
+    let mean_anom = mean_anomaly(jc);
+    let eccentricity = 0.016708634 - jc * (0.000042037 + 0.0000001267 * jc);
+
+    let declination = (obliquity.sin() * apparent_long.sin()).asin();
+
+    let y = (obliquity / 2.).tan().powi(2);
+    let equation_of_time = y * (2. * mean_long).sin() - 2. * eccentricity * mean_anom.sin()
+        + 4. * eccentricity * y * mean_anom.sin() * (2. * mean_long).cos()
+        - 0.5 * y * y * (4. * mean_long).sin()
+        - 1.25 * eccentricity * eccentricity * (2. * mean_anom).sin();
+
+    (declination, equation_of_time)
+}
+
+fn mean_longitude(jc: f64) -> f64 {
+    (280.46646 + jc * (36000.76983 + jc * 0.0003032)).to_radians()
+}
+
+fn mean_anomaly(jc: f64) -> f64 {
+    (357.52911 + jc * (35999.05029 - 0.0001537 * jc)).to_radians()
+}
+
+/// Apparent ecliptic longitude of the sun and corrected obliquity of the
+/// ecliptic, in radians, `jc` Julian centuries after J2000.0.
+fn ecliptic_longitude_and_obliquity(jc: f64) -> (f64, f64) {
+    let mean_long = mean_longitude(jc);
+    let mean_anom = mean_anomaly(jc);
+
+    let eq_of_center = mean_anom.sin() * (1.914602 - jc * (0.004817 + 0.000014 * jc))
+        + (2. * mean_anom).sin() * (0.019993 - 0.000101 * jc)
+        + (3. * mean_anom).sin() * 0.000289;
+    let true_long = mean_long + eq_of_center.to_radians();
+
+    let omega = (125.04 - 1934.136 * jc).to_radians();
+    let apparent_long = true_long - (0.00569 + 0.00478 * omega.sin()).to_radians();
+
+    let mean_obliquity =
+        23. + (26. + (21.448 - jc * (46.815 + jc * (0.00059 - jc * 0.001813))) / 60.) / 60.;
+    let obliquity = (mean_obliquity + 0.00256 * omega.cos()).to_radians();
+
+    (apparent_long, obliquity)
+}
+
+#[cfg(test)]
+mod sun_tests {
+    use super::*;
+    use crate::{
+        celestial::J2000_JD,
+        distance::{effective_radius, REFRACTION_COEFFICIENT},
+    };
+    use assert_approx_eq::assert_approx_eq;
+    use chrono::TimeZone;
+
+    fn utc(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, mo, d, h, mi, 0).unwrap()
+    }
+
+    /// Highest position of the sun during the given day, sampled every minute.
+    fn culmination(day: DateTime<Utc>, lat: f64, lon: f64) -> SolarPosition {
+        (0..24 * 60)
+            .map(|m| position(day + Duration::minutes(m), lat, lon))
+            .max_by(|a, b| a.elevation.partial_cmp(&b.elevation).unwrap())
+            .unwrap()
+    }
+
+    #[test]
+    fn julian_day_is_correct_for_known_values() {
+        assert_approx_eq!(J2000_JD, julian_day(utc(2000, 1, 1, 12, 0)), 1e-10);
+        assert_approx_eq!(UNIX_EPOCH_JD, julian_day(utc(1970, 1, 1, 0, 0)), 1e-10);
+    }
+
+    #[test]
+    fn sun_culminates_at_expected_elevation_on_solstices() {
+        let lat = 46.5_f64.to_radians();
+        let lon = 7_f64.to_radians();
+
+        let summer = culmination(utc(2024, 6, 21, 0, 0), lat, lon);
+        assert_approx_eq!(90. - 46.5 + 23.44, summer.elevation.to_degrees(), 0.05);
+        assert_approx_eq!(180., summer.azimuth.degrees(), 1.);
+
+        let winter = culmination(utc(2024, 12, 21, 0, 0), lat, lon);
+        assert_approx_eq!(90. - 46.5 - 23.44, winter.elevation.to_degrees(), 0.05);
+        assert_approx_eq!(180., winter.azimuth.degrees(), 1.);
+    }
+
+    #[test]
+    fn sun_culminates_to_the_north_in_southern_hemisphere() {
+        let p = culmination(
+            utc(2024, 6, 21, 0, 0),
+            -33_f64.to_radians(),
+            151_f64.to_radians(),
+        );
+        assert_approx_eq!(90. - 33. - 23.44, p.elevation.to_degrees(), 0.05);
+        assert!(p.azimuth.degrees() < 1. || p.azimuth.degrees() > 359.);
+    }
+
+    #[test]
+    fn sun_is_at_zenith_on_equator_at_equinox_noon() {
+        // solar noon at Greenwich on 2024-03-20 is around 12:07 UTC
+        let p = culmination(utc(2024, 3, 20, 0, 0), 0., 0.);
+        assert_approx_eq!(90., p.elevation.to_degrees(), 0.5);
+    }
+
+    #[test]
+    fn sun_rises_in_the_east_and_sets_in_the_west_at_equinox() {
+        let lat = 46.5_f64.to_radians();
+        let lon = 0.;
+        let morning = position(utc(2024, 3, 20, 6, 0), lat, lon);
+        let evening = position(utc(2024, 3, 20, 18, 0), lat, lon);
+        let midnight = position(utc(2024, 3, 20, 0, 0), lat, lon);
+
+        assert_approx_eq!(0., morning.elevation.to_degrees(), 2.);
+        assert_approx_eq!(90., morning.azimuth.degrees(), 3.);
+        assert_approx_eq!(0., evening.elevation.to_degrees(), 2.);
+        assert_approx_eq!(270., evening.azimuth.degrees(), 3.);
+        assert!(midnight.elevation < -40_f64.to_radians());
+    }
+
+    #[test]
+    fn horizon_crossings_on_flat_horizon_are_sunrise_and_sunset() {
+        let lat = 46.5_f64.to_radians();
+        let lon = 0.;
+        let start = utc(2024, 3, 20, 0, 0);
+        let crossings = horizon_crossings(start, start + Duration::days(1), lat, lon, |_| 0.);
+
+        assert_eq!(2, crossings.len());
+        assert_eq!(CrossingKind::Rise, crossings[0].kind);
+        assert_eq!(CrossingKind::Set, crossings[1].kind);
+        assert_approx_eq!(90., crossings[0].azimuth.degrees(), 2.);
+        assert_approx_eq!(270., crossings[1].azimuth.degrees(), 2.);
+
+        // about twelve hours of daylight at the equinox
+        let day = (crossings[1].time - crossings[0].time).num_minutes();
+        assert!((715..=735).contains(&day), "{} minutes of daylight", day);
+    }
+
+    #[test]
+    fn horizon_crossings_respect_terrain_horizon() {
+        let lat = 46.5_f64.to_radians();
+        let lon = 7_f64.to_radians();
+        let start = utc(2024, 6, 21, 0, 0);
+        let end = start + Duration::days(1);
+        let flat = horizon_crossings(start, end, lat, lon, |_| 0.);
+        let mountains = horizon_crossings(start, end, lat, lon, |_| 5_f64.to_radians());
+
+        assert_eq!(2, mountains.len());
+        assert!(mountains[0].time > flat[0].time);
+        assert!(mountains[1].time < flat[1].time);
+    }
+
+    #[test]
+    fn horizon_crossings_handle_a_peak_hiding_the_sun() {
+        let lat = 46.5_f64.to_radians();
+        let lon = 7_f64.to_radians();
+        let start = utc(2024, 6, 21, 0, 0);
+        // a tall, narrow peak due south hides the sun around noon
+        let peak = |a: Azimuth| {
+            if (a.degrees() - 180.).abs() < 5. {
+                80_f64.to_radians()
+            } else {
+                0.
+            }
+        };
+        let crossings = horizon_crossings(start, start + Duration::days(1), lat, lon, peak);
+
+        let kinds: Vec<CrossingKind> = crossings.iter().map(|c| c.kind).collect();
+        assert_eq!(
+            vec![
+                CrossingKind::Rise,
+                CrossingKind::Set,
+                CrossingKind::Rise,
+                CrossingKind::Set
+            ],
+            kinds
+        );
+    }
+
+    #[test]
+    fn solar_day_runs_from_local_mean_midnight() {
+        // midnight of the mean solar time 15° east is at 23:00 UTC
+        let (start, end) = solar_day(utc(2024, 6, 21, 23, 30), 15_f64.to_radians());
+        assert_eq!(utc(2024, 6, 21, 23, 0), start);
+        assert_eq!(utc(2024, 6, 22, 23, 0), end);
+        let (start, _) = solar_day(utc(2024, 6, 21, 22, 30), 15_f64.to_radians());
+        assert_eq!(utc(2024, 6, 20, 23, 0), start);
+    }
+
+    #[test]
+    fn lit_periods_are_bounded_by_changes_and_the_interval() {
+        let start = utc(2024, 6, 21, 0, 0);
+        let end = start + Duration::days(1);
+        let between = |from: DateTime<Utc>, to: DateTime<Utc>| move |t| from <= t && t < to;
+
+        let morning = between(utc(2024, 6, 21, 5, 30), utc(2024, 6, 21, 11, 0));
+        assert_eq!(
+            vec![(utc(2024, 6, 21, 5, 30), utc(2024, 6, 21, 11, 0))],
+            lit_periods(start, end, morning)
+        );
+        let night = |t| !between(utc(2024, 6, 21, 4, 0), utc(2024, 6, 21, 20, 0))(t);
+        assert_eq!(
+            vec![
+                (start, utc(2024, 6, 21, 4, 0)),
+                (utc(2024, 6, 21, 20, 0), end)
+            ],
+            lit_periods(start, end, night)
+        );
+        assert!(lit_periods(start, end, |_| false).is_empty());
+    }
+
+    #[test]
+    fn is_in_shadow_is_false_on_flat_terrain_with_sun_up() {
+        assert!(!is_in_shadow(
+            |_| 500.,
+            500.,
+            1_f64.to_radians(),
+            effective_radius(REFRACTION_COEFFICIENT),
+            50_000.,
+            10.
+        ));
+    }
+
+    #[test]
+    fn is_in_shadow_is_true_behind_a_ridge() {
+        // a 1000 m high ridge 2 km away toward the sun
+        let ridge = |d: f64| {
+            if (1990.0..2010.0).contains(&d) {
+                1000.
+            } else {
+                0.
+            }
+        };
+        let radius = effective_radius(REFRACTION_COEFFICIENT);
+        assert!(is_in_shadow(
+            ridge,
+            0.,
+            20_f64.to_radians(),
+            radius,
+            10_000.,
+            5.
+        ));
+        assert!(!is_in_shadow(
+            ridge,
+            0.,
+            30_f64.to_radians(),
+            radius,
+            10_000.,
+            5.
+        ));
+    }
+
+    #[test]
+    fn is_in_shadow_accounts_for_earth_curvature() {
+        // with the sun on the astronomical horizon, the tangent ray rises
+        // above the curved terrain, leaving level terrain in the light
+        let radius = effective_radius(REFRACTION_COEFFICIENT);
+        assert!(!is_in_shadow(|_| 0., 0., 0., radius, 50_000., 100.));
+        // a 100 m ridge 50 km away is below the ray, which rose about 170 m,
+        // but not 10 km away, where it rose about 7 m
+        let ridge = |at: f64| move |d: f64| if d == at { 100. } else { 0. };
+        assert!(!is_in_shadow(ridge(50_000.), 0., 0., radius, 60_000., 100.));
+        assert!(is_in_shadow(ridge(10_000.), 0., 0., radius, 60_000., 100.));
+        // nor on a flat earth, or on a smaller one, where the ray rose 1250 m
+        assert!(is_in_shadow(
+            ridge(50_000.),
+            0.,
+            0.,
+            f64::INFINITY,
+            60_000.,
+            100.
+        ));
+        assert!(!is_in_shadow(
+            ridge(10_000.),
+            0.,
+            0.,
+            40_000.,
+            60_000.,
+            100.
+        ));
+    }
+
+    #[test]
+    fn path_samples_positions_at_regular_steps() {
+        let start = utc(2024, 6, 21, 0, 0);
+        let p = path(
+            start,
+            start + Duration::days(1),
+            Duration::hours(1),
+            0.8,
+            0.1,
+        );
+
+        assert_eq!(24, p.len());
+        for (i, (t, pos)) in p.iter().enumerate() {
+            assert_eq!(start + Duration::hours(i as i64), *t);
+            assert_eq!(position(*t, 0.8, 0.1), *pos);
+        }
+    }
+
+    #[test]
+    fn path_is_empty_for_empty_interval() {
+        let start = utc(2024, 6, 21, 0, 0);
+        assert!(path(start, start, Duration::minutes(10), 0.8, 0.1).is_empty());
+    }
+
+    #[test]
+    fn daylight_is_correct_for_known_elevations() {
+        let data = [
+            (45_f64, Daylight::Day),
+            (6., Daylight::Day),
+            (3., Daylight::GoldenHour),
+            (-1., Daylight::CivilTwilight),
+            (-7., Daylight::NauticalTwilight),
+            (-15., Daylight::AstronomicalTwilight),
+            (-30., Daylight::Night),
+        ];
+        for (deg, expected) in data {
+            assert_eq!(expected, Daylight::from_elevation(deg.to_radians()));
+        }
+    }
+}
@@ -0,0 +1,201 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+
+use crate::image::Image;
+
+/// Width and height of the tiles, in pixels.
+pub const TILE_SIZE: usize = 256;
+
+/// Pyramid of tiles of an image, like the XYZ tiles of web maps: at zoom
+/// level `z`, the image is scaled by `2^(z - max_zoom)` and cut in tiles of
+/// [`TILE_SIZE`] pixels, the tile `(x, y)` being the `x`-th from the left and
+/// `y`-th from the top. The whole image fits in the single tile of zoom 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TilePyramid {
+    width: usize,
+    height: usize,
+}
+
+impl TilePyramid {
+    /// Pyramid of the tiles of images of the given dimensions.
+    pub fn new(width: usize, height: usize) -> TilePyramid {
+        TilePyramid { width, height }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Zoom level at which the image is at full resolution.
+    pub fn max_zoom(&self) -> u32 {
+        let mut zoom = 0;
+        while TILE_SIZE << zoom < self.width.max(self.height) {
+            zoom += 1;
+        }
+        zoom
+    }
+
+    /// Number of columns and rows of tiles at zoom level `zoom`.
+    pub fn tile_count(&self, zoom: u32) -> (usize, usize) {
+        let (width, height) = self.dimensions(zoom);
+        (width.div_ceil(TILE_SIZE), height.div_ceil(TILE_SIZE))
+    }
+
+    /// Width and height of the image at zoom level `zoom`, in pixels.
+    pub fn dimensions(&self, zoom: u32) -> (usize, usize) {
+        let shift = self.max_zoom() - zoom;
+        (
+            self.width.div_ceil(1 << shift),
+            self.height.div_ceil(1 << shift),
+        )
+    }
+
+    /// Metadata of the pyramid for web viewers, as JSON. With Leaflet, tiles
+    /// are shown by a `L.tileLayer(url, {tileSize, minZoom, maxZoom,
+    /// noWrap: true})` over a map of `L.CRS.Simple`.
+    pub fn metadata(&self) -> String {
+        format!(
+            "{{\"width\": {}, \"height\": {}, \"tileSize\": {}, \"minZoom\": 0, \"maxZoom\": {}, \"url\": \"{{z}}/{{x}}/{{y}}.png\"}}\n",
+            self.width,
+            self.height,
+            TILE_SIZE,
+            self.max_zoom()
+        )
+    }
+}
+
+/// Write `image` to `dir` as a pyramid of PNG tiles named like
+/// `{z}/{x}/{y}.png`, transparent beyond the image, and its metadata to
+/// `tiles.json`, creating the directories as needed.
+pub fn export_tiles(image: &Image, dir: impl AsRef<Path>) -> io::Result<TilePyramid> {
+    let dir = dir.as_ref();
+    let pyramid = TilePyramid::new(image.width(), image.height());
+
+    let mut level = image.clone();
+    for zoom in (0..=pyramid.max_zoom()).rev() {
+        let (columns, rows) = pyramid.tile_count(zoom);
+        for x in 0..columns {
+            let column_dir = dir.join(zoom.to_string()).join(x.to_string());
+            fs::create_dir_all(&column_dir)?;
+            for y in 0..rows {
+                tile(&level, x, y).save_png(column_dir.join(format!("{}.png", y)))?;
+            }
+        }
+        if zoom > 0 {
+            level = halved(&level);
+        }
+    }
+
+    fs::File::create(dir.join("tiles.json"))?.write_all(pyramid.metadata().as_bytes())?;
+    Ok(pyramid)
+}
+
+/// Tile `(x, y)` of `image`, transparent beyond it.
+fn tile(image: &Image, x: usize, y: usize) -> Image {
+    let mut tile = Image::new(TILE_SIZE, TILE_SIZE);
+    let (x0, y0) = (x * TILE_SIZE, y * TILE_SIZE);
+    for ty in 0..TILE_SIZE.min(image.height() - y0) {
+        for tx in 0..TILE_SIZE.min(image.width() - x0) {
+            tile.set(tx, ty, image.get(x0 + tx, y0 + ty));
+        }
+    }
+    tile
+}
+
+/// Image of half the dimensions of `image`, rounded up, each pixel averaging
+/// the pixels of a 2×2 block weighted by their opacity.
+fn halved(image: &Image) -> Image {
+    let mut halved = Image::new(image.width().div_ceil(2), image.height().div_ceil(2));
+    for y in 0..halved.height() {
+        for x in 0..halved.width() {
+            let mut sum = [0_u32; 4];
+            let mut count = 0;
+            for by in 2 * y..(2 * y + 2).min(image.height()) {
+                for bx in 2 * x..(2 * x + 2).min(image.width()) {
+                    let [r, g, b, a] = image.get(bx, by).map(u32::from);
+                    sum = [sum[0] + r * a, sum[1] + g * a, sum[2] + b * a, sum[3] + a];
+                    count += 1;
+                }
+            }
+            // transparent blocks have no color
+            let color = |c: u32| (c + sum[3] / 2).checked_div(sum[3]).unwrap_or(0);
+            halved.set(
+                x,
+                y,
+                [
+                    color(sum[0]) as u8,
+                    color(sum[1]) as u8,
+                    color(sum[2]) as u8,
+                    ((sum[3] + count / 2) / count) as u8,
+                ],
+            );
+        }
+    }
+    halved
+}
+
+#[cfg(test)]
+mod tiles_tests {
+    use super::*;
+
+    #[test]
+    fn max_zoom_fits_the_image_in_one_tile_at_zoom_0() {
+        assert_eq!(0, TilePyramid::new(256, 100).max_zoom());
+        assert_eq!(1, TilePyramid::new(257, 100).max_zoom());
+        assert_eq!(4, TilePyramid::new(2500, 800).max_zoom());
+        assert_eq!(3, TilePyramid::new(100, 2048).max_zoom());
+    }
+
+    #[test]
+    fn tile_count_covers_the_image_at_every_zoom() {
+        let pyramid = TilePyramid::new(2500, 800);
+        assert_eq!((10, 4), pyramid.tile_count(4));
+        assert_eq!((2500, 800), pyramid.dimensions(4));
+        assert_eq!((1250, 400), pyramid.dimensions(3));
+        assert_eq!((157, 50), pyramid.dimensions(0));
+        assert_eq!((1, 1), pyramid.tile_count(0));
+    }
+
+    #[test]
+    fn halved_averages_opaque_pixels() {
+        let mut image = Image::new(3, 2);
+        image.set(0, 0, [100, 0, 0, 255]);
+        image.set(1, 0, [200, 0, 0, 255]);
+        image.set(0, 1, [0, 0, 0, 0]);
+        image.set(1, 1, [150, 0, 0, 255]);
+        image.set(2, 0, [10, 20, 30, 255]);
+        let halved = halved(&image);
+        assert_eq!((2, 1), (halved.width(), halved.height()));
+        assert_eq!([150, 0, 0, 191], halved.get(0, 0));
+        // the last column has no neighbor to average with
+        assert_eq!([10, 20, 30, 128], halved.get(1, 0));
+    }
+
+    #[test]
+    fn export_tiles_writes_every_tile_and_metadata() {
+        let dir = std::env::temp_dir().join("alpano_tiles_tests");
+        let _ = fs::remove_dir_all(&dir);
+        let mut image = Image::new(600, 300);
+        image.set(599, 299, [1, 2, 3, 255]);
+        let pyramid = export_tiles(&image, &dir).unwrap();
+
+        assert_eq!(2, pyramid.max_zoom());
+        for (zoom, x, y) in [(0, 0, 0), (1, 1, 0), (2, 2, 1)] {
+            assert!(dir.join(format!("{}/{}/{}.png", zoom, x, y)).exists());
+        }
+        assert!(!dir.join("2/3/0.png").exists());
+        let corner = ::image::open(dir.join("2/2/1.png")).unwrap().to_rgba8();
+        assert_eq!((256, 256), corner.dimensions());
+        assert_eq!([1, 2, 3, 255], corner.get_pixel(599 - 512, 299 - 256).0);
+        let metadata = fs::read_to_string(dir.join("tiles.json")).unwrap();
+        assert!(metadata.contains("\"maxZoom\": 2"));
+        assert!(metadata.contains("\"url\": \"{z}/{x}/{y}.png\""));
+    }
+}
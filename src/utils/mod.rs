@@ -1,3 +0,0 @@
-pub mod distance;
-pub mod math;
-pub mod azimuth;
@@ -0,0 +1,118 @@
+use std::cmp::Reverse;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::{
+    dem::{ContinuousElevationModel, DiscreteElevationModel},
+    geo::GeoPoint,
+    labels::Summit,
+    viewshed::Viewshed,
+};
+
+/// Candidate viewpoint, with the target summits seen from it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Viewpoint<'a> {
+    /// Index of the viewpoint among the candidates.
+    pub candidate: usize,
+    pub position: GeoPoint,
+    /// Elevation of the observer, in meters.
+    pub elevation: f64,
+    /// Target summits seen from the viewpoint, in the order of the targets.
+    pub visible: Vec<&'a Summit>,
+}
+
+/// Rank the `candidates` by the number of `targets` seen by an observer
+/// standing `eye_height` meters above the ground at each of them, most
+/// first, keeping the order of the candidates seeing as many.
+///
+/// A target is seen if the sample nearest to it is in the [`Viewshed`] of
+/// the candidate, up to `max_distance` (in meters). With the `parallel`
+/// feature, the viewsheds of the candidates are computed on all cores.
+pub fn rank_viewpoints<'a, D: DiscreteElevationModel>(
+    cem: &ContinuousElevationModel<D>,
+    candidates: &[GeoPoint],
+    eye_height: f64,
+    targets: &'a [Summit],
+    max_distance: f64,
+) -> Vec<Viewpoint<'a>> {
+    let viewpoint = |(candidate, &position): (usize, &GeoPoint)| {
+        let elevation = cem.elevation_at(&position) + eye_height;
+        let viewshed = Viewshed::compute(cem, position, elevation, 0., max_distance);
+        Viewpoint {
+            candidate,
+            position,
+            elevation,
+            visible: targets
+                .iter()
+                .filter(|t| viewshed.sees(&t.position))
+                .collect(),
+        }
+    };
+
+    #[cfg(feature = "parallel")]
+    let mut viewpoints: Vec<Viewpoint> = candidates.par_iter().enumerate().map(viewpoint).collect();
+    #[cfg(not(feature = "parallel"))]
+    let mut viewpoints: Vec<Viewpoint> = candidates.iter().enumerate().map(viewpoint).collect();
+    viewpoints.sort_by_key(|v| Reverse(v.visible.len()));
+    viewpoints
+}
+
+#[cfg(test)]
+mod viewpoints_tests {
+    use super::*;
+    use crate::fixtures::meridian_wall;
+
+    fn point(longitude: f64, latitude: f64) -> GeoPoint {
+        GeoPoint::from_degrees(longitude, latitude).unwrap()
+    }
+
+    fn summit(name: &str, longitude: f64, latitude: f64) -> Summit {
+        Summit {
+            name: name.to_string(),
+            position: point(longitude, latitude),
+            elevation: 1000.,
+        }
+    }
+
+    fn names<'a>(viewpoint: &Viewpoint<'a>) -> Vec<&'a str> {
+        viewpoint.visible.iter().map(|s| s.name.as_str()).collect()
+    }
+
+    #[test]
+    fn rank_viewpoints_ranks_those_seeing_the_most_targets_first() {
+        let cem = ContinuousElevationModel::new(meridian_wall());
+        let targets = [
+            summit("west", 7.52, 46.5),
+            summit("east", 7.58, 46.5),
+            summit("far west", 7.47, 46.49),
+        ];
+        // east of the wall, west of it, and on top of it
+        let candidates = [point(7.6, 46.5), point(7.5, 46.5), point(7.55, 46.5)];
+
+        let ranked = rank_viewpoints(&cem, &candidates, 10., &targets, 10_000.);
+        assert_eq!(
+            vec![2, 1, 0],
+            ranked.iter().map(|v| v.candidate).collect::<Vec<_>>()
+        );
+        assert_eq!(vec!["west", "east", "far west"], names(&ranked[0]));
+        assert_eq!(vec!["west", "far west"], names(&ranked[1]));
+        assert_eq!(vec!["east"], names(&ranked[2]));
+        assert_eq!(2010., ranked[0].elevation);
+        assert_eq!(candidates[1], ranked[1].position);
+    }
+
+    #[test]
+    fn rank_viewpoints_keeps_the_order_of_candidates_seeing_as_many() {
+        let cem = ContinuousElevationModel::new(meridian_wall());
+        let targets = [summit("beyond", 7.9, 46.5)];
+        let candidates = [point(7.5, 46.5), point(7.6, 46.5), point(7.4, 46.5)];
+
+        let ranked = rank_viewpoints(&cem, &candidates, 10., &targets, 10_000.);
+        assert_eq!(
+            vec![0, 1, 2],
+            ranked.iter().map(|v| v.candidate).collect::<Vec<_>>()
+        );
+        assert!(ranked.iter().all(|v| v.visible.is_empty()));
+    }
+}
@@ -0,0 +1,299 @@
+use std::{
+    f64::consts::TAU,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use crate::{
+    azimuth::Azimuth,
+    dem::{ContinuousElevationModel, DiscreteElevationModel, ElevationProfile},
+    distance::{self, effective_radius, REFRACTION_COEFFICIENT},
+    geo::GeoPoint,
+    image::{Image, ImageFormat},
+    interval::{Interval1D, Interval2D},
+    panorama::ray_to_ground_distance,
+};
+
+/// Color of the visible cells in images of viewsheds.
+const VISIBLE: [u8; 4] = [255, 96, 0, 160];
+
+/// Samples of a DEM seen from an observer: a target at a given height above
+/// the terrain at a sample is visible if the straight line of sight joining
+/// it to the observer, bent by refraction over the curved earth, passes above
+/// the terrain in between.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Viewshed {
+    observer: GeoPoint,
+    max_distance: f64,
+    samples_per_degree: u32,
+    extent: Interval2D,
+    visible: Vec<bool>,
+}
+
+impl Viewshed {
+    /// Viewshed of the observer at `observer` and `observer_elevation`, over
+    /// the samples of `cem` within `max_distance` (in meters), for targets
+    /// `target_height` meters above the terrain.
+    ///
+    /// Rays leave the observer at azimuths about one sample apart at the
+    /// maximum distance, and the terrain along each of them is sampled about
+    /// every sample, marking the nearest sample as visible wherever the
+    /// target rises above the highest line of sight so far.
+    pub fn compute<D: DiscreteElevationModel>(
+        cem: &ContinuousElevationModel<D>,
+        observer: GeoPoint,
+        observer_elevation: f64,
+        target_height: f64,
+        max_distance: f64,
+    ) -> Viewshed {
+        let samples_per_degree = cem.dem().samples_per_degree();
+        let spd = samples_per_degree as f64;
+        let d_lat = max_distance / distance::meters_per_deg_lat();
+        let d_lon = max_distance / distance::meters_per_deg_lon(observer.latitude());
+        let (lon, lat) = (
+            observer.longitude().to_degrees(),
+            observer.latitude().to_degrees(),
+        );
+        let extent = Interval2D::new(
+            Interval1D::new(
+                ((lon - d_lon) * spd).floor() as i32,
+                ((lon + d_lon) * spd).ceil() as i32,
+            ),
+            Interval1D::new(
+                ((lat - d_lat) * spd).floor() as i32,
+                ((lat + d_lat) * spd).ceil() as i32,
+            ),
+        );
+        let mut viewshed = Viewshed {
+            observer,
+            max_distance,
+            samples_per_degree,
+            extent,
+            visible: vec![false; extent.size()],
+        };
+
+        let step = distance::meters_per_deg_lat()
+            .min(distance::meters_per_deg_lon(observer.latitude()))
+            / spd;
+        let rays = (TAU * max_distance / step).ceil() as usize;
+        let radius = effective_radius(REFRACTION_COEFFICIENT);
+        viewshed.mark_visible(observer);
+        for i in 0..rays {
+            let azimuth = Azimuth::new(i as f64 * TAU / rays as f64);
+            let profile = ElevationProfile::new(cem, observer, azimuth, max_distance);
+            // height of the terrain above the horizontal ray of the observer
+            let height = ray_to_ground_distance(&profile, observer_elevation, 0., radius);
+
+            let mut max_slope = f64::NEG_INFINITY;
+            let mut d = step;
+            while d <= max_distance {
+                let terrain = -height(d);
+                if (terrain + target_height) / d >= max_slope {
+                    viewshed.mark_visible(profile.position_at(d));
+                }
+                max_slope = max_slope.max(terrain / d);
+                d += step;
+            }
+        }
+
+        viewshed
+    }
+
+    pub fn samples_per_degree(&self) -> u32 {
+        self.samples_per_degree
+    }
+
+    /// Samples covered by the viewshed, around the maximum distance of the
+    /// observer.
+    pub fn extent(&self) -> Interval2D {
+        self.extent
+    }
+
+    /// Whether the sample `(x, y)` is visible, false outside of the extent.
+    pub fn is_visible(&self, x: i32, y: i32) -> bool {
+        self.extent.contains(x, y) && self.visible[self.index(x, y)]
+    }
+
+    /// Whether the sample nearest to `point` is visible, false outside of the
+    /// extent.
+    pub fn sees(&self, point: &GeoPoint) -> bool {
+        let (x, y) = self.nearest_sample(point);
+        self.is_visible(x, y)
+    }
+
+    /// Number of visible samples.
+    pub fn visible_count(&self) -> usize {
+        self.visible.iter().filter(|&&v| v).count()
+    }
+
+    /// Whether the sample `(x, y)` is within the maximum distance of the
+    /// observer.
+    fn is_in_range(&self, x: i32, y: i32) -> bool {
+        let spd = self.samples_per_degree as f64;
+        GeoPoint::from_degrees(x as f64 / spd, y as f64 / spd)
+            .is_ok_and(|p| self.observer.distance_to(&p) <= self.max_distance)
+    }
+
+    /// Write the viewshed to `writer` as an ESRI ASCII grid of the samples
+    /// of its extent in degrees, read by GIS software: 1 where visible, 0
+    /// where hidden and -1 beyond the maximum distance.
+    pub fn write_ascii_grid(&self, mut writer: impl Write) -> io::Result<()> {
+        let (xs, ys) = (self.extent.x(), self.extent.y());
+        let cell_size = 1. / self.samples_per_degree as f64;
+        writeln!(writer, "ncols {}", xs.size())?;
+        writeln!(writer, "nrows {}", ys.size())?;
+        // the corner of the grid is that of its south-west cell
+        writeln!(
+            writer,
+            "xllcorner {}",
+            (xs.included_from() as f64 - 0.5) * cell_size
+        )?;
+        writeln!(
+            writer,
+            "yllcorner {}",
+            (ys.included_from() as f64 - 0.5) * cell_size
+        )?;
+        writeln!(writer, "cellsize {}", cell_size)?;
+        writeln!(writer, "NODATA_value -1")?;
+
+        for y in (ys.included_from()..=ys.included_to()).rev() {
+            let row: Vec<&str> = (xs.included_from()..=xs.included_to())
+                .map(|x| match (self.is_in_range(x, y), self.is_visible(x, y)) {
+                    (false, _) => "-1",
+                    (true, true) => "1",
+                    (true, false) => "0",
+                })
+                .collect();
+            writeln!(writer, "{}", row.join(" "))?;
+        }
+        Ok(())
+    }
+
+    /// Image of the viewshed, north up, with a pixel per sample, colored
+    /// where visible and transparent elsewhere, to overlay on maps.
+    pub fn image(&self) -> Image {
+        let (xs, ys) = (self.extent.x(), self.extent.y());
+        let mut image = Image::new(xs.size(), ys.size());
+        for (row, y) in (ys.included_from()..=ys.included_to()).rev().enumerate() {
+            for (column, x) in (xs.included_from()..=xs.included_to()).enumerate() {
+                if self.is_visible(x, y) {
+                    image.set(column, row, VISIBLE);
+                }
+            }
+        }
+        image
+    }
+
+    /// Write the viewshed to the file at the given path, as an ESRI ASCII
+    /// grid if its extension is `asc`, and as an image otherwise.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        if path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("asc"))
+        {
+            let mut writer = BufWriter::new(File::create(path)?);
+            self.write_ascii_grid(&mut writer)?;
+            writer.flush()
+        } else {
+            self.image()
+                .save(path, ImageFormat::from_path(path).unwrap_or_default(), 90)
+        }
+    }
+
+    /// Mark the sample nearest to `point` as visible.
+    fn mark_visible(&mut self, point: GeoPoint) {
+        let (x, y) = self.nearest_sample(&point);
+        if self.extent.contains(x, y) {
+            let i = self.index(x, y);
+            self.visible[i] = true;
+        }
+    }
+
+    fn nearest_sample(&self, point: &GeoPoint) -> (i32, i32) {
+        let spd = self.samples_per_degree as f64;
+        (
+            (point.longitude().to_degrees() * spd).round() as i32,
+            (point.latitude().to_degrees() * spd).round() as i32,
+        )
+    }
+
+    fn index(&self, x: i32, y: i32) -> usize {
+        let (xs, ys) = (self.extent.x(), self.extent.y());
+        (y - ys.included_from()) as usize * xs.size() + (x - xs.included_from()) as usize
+    }
+}
+
+#[cfg(test)]
+mod viewshed_tests {
+    use super::*;
+    use crate::fixtures::viewshed;
+
+    #[test]
+    fn compute_hides_the_terrain_behind_the_wall() {
+        let viewshed = viewshed(0.);
+        assert_eq!(Interval1D::new(4641, 4659), viewshed.extent().y());
+        assert!(viewshed.is_visible(750, 4650));
+        assert!(viewshed.is_visible(745, 4652));
+        assert!(viewshed.is_visible(755, 4650));
+        assert!(!viewshed.is_visible(757, 4650));
+        assert!(!viewshed.is_visible(760, 4648));
+        assert!(viewshed.is_visible(740, 4650));
+        assert!(!viewshed.is_visible(0, 0));
+    }
+
+    #[test]
+    fn sees_the_nearest_sample() {
+        let viewshed = viewshed(0.);
+        let point = |longitude, latitude| GeoPoint::from_degrees(longitude, latitude).unwrap();
+        assert!(viewshed.sees(&point(7.5512, 46.5)));
+        assert!(!viewshed.sees(&point(7.5688, 46.5)));
+        assert!(!viewshed.sees(&point(8.5, 46.5)));
+    }
+
+    #[test]
+    fn compute_sees_high_targets_behind_the_wall() {
+        let low = viewshed(0.);
+        let high = viewshed(3000.);
+        assert!(high.visible_count() > low.visible_count());
+        assert!(high.is_visible(760, 4648));
+    }
+
+    #[test]
+    fn write_ascii_grid_writes_rows_from_the_north() {
+        let viewshed = viewshed(0.);
+        let mut grid = Vec::new();
+        viewshed.write_ascii_grid(&mut grid).unwrap();
+        let grid = String::from_utf8(grid).unwrap();
+        let lines: Vec<&str> = grid.lines().collect();
+
+        let (xs, ys) = (viewshed.extent().x(), viewshed.extent().y());
+        assert_eq!(format!("ncols {}", xs.size()), lines[0]);
+        assert_eq!("nrows 19", lines[1]);
+        assert_eq!("yllcorner 46.405", lines[3]);
+        assert_eq!("NODATA_value -1", lines[5]);
+        assert_eq!(6 + ys.size(), lines.len());
+        // the observer is in the middle row, the corners are out of range
+        let middle: Vec<&str> = lines[6 + 9].split(' ').collect();
+        assert_eq!("1", middle[(750 - xs.included_from()) as usize]);
+        assert_eq!("0", middle[(760 - xs.included_from()) as usize]);
+        assert_eq!("-1", lines[6].split(' ').next().unwrap());
+    }
+
+    #[test]
+    fn image_is_north_up() {
+        let viewshed = viewshed(0.);
+        let image = viewshed.image();
+        let (xs, ys) = (viewshed.extent().x(), viewshed.extent().y());
+        assert_eq!((xs.size(), ys.size()), (image.width(), image.height()));
+        let pixel = |x: i32, y: i32| {
+            image.get(
+                (x - xs.included_from()) as usize,
+                (ys.included_to() - y) as usize,
+            )
+        };
+        assert_eq!(VISIBLE, pixel(745, 4652));
+        assert_eq!([0; 4], pixel(760, 4648));
+    }
+}
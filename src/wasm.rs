@@ -0,0 +1,51 @@
+use std::sync::Arc;
+
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    config::RenderConfig,
+    dem::{CompositeDem, DiscreteElevationModel, HgtDem},
+    render,
+};
+
+/// Renderer of panoramas over HGT tiles given by the host, e.g. fetched by
+/// the browser, as no file can be read.
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct Renderer {
+    tiles: Vec<Arc<HgtDem>>,
+}
+
+#[wasm_bindgen]
+impl Renderer {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Renderer {
+        Renderer::default()
+    }
+
+    /// Add the tile whose south-west corner is at the given latitude and
+    /// longitude (in degrees), from the content of its HGT file.
+    #[wasm_bindgen(js_name = addHgtTile)]
+    pub fn add_hgt_tile(&mut self, south: i32, west: i32, bytes: Vec<u8>) -> Result<(), JsError> {
+        self.tiles
+            .push(Arc::new(HgtDem::from_bytes(south, west, bytes)?));
+        Ok(())
+    }
+
+    /// PNG image of the panorama of the render job declared in TOML, like
+    /// configuration files, over the added tiles, which must cover every
+    /// point within its maximum distance. Its `[dem]` directory is ignored.
+    pub fn render(&self, config: &str) -> Result<Vec<u8>, JsError> {
+        let config = RenderConfig::from_toml(config)?;
+        let tiles = self
+            .tiles
+            .iter()
+            .map(|tile| Box::new(tile.clone()) as Box<dyn DiscreteElevationModel>)
+            .collect();
+        let image = render::render_dem(&config, CompositeDem::new(tiles)?, |_, _| {})?;
+
+        let mut png = Vec::new();
+        image.write_png(&mut png)?;
+        Ok(png)
+    }
+}